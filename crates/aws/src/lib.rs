@@ -1,14 +1,85 @@
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use r2iac_core::{check_cidr, secret_var_name, RenderCtx, ValueFrom};
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value as Json, Map as JsonMap};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AwsProvider { pub region: String }
+pub struct AwsProvider {
+    pub region: String,
+    /// Set on the extra blocks of a multi-region stack; resources opt in with `provider_alias`.
+    #[serde(default)] pub alias: Option<String>,
+    #[serde(default)] pub profile: Option<String>,
+    #[serde(default)] pub assume_role: Option<AssumeRole>,
+    #[serde(default)] pub default_tags: BTreeMap<String, String>,
+    #[serde(default)] pub allowed_account_ids: Vec<String>,
+}
 impl AwsProvider {
     pub fn to_tf_json(&self) -> Json {
-        json!({ "provider": { "aws": { "region": self.region } } })
+        let mut p = json!({ "region": self.region });
+        if let Some(alias) = &self.alias { p["alias"] = json!(alias); }
+        if let Some(profile) = &self.profile { p["profile"] = json!(profile); }
+        if let Some(role) = &self.assume_role {
+            // A nested block, not an attribute: the provider rejects `assume_role = "arn:..."`.
+            let mut block = json!({ "role_arn": role.role_arn });
+            if let Some(s) = &role.session_name { block["session_name"] = json!(s); }
+            if let Some(e) = &role.external_id { block["external_id"] = json!(e); }
+            p["assume_role"] = json!([block]);
+        }
+        if !self.default_tags.is_empty() { p["default_tags"] = json!([{ "tags": self.default_tags }]); }
+        if !self.allowed_account_ids.is_empty() { p["allowed_account_ids"] = json!(self.allowed_account_ids); }
+        json!({ "provider": { "aws": p } })
+    }
+}
+
+/// `provider.aws` in a stack: one block, or a list of blocks told apart by `alias`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AwsProviders { One(AwsProvider), Many(Vec<AwsProvider>) }
+impl AwsProviders {
+    pub fn all(&self) -> &[AwsProvider] {
+        match self {
+            AwsProviders::One(p) => std::slice::from_ref(p),
+            AwsProviders::Many(ps) => ps,
+        }
+    }
+
+    /// The unaliased block, or the first one if every block carries an alias.
+    pub fn default_provider(&self) -> Option<&AwsProvider> {
+        self.all().iter().find(|p| p.alias.is_none()).or_else(|| self.all().first())
+    }
+
+    pub fn has_alias(&self, alias: &str) -> bool {
+        self.all().iter().any(|p| p.alias.as_deref() == Some(alias))
+    }
+
+    pub fn to_tf_json(&self) -> Result<Json> {
+        let mut seen = std::collections::BTreeSet::new();
+        for p in self.all() {
+            if !seen.insert(p.alias.as_deref()) {
+                match &p.alias {
+                    Some(a) => anyhow::bail!("provider.aws: alias '{}' is defined more than once", a),
+                    None => anyhow::bail!("provider.aws: only one entry may omit alias"),
+                }
+            }
+        }
+        Ok(match self {
+            AwsProviders::One(p) => p.to_tf_json(),
+            AwsProviders::Many(ps) => json!({ "provider": { "aws": ps.iter().map(|p| p.to_tf_json()["provider"]["aws"].take()).collect::<Vec<_>>() } }),
+        })
     }
 }
 
+/// Role the provider assumes into the target account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssumeRole {
+    pub role_arn: String,
+    #[serde(default)] pub session_name: Option<String>,
+    #[serde(default)] pub external_id: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag="type")]
 pub enum AwsResource {
@@ -20,6 +91,17 @@ pub enum AwsResource {
         force_destroy: bool,
         #[serde(default)]
         kms_key_id: Option<String>,
+        /// S3 Bucket Keys cut KMS request costs; only meaningful with `kms_key_id`.
+        #[serde(default)]
+        bucket_key_enabled: Option<bool>,
+        #[serde(default)]
+        versioning: Option<bool>,
+        #[serde(default)]
+        lifecycle_rules: Vec<S3LifecycleRule>,
+        #[serde(default="some_true")]
+        block_public_access: Option<bool>,
+        #[serde(default)]
+        bucket_policy: Option<S3BucketPolicy>,
     },
     #[serde(rename="aws_kms_key")]
     KmsKey {
@@ -34,6 +116,18 @@ pub enum AwsResource {
         key_usage: Option<String>,
         #[serde(default)]
         key_spec: Option<String>,
+        #[serde(default)]
+        alias: Option<String>,
+        #[serde(default)]
+        policy: Option<KeyPolicy>,
+        #[serde(default)]
+        multi_region: Option<bool>,
+    },
+    #[serde(rename="aws_kms_alias")]
+    KmsAlias {
+        name: String,
+        alias_name: String,
+        target_key_ref: String,
     },
     #[serde(rename="aws_secretsmanager_secret")]
     SecretsManagerSecret {
@@ -47,75 +141,1522 @@ pub enum AwsResource {
         #[serde(default)]
         force_delete_without_recovery: Option<bool>,
     },
+    #[serde(rename="aws_sqs_queue")]
+    SqsQueue {
+        name: String,
+        #[serde(default)]
+        fifo_queue: bool,
+        #[serde(default)]
+        visibility_timeout_seconds: Option<u32>,
+        #[serde(default)]
+        message_retention_seconds: Option<u32>,
+        #[serde(default)]
+        kms_master_key_id: Option<String>,
+        #[serde(default)]
+        redrive_policy: Option<RedrivePolicy>,
+    },
+    #[serde(rename="aws_sns_topic")]
+    SnsTopic {
+        name: String,
+        #[serde(default)]
+        display_name: Option<String>,
+        #[serde(default)]
+        kms_master_key_id: Option<String>,
+        #[serde(default)]
+        fifo_topic: bool,
+    },
+    #[serde(rename="aws_sns_topic_subscription")]
+    SnsSubscription {
+        name: String,
+        topic_ref: String,
+        protocol: String,
+        endpoint: String,
+        #[serde(default)]
+        raw_message_delivery: Option<bool>,
+    },
+    #[serde(rename="aws_iam_role")]
+    IamRole {
+        name: String,
+        #[serde(default)]
+        service_principals: Vec<String>,
+        #[serde(default)]
+        account_ids: Vec<String>,
+        #[serde(default)]
+        max_session_duration: Option<u32>,
+        #[serde(default)]
+        managed_policy_arns: Vec<String>,
+    },
+    #[serde(rename="aws_iam_policy")]
+    IamPolicy {
+        name: String,
+        #[serde(default)]
+        description: Option<String>,
+        statements: Vec<PolicyStatement>,
+    },
+    #[serde(rename="aws_lambda_function")]
+    LambdaFunction {
+        name: String,
+        function_name: String,
+        runtime: String,
+        handler: String,
+        role: String,
+        filename: String,
+        #[serde(default)]
+        memory_size: Option<u32>,
+        #[serde(default)]
+        timeout: Option<u32>,
+        #[serde(default)]
+        environment: BTreeMap<String, String>,
+    },
+    #[serde(rename="aws_vpc")]
+    Vpc {
+        name: String,
+        cidr_block: String,
+        #[serde(default)]
+        enable_dns_support: Option<bool>,
+        #[serde(default)]
+        enable_dns_hostnames: Option<bool>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="aws_subnet")]
+    Subnet {
+        name: String,
+        vpc_ref: String,
+        cidr_block: String,
+        #[serde(default)]
+        availability_zone: Option<String>,
+        #[serde(default)]
+        map_public_ip_on_launch: Option<bool>,
+    },
+    #[serde(rename="aws_internet_gateway")]
+    InternetGateway {
+        name: String,
+        vpc_ref: String,
+    },
+    #[serde(rename="aws_security_group")]
+    SecurityGroup {
+        name: String,
+        vpc_ref: String,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        ingress: Vec<SecurityGroupRule>,
+        #[serde(default)]
+        egress: Vec<SecurityGroupRule>,
+    },
+    #[serde(rename="aws_instance")]
+    Ec2Instance {
+        name: String,
+        ami: String,
+        instance_type: String,
+        #[serde(default)]
+        subnet_ref: Option<String>,
+        #[serde(default)]
+        key_name: Option<String>,
+        #[serde(default)]
+        security_group_refs: Vec<String>,
+        #[serde(default)]
+        user_data: Option<String>,
+        /// User data that is already base64, e.g. a gzipped cloud-init archive; passed through.
+        #[serde(default)]
+        user_data_base64: Option<String>,
+        #[serde(default)]
+        root_volume_size_gb: Option<u32>,
+        #[serde(default)]
+        root_volume_encrypted: Option<bool>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="aws_db_instance")]
+    RdsInstance {
+        name: String,
+        engine: String,
+        #[serde(default)]
+        engine_version: Option<String>,
+        instance_class: String,
+        allocated_storage: u32,
+        #[serde(default)]
+        db_name: Option<String>,
+        username: String,
+        password_ref: PasswordRef,
+        #[serde(default)]
+        storage_encrypted: Option<bool>,
+        #[serde(default)]
+        kms_key_id: Option<String>,
+        #[serde(default)]
+        multi_az: Option<bool>,
+        #[serde(default)]
+        skip_final_snapshot: bool,
+        #[serde(default)]
+        final_snapshot_identifier: Option<String>,
+        #[serde(default)]
+        deletion_protection: Option<bool>,
+    },
+    #[serde(rename="aws_cloudwatch_log_group")]
+    CloudWatchLogGroup {
+        name: String,
+        log_group_name: String,
+        #[serde(default)]
+        retention_in_days: Option<u32>,
+        #[serde(default)]
+        kms_key_id: Option<String>,
+    },
+    #[serde(rename="aws_route53_zone")]
+    Route53Zone {
+        name: String,
+        zone_name: String,
+        #[serde(default)]
+        private: bool,
+        #[serde(default)]
+        vpc_ref: Option<String>,
+    },
+    /// `type` selects the variant, so the DNS type is `record_type`.
+    #[serde(rename="aws_route53_record")]
+    Route53Record {
+        name: String,
+        zone_ref: String,
+        record_name: String,
+        record_type: DnsRecordType,
+        #[serde(default)]
+        ttl: Option<u32>,
+        #[serde(default)]
+        records: Vec<String>,
+        #[serde(default)]
+        alias: Option<Route53Alias>,
+    },
+    #[serde(rename="aws_ecr_repository")]
+    EcrRepository {
+        name: String,
+        #[serde(default)]
+        image_tag_mutability: Option<String>,
+        #[serde(default)]
+        scan_on_push: bool,
+        #[serde(default)]
+        encryption: Option<EcrEncryption>,
+        #[serde(default)]
+        keep_last_n_images: Option<u32>,
+    },
+    #[serde(rename="aws_eks_cluster")]
+    EksCluster {
+        name: String,
+        role_ref: String,
+        #[serde(default)]
+        version: Option<String>,
+        subnet_refs: Vec<String>,
+        #[serde(default)]
+        endpoint_private_access: Option<bool>,
+        #[serde(default)]
+        endpoint_public_access: Option<bool>,
+        #[serde(default)]
+        enabled_log_types: Vec<String>,
+    },
+    #[serde(rename="aws_eks_node_group")]
+    EksNodeGroup {
+        name: String,
+        cluster_ref: String,
+        node_role_ref: String,
+        subnet_refs: Vec<String>,
+        #[serde(default)]
+        instance_types: Vec<String>,
+        desired_size: u32,
+        min_size: u32,
+        max_size: u32,
+    },
+    #[serde(rename="aws_lb")]
+    Alb {
+        name: String,
+        #[serde(default)]
+        internal: bool,
+        subnet_refs: Vec<String>,
+        #[serde(default)]
+        security_group_refs: Vec<String>,
+    },
+    #[serde(rename="aws_lb_target_group")]
+    AlbTargetGroup {
+        name: String,
+        port: u16,
+        protocol: String,
+        vpc_ref: String,
+        #[serde(default)]
+        target_type: Option<String>,
+        #[serde(default)]
+        health_check: HealthCheck,
+    },
+    #[serde(rename="aws_lb_listener")]
+    AlbListener {
+        name: String,
+        load_balancer_ref: String,
+        port: u16,
+        protocol: String,
+        #[serde(default)]
+        certificate_arn: Option<String>,
+        #[serde(default)]
+        ssl_policy: Option<String>,
+        target_group_ref: String,
+    },
+    #[serde(rename="aws_cloudfront_distribution")]
+    CloudFrontDistribution {
+        name: String,
+        /// A sibling `aws_s3_bucket`, or the domain name of some other origin.
+        origin: String,
+        #[serde(default)]
+        default_cache_behavior: CacheBehavior,
+        #[serde(default)]
+        price_class: Option<String>,
+        #[serde(default)]
+        aliases: Vec<String>,
+        #[serde(default)]
+        acm_certificate_arn: Option<String>,
+        #[serde(default)]
+        default_root_object: Option<String>,
+    },
+    #[serde(rename="aws_secretsmanager_secret_version")]
+    SecretsManagerSecretVersion {
+        name: String,
+        secret_ref: String,
+        value_from: ValueFrom,
+    },
+    /// `type` selects the variant, so the parameter type is `parameter_type`.
+    #[serde(rename="aws_ssm_parameter")]
+    SsmParameter {
+        name: String,
+        parameter_name: String,
+        parameter_type: SsmParameterType,
+        #[serde(default)]
+        value: Option<String>,
+        #[serde(default)]
+        value_from: Option<ValueFrom>,
+        #[serde(default)]
+        kms_key_id: Option<String>,
+        #[serde(default)]
+        tier: Option<String>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    #[serde(rename="aws_cloudwatch_event_rule")]
+    EventBridgeRule {
+        name: String,
+        #[serde(default)]
+        schedule_expression: Option<String>,
+        /// Inline pattern; serialized to the JSON string the provider expects.
+        #[serde(default)]
+        event_pattern: Option<Json>,
+        #[serde(default)]
+        state: Option<String>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    #[serde(rename="aws_cloudwatch_event_target")]
+    EventBridgeTarget {
+        name: String,
+        rule_ref: String,
+        /// A sibling Lambda function, SNS topic or SQS queue, or an ARN.
+        arn_or_ref: String,
+        #[serde(default)]
+        role_ref: Option<String>,
+        #[serde(default)]
+        input: Option<Json>,
+    },
+    #[serde(rename="aws_kinesis_stream")]
+    KinesisStream {
+        name: String,
+        #[serde(default)]
+        shard_count: Option<u32>,
+        #[serde(default)]
+        stream_mode: Option<KinesisStreamMode>,
+        #[serde(default)]
+        retention_period_hours: Option<u32>,
+        #[serde(default)]
+        encryption_type: Option<String>,
+        #[serde(default)]
+        kms_key_ref: Option<String>,
+    },
+    #[serde(rename="aws_apigatewayv2_api")]
+    ApiGatewayHttpApi {
+        name: String,
+        #[serde(default)]
+        protocol_type: Option<String>,
+        #[serde(default)]
+        cors: Option<ApiCors>,
+        #[serde(default)]
+        routes: Vec<ApiRoute>,
+    },
+    /// `type` selects the variant, so STANDARD/EXPRESS is `machine_type`.
+    #[serde(rename="aws_sfn_state_machine")]
+    SfnStateMachine {
+        name: String,
+        role_ref: String,
+        /// Inline Amazon States Language document.
+        definition: Json,
+        #[serde(default)]
+        machine_type: Option<SfnType>,
+        #[serde(default)]
+        logging: Option<SfnLogging>,
+    },
+    #[serde(rename="aws_efs_file_system")]
+    EfsFileSystem {
+        name: String,
+        #[serde(default)]
+        encrypted: Option<bool>,
+        #[serde(default)]
+        kms_key_ref: Option<String>,
+        #[serde(default)]
+        performance_mode: Option<String>,
+        #[serde(default)]
+        throughput_mode: Option<String>,
+        /// Transition to IA, e.g. `AFTER_30_DAYS`.
+        #[serde(default)]
+        lifecycle_policy: Option<String>,
+    },
+    #[serde(rename="aws_efs_mount_target")]
+    EfsMountTarget {
+        name: String,
+        file_system_ref: String,
+        subnet_ref: String,
+        #[serde(default)]
+        security_group_refs: Vec<String>,
+    },
+    #[serde(rename="aws_elasticache_replication_group")]
+    ElastiCacheRedis {
+        name: String,
+        node_type: String,
+        #[serde(default)]
+        num_cache_clusters: Option<u32>,
+        #[serde(default)]
+        engine_version: Option<String>,
+        /// Subnets for an `aws_elasticache_subnet_group` emitted alongside the group.
+        #[serde(default)]
+        subnet_refs: Vec<String>,
+        #[serde(default)]
+        security_group_refs: Vec<String>,
+        #[serde(default)]
+        at_rest_encryption_enabled: Option<bool>,
+        #[serde(default)]
+        transit_encryption_enabled: Option<bool>,
+        #[serde(default)]
+        auth_token_from: Option<ValueFrom>,
+    },
 }
 
-impl AwsResource {
-    pub fn to_tf_json(&self) -> Json {
-        match self {
-            AwsResource::S3Bucket { name, bucket, force_destroy, kms_key_id } => {
-                let mut o = json!({
-                  "resource": { "aws_s3_bucket": {
-                      name: { "bucket": bucket, "force_destroy": force_destroy }
-                  }}
-                });
-                o["resource"]["aws_s3_bucket"][name]["bucket_encryption"] =
-                  if let Some(kms) = kms_key_id {
-                    json!({ "server_side_encryption_configuration": {
-                      "rule": { "apply_server_side_encryption_by_default": {
-                        "sse_algorithm":"aws:kms", "kms_master_key_id": kms
-                      }}}})
-                  } else {
-                    json!({ "server_side_encryption_configuration": {
-                      "rule": { "apply_server_side_encryption_by_default": {
-                        "sse_algorithm":"AES256"
-                      }}}})
-                  };
-                o
-            }
-            AwsResource::KmsKey { name, description, enable_key_rotation, deletion_window_in_days, key_usage, key_spec } => {
-                let mut body = json!({
-                    "enable_key_rotation": enable_key_rotation,
-                });
-                if let Some(desc) = description { body["description"] = json!(desc); }
-                if let Some(days) = deletion_window_in_days { body["deletion_window_in_days"] = json!(days); }
-                if let Some(u) = key_usage { body["key_usage"] = json!(u); }
-                if let Some(s) = key_spec { body["key_spec"] = json!(s); }
-                json!({
-                    "resource": { "aws_kms_key": {
-                        name: body
-                    }}
-                })
-            }
-            AwsResource::SecretsManagerSecret { name, description, kms_key_id, recovery_window_in_days, force_delete_without_recovery } => {
-                let mut body = json!({});
-                if let Some(desc) = description { body["description"] = json!(desc); }
-                if let Some(kms) = kms_key_id { body["kms_key_id"] = json!(kms); }
-                if let Some(days) = recovery_window_in_days { body["recovery_window_in_days"] = json!(days); }
-                if let Some(force) = force_delete_without_recovery { body["force_delete_without_recovery"] = json!(force); }
-                json!({
-                    "resource": { "aws_secretsmanager_secret": {
-                        name: body
-                    }}
-                })
-            }
+fn some_true() -> Option<bool> { Some(true) }
+
+/// Dead-letter wiring for an SQS queue; rendered as the JSON string `redrive_policy` expects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedrivePolicy {
+    pub dead_letter_target_arn: String,
+    pub max_receive_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3BucketPolicy {
+    #[serde(default)]
+    pub statements: Vec<PolicyStatement>,
+    /// Deny any request not made over TLS (`aws:SecureTransport = false`).
+    #[serde(default)]
+    pub enforce_tls_only: bool,
+}
+
+impl S3BucketPolicy {
+    /// Statements without resources apply to the bucket and all of its objects.
+    fn to_document(&self, bucket: &str) -> Result<String> {
+        let arn = format!("${{aws_s3_bucket.{}.arn}}", bucket);
+        let whole_bucket = vec![arn.clone(), format!("{}/*", arn)];
+        let mut statements: Vec<PolicyStatement> = self.statements.iter().cloned().map(|mut s| {
+            if s.resources.is_empty() { s.resources = whole_bucket.clone(); }
+            s
+        }).collect();
+        if self.enforce_tls_only {
+            let mut cond = JsonMap::new();
+            cond.insert("Bool".to_string(), json!({ "aws:SecureTransport": "false" }));
+            statements.push(PolicyStatement {
+                sid: Some("EnforceTLS".to_string()),
+                effect: "Deny".to_string(),
+                actions: vec!["s3:*".to_string()],
+                resources: whole_bucket,
+                conditions: Some(cond),
+                principals: Some(Principal::Any("*".to_string())),
+            });
         }
+        policy_document(&format!("aws_s3_bucket.{} bucket_policy", bucket), &statements)
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AwsAnyResource {
-    #[serde(rename="type")]
-    pub type_name: String,
-    pub name: String,
-    #[serde(flatten)]
-    pub properties: JsonMap<String, Json>,
+pub struct S3LifecycleRule {
+    pub id: String,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub transition_days: Option<u32>,
+    #[serde(default)]
+    pub storage_class: Option<String>,
+    #[serde(default)]
+    pub expiration_days: Option<u32>,
+    #[serde(default)]
+    pub noncurrent_version_expiration_days: Option<u32>,
 }
 
-impl AwsAnyResource {
-    pub fn to_tf_json(&self) -> Json {
-        let mut props = self.properties.clone();
-        if !props.contains_key("bucket") && !props.contains_key("name") {
-            // add a best-effort name/bucket to reduce typos causing missing identifier
+impl S3LifecycleRule {
+    fn to_tf_json(&self, owner: &str) -> Result<Json> {
+        let id = &self.id;
+        if self.transition_days.is_none() && self.expiration_days.is_none() && self.noncurrent_version_expiration_days.is_none() {
+            anyhow::bail!("{}: lifecycle rule '{}' has no transition or expiration", owner, id);
+        }
+        let mut rule = json!({
+            "id": id, "status": "Enabled",
+            "filter": [{ "prefix": self.prefix.clone().unwrap_or_default() }],
+        });
+        match (self.transition_days, &self.storage_class) {
+            (Some(days), Some(class)) => {
+                if let Some(exp) = self.expiration_days.filter(|exp| days >= *exp) {
+                    anyhow::bail!("{}: lifecycle rule '{}' transitions at day {} but expires at day {}", owner, id, days, exp);
+                }
+                rule["transition"] = json!([{ "days": days, "storage_class": class }]);
+            }
+            (None, None) => {}
+            _ => anyhow::bail!("{}: lifecycle rule '{}' needs both transition_days and storage_class", owner, id),
+        }
+        if let Some(days) = self.expiration_days { rule["expiration"] = json!([{ "days": days }]); }
+        if let Some(days) = self.noncurrent_version_expiration_days {
+            rule["noncurrent_version_expiration"] = json!([{ "noncurrent_days": days }]);
+        }
+        Ok(rule)
+    }
+}
+
+/// Where a database password comes from. There is deliberately no literal form,
+/// so a password can never end up in main.tf.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all="snake_case")]
+pub enum PasswordRef {
+    /// A sensitive Terraform variable of this name.
+    Var(String),
+    /// The current value of a Secrets Manager secret (sibling or ARN).
+    Secret(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DnsRecordType { A, AAAA, CAA, CNAME, DS, MX, NAPTR, NS, PTR, SOA, SPF, SRV, TXT }
+
+/// Alias target for a Route53 record. `name` may name a sibling `aws_lb`,
+/// in which case its DNS name and zone id are interpolated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route53Alias {
+    pub name: String,
+    #[serde(default)]
+    pub zone_id: Option<String>,
+    #[serde(default)]
+    pub evaluate_target_health: bool,
+}
+
+/// ECR encryption: `aes256`, or `{ kms: <key ref or ARN> }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all="snake_case")]
+pub enum EcrEncryption {
+    Aes256,
+    Kms(String),
+}
+
+/// Target group health check; every field falls back to the provider's usual default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthCheck {
+    pub path: String,
+    pub interval: u32,
+    pub healthy_threshold: u32,
+    pub unhealthy_threshold: u32,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self { Self { path: "/".to_string(), interval: 30, healthy_threshold: 3, unhealthy_threshold: 3 } }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheBehavior {
+    pub allowed_methods: Vec<String>,
+    pub cached_methods: Vec<String>,
+    pub viewer_protocol_policy: String,
+}
+
+impl Default for CacheBehavior {
+    fn default() -> Self {
+        let get_head = vec!["GET".to_string(), "HEAD".to_string()];
+        Self { allowed_methods: get_head.clone(), cached_methods: get_head, viewer_protocol_policy: "redirect-to-https".to_string() }
+    }
+}
+
+/// AWS-managed "CachingOptimized" cache policy.
+const CACHING_OPTIMIZED_POLICY_ID: &str = "658327ea-f89d-4fab-a63d-7e88639e58f6";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SsmParameterType { String, StringList, SecureString }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum KinesisStreamMode { Provisioned, OnDemand }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiCors {
+    #[serde(default)]
+    pub allow_origins: Vec<String>,
+    #[serde(default)]
+    pub allow_methods: Vec<String>,
+    #[serde(default)]
+    pub allow_headers: Vec<String>,
+    #[serde(default)]
+    pub max_age: Option<u32>,
+}
+
+/// An HTTP API route (`GET /items`, or `$default`) proxied to a Lambda function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiRoute {
+    pub route_key: String,
+    pub lambda_ref: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum SfnType { Standard, Express }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SfnLogging {
+    pub log_group_ref: String,
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub include_execution_data: bool,
+}
+
+/// An `ingress`/`egress` entry on a security group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityGroupRule {
+    pub from_port: u16,
+    pub to_port: u16,
+    pub protocol: String,
+    #[serde(default)]
+    pub cidr_blocks: Vec<String>,
+    #[serde(default)]
+    pub security_group_refs: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl SecurityGroupRule {
+    /// Rules are attributes-as-blocks in the provider, so the JSON form must
+    /// spell out every attribute, not just the ones that were set.
+    fn to_tf_json(&self, ctx: &RenderCtx, group: &str, owner: &str) -> Result<Json> {
+        if self.from_port > self.to_port {
+            anyhow::bail!("{}: from_port {} is greater than to_port {}", owner, self.from_port, self.to_port);
+        }
+        if self.protocol == "-1" && (self.from_port != 0 || self.to_port != 0) {
+            anyhow::bail!("{}: protocol -1 (all traffic) requires from_port and to_port of 0", owner);
+        }
+        let (mut v4, mut v6) = (Vec::new(), Vec::new());
+        for c in &self.cidr_blocks {
+            check_cidr(owner, c)?;
+            if c.contains(':') { v6.push(c) } else { v4.push(c) }
+        }
+        // Referencing the group itself would be a cycle; the provider spells that `self`.
+        let is_self = self.security_group_refs.iter().any(|r| r == group);
+        let groups: Vec<String> = self.security_group_refs.iter().filter(|r| *r != group)
+            .map(|r| ctx.resolve("aws_security_group", r, "id")).collect();
+        Ok(json!({
+            "from_port": self.from_port, "to_port": self.to_port, "protocol": self.protocol,
+            "cidr_blocks": v4, "ipv6_cidr_blocks": v6, "prefix_list_ids": [],
+            "security_groups": groups, "self": is_self,
+            "description": self.description.clone().unwrap_or_default(),
+        }))
+    }
+}
+
+/// One statement of an IAM policy document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyStatement {
+    #[serde(default)]
+    pub sid: Option<String>,
+    pub effect: String,
+    pub actions: Vec<String>,
+    #[serde(default)]
+    pub resources: Vec<String>,
+    #[serde(default)]
+    pub conditions: Option<JsonMap<String, Json>>,
+    /// Only meaningful on resource policies (buckets, keys).
+    #[serde(default)]
+    pub principals: Option<Principal>,
+}
+
+/// A KMS key policy: a list of statements, or a complete document given as a
+/// mapping or a JSON string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyPolicy {
+    Statements(Vec<PolicyStatement>),
+    Document(JsonMap<String, Json>),
+    Raw(String),
+}
+
+impl KeyPolicy {
+    fn to_document(&self, owner: &str) -> Result<String> {
+        match self {
+            KeyPolicy::Statements(statements) => {
+                for (i, st) in statements.iter().enumerate() {
+                    let empty = match &st.principals {
+                        None => true,
+                        Some(Principal::Any(_)) => false,
+                        Some(Principal::Typed(m)) => m.is_empty() || m.values().any(|v| v.is_empty()),
+                    };
+                    if empty {
+                        anyhow::bail!("{}: key policy statement {} must name at least one principal", owner, i);
+                    }
+                }
+                policy_document(owner, statements)
+            }
+            KeyPolicy::Document(doc) => Ok(Json::Object(doc.clone()).to_string()),
+            KeyPolicy::Raw(s) => {
+                let doc: Json = serde_json::from_str(s).with_context(|| format!("{}: policy is not valid JSON", owner))?;
+                Ok(doc.to_string())
+            }
+        }
+    }
+}
+
+/// `"*"`, or principals grouped by kind (`AWS`, `Service`, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Principal {
+    Any(String),
+    Typed(BTreeMap<String, Vec<String>>),
+}
+
+/// Serialize statements into a policy document string; `owner` prefixes errors.
+pub fn policy_document(owner: &str, statements: &[PolicyStatement]) -> Result<String> {
+    if statements.is_empty() {
+        anyhow::bail!("{}: policy must have at least one statement", owner);
+    }
+    let mut out = Vec::new();
+    for (i, st) in statements.iter().enumerate() {
+        if st.effect != "Allow" && st.effect != "Deny" {
+            anyhow::bail!("{}: statement {} has effect '{}'; expected 'Allow' or 'Deny'", owner, i, st.effect);
+        }
+        if st.actions.is_empty() {
+            anyhow::bail!("{}: statement {} has no actions", owner, i);
+        }
+        let mut o = json!({ "Effect": st.effect, "Action": st.actions });
+        if let Some(sid) = &st.sid { o["Sid"] = json!(sid); }
+        if !st.resources.is_empty() { o["Resource"] = json!(st.resources); }
+        if let Some(c) = &st.conditions { o["Condition"] = json!(c); }
+        match &st.principals {
+            Some(Principal::Any(p)) if p != "*" => {
+                anyhow::bail!("{}: statement {} principal '{}' must be \"*\" or a map of principal lists", owner, i, p);
+            }
+            Some(Principal::Any(p)) => o["Principal"] = json!(p),
+            Some(Principal::Typed(m)) => o["Principal"] = json!(m),
+            None => {}
+        }
+        out.push(o);
+    }
+    Ok(json!({ "Version": "2012-10-17", "Statement": out }).to_string())
+}
+
+/// Retention periods CloudWatch Logs accepts (0 means never expire).
+const LOG_RETENTION_DAYS: &[u32] = &[0, 1, 3, 5, 7, 14, 30, 60, 90, 120, 150, 180, 365, 400, 545, 731, 1096, 1827, 2192, 2557, 2922, 3288, 3653];
+
+/// Logical name for resources whose AWS name carries a `.fifo` suffix, which
+/// Terraform identifiers can't contain.
+fn fifo_key(name: &str) -> &str { name.strip_suffix(".fifo").unwrap_or(name) }
+
+/// `${type_name.r.attr}` for a sibling, `r` itself when it is already an ARN.
+fn arn_ref(ctx: &RenderCtx, type_name: &str, r: &str, attr: &str) -> Option<String> {
+    ctx.sibling(type_name, r, attr).or_else(|| r.starts_with("arn:").then(|| r.to_string()))
+}
+
+/// Alias names must live under `alias/`, and `alias/aws/` is reserved for AWS-managed keys.
+fn check_kms_alias(owner: &str, alias: &str) -> Result<()> {
+    if !alias.starts_with("alias/") {
+        anyhow::bail!("{}: alias '{}' must start with 'alias/'", owner, alias);
+    }
+    if alias.starts_with("alias/aws/") {
+        anyhow::bail!("{}: alias '{}' uses the reserved 'alias/aws/' prefix", owner, alias);
+    }
+    Ok(())
+}
+
+fn resolve_all(ctx: &RenderCtx, type_name: &str, refs: &[String], attr: &str) -> Vec<String> {
+    refs.iter().map(|r| ctx.resolve(type_name, r, attr)).collect()
+}
+
+/// Replace anything Terraform doesn't allow in an identifier with `_`.
+fn tf_ident(s: &str) -> String {
+    s.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+/// Identifier for the function a Lambda reference resolves to: the sibling name, or for
+/// an ARN the function name plus any version/alias qualifier (which a grant is scoped to).
+fn lambda_key(r: &str) -> String {
+    let parts: Vec<&str> = r.split(':').collect();
+    match parts.as_slice() {
+        ["arn", _, "lambda", _, _, "function", function] => tf_ident(function),
+        ["arn", _, "lambda", _, _, "function", function, qualifier] => tf_ident(&format!("{}-{}", function, qualifier)),
+        _ => tf_ident(r),
+    }
+}
+
+impl AwsResource {
+    /// Terraform type and logical name this resource is declared under.
+    pub fn type_and_name(&self) -> (&'static str, &str) {
+        match self {
+            AwsResource::S3Bucket { name, .. } => ("aws_s3_bucket", name),
+            AwsResource::KmsKey { name, .. } => ("aws_kms_key", name),
+            AwsResource::KmsAlias { name, .. } => ("aws_kms_alias", name),
+            AwsResource::SecretsManagerSecret { name, .. } => ("aws_secretsmanager_secret", name),
+            AwsResource::SqsQueue { name, .. } => ("aws_sqs_queue", fifo_key(name)),
+            AwsResource::SnsTopic { name, .. } => ("aws_sns_topic", fifo_key(name)),
+            AwsResource::SnsSubscription { name, .. } => ("aws_sns_topic_subscription", name),
+            AwsResource::IamRole { name, .. } => ("aws_iam_role", name),
+            AwsResource::IamPolicy { name, .. } => ("aws_iam_policy", name),
+            AwsResource::LambdaFunction { name, .. } => ("aws_lambda_function", name),
+            AwsResource::Vpc { name, .. } => ("aws_vpc", name),
+            AwsResource::Subnet { name, .. } => ("aws_subnet", name),
+            AwsResource::InternetGateway { name, .. } => ("aws_internet_gateway", name),
+            AwsResource::SecurityGroup { name, .. } => ("aws_security_group", name),
+            AwsResource::Ec2Instance { name, .. } => ("aws_instance", name),
+            AwsResource::RdsInstance { name, .. } => ("aws_db_instance", name),
+            AwsResource::CloudWatchLogGroup { name, .. } => ("aws_cloudwatch_log_group", name),
+            AwsResource::Route53Zone { name, .. } => ("aws_route53_zone", name),
+            AwsResource::Route53Record { name, .. } => ("aws_route53_record", name),
+            AwsResource::EcrRepository { name, .. } => ("aws_ecr_repository", name),
+            AwsResource::EksCluster { name, .. } => ("aws_eks_cluster", name),
+            AwsResource::EksNodeGroup { name, .. } => ("aws_eks_node_group", name),
+            AwsResource::Alb { name, .. } => ("aws_lb", name),
+            AwsResource::AlbTargetGroup { name, .. } => ("aws_lb_target_group", name),
+            AwsResource::AlbListener { name, .. } => ("aws_lb_listener", name),
+            AwsResource::CloudFrontDistribution { name, .. } => ("aws_cloudfront_distribution", name),
+            AwsResource::SecretsManagerSecretVersion { name, .. } => ("aws_secretsmanager_secret_version", name),
+            AwsResource::SsmParameter { name, .. } => ("aws_ssm_parameter", name),
+            AwsResource::EventBridgeRule { name, .. } => ("aws_cloudwatch_event_rule", name),
+            AwsResource::EventBridgeTarget { name, .. } => ("aws_cloudwatch_event_target", name),
+            AwsResource::KinesisStream { name, .. } => ("aws_kinesis_stream", name),
+            AwsResource::ApiGatewayHttpApi { name, .. } => ("aws_apigatewayv2_api", name),
+            AwsResource::SfnStateMachine { name, .. } => ("aws_sfn_state_machine", name),
+            AwsResource::EfsFileSystem { name, .. } => ("aws_efs_file_system", name),
+            AwsResource::EfsMountTarget { name, .. } => ("aws_efs_mount_target", name),
+            AwsResource::ElastiCacheRedis { name, .. } => ("aws_elasticache_replication_group", name),
+        }
+    }
+
+    pub fn to_tf_json(&self, ctx: &mut RenderCtx) -> Result<Json> {
+        Ok(match self {
+            AwsResource::S3Bucket { name, bucket, force_destroy, kms_key_id, bucket_key_enabled, versioning, lifecycle_rules, block_public_access, bucket_policy } => {
+                let mut o = json!({
+                  "resource": { "aws_s3_bucket": {
+                      name: { "bucket": bucket, "force_destroy": force_destroy }
+                  }}
+                });
+                // Provider v5 dropped inline encryption on aws_s3_bucket; it is a resource of its own.
+                let rule = if let Some(kms) = kms_key_id {
+                    json!({
+                      "apply_server_side_encryption_by_default": {
+                        "sse_algorithm":"aws:kms", "kms_master_key_id": ctx.resolve("aws_kms_key", kms, "arn")
+                      },
+                      "bucket_key_enabled": bucket_key_enabled.unwrap_or(true),
+                    })
+                } else {
+                    if bucket_key_enabled == &Some(true) {
+                        anyhow::bail!("aws_s3_bucket.{}: bucket_key_enabled requires kms_key_id", name);
+                    }
+                    json!({ "apply_server_side_encryption_by_default": { "sse_algorithm":"AES256" } })
+                };
+                o["resource"]["aws_s3_bucket_server_side_encryption_configuration"][name] = json!({
+                    "bucket": format!("${{aws_s3_bucket.{}.id}}", name),
+                    "rule": rule,
+                });
+                if let Some(enabled) = versioning {
+                    // Provider v5 manages versioning as its own resource, keyed like the bucket.
+                    o["resource"]["aws_s3_bucket_versioning"][name] = json!({
+                        "bucket": format!("${{aws_s3_bucket.{}.id}}", name),
+                        "versioning_configuration": { "status": if *enabled { "Enabled" } else { "Suspended" } },
+                    });
+                }
+                if block_public_access.unwrap_or(true) {
+                    o["resource"]["aws_s3_bucket_public_access_block"][name] = json!({
+                        "bucket": format!("${{aws_s3_bucket.{}.id}}", name),
+                        "block_public_acls": true, "block_public_policy": true,
+                        "ignore_public_acls": true, "restrict_public_buckets": true,
+                    });
+                }
+                if let Some(p) = bucket_policy {
+                    o["resource"]["aws_s3_bucket_policy"][name] = json!({
+                        "bucket": format!("${{aws_s3_bucket.{}.id}}", name),
+                        "policy": p.to_document(name)?,
+                    });
+                }
+                if !lifecycle_rules.is_empty() {
+                    let mut rules = Vec::new();
+                    for r in lifecycle_rules { rules.push(r.to_tf_json(&format!("aws_s3_bucket.{}", name))?); }
+                    o["resource"]["aws_s3_bucket_lifecycle_configuration"][name] = json!({
+                        "bucket": format!("${{aws_s3_bucket.{}.id}}", name),
+                        "rule": rules,
+                    });
+                }
+                o
+            }
+            AwsResource::KmsKey { name, description, enable_key_rotation, deletion_window_in_days, key_usage, key_spec, alias, policy, multi_region } => {
+                let mut body = json!({
+                    "enable_key_rotation": enable_key_rotation,
+                });
+                if let Some(desc) = description { body["description"] = json!(desc); }
+                if let Some(days) = deletion_window_in_days { body["deletion_window_in_days"] = json!(days); }
+                if let Some(u) = key_usage { body["key_usage"] = json!(u); }
+                if let Some(s) = key_spec { body["key_spec"] = json!(s); }
+                if let Some(p) = policy { body["policy"] = json!(p.to_document(&format!("aws_kms_key.{}", name))?); }
+                if let Some(m) = multi_region { body["multi_region"] = json!(m); }
+                let mut o = json!({
+                    "resource": { "aws_kms_key": {
+                        name: body
+                    }}
+                });
+                if let Some(a) = alias {
+                    check_kms_alias(&format!("aws_kms_key.{}", name), a)?;
+                    o["resource"]["aws_kms_alias"][name] = json!({
+                        "name": a, "target_key_id": format!("${{aws_kms_key.{}.key_id}}", name),
+                    });
+                }
+                o
+            }
+            AwsResource::KmsAlias { name, alias_name, target_key_ref } => {
+                check_kms_alias(&format!("aws_kms_alias.{}", name), alias_name)?;
+                json!({ "resource": { "aws_kms_alias": { name: {
+                    "name": alias_name, "target_key_id": ctx.resolve("aws_kms_key", target_key_ref, "key_id"),
+                } } } })
+            }
+            AwsResource::SecretsManagerSecret { name, description, kms_key_id, recovery_window_in_days, force_delete_without_recovery } => {
+                let mut body = json!({});
+                if let Some(desc) = description { body["description"] = json!(desc); }
+                if let Some(kms) = kms_key_id { body["kms_key_id"] = json!(kms); }
+                if let Some(days) = recovery_window_in_days { body["recovery_window_in_days"] = json!(days); }
+                if let Some(force) = force_delete_without_recovery { body["force_delete_without_recovery"] = json!(force); }
+                json!({
+                    "resource": { "aws_secretsmanager_secret": {
+                        name: body
+                    }}
+                })
+            }
+            AwsResource::SqsQueue { name, fifo_queue, visibility_timeout_seconds, message_retention_seconds, kms_master_key_id, redrive_policy } => {
+                // Terraform identifiers can't contain '.', so the logical name drops the suffix
+                // while the queue name gets it appended when missing.
+                let key = fifo_key(name);
+                if !*fifo_queue && key != name { anyhow::bail!("aws_sqs_queue.{}: a name ending in .fifo needs fifo_queue: true", key); }
+                let queue_name = if *fifo_queue { format!("{}.fifo", key) } else { name.clone() };
+                let mut body = json!({ "name": queue_name });
+                if *fifo_queue { body["fifo_queue"] = json!(true); }
+                if let Some(v) = visibility_timeout_seconds { body["visibility_timeout_seconds"] = json!(v); }
+                if let Some(v) = message_retention_seconds { body["message_retention_seconds"] = json!(v); }
+                if let Some(kms) = kms_master_key_id { body["kms_master_key_id"] = json!(kms); }
+                if let Some(rp) = redrive_policy {
+                    body["redrive_policy"] = json!(json!({
+                        "deadLetterTargetArn": rp.dead_letter_target_arn,
+                        "maxReceiveCount": rp.max_receive_count,
+                    }).to_string());
+                }
+                json!({
+                    "resource": { "aws_sqs_queue": {
+                        key: body
+                    }}
+                })
+            }
+            AwsResource::SnsTopic { name, display_name, kms_master_key_id, fifo_topic } => {
+                let key = fifo_key(name);
+                if !*fifo_topic && key != name {
+                    anyhow::bail!("aws_sns_topic.{}: a name ending in .fifo needs fifo_topic: true", key);
+                }
+                let topic_name = if *fifo_topic { format!("{}.fifo", key) } else { name.clone() };
+                let mut body = json!({ "name": topic_name });
+                if *fifo_topic { body["fifo_topic"] = json!(true); }
+                if let Some(d) = display_name { body["display_name"] = json!(d); }
+                if let Some(kms) = kms_master_key_id { body["kms_master_key_id"] = json!(kms); }
+                json!({
+                    "resource": { "aws_sns_topic": {
+                        key: body
+                    }}
+                })
+            }
+            AwsResource::SnsSubscription { name, topic_ref, protocol, endpoint, raw_message_delivery } => {
+                let topic_arn = arn_ref(ctx, "aws_sns_topic", topic_ref, "arn").ok_or_else(|| anyhow::anyhow!(
+                    "aws_sns_topic_subscription.{}: topic_ref '{}' is neither a sibling aws_sns_topic nor an ARN", name, topic_ref))?;
+                let endpoint = if protocol == "sqs" { ctx.resolve("aws_sqs_queue", endpoint, "arn") } else { endpoint.clone() };
+                let mut body = json!({ "topic_arn": topic_arn, "protocol": protocol, "endpoint": endpoint });
+                if let Some(raw) = raw_message_delivery { body["raw_message_delivery"] = json!(raw); }
+                json!({
+                    "resource": { "aws_sns_topic_subscription": {
+                        name: body
+                    }}
+                })
+            }
+            AwsResource::IamRole { name, service_principals, account_ids, max_session_duration, managed_policy_arns } => {
+                if service_principals.is_empty() && account_ids.is_empty() {
+                    anyhow::bail!("aws_iam_role.{}: at least one service principal or account id is required", name);
+                }
+                let mut principal = json!({});
+                if !service_principals.is_empty() { principal["Service"] = json!(service_principals); }
+                if !account_ids.is_empty() {
+                    let mut arns = Vec::new();
+                    for id in account_ids {
+                        if id.len() != 12 || !id.chars().all(|c| c.is_ascii_digit()) {
+                            anyhow::bail!("aws_iam_role.{}: account id '{}' must be 12 digits", name, id);
+                        }
+                        arns.push(format!("arn:aws:iam::{}:root", id));
+                    }
+                    principal["AWS"] = json!(arns);
+                }
+                let doc = json!({
+                    "Version": "2012-10-17",
+                    "Statement": [{ "Effect": "Allow", "Principal": principal, "Action": "sts:AssumeRole" }],
+                });
+                let mut body = json!({ "name": name, "assume_role_policy": doc.to_string() });
+                if let Some(d) = max_session_duration { body["max_session_duration"] = json!(d); }
+                let mut o = json!({ "resource": { "aws_iam_role": { name: body } } });
+                let mut keys = BTreeMap::new();
+                for arn in managed_policy_arns {
+                    // Key attachments by policy name so reordering the list doesn't churn addresses.
+                    let key = format!("{}-{}", name, tf_ident(arn.rsplit('/').next().unwrap_or(arn)));
+                    if let Some(other) = keys.insert(key.clone(), arn) {
+                        anyhow::bail!("aws_iam_role.{}: managed policy '{}' attaches as '{}', same as '{}'", name, arn, key, other);
+                    }
+                    o["resource"]["aws_iam_role_policy_attachment"][key] = json!({
+                        "role": format!("${{aws_iam_role.{}.name}}", name),
+                        "policy_arn": ctx.resolve("aws_iam_policy", arn, "arn"),
+                    });
+                }
+                o
+            }
+            AwsResource::IamPolicy { name, description, statements } => {
+                if statements.iter().any(|s| s.principals.is_some()) {
+                    anyhow::bail!("aws_iam_policy.{}: identity policies cannot name principals", name);
+                }
+                let mut body = json!({ "name": name, "policy": policy_document(&format!("aws_iam_policy.{}", name), statements)? });
+                if let Some(d) = description { body["description"] = json!(d); }
+                json!({ "resource": { "aws_iam_policy": { name: body } } })
+            }
+            AwsResource::LambdaFunction { name, function_name, runtime, handler, role, filename, memory_size, timeout, environment } => {
+                let role_arn = arn_ref(ctx, "aws_iam_role", role, "arn").ok_or_else(|| anyhow::anyhow!(
+                    "aws_lambda_function.{}: role '{}' is neither a sibling aws_iam_role nor an ARN", name, role))?;
+                // Terraform runs with -chdir into the out dir, so a relative path would
+                // resolve against the wrong directory; render the absolute one.
+                let path = std::fs::canonicalize(filename)
+                    .with_context(|| format!("aws_lambda_function.{}: read package {}", name, filename))?;
+                let path_str = path.to_str().ok_or_else(|| anyhow::anyhow!(
+                    "aws_lambda_function.{}: package path {} is not valid UTF-8", name, path.display()))?;
+                // Hash the package here so Terraform sees code changes even when the path doesn't change.
+                let zip = std::fs::read(&path)
+                    .with_context(|| format!("aws_lambda_function.{}: read package {}", name, path.display()))?;
+                let hash = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(&zip));
+                let mut body = json!({
+                    "function_name": function_name, "runtime": runtime, "handler": handler,
+                    "role": role_arn, "filename": path_str, "source_code_hash": hash,
+                });
+                if let Some(m) = memory_size { body["memory_size"] = json!(m); }
+                if let Some(t) = timeout { body["timeout"] = json!(t); }
+                if !environment.is_empty() { body["environment"] = json!({ "variables": environment }); }
+                json!({ "resource": { "aws_lambda_function": { name: body } } })
+            }
+            AwsResource::Vpc { name, cidr_block, enable_dns_support, enable_dns_hostnames, tags } => {
+                check_cidr(&format!("aws_vpc.{}", name), cidr_block)?;
+                let mut body = json!({ "cidr_block": cidr_block });
+                if let Some(v) = enable_dns_support { body["enable_dns_support"] = json!(v); }
+                if let Some(v) = enable_dns_hostnames { body["enable_dns_hostnames"] = json!(v); }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "aws_vpc": { name: body } } })
+            }
+            AwsResource::Subnet { name, vpc_ref, cidr_block, availability_zone, map_public_ip_on_launch } => {
+                // Keyed by the VPC as written, so a sibling and a literal id are told apart.
+                ctx.claim_cidr(&format!("aws_vpc.{}", vpc_ref), &format!("aws_subnet.{}", name), cidr_block)?;
+                let mut body = json!({ "vpc_id": ctx.resolve("aws_vpc", vpc_ref, "id"), "cidr_block": cidr_block });
+                if let Some(az) = availability_zone { body["availability_zone"] = json!(az); }
+                if let Some(v) = map_public_ip_on_launch { body["map_public_ip_on_launch"] = json!(v); }
+                json!({ "resource": { "aws_subnet": { name: body } } })
+            }
+            AwsResource::InternetGateway { name, vpc_ref } => json!({
+                "resource": { "aws_internet_gateway": { name: { "vpc_id": ctx.resolve("aws_vpc", vpc_ref, "id") } } }
+            }),
+            AwsResource::SecurityGroup { name, vpc_ref, description, ingress, egress } => {
+                let mut body = json!({ "name": name, "vpc_id": ctx.resolve("aws_vpc", vpc_ref, "id") });
+                if let Some(d) = description { body["description"] = json!(d); }
+                for (dir, rules) in [("ingress", ingress), ("egress", egress)] {
+                    let mut out = Vec::new();
+                    for (i, r) in rules.iter().enumerate() {
+                        out.push(r.to_tf_json(ctx, name, &format!("aws_security_group.{} {} rule {}", name, dir, i))?);
+                    }
+                    body[dir] = json!(out);
+                }
+                json!({ "resource": { "aws_security_group": { name: body } } })
+            }
+            AwsResource::Ec2Instance { name, ami, instance_type, subnet_ref, key_name, security_group_refs, user_data, user_data_base64, root_volume_size_gb, root_volume_encrypted, tags } => {
+                let mut body = json!({ "ami": ami, "instance_type": instance_type });
+                if let Some(s) = subnet_ref { body["subnet_id"] = json!(ctx.resolve("aws_subnet", s, "id")); }
+                if let Some(k) = key_name { body["key_name"] = json!(k); }
+                if !security_group_refs.is_empty() {
+                    body["vpc_security_group_ids"] = json!(resolve_all(ctx, "aws_security_group", security_group_refs, "id"));
+                }
+                let b64 = base64::engine::general_purpose::STANDARD;
+                match (user_data, user_data_base64) {
+                    (Some(_), Some(_)) => anyhow::bail!("aws_instance.{}: set only one of user_data and user_data_base64", name),
+                    (Some(ud), None) => body["user_data_base64"] = json!(b64.encode(ud)),
+                    (None, Some(ud)) => {
+                        b64.decode(ud.trim()).map_err(|e| anyhow::anyhow!("aws_instance.{}: user_data_base64 is not valid base64: {}", name, e))?;
+                        body["user_data_base64"] = json!(ud.trim());
+                    }
+                    (None, None) => {}
+                }
+                let mut root = json!({ "encrypted": root_volume_encrypted.unwrap_or(true) });
+                if let Some(size) = root_volume_size_gb { root["volume_size"] = json!(size); }
+                body["root_block_device"] = root;
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "aws_instance": { name: body } } })
+            }
+            AwsResource::RdsInstance { name, engine, engine_version, instance_class, allocated_storage, db_name, username, password_ref, storage_encrypted, kms_key_id, multi_az, skip_final_snapshot, final_snapshot_identifier, deletion_protection } => {
+                if !skip_final_snapshot && final_snapshot_identifier.is_none() {
+                    anyhow::bail!("aws_db_instance.{}: final_snapshot_identifier is required unless skip_final_snapshot is true", name);
+                }
+                let mut o = json!({});
+                let password = match password_ref {
+                    PasswordRef::Var(v) => {
+                        o["variable"][v] = json!({ "type": "string", "sensitive": true });
+                        format!("${{var.{}}}", v)
+                    }
+                    PasswordRef::Secret(r) => {
+                        let key = format!("{}-password", name);
+                        o["data"]["aws_secretsmanager_secret_version"][&key] =
+                            json!({ "secret_id": ctx.resolve("aws_secretsmanager_secret", r, "id") });
+                        format!("${{data.aws_secretsmanager_secret_version.{}.secret_string}}", key)
+                    }
+                };
+                let mut body = json!({
+                    "engine": engine, "instance_class": instance_class, "allocated_storage": allocated_storage,
+                    "username": username, "password": password,
+                    "storage_encrypted": storage_encrypted.unwrap_or(true),
+                    "skip_final_snapshot": skip_final_snapshot,
+                });
+                if let Some(v) = engine_version { body["engine_version"] = json!(v); }
+                if let Some(d) = db_name { body["db_name"] = json!(d); }
+                if let Some(k) = kms_key_id { body["kms_key_id"] = json!(ctx.resolve("aws_kms_key", k, "arn")); }
+                if let Some(m) = multi_az { body["multi_az"] = json!(m); }
+                if let Some(f) = final_snapshot_identifier { body["final_snapshot_identifier"] = json!(f); }
+                if let Some(d) = deletion_protection { body["deletion_protection"] = json!(d); }
+                o["resource"]["aws_db_instance"][name] = body;
+                o
+            }
+            AwsResource::CloudWatchLogGroup { name, log_group_name, retention_in_days, kms_key_id } => {
+                let mut body = json!({ "name": log_group_name });
+                if let Some(days) = retention_in_days {
+                    if !LOG_RETENTION_DAYS.contains(days) {
+                        anyhow::bail!("aws_cloudwatch_log_group.{}: retention_in_days {} is not allowed; use one of {:?}", name, days, LOG_RETENTION_DAYS);
+                    }
+                    body["retention_in_days"] = json!(days);
+                }
+                if let Some(k) = kms_key_id {
+                    body["kms_key_id"] = json!(arn_ref(ctx, "aws_kms_key", k, "arn").ok_or_else(|| anyhow::anyhow!(
+                        "aws_cloudwatch_log_group.{}: kms_key_id '{}' is neither a sibling aws_kms_key nor an ARN", name, k))?);
+                }
+                json!({ "resource": { "aws_cloudwatch_log_group": { name: body } } })
+            }
+            AwsResource::Route53Zone { name, zone_name, private, vpc_ref } => {
+                let mut body = json!({ "name": zone_name });
+                match (private, vpc_ref) {
+                    (true, Some(v)) => body["vpc"] = json!([{ "vpc_id": ctx.resolve("aws_vpc", v, "id") }]),
+                    (true, None) => anyhow::bail!("aws_route53_zone.{}: private zones require vpc_ref", name),
+                    (false, Some(_)) => anyhow::bail!("aws_route53_zone.{}: vpc_ref is only valid on private zones", name),
+                    (false, None) => {}
+                }
+                json!({ "resource": { "aws_route53_zone": { name: body } } })
+            }
+            AwsResource::Route53Record { name, zone_ref, record_name, record_type, ttl, records, alias } => {
+                let mut body = json!({
+                    "zone_id": ctx.resolve("aws_route53_zone", zone_ref, "zone_id"),
+                    "name": record_name, "type": record_type,
+                });
+                if let Some(a) = alias {
+                    if ttl.is_some() || !records.is_empty() {
+                        anyhow::bail!("aws_route53_record.{}: alias records cannot set ttl or records", name);
+                    }
+                    let (target, zone_id) = if ctx.has("aws_lb", &a.name) {
+                        (format!("${{aws_lb.{}.dns_name}}", a.name), a.zone_id.clone().unwrap_or(format!("${{aws_lb.{}.zone_id}}", a.name)))
+                    } else {
+                        let zone_id = a.zone_id.clone().ok_or_else(|| anyhow::anyhow!(
+                            "aws_route53_record.{}: alias to '{}' needs zone_id unless it names a sibling aws_lb", name, a.name))?;
+                        (a.name.clone(), zone_id)
+                    };
+                    body["alias"] = json!([{ "name": target, "zone_id": zone_id, "evaluate_target_health": a.evaluate_target_health }]);
+                } else {
+                    let ttl = ttl.ok_or_else(|| anyhow::anyhow!("aws_route53_record.{}: ttl is required for non-alias records", name))?;
+                    if records.is_empty() {
+                        anyhow::bail!("aws_route53_record.{}: records is required for non-alias records", name);
+                    }
+                    body["ttl"] = json!(ttl);
+                    body["records"] = json!(records);
+                }
+                json!({ "resource": { "aws_route53_record": { name: body } } })
+            }
+            AwsResource::EcrRepository { name, image_tag_mutability, scan_on_push, encryption, keep_last_n_images } => {
+                let mut body = json!({
+                    "name": name,
+                    "image_scanning_configuration": { "scan_on_push": scan_on_push },
+                });
+                if let Some(m) = image_tag_mutability { body["image_tag_mutability"] = json!(m); }
+                match encryption {
+                    Some(EcrEncryption::Aes256) => body["encryption_configuration"] = json!({ "encryption_type": "AES256" }),
+                    Some(EcrEncryption::Kms(k)) => body["encryption_configuration"] = json!({
+                        "encryption_type": "KMS", "kms_key": ctx.resolve("aws_kms_key", k, "arn"),
+                    }),
+                    None => {}
+                }
+                let mut o = json!({ "resource": { "aws_ecr_repository": { name: body } } });
+                if let Some(n) = keep_last_n_images {
+                    if *n == 0 {
+                        anyhow::bail!("aws_ecr_repository.{}: keep_last_n_images must be at least 1", name);
+                    }
+                    let policy = json!({ "rules": [{
+                        "rulePriority": 1,
+                        "description": format!("Keep last {} images", n),
+                        "selection": { "tagStatus": "any", "countType": "imageCountMoreThan", "countNumber": n },
+                        "action": { "type": "expire" },
+                    }]});
+                    o["resource"]["aws_ecr_lifecycle_policy"][name] = json!({
+                        "repository": format!("${{aws_ecr_repository.{}.name}}", name),
+                        "policy": policy.to_string(),
+                    });
+                }
+                o
+            }
+            AwsResource::EksCluster { name, role_ref, version, subnet_refs, endpoint_private_access, endpoint_public_access, enabled_log_types } => {
+                let role_arn = arn_ref(ctx, "aws_iam_role", role_ref, "arn").ok_or_else(|| anyhow::anyhow!(
+                    "aws_eks_cluster.{}: role_ref '{}' is neither a sibling aws_iam_role nor an ARN", name, role_ref))?;
+                let mut vpc = json!({ "subnet_ids": resolve_all(ctx, "aws_subnet", subnet_refs, "id") });
+                if let Some(v) = endpoint_private_access { vpc["endpoint_private_access"] = json!(v); }
+                if let Some(v) = endpoint_public_access { vpc["endpoint_public_access"] = json!(v); }
+                let mut body = json!({ "name": name, "role_arn": role_arn, "vpc_config": vpc });
+                if let Some(v) = version { body["version"] = json!(v); }
+                if !enabled_log_types.is_empty() { body["enabled_cluster_log_types"] = json!(enabled_log_types); }
+                json!({ "resource": { "aws_eks_cluster": { name: body } } })
+            }
+            AwsResource::EksNodeGroup { name, cluster_ref, node_role_ref, subnet_refs, instance_types, desired_size, min_size, max_size } => {
+                if !(min_size <= desired_size && desired_size <= max_size) {
+                    anyhow::bail!("aws_eks_node_group.{}: sizes must satisfy min_size ({}) <= desired_size ({}) <= max_size ({})",
+                        name, min_size, desired_size, max_size);
+                }
+                let role_arn = arn_ref(ctx, "aws_iam_role", node_role_ref, "arn").ok_or_else(|| anyhow::anyhow!(
+                    "aws_eks_node_group.{}: node_role_ref '{}' is neither a sibling aws_iam_role nor an ARN", name, node_role_ref))?;
+                let mut body = json!({
+                    "cluster_name": ctx.resolve("aws_eks_cluster", cluster_ref, "name"),
+                    "node_group_name": name,
+                    "node_role_arn": role_arn,
+                    "subnet_ids": resolve_all(ctx, "aws_subnet", subnet_refs, "id"),
+                    "scaling_config": { "desired_size": desired_size, "min_size": min_size, "max_size": max_size },
+                });
+                if !instance_types.is_empty() { body["instance_types"] = json!(instance_types); }
+                json!({ "resource": { "aws_eks_node_group": { name: body } } })
+            }
+            AwsResource::Alb { name, internal, subnet_refs, security_group_refs } => {
+                let mut body = json!({
+                    "name": name, "load_balancer_type": "application", "internal": internal,
+                    "subnets": resolve_all(ctx, "aws_subnet", subnet_refs, "id"),
+                });
+                if !security_group_refs.is_empty() {
+                    body["security_groups"] = json!(resolve_all(ctx, "aws_security_group", security_group_refs, "id"));
+                }
+                json!({ "resource": { "aws_lb": { name: body } } })
+            }
+            AwsResource::AlbTargetGroup { name, port, protocol, vpc_ref, target_type, health_check } => {
+                let mut body = json!({
+                    "name": name, "port": port, "protocol": protocol,
+                    "vpc_id": ctx.resolve("aws_vpc", vpc_ref, "id"),
+                    "health_check": {
+                        "path": health_check.path, "interval": health_check.interval,
+                        "healthy_threshold": health_check.healthy_threshold,
+                        "unhealthy_threshold": health_check.unhealthy_threshold,
+                    },
+                });
+                if let Some(t) = target_type { body["target_type"] = json!(t); }
+                json!({ "resource": { "aws_lb_target_group": { name: body } } })
+            }
+            AwsResource::AlbListener { name, load_balancer_ref, port, protocol, certificate_arn, ssl_policy, target_group_ref } => {
+                if protocol == "HTTPS" && certificate_arn.is_none() {
+                    anyhow::bail!("aws_lb_listener.{}: HTTPS listeners require certificate_arn", name);
+                }
+                let mut body = json!({
+                    "load_balancer_arn": ctx.resolve("aws_lb", load_balancer_ref, "arn"),
+                    "port": port, "protocol": protocol,
+                    "default_action": [{
+                        "type": "forward",
+                        "target_group_arn": ctx.resolve("aws_lb_target_group", target_group_ref, "arn"),
+                    }],
+                });
+                if let Some(c) = certificate_arn { body["certificate_arn"] = json!(c); }
+                if let Some(p) = ssl_policy { body["ssl_policy"] = json!(p); }
+                json!({ "resource": { "aws_lb_listener": { name: body } } })
+            }
+            AwsResource::CloudFrontDistribution { name, origin, default_cache_behavior, price_class, aliases, acm_certificate_arn, default_root_object } => {
+                let mut o = json!({});
+                let origin_block = if ctx.has("aws_s3_bucket", origin) {
+                    // Private bucket: CloudFront signs its requests through an origin access control.
+                    o["resource"]["aws_cloudfront_origin_access_control"][name] = json!({
+                        "name": name, "origin_access_control_origin_type": "s3",
+                        "signing_behavior": "always", "signing_protocol": "sigv4",
+                    });
+                    json!({
+                        "origin_id": origin,
+                        "domain_name": format!("${{aws_s3_bucket.{}.bucket_regional_domain_name}}", origin),
+                        "origin_access_control_id": format!("${{aws_cloudfront_origin_access_control.{}.id}}", name),
+                    })
+                } else {
+                    json!({
+                        "origin_id": origin, "domain_name": origin,
+                        "custom_origin_config": [{
+                            "http_port": 80, "https_port": 443,
+                            "origin_protocol_policy": "https-only", "origin_ssl_protocols": ["TLSv1.2"],
+                        }],
+                    })
+                };
+                let viewer_certificate = match acm_certificate_arn {
+                    Some(arn) => json!({ "acm_certificate_arn": arn, "ssl_support_method": "sni-only", "minimum_protocol_version": "TLSv1.2_2021" }),
+                    None if !aliases.is_empty() => anyhow::bail!("aws_cloudfront_distribution.{}: aliases require acm_certificate_arn", name),
+                    None => json!({ "cloudfront_default_certificate": true }),
+                };
+                let mut body = json!({
+                    "enabled": true,
+                    "origin": [origin_block],
+                    "default_cache_behavior": [{
+                        "target_origin_id": origin,
+                        "allowed_methods": default_cache_behavior.allowed_methods,
+                        "cached_methods": default_cache_behavior.cached_methods,
+                        "viewer_protocol_policy": default_cache_behavior.viewer_protocol_policy,
+                        "cache_policy_id": CACHING_OPTIMIZED_POLICY_ID,
+                    }],
+                    "restrictions": [{ "geo_restriction": [{ "restriction_type": "none" }] }],
+                    "viewer_certificate": [viewer_certificate],
+                });
+                if let Some(p) = price_class { body["price_class"] = json!(p); }
+                if !aliases.is_empty() { body["aliases"] = json!(aliases); }
+                if let Some(r) = default_root_object { body["default_root_object"] = json!(r); }
+                o["resource"]["aws_cloudfront_distribution"][name] = body;
+                o
+            }
+            AwsResource::SecretsManagerSecretVersion { name, secret_ref, value_from } => {
+                let value = ctx.secret_var(&secret_var_name("aws_secretsmanager_secret_version", name), value_from);
+                json!({ "resource": { "aws_secretsmanager_secret_version": { name: {
+                    "secret_id": ctx.resolve("aws_secretsmanager_secret", secret_ref, "id"),
+                    "secret_string": value,
+                } } } })
+            }
+            AwsResource::SsmParameter { name, parameter_name, parameter_type, value, value_from, kms_key_id, tier, description } => {
+                let first = parameter_name.trim_start_matches('/').to_ascii_lowercase();
+                if first.starts_with("aws") || first.starts_with("ssm") {
+                    anyhow::bail!("aws_ssm_parameter.{}: parameter names starting with 'aws' or 'ssm' are reserved", name);
+                }
+                let secure = *parameter_type == SsmParameterType::SecureString;
+                if kms_key_id.is_some() && !secure {
+                    anyhow::bail!("aws_ssm_parameter.{}: kms_key_id is only valid for SecureString parameters", name);
+                }
+                let value = match (value, value_from) {
+                    (Some(_), _) if secure => anyhow::bail!("aws_ssm_parameter.{}: SecureString values must use value_from", name),
+                    (Some(v), None) => v.clone(),
+                    (None, Some(from)) => ctx.secret_var(&secret_var_name("aws_ssm_parameter", name), from),
+                    _ => anyhow::bail!("aws_ssm_parameter.{}: set exactly one of value or value_from", name),
+                };
+                let mut body = json!({ "name": parameter_name, "type": parameter_type, "value": value });
+                if let Some(k) = kms_key_id { body["key_id"] = json!(ctx.resolve("aws_kms_key", k, "arn")); }
+                if let Some(t) = tier { body["tier"] = json!(t); }
+                if let Some(d) = description { body["description"] = json!(d); }
+                json!({ "resource": { "aws_ssm_parameter": { name: body } } })
+            }
+            AwsResource::EventBridgeRule { name, schedule_expression, event_pattern, state, description } => {
+                let mut body = json!({ "name": name });
+                match (schedule_expression, event_pattern) {
+                    (Some(s), None) => body["schedule_expression"] = json!(s),
+                    (None, Some(p)) => body["event_pattern"] = json!(p.to_string()),
+                    _ => anyhow::bail!("aws_cloudwatch_event_rule.{}: set exactly one of schedule_expression or event_pattern", name),
+                }
+                if let Some(s) = state { body["state"] = json!(s); }
+                if let Some(d) = description { body["description"] = json!(d); }
+                json!({ "resource": { "aws_cloudwatch_event_rule": { name: body } } })
+            }
+            AwsResource::EventBridgeTarget { name, rule_ref, arn_or_ref, role_ref, input } => {
+                let arn = ["aws_lambda_function", "aws_sns_topic", "aws_sqs_queue"].iter()
+                    .find_map(|t| ctx.sibling(t, arn_or_ref, "arn"))
+                    .or_else(|| arn_or_ref.starts_with("arn:").then(|| arn_or_ref.clone()))
+                    .ok_or_else(|| anyhow::anyhow!(
+                        "aws_cloudwatch_event_target.{}: '{}' is neither a sibling Lambda/SNS/SQS resource nor an ARN", name, arn_or_ref))?;
+                let mut body = json!({ "rule": ctx.resolve("aws_cloudwatch_event_rule", rule_ref, "name"), "arn": arn });
+                if let Some(r) = role_ref {
+                    body["role_arn"] = json!(arn_ref(ctx, "aws_iam_role", r, "arn").ok_or_else(|| anyhow::anyhow!(
+                        "aws_cloudwatch_event_target.{}: role_ref '{}' is neither a sibling aws_iam_role nor an ARN", name, r))?);
+                }
+                if let Some(i) = input { body["input"] = json!(i.to_string()); }
+                json!({ "resource": { "aws_cloudwatch_event_target": { name: body } } })
+            }
+            AwsResource::KinesisStream { name, shard_count, stream_mode, retention_period_hours, encryption_type, kms_key_ref } => {
+                let mode = stream_mode.unwrap_or(KinesisStreamMode::Provisioned);
+                let mut body = json!({ "name": name, "stream_mode_details": { "stream_mode": mode } });
+                match (mode, shard_count) {
+                    (KinesisStreamMode::Provisioned, Some(n)) => body["shard_count"] = json!(n),
+                    (KinesisStreamMode::Provisioned, None) => anyhow::bail!("aws_kinesis_stream.{}: PROVISIONED streams require shard_count", name),
+                    (KinesisStreamMode::OnDemand, Some(_)) => anyhow::bail!("aws_kinesis_stream.{}: ON_DEMAND streams cannot set shard_count", name),
+                    (KinesisStreamMode::OnDemand, None) => {}
+                }
+                if let Some(h) = retention_period_hours {
+                    if !(24..=8760).contains(h) {
+                        anyhow::bail!("aws_kinesis_stream.{}: retention_period_hours {} must be between 24 and 8760", name, h);
+                    }
+                    body["retention_period"] = json!(h);
+                }
+                let enc = encryption_type.clone().or_else(|| kms_key_ref.as_ref().map(|_| "KMS".to_string()));
+                if let Some(e) = enc { body["encryption_type"] = json!(e); }
+                if let Some(k) = kms_key_ref { body["kms_key_id"] = json!(ctx.resolve("aws_kms_key", k, "arn")); }
+                json!({ "resource": { "aws_kinesis_stream": { name: body } } })
+            }
+            AwsResource::ApiGatewayHttpApi { name, protocol_type, cors, routes } => {
+                let api_id = format!("${{aws_apigatewayv2_api.{}.id}}", name);
+                let mut api = json!({ "name": name, "protocol_type": protocol_type.as_deref().unwrap_or("HTTP") });
+                if let Some(c) = cors {
+                    let mut cfg = json!({});
+                    if !c.allow_origins.is_empty() { cfg["allow_origins"] = json!(c.allow_origins); }
+                    if !c.allow_methods.is_empty() { cfg["allow_methods"] = json!(c.allow_methods); }
+                    if !c.allow_headers.is_empty() { cfg["allow_headers"] = json!(c.allow_headers); }
+                    if let Some(m) = c.max_age { cfg["max_age"] = json!(m); }
+                    api["cors_configuration"] = cfg;
+                }
+                let mut o = json!({ "resource": {
+                    "aws_apigatewayv2_api": { name: api },
+                    "aws_apigatewayv2_stage": { name: { "api_id": api_id, "name": "$default", "auto_deploy": true } },
+                }});
+                let mut seen = std::collections::BTreeSet::new();
+                let mut keys = std::collections::BTreeMap::new();
+                for route in routes {
+                    let owner = format!("aws_apigatewayv2_api.{} route '{}'", name, route.route_key);
+                    let valid_key = route.route_key == "$default" || route.route_key.split_once(' ')
+                        .is_some_and(|(m, p)| !m.is_empty() && p.starts_with('/'));
+                    if !valid_key {
+                        anyhow::bail!("{}: route_key must be '$default' or 'METHOD /path'", owner);
+                    }
+                    if !seen.insert(route.route_key.as_str()) {
+                        anyhow::bail!("{}: duplicate route_key", owner);
+                    }
+                    let function_arn = arn_ref(ctx, "aws_lambda_function", &route.lambda_ref, "arn").ok_or_else(|| anyhow::anyhow!(
+                        "{}: lambda_ref '{}' is neither a sibling aws_lambda_function nor an ARN", owner, route.lambda_ref))?;
+                    let key = format!("{}-{}", name, tf_ident(&route.route_key.replace(['$', '{', '}'], "")));
+                    if let Some(other) = keys.insert(key.clone(), route.route_key.as_str()) {
+                        anyhow::bail!("{}: renders as '{}', same as route '{}'", owner, key, other);
+                    }
+                    o["resource"]["aws_apigatewayv2_integration"][&key] = json!({
+                        "api_id": api_id, "integration_type": "AWS_PROXY",
+                        "integration_uri": function_arn, "payload_format_version": "2.0",
+                    });
+                    o["resource"]["aws_apigatewayv2_route"][&key] = json!({
+                        "api_id": api_id, "route_key": route.route_key,
+                        "target": format!("integrations/${{aws_apigatewayv2_integration.{}.id}}", key),
+                    });
+                    // One grant per function covers every route that targets it.
+                    let perm_key = format!("{}-{}", name, lambda_key(&route.lambda_ref));
+                    o["resource"]["aws_lambda_permission"][&perm_key] = json!({
+                        "statement_id": format!("AllowInvokeFrom-{}", tf_ident(name)),
+                        "action": "lambda:InvokeFunction",
+                        "function_name": function_arn,
+                        "principal": "apigateway.amazonaws.com",
+                        "source_arn": format!("${{aws_apigatewayv2_api.{}.execution_arn}}/*/*", name),
+                    });
+                }
+                o
+            }
+            AwsResource::SfnStateMachine { name, role_ref, definition, machine_type, logging } => {
+                let start = definition.get("StartAt").and_then(|s| s.as_str()).ok_or_else(|| anyhow::anyhow!(
+                    "aws_sfn_state_machine.{}: definition needs a StartAt state name", name))?;
+                let states = definition.get("States").and_then(|s| s.as_object()).ok_or_else(|| anyhow::anyhow!(
+                    "aws_sfn_state_machine.{}: definition needs a States map", name))?;
+                if !states.contains_key(start) {
+                    anyhow::bail!("aws_sfn_state_machine.{}: StartAt '{}' is not one of the States", name, start);
+                }
+                let role_arn = arn_ref(ctx, "aws_iam_role", role_ref, "arn").ok_or_else(|| anyhow::anyhow!(
+                    "aws_sfn_state_machine.{}: role_ref '{}' is neither a sibling aws_iam_role nor an ARN", name, role_ref))?;
+                let mut body = json!({ "name": name, "role_arn": role_arn, "definition": definition.to_string() });
+                if let Some(t) = machine_type { body["type"] = json!(t); }
+                if let Some(l) = logging {
+                    let group = arn_ref(ctx, "aws_cloudwatch_log_group", &l.log_group_ref, "arn").ok_or_else(|| anyhow::anyhow!(
+                        "aws_sfn_state_machine.{}: log_group_ref '{}' is neither a sibling aws_cloudwatch_log_group nor an ARN", name, l.log_group_ref))?;
+                    body["logging_configuration"] = json!({
+                        "log_destination": format!("{}:*", group.trim_end_matches(":*")),
+                        "include_execution_data": l.include_execution_data,
+                        "level": l.level.as_deref().unwrap_or("ERROR"),
+                    });
+                }
+                json!({ "resource": { "aws_sfn_state_machine": { name: body } } })
+            }
+            AwsResource::EfsFileSystem { name, encrypted, kms_key_ref, performance_mode, throughput_mode, lifecycle_policy } => {
+                let mut body = json!({ "creation_token": name, "encrypted": encrypted.unwrap_or(true) });
+                if let Some(k) = kms_key_ref { body["kms_key_id"] = json!(ctx.resolve("aws_kms_key", k, "arn")); }
+                if let Some(p) = performance_mode { body["performance_mode"] = json!(p); }
+                if let Some(t) = throughput_mode { body["throughput_mode"] = json!(t); }
+                if let Some(l) = lifecycle_policy { body["lifecycle_policy"] = json!([{ "transition_to_ia": l }]); }
+                json!({ "resource": { "aws_efs_file_system": { name: body } } })
+            }
+            AwsResource::EfsMountTarget { name, file_system_ref, subnet_ref, security_group_refs } => {
+                let mut body = json!({
+                    "file_system_id": ctx.resolve("aws_efs_file_system", file_system_ref, "id"),
+                    "subnet_id": ctx.resolve("aws_subnet", subnet_ref, "id"),
+                });
+                if !security_group_refs.is_empty() {
+                    body["security_groups"] = json!(resolve_all(ctx, "aws_security_group", security_group_refs, "id"));
+                }
+                json!({ "resource": { "aws_efs_mount_target": { name: body } } })
+            }
+            AwsResource::ElastiCacheRedis { name, node_type, num_cache_clusters, engine_version, subnet_refs, security_group_refs, at_rest_encryption_enabled, transit_encryption_enabled, auth_token_from } => {
+                let transit = transit_encryption_enabled.unwrap_or(true);
+                let mut body = json!({
+                    "replication_group_id": name,
+                    "description": format!("{} (managed by r2iac)", name),
+                    "engine": "redis", "node_type": node_type,
+                    "at_rest_encryption_enabled": at_rest_encryption_enabled.unwrap_or(true),
+                    "transit_encryption_enabled": transit,
+                });
+                if let Some(n) = num_cache_clusters { body["num_cache_clusters"] = json!(n); }
+                if let Some(v) = engine_version { body["engine_version"] = json!(v); }
+                if !security_group_refs.is_empty() {
+                    body["security_group_ids"] = json!(resolve_all(ctx, "aws_security_group", security_group_refs, "id"));
+                }
+                if let Some(from) = auth_token_from {
+                    if !transit {
+                        anyhow::bail!("aws_elasticache_replication_group.{}: auth_token_from requires transit_encryption_enabled", name);
+                    }
+                    body["auth_token"] = json!(ctx.secret_var(&secret_var_name("aws_elasticache_replication_group", name), from));
+                }
+                let mut o = json!({});
+                if !subnet_refs.is_empty() {
+                    o["resource"]["aws_elasticache_subnet_group"][name] = json!({
+                        "name": name, "subnet_ids": resolve_all(ctx, "aws_subnet", subnet_refs, "id"),
+                    });
+                    body["subnet_group_name"] = json!(format!("${{aws_elasticache_subnet_group.{}.name}}", name));
+                }
+                o["resource"]["aws_elasticache_replication_group"][name] = body;
+                o
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwsAnyResource {
+    #[serde(rename="type")]
+    pub type_name: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub properties: JsonMap<String, Json>,
+}
+
+impl AwsAnyResource {
+    pub fn to_tf_json(&self) -> Json {
+        let mut props = self.properties.clone();
+        if !props.contains_key("bucket") && !props.contains_key("name") {
+            // add a best-effort name/bucket to reduce typos causing missing identifier
             props.insert("name".to_string(), json!(self.name.clone()));
         }
         json!({
@@ -127,3 +1668,951 @@ impl AwsAnyResource {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2iac_core::test_util::{assert_errors, ctx, with, Captured};
+
+    fn render(siblings: &[(&str, &str)], res: Json) -> Result<Json> {
+        serde_json::from_value::<AwsResource>(res)?.to_tf_json(&mut ctx(siblings))
+    }
+
+    /// Like `render`, but into a caller-owned context so the recorded secret vars can be checked.
+    fn render_in(ctx: &mut RenderCtx, res: Json) -> Result<Json> {
+        serde_json::from_value::<AwsResource>(res)?.to_tf_json(ctx)
+    }
+
+    #[test]
+    fn sqs_standard_queue() {
+        let tf = render(&[], json!({ "type": "aws_sqs_queue", "name": "jobs", "visibility_timeout_seconds": 30 })).unwrap();
+        let q = &tf["resource"]["aws_sqs_queue"]["jobs"];
+        assert_eq!(q["name"], "jobs");
+        assert_eq!(q["visibility_timeout_seconds"], 30);
+        assert!(q.get("fifo_queue").is_none());
+    }
+
+    #[test]
+    fn sqs_fifo_queue_gets_suffix() {
+        for name in ["jobs", "jobs.fifo"] {
+            let tf = render(&[], json!({ "type": "aws_sqs_queue", "name": name, "fifo_queue": true })).unwrap();
+            let q = &tf["resource"]["aws_sqs_queue"]["jobs"];
+            assert_eq!(q["name"], "jobs.fifo");
+            assert_eq!(q["fifo_queue"], true);
+        }
+    }
+
+    #[test]
+    fn sqs_fifo_suffix_without_fifo_queue_is_rejected() {
+        let err = render(&[], json!({ "type": "aws_sqs_queue", "name": "jobs.fifo" })).unwrap_err();
+        assert!(err.to_string().contains("fifo_queue: true"), "{}", err);
+    }
+
+    #[test]
+    fn sqs_redrive_policy_is_a_json_string() {
+        let tf = render(&[], json!({
+            "type": "aws_sqs_queue", "name": "jobs",
+            "redrive_policy": { "dead_letter_target_arn": "${aws_sqs_queue.dlq.arn}", "max_receive_count": 5 },
+        })).unwrap();
+        let rp = tf["resource"]["aws_sqs_queue"]["jobs"]["redrive_policy"].as_str().expect("redrive_policy is a string");
+        let parsed: Json = serde_json::from_str(rp).unwrap();
+        assert_eq!(parsed, json!({ "deadLetterTargetArn": "${aws_sqs_queue.dlq.arn}", "maxReceiveCount": 5 }));
+    }
+
+    fn rds(password_ref: Json) -> Json {
+        json!({
+            "type": "aws_db_instance", "name": "db", "engine": "postgres", "instance_class": "db.t3.micro",
+            "allocated_storage": 20, "username": "app", "password_ref": password_ref, "skip_final_snapshot": true,
+        })
+    }
+
+    #[test]
+    fn rds_password_var_is_a_sensitive_variable() {
+        let tf = render(&[], rds(json!({ "var": "db_password" }))).unwrap();
+        assert_eq!(tf["resource"]["aws_db_instance"]["db"]["password"], "${var.db_password}");
+        assert_eq!(tf["variable"]["db_password"]["sensitive"], true);
+    }
+
+    #[test]
+    fn rds_password_secret_reads_the_secret_version() {
+        let tf = render(&[("aws_secretsmanager_secret", "dbpw")], rds(json!({ "secret": "dbpw" }))).unwrap();
+        assert_eq!(tf["resource"]["aws_db_instance"]["db"]["password"],
+            "${data.aws_secretsmanager_secret_version.db-password.secret_string}");
+        assert_eq!(tf["data"]["aws_secretsmanager_secret_version"]["db-password"]["secret_id"],
+            "${aws_secretsmanager_secret.dbpw.id}");
+    }
+
+    #[test]
+    fn rds_literal_password_is_rejected() {
+        let mut res = rds(json!(null));
+        res.as_object_mut().unwrap().remove("password_ref");
+        res["password"] = json!("hunter2-plaintext");
+        assert!(render(&[], res).is_err());
+    }
+
+    #[test]
+    fn rds_final_snapshot_identifier_required() {
+        let mut res = rds(json!({ "var": "db_password" }));
+        res["skip_final_snapshot"] = json!(false);
+        let err = render(&[], res).unwrap_err();
+        assert!(err.to_string().contains("final_snapshot_identifier"), "{}", err);
+    }
+
+    #[test]
+    fn secret_version_never_renders_the_secret() {
+        std::env::set_var("R2IAC_TEST_API_KEY", "plaintext-api-key-value");
+        for src in ["env:R2IAC_TEST_API_KEY", "file:/run/secrets/api-key", "age:secrets/api-key.age"] {
+            let mut ctx = ctx(&[("aws_secretsmanager_secret", "api")]);
+            let logs = Captured::default();
+            let tf = logs.during(|| render_in(&mut ctx, json!({
+                "type": "aws_secretsmanager_secret_version", "name": "api-v1", "secret_ref": "api", "value_from": src,
+            }))).unwrap();
+            let out = serde_json::to_string(&tf).unwrap();
+            assert!(!out.contains(src) && !out.contains(src.split_once(':').unwrap().1), "{}", out);
+            for text in [out, logs.text()] {
+                assert!(!text.contains("plaintext-api-key-value"), "{}", text);
+            }
+            let v = &tf["resource"]["aws_secretsmanager_secret_version"]["api-v1"];
+            assert_eq!(v["secret_string"], "${var.aws_secretsmanager_secret_version_api_v1}");
+            assert_eq!(v["secret_id"], "${aws_secretsmanager_secret.api.id}");
+            assert!(ctx.secret_vars().contains_key("aws_secretsmanager_secret_version_api_v1"));
+        }
+    }
+
+    #[test]
+    fn redis_auth_token_never_renders_the_secret() {
+        let mut ctx = RenderCtx::default();
+        let tf = render_in(&mut ctx, json!({
+            "type": "aws_elasticache_replication_group", "name": "cache", "node_type": "cache.t4g.small",
+            "auth_token_from": "env:REDIS_AUTH_TOKEN",
+        })).unwrap();
+        let out = serde_json::to_string(&tf).unwrap();
+        assert!(!out.contains("REDIS_AUTH_TOKEN"), "{}", out);
+        let rg = &tf["resource"]["aws_elasticache_replication_group"]["cache"];
+        assert_eq!(rg["auth_token"], "${var.aws_elasticache_replication_group_cache}");
+        assert_eq!(rg["transit_encryption_enabled"], true);
+        assert!(ctx.secret_vars().contains_key("aws_elasticache_replication_group_cache"));
+    }
+
+    #[test]
+    fn redis_auth_token_requires_transit_encryption() {
+        let err = render(&[], json!({
+            "type": "aws_elasticache_replication_group", "name": "cache", "node_type": "cache.t4g.small",
+            "transit_encryption_enabled": false, "auth_token_from": "env:REDIS_AUTH_TOKEN",
+        })).unwrap_err();
+        assert!(err.to_string().contains("requires transit_encryption_enabled"), "{}", err);
+    }
+
+    fn lambda(filename: &str) -> Json {
+        json!({
+            "type": "aws_lambda_function", "name": "fn", "function_name": "worker", "runtime": "python3.12",
+            "handler": "main.handler", "role": "arn:aws:iam::123456789012:role/worker", "filename": filename,
+        })
+    }
+
+    #[test]
+    fn lambda_package_path_is_absolute_and_hashed() {
+        let dir = std::env::temp_dir().join(format!("r2iac-lambda-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("pkg.zip"), b"zip bytes").unwrap();
+        let indirect = dir.join("sub").join("..").join("pkg.zip");
+        let tf = render(&[], lambda(indirect.to_str().unwrap())).unwrap();
+        let f = &tf["resource"]["aws_lambda_function"]["fn"];
+        let canonical = std::fs::canonicalize(dir.join("pkg.zip")).unwrap();
+        assert_eq!(f["filename"], canonical.to_str().unwrap());
+        assert_eq!(f["source_code_hash"], base64::engine::general_purpose::STANDARD.encode(Sha256::digest(b"zip bytes")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lambda_missing_package_is_an_error() {
+        let err = render(&[], lambda("does/not/exist.zip")).unwrap_err();
+        assert!(err.to_string().contains("read package does/not/exist.zip"), "{}", err);
+    }
+
+    fn http_api(routes: Json) -> Json {
+        json!({ "type": "aws_apigatewayv2_api", "name": "api", "routes": routes })
+    }
+
+    #[test]
+    fn http_api_routes_get_integrations_and_one_grant_per_function() {
+        let tf = render(&[("aws_lambda_function", "items")], http_api(json!([
+            { "route_key": "GET /items/{id}", "lambda_ref": "items" },
+            { "route_key": "POST /items", "lambda_ref": "items" },
+        ]))).unwrap();
+        let r = &tf["resource"];
+        assert_eq!(r["aws_apigatewayv2_route"]["api-GET__items_id"]["route_key"], "GET /items/{id}");
+        assert_eq!(r["aws_apigatewayv2_integration"]["api-POST__items"]["integration_uri"], "${aws_lambda_function.items.arn}");
+        assert_eq!(r["aws_lambda_permission"].as_object().unwrap().keys().collect::<Vec<_>>(), ["api-items"]);
+    }
+
+    #[test]
+    fn http_api_colliding_route_names_are_rejected() {
+        let err = render(&[("aws_lambda_function", "items")], http_api(json!([
+            { "route_key": "GET /items/{id}", "lambda_ref": "items" },
+            { "route_key": "GET /items/id", "lambda_ref": "items" },
+        ]))).unwrap_err();
+        assert!(err.to_string().contains("same as route 'GET /items/{id}'"), "{}", err);
+    }
+
+    #[test]
+    fn http_api_grants_are_keyed_by_function_not_qualifier() {
+        let tf = render(&[], http_api(json!([
+            { "route_key": "GET /a", "lambda_ref": "arn:aws:lambda:us-east-1:123456789012:function:fn-a:live" },
+            { "route_key": "GET /b", "lambda_ref": "arn:aws:lambda:us-east-1:123456789012:function:fn-b:live" },
+            { "route_key": "GET /c", "lambda_ref": "arn:aws:lambda:us-east-1:123456789012:function:fn-c" },
+        ]))).unwrap();
+        let perms = tf["resource"]["aws_lambda_permission"].as_object().unwrap();
+        assert_eq!(perms.keys().collect::<Vec<_>>(), ["api-fn-a-live", "api-fn-b-live", "api-fn-c"]);
+        assert_eq!(perms["api-fn-b-live"]["function_name"], "arn:aws:lambda:us-east-1:123456789012:function:fn-b:live");
+    }
+
+    #[test]
+    fn http_api_bad_route_key_is_rejected() {
+        let err = render(&[("aws_lambda_function", "items")], http_api(json!([
+            { "route_key": "/items", "lambda_ref": "items" },
+        ]))).unwrap_err();
+        assert!(err.to_string().contains("route_key must be"), "{}", err);
+    }
+
+    fn instance(extra: Json) -> Json {
+        with(json!({ "type": "aws_instance", "name": "web", "ami": "ami-123", "instance_type": "t3.micro" }), extra)
+    }
+
+    #[test]
+    fn instance_user_data_is_always_encoded() {
+        // "abcd" happens to be valid base64 too; it must still be encoded as text.
+        for ud in ["#!/bin/sh\necho hi\n", "abcd"] {
+            let tf = render(&[], instance(json!({ "user_data": ud }))).unwrap();
+            let encoded = base64::engine::general_purpose::STANDARD.encode(ud);
+            assert_eq!(tf["resource"]["aws_instance"]["web"]["user_data_base64"], encoded);
+        }
+    }
+
+    #[test]
+    fn instance_user_data_base64_passes_through() {
+        let tf = render(&[], instance(json!({ "user_data_base64": " H4sIAAAAAAAAAwMAAAAAAAAAAAA= \n" }))).unwrap();
+        assert_eq!(tf["resource"]["aws_instance"]["web"]["user_data_base64"], "H4sIAAAAAAAAAwMAAAAAAAAAAAA=");
+    }
+
+    #[test]
+    fn instance_user_data_base64_must_decode() {
+        let err = render(&[], instance(json!({ "user_data_base64": "not base64!" }))).unwrap_err();
+        assert!(err.to_string().contains("user_data_base64 is not valid base64"), "{}", err);
+    }
+
+    #[test]
+    fn instance_user_data_forms_are_exclusive() {
+        let err = render(&[], instance(json!({ "user_data": "x", "user_data_base64": "eA==" }))).unwrap_err();
+        assert!(err.to_string().contains("only one of user_data and user_data_base64"), "{}", err);
+    }
+
+    #[test]
+    fn sns_topic_and_fifo_suffix() {
+        let tf = render(&[], json!({ "type": "aws_sns_topic", "name": "alerts", "display_name": "Alerts" })).unwrap();
+        assert_eq!(tf["resource"]["aws_sns_topic"]["alerts"], json!({ "name": "alerts", "display_name": "Alerts" }));
+        let tf = render(&[], json!({ "type": "aws_sns_topic", "name": "orders", "fifo_topic": true })).unwrap();
+        assert_eq!(tf["resource"]["aws_sns_topic"]["orders"], json!({ "name": "orders.fifo", "fifo_topic": true }));
+    }
+
+    #[test]
+    fn sns_fifo_suffix_without_fifo_topic_is_rejected() {
+        let err = render(&[], json!({ "type": "aws_sns_topic", "name": "orders.fifo" })).unwrap_err();
+        assert!(err.to_string().contains("fifo_topic: true"), "{}", err);
+    }
+
+    #[test]
+    fn sns_subscription_resolves_sibling_topic_and_queue() {
+        let tf = render(&[("aws_sns_topic", "alerts"), ("aws_sqs_queue", "jobs")], json!({
+            "type": "aws_sns_topic_subscription", "name": "to-jobs", "topic_ref": "alerts",
+            "protocol": "sqs", "endpoint": "jobs", "raw_message_delivery": true,
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_sns_topic_subscription"]["to-jobs"], json!({
+            "topic_arn": "${aws_sns_topic.alerts.arn}", "protocol": "sqs",
+            "endpoint": "${aws_sqs_queue.jobs.arn}", "raw_message_delivery": true,
+        }));
+        let arn = "arn:aws:sns:us-east-1:123456789012:external";
+        let tf = render(&[], json!({
+            "type": "aws_sns_topic_subscription", "name": "mail", "topic_ref": arn,
+            "protocol": "email", "endpoint": "ops@example.com",
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_sns_topic_subscription"]["mail"]["topic_arn"], arn);
+        assert_eq!(tf["resource"]["aws_sns_topic_subscription"]["mail"]["endpoint"], "ops@example.com");
+    }
+
+    #[test]
+    fn sns_subscription_unknown_topic_is_rejected() {
+        let err = render(&[], json!({
+            "type": "aws_sns_topic_subscription", "name": "s", "topic_ref": "missing", "protocol": "email", "endpoint": "a@b.c",
+        })).unwrap_err();
+        assert!(err.to_string().contains("neither a sibling aws_sns_topic nor an ARN"), "{}", err);
+    }
+
+    #[test]
+    fn route53_public_and_private_zones() {
+        let tf = render(&[], json!({ "type": "aws_route53_zone", "name": "pub", "zone_name": "example.com" })).unwrap();
+        assert_eq!(tf["resource"]["aws_route53_zone"]["pub"], json!({ "name": "example.com" }));
+        let tf = render(&[("aws_vpc", "main")], json!({
+            "type": "aws_route53_zone", "name": "int", "zone_name": "internal.example.com", "private": true, "vpc_ref": "main",
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_route53_zone"]["int"]["vpc"], json!([{ "vpc_id": "${aws_vpc.main.id}" }]));
+    }
+
+    #[test]
+    fn route53_zone_vpc_ref_must_match_private() {
+        let err = render(&[], json!({ "type": "aws_route53_zone", "name": "int", "zone_name": "i.example.com", "private": true })).unwrap_err();
+        assert!(err.to_string().contains("private zones require vpc_ref"), "{}", err);
+        let err = render(&[("aws_vpc", "main")], json!({
+            "type": "aws_route53_zone", "name": "pub", "zone_name": "example.com", "vpc_ref": "main",
+        })).unwrap_err();
+        assert!(err.to_string().contains("vpc_ref is only valid on private zones"), "{}", err);
+    }
+
+    fn record(extra: Json) -> Json {
+        with(json!({ "type": "aws_route53_record", "name": "www", "zone_ref": "pub", "record_name": "www.example.com", "record_type": "A" }), extra)
+    }
+
+    #[test]
+    fn route53_plain_record() {
+        let tf = render(&[("aws_route53_zone", "pub")], record(json!({ "ttl": 300, "records": ["192.0.2.1"] }))).unwrap();
+        assert_eq!(tf["resource"]["aws_route53_record"]["www"], json!({
+            "zone_id": "${aws_route53_zone.pub.zone_id}", "name": "www.example.com", "type": "A",
+            "ttl": 300, "records": ["192.0.2.1"],
+        }));
+    }
+
+    #[test]
+    fn route53_cname_record() {
+        let tf = render(&[("aws_route53_zone", "pub")], record(json!({
+            "record_name": "docs.example.com", "record_type": "CNAME", "ttl": 3600, "records": ["acme.github.io"],
+        }))).unwrap();
+        let r = &tf["resource"]["aws_route53_record"]["www"];
+        assert_eq!(r["type"], "CNAME");
+        assert_eq!(r["ttl"], 3600);
+        assert_eq!(r["records"], json!(["acme.github.io"]));
+        assert!(r.get("alias").is_none());
+        // A zone outside the stack is used by id as given.
+        let tf = render(&[], record(json!({ "zone_ref": "Z0123456789ABC", "record_type": "CNAME", "ttl": 60, "records": ["lb.example.net"] }))).unwrap();
+        assert_eq!(tf["resource"]["aws_route53_record"]["www"]["zone_id"], "Z0123456789ABC");
+
+        let err = render(&[], record(json!({ "record_type": "cname", "ttl": 60, "records": ["x"] }))).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `cname`"), "{}", err);
+    }
+
+    #[test]
+    fn route53_alias_to_sibling_lb_or_external_target() {
+        let tf = render(&[("aws_route53_zone", "pub"), ("aws_lb", "web")], record(json!({ "alias": { "name": "web" } }))).unwrap();
+        assert_eq!(tf["resource"]["aws_route53_record"]["www"]["alias"], json!([{
+            "name": "${aws_lb.web.dns_name}", "zone_id": "${aws_lb.web.zone_id}", "evaluate_target_health": false,
+        }]));
+        let tf = render(&[("aws_route53_zone", "pub")], record(json!({
+            "alias": { "name": "d111.cloudfront.net", "zone_id": "Z2FDTNDATAQYW2", "evaluate_target_health": true },
+        }))).unwrap();
+        assert_eq!(tf["resource"]["aws_route53_record"]["www"]["alias"][0]["zone_id"], "Z2FDTNDATAQYW2");
+    }
+
+    #[test]
+    fn route53_record_shape_errors() {
+        let zone = [("aws_route53_zone", "pub")];
+        assert_errors(&record(json!({})), &[
+            (json!({ "alias": { "name": "web.example.net" } }), "needs zone_id unless it names a sibling aws_lb"),
+            (json!({ "alias": { "name": "x", "zone_id": "Z1" }, "ttl": 60 }), "alias records cannot set ttl or records"),
+            (json!({ "records": ["192.0.2.1"] }), "ttl is required for non-alias records"),
+            (json!({ "ttl": 60 }), "records is required for non-alias records"),
+        ], |r| render(&zone, r));
+    }
+
+    #[test]
+    fn ecr_repository_with_kms_and_lifecycle() {
+        let tf = render(&[("aws_kms_key", "images")], json!({
+            "type": "aws_ecr_repository", "name": "app", "image_tag_mutability": "IMMUTABLE", "scan_on_push": true,
+            "encryption": { "kms": "images" }, "keep_last_n_images": 10,
+        })).unwrap();
+        let r = &tf["resource"];
+        assert_eq!(r["aws_ecr_repository"]["app"], json!({
+            "name": "app", "image_tag_mutability": "IMMUTABLE",
+            "image_scanning_configuration": { "scan_on_push": true },
+            "encryption_configuration": { "encryption_type": "KMS", "kms_key": "${aws_kms_key.images.arn}" },
+        }));
+        let lp = &r["aws_ecr_lifecycle_policy"]["app"];
+        assert_eq!(lp["repository"], "${aws_ecr_repository.app.name}");
+        let policy: Json = serde_json::from_str(lp["policy"].as_str().unwrap()).unwrap();
+        assert_eq!(policy["rules"][0]["selection"], json!({ "tagStatus": "any", "countType": "imageCountMoreThan", "countNumber": 10 }));
+    }
+
+    #[test]
+    fn ecr_repository_defaults() {
+        let tf = render(&[], json!({ "type": "aws_ecr_repository", "name": "app", "encryption": "aes256" })).unwrap();
+        assert_eq!(tf["resource"]["aws_ecr_repository"]["app"]["encryption_configuration"], json!({ "encryption_type": "AES256" }));
+        assert!(tf["resource"].get("aws_ecr_lifecycle_policy").is_none());
+    }
+
+    #[test]
+    fn ecr_keep_zero_images_is_rejected() {
+        let err = render(&[], json!({ "type": "aws_ecr_repository", "name": "app", "keep_last_n_images": 0 })).unwrap_err();
+        assert!(err.to_string().contains("keep_last_n_images must be at least 1"), "{}", err);
+    }
+
+    #[test]
+    fn cloudfront_s3_origin_gets_origin_access_control() {
+        let tf = render(&[("aws_s3_bucket", "site")], json!({
+            "type": "aws_cloudfront_distribution", "name": "cdn", "origin": "site", "default_root_object": "index.html",
+        })).unwrap();
+        let r = &tf["resource"];
+        assert_eq!(r["aws_cloudfront_origin_access_control"]["cdn"]["origin_access_control_origin_type"], "s3");
+        let d = &r["aws_cloudfront_distribution"]["cdn"];
+        assert_eq!(d["origin"], json!([{
+            "origin_id": "site",
+            "domain_name": "${aws_s3_bucket.site.bucket_regional_domain_name}",
+            "origin_access_control_id": "${aws_cloudfront_origin_access_control.cdn.id}",
+        }]));
+        assert_eq!(d["default_cache_behavior"][0]["viewer_protocol_policy"], "redirect-to-https");
+        assert_eq!(d["default_cache_behavior"][0]["allowed_methods"], json!(["GET", "HEAD"]));
+        assert_eq!(d["viewer_certificate"], json!([{ "cloudfront_default_certificate": true }]));
+    }
+
+    #[test]
+    fn cloudfront_custom_origin_with_aliases() {
+        let cert = "arn:aws:acm:us-east-1:123456789012:certificate/abc";
+        let tf = render(&[], json!({
+            "type": "aws_cloudfront_distribution", "name": "cdn", "origin": "api.example.com",
+            "aliases": ["www.example.com"], "acm_certificate_arn": cert,
+        })).unwrap();
+        let r = &tf["resource"];
+        assert!(r.get("aws_cloudfront_origin_access_control").is_none());
+        let d = &r["aws_cloudfront_distribution"]["cdn"];
+        assert_eq!(d["origin"][0]["custom_origin_config"][0]["origin_protocol_policy"], "https-only");
+        assert_eq!(d["aliases"], json!(["www.example.com"]));
+        assert_eq!(d["viewer_certificate"][0]["acm_certificate_arn"], cert);
+    }
+
+    #[test]
+    fn cloudfront_aliases_need_a_certificate() {
+        let err = render(&[], json!({
+            "type": "aws_cloudfront_distribution", "name": "cdn", "origin": "api.example.com", "aliases": ["www.example.com"],
+        })).unwrap_err();
+        assert!(err.to_string().contains("aliases require acm_certificate_arn"), "{}", err);
+    }
+
+    #[test]
+    fn s3_bucket_blocks_public_access_by_default() {
+        let tf = render(&[], json!({ "type": "aws_s3_bucket", "name": "logs", "bucket": "acme-logs" })).unwrap();
+        assert_eq!(tf["resource"]["aws_s3_bucket_public_access_block"]["logs"], json!({
+            "bucket": "${aws_s3_bucket.logs.id}",
+            "block_public_acls": true, "block_public_policy": true, "ignore_public_acls": true, "restrict_public_buckets": true,
+        }));
+        let tf = render(&[], json!({ "type": "aws_s3_bucket", "name": "site", "bucket": "acme-site", "block_public_access": false })).unwrap();
+        assert!(tf["resource"].get("aws_s3_bucket_public_access_block").is_none());
+    }
+
+    fn bucket_policy(policy: Json) -> Result<Json> {
+        let tf = render(&[], json!({ "type": "aws_s3_bucket", "name": "logs", "bucket": "acme-logs", "bucket_policy": policy }))?;
+        let p = &tf["resource"]["aws_s3_bucket_policy"]["logs"];
+        assert_eq!(p["bucket"], "${aws_s3_bucket.logs.id}");
+        Ok(serde_json::from_str(p["policy"].as_str().unwrap())?)
+    }
+
+    #[test]
+    fn s3_bucket_policy_tls_only_and_default_resources() {
+        let doc = bucket_policy(json!({
+            "enforce_tls_only": true,
+            "statements": [{ "effect": "Allow", "actions": ["s3:GetObject"], "principals": { "AWS": ["arn:aws:iam::123456789012:root"] } }],
+        })).unwrap();
+        let whole = json!(["${aws_s3_bucket.logs.arn}", "${aws_s3_bucket.logs.arn}/*"]);
+        assert_eq!(doc["Statement"][0]["Resource"], whole);
+        assert_eq!(doc["Statement"][1], json!({
+            "Sid": "EnforceTLS", "Effect": "Deny", "Action": ["s3:*"], "Resource": whole, "Principal": "*",
+            "Condition": { "Bool": { "aws:SecureTransport": "false" } },
+        }));
+    }
+
+    #[test]
+    fn s3_bucket_policy_statement_errors() {
+        for (policy, msg) in [
+            (json!({}), "policy must have at least one statement"),
+            (json!({ "statements": [{ "effect": "allow", "actions": ["s3:GetObject"] }] }), "expected 'Allow' or 'Deny'"),
+            (json!({ "statements": [{ "effect": "Allow", "actions": [] }] }), "statement 0 has no actions"),
+            (json!({ "statements": [{ "effect": "Allow", "actions": ["s3:GetObject"], "principals": "someone" }] }), "must be \"*\" or a map"),
+        ] {
+            let err = bucket_policy(policy).unwrap_err();
+            assert!(err.to_string().starts_with("aws_s3_bucket.logs bucket_policy: "), "{}", err);
+            assert!(err.to_string().contains(msg), "{}", err);
+        }
+    }
+
+    #[test]
+    fn s3_encryption_is_its_own_resource() {
+        let tf = render(&[], json!({ "type": "aws_s3_bucket", "name": "logs", "bucket": "acme-logs" })).unwrap();
+        assert!(tf["resource"]["aws_s3_bucket"]["logs"].get("server_side_encryption_configuration").is_none());
+        assert_eq!(tf["resource"]["aws_s3_bucket_server_side_encryption_configuration"]["logs"], json!({
+            "bucket": "${aws_s3_bucket.logs.id}",
+            "rule": { "apply_server_side_encryption_by_default": { "sse_algorithm": "AES256" } },
+        }));
+        let tf = render(&[("aws_kms_key", "data")], json!({ "type": "aws_s3_bucket", "name": "logs", "bucket": "acme-logs", "kms_key_id": "data" })).unwrap();
+        assert_eq!(tf["resource"]["aws_s3_bucket_server_side_encryption_configuration"]["logs"]["rule"], json!({
+            "apply_server_side_encryption_by_default": { "sse_algorithm": "aws:kms", "kms_master_key_id": "${aws_kms_key.data.arn}" },
+            "bucket_key_enabled": true,
+        }));
+    }
+
+    #[test]
+    fn s3_bucket_key_needs_kms() {
+        let err = render(&[], json!({ "type": "aws_s3_bucket", "name": "logs", "bucket": "acme-logs", "bucket_key_enabled": true })).unwrap_err();
+        assert!(err.to_string().contains("bucket_key_enabled requires kms_key_id"), "{}", err);
+    }
+
+    #[test]
+    fn provider_region_only() {
+        let p: AwsProvider = serde_json::from_value(json!({ "region": "eu-west-1" })).unwrap();
+        assert_eq!(p.to_tf_json(), json!({ "provider": { "aws": { "region": "eu-west-1" } } }));
+    }
+
+    #[test]
+    fn provider_profile_assume_role_tags_and_accounts() {
+        let p: AwsProvider = serde_json::from_value(json!({
+            "region": "eu-west-1", "profile": "deploy",
+            "assume_role": { "role_arn": "arn:aws:iam::123456789012:role/deploy", "session_name": "r2iac", "external_id": "xyz" },
+            "default_tags": { "team": "platform" }, "allowed_account_ids": ["123456789012"],
+        })).unwrap();
+        assert_eq!(p.to_tf_json(), json!({ "provider": { "aws": {
+            "region": "eu-west-1", "profile": "deploy",
+            "assume_role": [{ "role_arn": "arn:aws:iam::123456789012:role/deploy", "session_name": "r2iac", "external_id": "xyz" }],
+            "default_tags": [{ "tags": { "team": "platform" } }],
+            "allowed_account_ids": ["123456789012"],
+        } } }));
+    }
+
+    fn role(extra: Json) -> Json {
+        with(json!({ "type": "aws_iam_role", "name": "app", "service_principals": ["lambda.amazonaws.com"] }), extra)
+    }
+
+    #[test]
+    fn iam_role_trust_policy_and_attachments() {
+        let tf = render(&[("aws_iam_policy", "read-config")], role(json!({
+            "account_ids": ["123456789012"], "max_session_duration": 7200,
+            "managed_policy_arns": ["arn:aws:iam::aws:policy/service-role/AWSLambdaBasicExecutionRole", "read-config"],
+        }))).unwrap();
+        let r = &tf["resource"]["aws_iam_role"]["app"];
+        assert_eq!(r["max_session_duration"], 7200);
+        let trust: Json = serde_json::from_str(r["assume_role_policy"].as_str().unwrap()).unwrap();
+        assert_eq!(trust["Statement"][0]["Principal"], json!({
+            "Service": ["lambda.amazonaws.com"], "AWS": ["arn:aws:iam::123456789012:root"],
+        }));
+        assert_eq!(tf["resource"]["aws_iam_role_policy_attachment"], json!({
+            "app-AWSLambdaBasicExecutionRole": {
+                "role": "${aws_iam_role.app.name}", "policy_arn": "arn:aws:iam::aws:policy/service-role/AWSLambdaBasicExecutionRole",
+            },
+            "app-read-config": { "role": "${aws_iam_role.app.name}", "policy_arn": "${aws_iam_policy.read-config.arn}" },
+        }));
+    }
+
+    #[test]
+    fn iam_role_errors() {
+        assert_errors(&role(json!({})), &[
+            (json!({ "service_principals": [] }), "at least one service principal or account id is required"),
+            (json!({ "account_ids": ["12345"] }), "account id '12345' must be 12 digits"),
+            // Same policy name in two accounts would otherwise overwrite one attachment.
+            (json!({ "managed_policy_arns": ["arn:aws:iam::aws:policy/ReadOnlyAccess", "arn:aws:iam::123456789012:policy/ReadOnlyAccess"] }),
+             "managed policy 'arn:aws:iam::123456789012:policy/ReadOnlyAccess' attaches as 'app-ReadOnlyAccess', same as 'arn:aws:iam::aws:policy/ReadOnlyAccess'"),
+            (json!({ "managed_policy_arns": ["arn:aws:iam::aws:policy/ReadOnlyAccess", "arn:aws:iam::aws:policy/ReadOnlyAccess"] }), "same as"),
+        ], |r| render(&[], r));
+    }
+
+    fn iam_policy(statements: Json) -> Json {
+        json!({ "type": "aws_iam_policy", "name": "read-config", "description": "Read app config", "statements": statements })
+    }
+
+    #[test]
+    fn iam_policy_document() {
+        let tf = render(&[], iam_policy(json!([{
+            "sid": "Read", "effect": "Allow", "actions": ["ssm:GetParameter"], "resources": ["arn:aws:ssm:*:*:parameter/app/*"],
+            "conditions": { "Bool": { "aws:SecureTransport": "true" } },
+        }]))).unwrap();
+        let p = &tf["resource"]["aws_iam_policy"]["read-config"];
+        assert_eq!(p["description"], "Read app config");
+        let doc: Json = serde_json::from_str(p["policy"].as_str().unwrap()).unwrap();
+        assert_eq!(doc, json!({ "Version": "2012-10-17", "Statement": [{
+            "Sid": "Read", "Effect": "Allow", "Action": ["ssm:GetParameter"], "Resource": ["arn:aws:ssm:*:*:parameter/app/*"],
+            "Condition": { "Bool": { "aws:SecureTransport": "true" } },
+        }] }));
+    }
+
+    #[test]
+    fn iam_policy_errors() {
+        assert_errors(&iam_policy(json!([{ "effect": "Allow", "actions": ["s3:GetObject"] }])), &[
+            (json!({ "statements": [{ "effect": "Allow", "actions": ["s3:GetObject"], "principals": "*" }] }), "identity policies cannot name principals"),
+            (json!({ "statements": [] }), "policy must have at least one statement"),
+            (json!({ "statements": [{ "effect": "allow", "actions": ["s3:GetObject"] }] }), "statement 0 has effect 'allow'; expected 'Allow' or 'Deny'"),
+            (json!({ "statements": [{ "effect": "Deny", "actions": [] }] }), "statement 0 has no actions"),
+        ], |r| render(&[], r));
+    }
+
+    fn statement(extra: Json) -> PolicyStatement {
+        serde_json::from_value(with(json!({ "effect": "Allow", "actions": ["kms:Decrypt"] }), extra)).unwrap()
+    }
+
+    #[test]
+    fn policy_document_principals() {
+        let doc = policy_document("owner", &[
+            statement(json!({ "principals": "*" })),
+            statement(json!({ "effect": "Deny", "principals": { "AWS": ["arn:aws:iam::123456789012:root"] }, "resources": ["*"] })),
+        ]).unwrap();
+        assert_eq!(serde_json::from_str::<Json>(&doc).unwrap()["Statement"], json!([
+            { "Effect": "Allow", "Action": ["kms:Decrypt"], "Principal": "*" },
+            { "Effect": "Deny", "Action": ["kms:Decrypt"], "Principal": { "AWS": ["arn:aws:iam::123456789012:root"] }, "Resource": ["*"] },
+        ]));
+
+        let err = policy_document("aws_kms_key.data", &[statement(json!({})), statement(json!({ "principals": "everyone" }))]).unwrap_err();
+        assert_eq!(err.to_string(), "aws_kms_key.data: statement 1 principal 'everyone' must be \"*\" or a map of principal lists");
+    }
+
+    #[test]
+    fn vpc_and_subnet_cidrs_are_checked() {
+        assert_errors(&json!({ "type": "aws_vpc", "name": "main", "cidr_block": "10.0.0.0/16" }), &[
+            (json!({ "cidr_block": "10.0.0.0/33" }), "'10.0.0.0/33' prefix length must be <= 32"),
+            (json!({ "cidr_block": "10.0.0.0" }), "'10.0.0.0' is not a CIDR block"),
+            (json!({ "cidr_block": "10.0.0.256/24" }), "'10.0.0.256/24' has an invalid address"),
+            (json!({ "cidr_block": "10.0.1.0/16" }), "'10.0.1.0/16' has host bits set"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "aws_subnet", "name": "a", "vpc_ref": "main", "cidr_block": "10.0.0.0/24" }), &[
+            (json!({ "cidr_block": "10.0.0.0/x" }), "'10.0.0.0/x' has an invalid prefix length"),
+            (json!({ "cidr_block": "fd00::/129" }), "prefix length must be <= 128"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn subnets_in_one_vpc_must_not_overlap() {
+        let subnet = |name: &str, vpc: &str, cidr: &str| json!({ "type": "aws_subnet", "name": name, "vpc_ref": vpc, "cidr_block": cidr });
+        let mut ctx = ctx(&[("aws_vpc", "main"), ("aws_vpc", "edge")]);
+        let tf = render_in(&mut ctx, subnet("a", "main", "10.0.0.0/24")).unwrap();
+        assert_eq!(tf["resource"]["aws_subnet"]["a"], json!({ "vpc_id": "${aws_vpc.main.id}", "cidr_block": "10.0.0.0/24" }));
+        render_in(&mut ctx, subnet("b", "main", "10.0.1.0/24")).unwrap();
+        // The same block in another VPC, and IPv6 beside IPv4, are fine.
+        render_in(&mut ctx, subnet("c", "edge", "10.0.0.0/24")).unwrap();
+        render_in(&mut ctx, subnet("d", "main", "::/120")).unwrap();
+
+        let err = render_in(&mut ctx, subnet("e", "main", "10.0.0.128/25")).unwrap_err();
+        assert_eq!(err.to_string(), "aws_subnet.e: '10.0.0.128/25' overlaps '10.0.0.0/24' of aws_subnet.a in aws_vpc.main");
+        let err = render_in(&mut ctx, subnet("f", "main", "10.0.0.0/23")).unwrap_err();
+        assert_eq!(err.to_string(), "aws_subnet.f: '10.0.0.0/23' overlaps '10.0.0.0/24' of aws_subnet.a in aws_vpc.main");
+        let err = render_in(&mut ctx, subnet("g", "main", "::80/121")).unwrap_err();
+        assert_eq!(err.to_string(), "aws_subnet.g: '::80/121' overlaps '::/120' of aws_subnet.d in aws_vpc.main");
+    }
+
+    fn security_group(ingress: Json) -> Json {
+        json!({ "type": "aws_security_group", "name": "web", "vpc_ref": "main", "ingress": ingress })
+    }
+
+    #[test]
+    fn security_group_rules_spell_out_every_attribute() {
+        let tf = render(&[("aws_vpc", "main"), ("aws_security_group", "lb")], with(security_group(json!([
+            { "from_port": 443, "to_port": 443, "protocol": "tcp", "cidr_blocks": ["10.0.0.0/16", "fd00::/8"], "description": "https" },
+            { "from_port": 8080, "to_port": 8081, "protocol": "tcp", "security_group_refs": ["lb", "web"] },
+        ])), json!({ "egress": [{ "from_port": 0, "to_port": 0, "protocol": "-1", "cidr_blocks": ["0.0.0.0/0"] }] }))).unwrap();
+        assert_eq!(tf["resource"]["aws_security_group"]["web"], json!({
+            "name": "web", "vpc_id": "${aws_vpc.main.id}",
+            "ingress": [
+                { "from_port": 443, "to_port": 443, "protocol": "tcp", "cidr_blocks": ["10.0.0.0/16"], "ipv6_cidr_blocks": ["fd00::/8"],
+                  "prefix_list_ids": [], "security_groups": [], "self": false, "description": "https" },
+                { "from_port": 8080, "to_port": 8081, "protocol": "tcp", "cidr_blocks": [], "ipv6_cidr_blocks": [],
+                  "prefix_list_ids": [], "security_groups": ["${aws_security_group.lb.id}"], "self": true, "description": "" },
+            ],
+            "egress": [
+                { "from_port": 0, "to_port": 0, "protocol": "-1", "cidr_blocks": ["0.0.0.0/0"], "ipv6_cidr_blocks": [],
+                  "prefix_list_ids": [], "security_groups": [], "self": false, "description": "" },
+            ],
+        }));
+    }
+
+    #[test]
+    fn security_group_rule_errors() {
+        // Rule errors name the rule as well as the group, so they are checked whole.
+        for (extra, msg) in [
+            (json!({ "ingress": [{ "from_port": 443, "to_port": 80, "protocol": "tcp" }] }),
+                "aws_security_group.web ingress rule 0: from_port 443 is greater than to_port 80"),
+            (json!({ "egress": [{ "from_port": 0, "to_port": 65535, "protocol": "-1" }] }),
+                "aws_security_group.web egress rule 0: protocol -1 (all traffic) requires from_port and to_port of 0"),
+            (json!({ "ingress": [
+                { "from_port": 22, "to_port": 22, "protocol": "tcp", "cidr_blocks": ["10.0.0.0/8"] },
+                { "from_port": 22, "to_port": 22, "protocol": "tcp", "cidr_blocks": ["10.0.0.1/8"] },
+            ] }), "aws_security_group.web ingress rule 1: '10.0.0.1/8' has host bits set"),
+        ] {
+            assert_eq!(render(&[], with(security_group(json!([])), extra)).unwrap_err().to_string(), msg);
+        }
+    }
+
+    #[test]
+    fn log_group_retention_and_key() {
+        let tf = render(&[("aws_kms_key", "logs")], json!({
+            "type": "aws_cloudwatch_log_group", "name": "app", "log_group_name": "/acme/app", "retention_in_days": 30, "kms_key_id": "logs",
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_cloudwatch_log_group"]["app"], json!({
+            "name": "/acme/app", "retention_in_days": 30, "kms_key_id": "${aws_kms_key.logs.arn}",
+        }));
+        assert_errors(&json!({ "type": "aws_cloudwatch_log_group", "name": "app", "log_group_name": "/acme/app" }), &[
+            (json!({ "retention_in_days": 45 }), "retention_in_days 45 is not allowed; use one of [0, 1, 3, 5, 7, 14, 30,"),
+            (json!({ "kms_key_id": "logs" }), "kms_key_id 'logs' is neither a sibling aws_kms_key nor an ARN"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn eks_cluster_and_node_group_errors() {
+        assert_errors(&json!({ "type": "aws_eks_cluster", "name": "main", "role_ref": "cluster", "subnet_refs": ["a"] }), &[
+            (json!({}), "role_ref 'cluster' is neither a sibling aws_iam_role nor an ARN"),
+        ], |r| render(&[], r));
+        let group = json!({
+            "type": "aws_eks_node_group", "name": "general", "cluster_ref": "main", "node_role_ref": "nodes",
+            "subnet_refs": ["a"], "desired_size": 2, "min_size": 1, "max_size": 3,
+        });
+        assert_errors(&group, &[
+            (json!({ "desired_size": 4 }), "sizes must satisfy min_size (1) <= desired_size (4) <= max_size (3)"),
+            (json!({ "min_size": 3, "desired_size": 2 }), "min_size (3) <= desired_size (2)"),
+            (json!({}), "node_role_ref 'nodes' is neither a sibling aws_iam_role nor an ARN"),
+        ], |r| render(&[], r));
+        let tf = render(&[("aws_eks_cluster", "main"), ("aws_iam_role", "nodes"), ("aws_subnet", "a")], group).unwrap();
+        assert_eq!(tf["resource"]["aws_eks_node_group"]["general"]["cluster_name"], "${aws_eks_cluster.main.name}");
+        assert_eq!(tf["resource"]["aws_eks_node_group"]["general"]["scaling_config"], json!({ "desired_size": 2, "min_size": 1, "max_size": 3 }));
+    }
+
+    #[test]
+    fn https_listener_needs_a_certificate() {
+        let listener = json!({ "type": "aws_lb_listener", "name": "https", "load_balancer_ref": "web", "port": 443, "protocol": "HTTPS", "target_group_ref": "app" });
+        assert_errors(&listener, &[(json!({}), "HTTPS listeners require certificate_arn")], |r| render(&[], r));
+        let tf = render(&[("aws_lb", "web"), ("aws_lb_target_group", "app")], with(listener, json!({
+            "certificate_arn": "arn:aws:acm:us-east-1:123456789012:certificate/abc", "ssl_policy": "ELBSecurityPolicy-TLS13-1-2-2021-06",
+        }))).unwrap();
+        assert_eq!(tf["resource"]["aws_lb_listener"]["https"], json!({
+            "load_balancer_arn": "${aws_lb.web.arn}", "port": 443, "protocol": "HTTPS",
+            "default_action": [{ "type": "forward", "target_group_arn": "${aws_lb_target_group.app.arn}" }],
+            "certificate_arn": "arn:aws:acm:us-east-1:123456789012:certificate/abc", "ssl_policy": "ELBSecurityPolicy-TLS13-1-2-2021-06",
+        }));
+    }
+
+    fn bucket(extra: Json) -> Json {
+        with(json!({ "type": "aws_s3_bucket", "name": "logs", "bucket": "acme-logs" }), extra)
+    }
+
+    #[test]
+    fn s3_versioning_is_its_own_resource() {
+        let tf = render(&[], bucket(json!({ "versioning": true }))).unwrap();
+        assert_eq!(tf["resource"]["aws_s3_bucket_versioning"]["logs"], json!({
+            "bucket": "${aws_s3_bucket.logs.id}", "versioning_configuration": { "status": "Enabled" },
+        }));
+        let tf = render(&[], bucket(json!({ "versioning": false }))).unwrap();
+        assert_eq!(tf["resource"]["aws_s3_bucket_versioning"]["logs"]["versioning_configuration"]["status"], "Suspended");
+        let tf = render(&[], bucket(json!({}))).unwrap();
+        assert!(tf["resource"].get("aws_s3_bucket_versioning").is_none());
+    }
+
+    #[test]
+    fn s3_lifecycle_rules() {
+        let tf = render(&[], bucket(json!({ "lifecycle_rules": [
+            { "id": "archive", "prefix": "logs/", "transition_days": 30, "storage_class": "GLACIER", "expiration_days": 365 },
+            { "id": "old-versions", "noncurrent_version_expiration_days": 7 },
+        ] }))).unwrap();
+        assert_eq!(tf["resource"]["aws_s3_bucket_lifecycle_configuration"]["logs"], json!({
+            "bucket": "${aws_s3_bucket.logs.id}",
+            "rule": [
+                { "id": "archive", "status": "Enabled", "filter": [{ "prefix": "logs/" }],
+                  "transition": [{ "days": 30, "storage_class": "GLACIER" }], "expiration": [{ "days": 365 }] },
+                { "id": "old-versions", "status": "Enabled", "filter": [{ "prefix": "" }],
+                  "noncurrent_version_expiration": [{ "noncurrent_days": 7 }] },
+            ],
+        }));
+        assert_errors(&bucket(json!({})), &[
+            (json!({ "lifecycle_rules": [{ "id": "noop", "prefix": "tmp/" }] }), "lifecycle rule 'noop' has no transition or expiration"),
+            (json!({ "lifecycle_rules": [{ "id": "archive", "transition_days": 90, "storage_class": "GLACIER", "expiration_days": 90 }] }),
+                "lifecycle rule 'archive' transitions at day 90 but expires at day 90"),
+            (json!({ "lifecycle_rules": [{ "id": "archive", "transition_days": 30 }] }), "lifecycle rule 'archive' needs both transition_days and storage_class"),
+            (json!({ "lifecycle_rules": [{ "id": "archive", "storage_class": "GLACIER", "expiration_days": 30 }] }), "needs both transition_days and storage_class"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn kms_alias_names() {
+        let tf = render(&[], json!({ "type": "aws_kms_key", "name": "data", "alias": "alias/acme-data" })).unwrap();
+        assert_eq!(tf["resource"]["aws_kms_alias"]["data"], json!({ "name": "alias/acme-data", "target_key_id": "${aws_kms_key.data.key_id}" }));
+        let tf = render(&[("aws_kms_key", "data")], json!({ "type": "aws_kms_alias", "name": "data-legacy", "alias_name": "alias/legacy", "target_key_ref": "data" })).unwrap();
+        assert_eq!(tf["resource"]["aws_kms_alias"]["data-legacy"], json!({ "name": "alias/legacy", "target_key_id": "${aws_kms_key.data.key_id}" }));
+
+        assert_errors(&json!({ "type": "aws_kms_key", "name": "data" }), &[
+            (json!({ "alias": "acme-data" }), "alias 'acme-data' must start with 'alias/'"),
+            (json!({ "alias": "alias/aws/s3" }), "alias 'alias/aws/s3' uses the reserved 'alias/aws/' prefix"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "aws_kms_alias", "name": "data-legacy", "target_key_ref": "data" }), &[
+            (json!({ "alias_name": "legacy" }), "alias 'legacy' must start with 'alias/'"),
+            (json!({ "alias_name": "alias/aws/ebs" }), "uses the reserved 'alias/aws/' prefix"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn kms_key_policy_forms() {
+        let key = |policy: Json| json!({ "type": "aws_kms_key", "name": "data", "policy": policy });
+        let tf = render(&[], key(json!([{ "effect": "Allow", "actions": ["kms:*"], "resources": ["*"], "principals": { "AWS": ["arn:aws:iam::123456789012:root"] } }]))).unwrap();
+        assert_eq!(serde_json::from_str::<Json>(tf["resource"]["aws_kms_key"]["data"]["policy"].as_str().unwrap()).unwrap(), json!({
+            "Version": "2012-10-17",
+            "Statement": [{ "Effect": "Allow", "Action": ["kms:*"], "Resource": ["*"], "Principal": { "AWS": ["arn:aws:iam::123456789012:root"] } }],
+        }));
+        let doc = json!({ "Version": "2012-10-17", "Statement": [] });
+        let tf = render(&[], key(doc.clone())).unwrap();
+        assert_eq!(tf["resource"]["aws_kms_key"]["data"]["policy"], doc.to_string());
+        let tf = render(&[], key(json!(doc.to_string()))).unwrap();
+        assert_eq!(tf["resource"]["aws_kms_key"]["data"]["policy"], doc.to_string());
+
+        assert_errors(&key(json!(null)), &[
+            (json!({ "policy": [{ "effect": "Allow", "actions": ["kms:*"] }] }), "key policy statement 0 must name at least one principal"),
+            (json!({ "policy": [{ "effect": "Allow", "actions": ["kms:*"], "principals": { "AWS": [] } }] }), "key policy statement 0 must name at least one principal"),
+            (json!({ "policy": [{ "effect": "Allow", "actions": ["kms:*"], "principals": {} }] }), "must name at least one principal"),
+            (json!({ "policy": [] }), "policy must have at least one statement"),
+            (json!({ "policy": "{ not json" }), "policy is not valid JSON"),
+        ], |r| render(&[], r));
+    }
+
+    fn ssm(extra: Json) -> Json {
+        with(json!({ "type": "aws_ssm_parameter", "name": "db-host", "parameter_name": "/acme/db/host", "parameter_type": "String" }), extra)
+    }
+
+    #[test]
+    fn ssm_parameter_values() {
+        let tf = render(&[], ssm(json!({ "value": "db.internal", "tier": "Standard" }))).unwrap();
+        assert_eq!(tf["resource"]["aws_ssm_parameter"]["db-host"], json!({ "name": "/acme/db/host", "type": "String", "value": "db.internal", "tier": "Standard" }));
+        let mut ctx = ctx(&[("aws_kms_key", "params")]);
+        let tf = render_in(&mut ctx, ssm(json!({ "parameter_type": "SecureString", "value_from": "env:DB_PASSWORD", "kms_key_id": "params" }))).unwrap();
+        assert_eq!(tf["resource"]["aws_ssm_parameter"]["db-host"], json!({
+            "name": "/acme/db/host", "type": "SecureString", "value": "${var.aws_ssm_parameter_db_host}", "key_id": "${aws_kms_key.params.arn}",
+        }));
+        assert_eq!(String::from(ctx.secret_vars()["aws_ssm_parameter_db_host"].clone()), "env:DB_PASSWORD");
+    }
+
+    #[test]
+    fn ssm_parameter_errors() {
+        assert_errors(&ssm(json!({ "value": "db.internal" })), &[
+            (json!({ "parameter_name": "/aws/reference/x" }), "parameter names starting with 'aws' or 'ssm' are reserved"),
+            (json!({ "parameter_name": "SSM-config" }), "are reserved"),
+            (json!({ "kms_key_id": "params" }), "kms_key_id is only valid for SecureString parameters"),
+            (json!({ "parameter_type": "SecureString" }), "SecureString values must use value_from"),
+            (json!({ "value_from": "env:DB_HOST" }), "set exactly one of value or value_from"),
+            (json!({ "value": null }), "set exactly one of value or value_from"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn eventbridge_rule_and_target() {
+        let tf = render(&[], json!({ "type": "aws_cloudwatch_event_rule", "name": "nightly", "schedule_expression": "cron(0 3 * * ? *)", "state": "ENABLED" })).unwrap();
+        assert_eq!(tf["resource"]["aws_cloudwatch_event_rule"]["nightly"], json!({ "name": "nightly", "schedule_expression": "cron(0 3 * * ? *)", "state": "ENABLED" }));
+        let tf = render(&[], json!({ "type": "aws_cloudwatch_event_rule", "name": "uploads", "event_pattern": { "source": ["aws.s3"] } })).unwrap();
+        assert_eq!(tf["resource"]["aws_cloudwatch_event_rule"]["uploads"]["event_pattern"], r#"{"source":["aws.s3"]}"#);
+        let tf = render(&[("aws_cloudwatch_event_rule", "nightly"), ("aws_sqs_queue", "jobs"), ("aws_iam_role", "events")], json!({
+            "type": "aws_cloudwatch_event_target", "name": "nightly-jobs", "rule_ref": "nightly", "arn_or_ref": "jobs", "role_ref": "events", "input": { "kind": "nightly" },
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_cloudwatch_event_target"]["nightly-jobs"], json!({
+            "rule": "${aws_cloudwatch_event_rule.nightly.name}", "arn": "${aws_sqs_queue.jobs.arn}",
+            "role_arn": "${aws_iam_role.events.arn}", "input": r#"{"kind":"nightly"}"#,
+        }));
+
+        assert_errors(&json!({ "type": "aws_cloudwatch_event_rule", "name": "nightly" }), &[
+            (json!({}), "set exactly one of schedule_expression or event_pattern"),
+            (json!({ "schedule_expression": "rate(1 day)", "event_pattern": { "source": ["aws.s3"] } }), "set exactly one of schedule_expression or event_pattern"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "aws_cloudwatch_event_target", "name": "nightly-jobs", "rule_ref": "nightly", "arn_or_ref": "arn:aws:sqs:us-east-1:123456789012:jobs" }), &[
+            (json!({ "arn_or_ref": "jobs" }), "'jobs' is neither a sibling Lambda/SNS/SQS resource nor an ARN"),
+            (json!({ "role_ref": "events" }), "role_ref 'events' is neither a sibling aws_iam_role nor an ARN"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn kinesis_stream_modes() {
+        let tf = render(&[("aws_kms_key", "streams")], json!({
+            "type": "aws_kinesis_stream", "name": "clicks", "shard_count": 2, "retention_period_hours": 48, "kms_key_ref": "streams",
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_kinesis_stream"]["clicks"], json!({
+            "name": "clicks", "stream_mode_details": { "stream_mode": "PROVISIONED" }, "shard_count": 2, "retention_period": 48,
+            "encryption_type": "KMS", "kms_key_id": "${aws_kms_key.streams.arn}",
+        }));
+        let tf = render(&[], json!({ "type": "aws_kinesis_stream", "name": "clicks", "stream_mode": "ON_DEMAND" })).unwrap();
+        assert_eq!(tf["resource"]["aws_kinesis_stream"]["clicks"], json!({ "name": "clicks", "stream_mode_details": { "stream_mode": "ON_DEMAND" } }));
+
+        assert_errors(&json!({ "type": "aws_kinesis_stream", "name": "clicks" }), &[
+            (json!({}), "PROVISIONED streams require shard_count"),
+            (json!({ "stream_mode": "ON_DEMAND", "shard_count": 1 }), "ON_DEMAND streams cannot set shard_count"),
+            (json!({ "shard_count": 1, "retention_period_hours": 12 }), "retention_period_hours 12 must be between 24 and 8760"),
+            (json!({ "shard_count": 1, "retention_period_hours": 8761 }), "retention_period_hours 8761 must be between 24 and 8760"),
+        ], |r| render(&[], r));
+    }
+
+    fn state_machine(extra: Json) -> Json {
+        with(json!({
+            "type": "aws_sfn_state_machine", "name": "orders", "role_ref": "sfn",
+            "definition": { "StartAt": "Charge", "States": { "Charge": { "Type": "Pass", "End": true } } },
+        }), extra)
+    }
+
+    #[test]
+    fn step_functions_definition_and_logging() {
+        let tf = render(&[("aws_iam_role", "sfn"), ("aws_cloudwatch_log_group", "sfn")], state_machine(json!({
+            "machine_type": "EXPRESS", "logging": { "log_group_ref": "sfn", "include_execution_data": true },
+        }))).unwrap();
+        let sm = &tf["resource"]["aws_sfn_state_machine"]["orders"];
+        assert_eq!(sm["role_arn"], "${aws_iam_role.sfn.arn}");
+        assert_eq!(sm["type"], "EXPRESS");
+        assert_eq!(serde_json::from_str::<Json>(sm["definition"].as_str().unwrap()).unwrap()["StartAt"], "Charge");
+        assert_eq!(sm["logging_configuration"], json!({
+            "log_destination": "${aws_cloudwatch_log_group.sfn.arn}:*", "include_execution_data": true, "level": "ERROR",
+        }));
+        // An ARN that already carries the `:*` suffix is not doubled.
+        let tf = render(&[("aws_iam_role", "sfn")], state_machine(json!({
+            "logging": { "log_group_ref": "arn:aws:logs:us-east-1:123456789012:log-group:/sfn/orders:*", "level": "ALL" },
+        }))).unwrap();
+        assert_eq!(tf["resource"]["aws_sfn_state_machine"]["orders"]["logging_configuration"]["log_destination"],
+            "arn:aws:logs:us-east-1:123456789012:log-group:/sfn/orders:*");
+    }
+
+    #[test]
+    fn step_functions_errors() {
+        assert_errors(&state_machine(json!({})), &[
+            (json!({ "definition": { "States": {} } }), "definition needs a StartAt state name"),
+            (json!({ "definition": { "StartAt": "Charge" } }), "definition needs a States map"),
+            (json!({ "definition": { "StartAt": "Ship", "States": { "Charge": { "Type": "Pass", "End": true } } } }), "StartAt 'Ship' is not one of the States"),
+            (json!({}), "role_ref 'sfn' is neither a sibling aws_iam_role nor an ARN"),
+            (json!({ "role_ref": "arn:aws:iam::123456789012:role/sfn", "logging": { "log_group_ref": "sfn" } }),
+                "log_group_ref 'sfn' is neither a sibling aws_cloudwatch_log_group nor an ARN"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn efs_is_encrypted_unless_disabled() {
+        let tf = render(&[], json!({ "type": "aws_efs_file_system", "name": "shared" })).unwrap();
+        assert_eq!(tf["resource"]["aws_efs_file_system"]["shared"], json!({ "creation_token": "shared", "encrypted": true }));
+        let tf = render(&[("aws_kms_key", "efs")], json!({
+            "type": "aws_efs_file_system", "name": "shared", "kms_key_ref": "efs", "throughput_mode": "elastic", "lifecycle_policy": "AFTER_30_DAYS",
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_efs_file_system"]["shared"], json!({
+            "creation_token": "shared", "encrypted": true, "kms_key_id": "${aws_kms_key.efs.arn}",
+            "throughput_mode": "elastic", "lifecycle_policy": [{ "transition_to_ia": "AFTER_30_DAYS" }],
+        }));
+        let tf = render(&[], json!({ "type": "aws_efs_file_system", "name": "scratch", "encrypted": false })).unwrap();
+        assert_eq!(tf["resource"]["aws_efs_file_system"]["scratch"]["encrypted"], false);
+
+        let tf = render(&[("aws_efs_file_system", "shared"), ("aws_subnet", "a"), ("aws_security_group", "nfs")], json!({
+            "type": "aws_efs_mount_target", "name": "shared-a", "file_system_ref": "shared", "subnet_ref": "a", "security_group_refs": ["nfs"],
+        })).unwrap();
+        assert_eq!(tf["resource"]["aws_efs_mount_target"]["shared-a"], json!({
+            "file_system_id": "${aws_efs_file_system.shared.id}", "subnet_id": "${aws_subnet.a.id}", "security_groups": ["${aws_security_group.nfs.id}"],
+        }));
+    }
+
+    #[test]
+    fn provider_aliases() {
+        let providers = |p: Json| serde_json::from_value::<AwsProviders>(p).unwrap().to_tf_json();
+        let tf = providers(json!([{ "region": "us-east-1" }, { "region": "eu-west-1", "alias": "eu" }])).unwrap();
+        assert_eq!(tf, json!({ "provider": { "aws": [{ "region": "us-east-1" }, { "region": "eu-west-1", "alias": "eu" }] } }));
+        assert_eq!(providers(json!([{ "region": "eu-west-1", "alias": "eu" }, { "region": "eu-central-1", "alias": "eu" }])).unwrap_err().to_string(),
+            "provider.aws: alias 'eu' is defined more than once");
+        assert_eq!(providers(json!([{ "region": "us-east-1" }, { "region": "us-west-2" }])).unwrap_err().to_string(),
+            "provider.aws: only one entry may omit alias");
+
+        let all: AwsProviders = serde_json::from_value(json!([{ "region": "eu-west-1", "alias": "eu" }, { "region": "us-east-1", "alias": "us" }])).unwrap();
+        assert_eq!(all.default_provider().unwrap().region, "eu-west-1");
+        assert!(all.has_alias("us") && !all.has_alias("ap"));
+    }
+}
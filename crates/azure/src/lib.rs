@@ -1,14 +1,1239 @@
+use anyhow::{Context, Result};
+use r2iac_core::{check_cidr, secret_var_name, RenderCtx, ValueFrom};
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value as Json, Map as JsonMap};
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AzureProvider { #[serde(default)] pub features: JsonMap<String, Json>, pub subscription_id: Option<String> }
+pub struct AzureProvider {
+    #[serde(default)] pub features: JsonMap<String, Json>,
+    pub subscription_id: Option<String>,
+    #[serde(default)] pub tenant_id: Option<String>,
+    #[serde(default)] pub client_id: Option<String>,
+    /// Resolved by the CLI and handed to the runner as `ARM_CLIENT_SECRET`.
+    #[serde(default)] pub client_secret_from: Option<ValueFrom>,
+    #[serde(default)] pub environment: Option<AzureEnvironment>,
+    #[serde(default)] pub use_oidc: bool,
+    #[serde(default)] pub skip_provider_registration: bool,
+}
 impl AzureProvider {
     pub fn to_tf_json(&self) -> Json {
         let mut provider = json!({ "features": self.features });
         if let Some(sid) = &self.subscription_id { provider["subscription_id"] = json!(sid); }
+        if self.skip_provider_registration { provider["resource_provider_registrations"] = json!("none"); }
         json!({ "provider": { "azurerm": provider } })
     }
+
+    /// Non-secret `ARM_*` variables for the runner; identity settings stay out of
+    /// main.tf.json so the same config works across CI identities.
+    pub fn runner_env(&self) -> Vec<(&'static str, String)> {
+        let mut env = Vec::new();
+        if let Some(t) = &self.tenant_id { env.push(("ARM_TENANT_ID", t.clone())); }
+        if let Some(c) = &self.client_id { env.push(("ARM_CLIENT_ID", c.clone())); }
+        if let Some(e) = self.environment { env.push(("ARM_ENVIRONMENT", e.as_str().to_string())); }
+        if self.use_oidc { env.push(("ARM_USE_OIDC", "true".to_string())); }
+        env
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum AzureEnvironment { Public, UsGovernment, China }
+
+impl AzureEnvironment {
+    fn as_str(self) -> &'static str {
+        match self {
+            AzureEnvironment::Public => "public",
+            AzureEnvironment::UsGovernment => "usgovernment",
+            AzureEnvironment::China => "china",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag="type")]
+pub enum AzureResource {
+    #[serde(rename="azurerm_resource_group")]
+    ResourceGroup {
+        name: String,
+        location: String,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_storage_account")]
+    StorageAccount {
+        name: String,
+        resource_group_ref: String,
+        /// Defaults to the sibling resource group's location.
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default)]
+        account_tier: AccountTier,
+        #[serde(default)]
+        account_replication_type: ReplicationType,
+        #[serde(default)]
+        min_tls_version: Option<String>,
+        #[serde(default="default_true")]
+        https_traffic_only: bool,
+        #[serde(default)]
+        allow_blob_public_access: bool,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_key_vault")]
+    KeyVault {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        /// Defaults to the tenant Terraform is authenticated against.
+        #[serde(default)]
+        tenant_id: Option<String>,
+        #[serde(default)]
+        sku_name: KeyVaultSku,
+        #[serde(default="default_true")]
+        purge_protection_enabled: bool,
+        #[serde(default)]
+        soft_delete_retention_days: Option<u32>,
+        #[serde(default)]
+        enable_rbac_authorization: Option<bool>,
+        #[serde(default)]
+        network_acls: Option<NetworkAcls>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_key_vault_secret")]
+    KeyVaultSecret {
+        name: String,
+        key_vault_ref: String,
+        secret_name: String,
+        /// Passed to Terraform as a sensitive variable, never inlined.
+        value_from: ValueFrom,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_virtual_network")]
+    VirtualNetwork {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        address_space: Vec<String>,
+        #[serde(default)]
+        dns_servers: Vec<String>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_subnet")]
+    Subnet {
+        name: String,
+        vnet_ref: String,
+        /// Only needed when `vnet_ref` is not a sibling network.
+        #[serde(default)]
+        resource_group_ref: Option<String>,
+        address_prefixes: Vec<String>,
+        #[serde(default)]
+        service_endpoints: Vec<String>,
+        #[serde(default)]
+        delegation: Option<SubnetDelegation>,
+    },
+    #[serde(rename="azurerm_network_security_group")]
+    NetworkSecurityGroup {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default)]
+        rules: Vec<NsgRule>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_linux_virtual_machine")]
+    LinuxVirtualMachine {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        size: String,
+        admin_username: String,
+        /// An `ssh-...` key literal, or a path to a `.pub` file.
+        #[serde(default)]
+        admin_ssh_public_key: Option<String>,
+        /// Opt-in password login; the password is passed as a sensitive variable.
+        #[serde(default)]
+        admin_password_from: Option<ValueFrom>,
+        /// The VM's NIC is emitted alongside it, attached to this subnet.
+        subnet_ref: String,
+        #[serde(default)]
+        os_disk: OsDisk,
+        source_image_reference: SourceImage,
+        /// Also set on the NIC emitted with the VM.
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_kubernetes_cluster")]
+    KubernetesCluster {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        /// Defaults to the cluster name.
+        #[serde(default)]
+        dns_prefix: Option<String>,
+        default_node_pool: AksNodePool,
+        #[serde(default)]
+        identity: Identity,
+        #[serde(default)]
+        network_profile: Option<AksNetworkProfile>,
+        #[serde(default)]
+        private_cluster_enabled: bool,
+        #[serde(default)]
+        api_server_authorized_ip_ranges: Vec<String>,
+        #[serde(default)]
+        oidc_issuer_enabled: bool,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_service_plan")]
+    ServicePlan {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default)]
+        os_type: OsType,
+        sku_name: String,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_linux_web_app")]
+    LinuxWebApp {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        service_plan_ref: String,
+        #[serde(default)]
+        site_config: SiteConfig,
+        #[serde(default)]
+        app_settings: BTreeMap<String, AppSetting>,
+        #[serde(default="default_true")]
+        https_only: bool,
+        #[serde(default)]
+        identity: Option<Identity>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_mssql_server")]
+    MssqlServer {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default="default_mssql_version")]
+        version: String,
+        #[serde(default)]
+        administrator_login: Option<String>,
+        #[serde(default)]
+        administrator_password_from: Option<ValueFrom>,
+        #[serde(default)]
+        azuread_administrator: Option<AzureAdAdministrator>,
+        #[serde(default="default_tls12")]
+        minimum_tls_version: String,
+        #[serde(default)]
+        public_network_access_enabled: bool,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_mssql_database")]
+    MssqlDatabase {
+        name: String,
+        server_ref: String,
+        #[serde(default)]
+        sku_name: Option<String>,
+        #[serde(default)]
+        max_size_gb: Option<u32>,
+        #[serde(default)]
+        zone_redundant: Option<bool>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_container_registry")]
+    ContainerRegistry {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default)]
+        sku: AcrSku,
+        #[serde(default)]
+        admin_enabled: bool,
+        #[serde(default="default_true")]
+        public_network_access_enabled: bool,
+        #[serde(default)]
+        zone_redundancy_enabled: bool,
+        /// Premium only.
+        #[serde(default)]
+        georeplications: Vec<Georeplication>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_log_analytics_workspace")]
+    LogAnalyticsWorkspace {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default="default_law_sku")]
+        sku: String,
+        #[serde(default)]
+        retention_in_days: Option<u32>,
+        /// `-1` for no cap.
+        #[serde(default)]
+        daily_quota_gb: Option<f64>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_monitor_diagnostic_setting")]
+    MonitorDiagnosticSetting {
+        name: String,
+        /// `<azurerm type>.<name>` of any sibling resource, or a literal resource id.
+        target_resource_ref: String,
+        workspace_ref: String,
+        #[serde(default)]
+        enabled_log_categories: Vec<String>,
+        #[serde(default="default_true")]
+        metrics_enabled: bool,
+    },
+    #[serde(rename="azurerm_role_assignment")]
+    RoleAssignment {
+        name: String,
+        /// `<azurerm type>.<name>` of a sibling resource, or a literal scope such as `/subscriptions/<id>`.
+        scope_ref: String,
+        role_definition_name: String,
+        /// `<azurerm type>.<name>` of a sibling with a managed identity, or a literal object id.
+        principal_id_ref: String,
+    },
+    #[serde(rename="azurerm_cosmosdb_account")]
+    CosmosDbAccount {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default="default_offer_type")]
+        offer_type: String,
+        #[serde(default)]
+        kind: CosmosDbKind,
+        #[serde(default)]
+        consistency_policy: ConsistencyPolicy,
+        /// Defaults to the account location alone.
+        #[serde(default)]
+        geo_locations: Vec<GeoLocation>,
+        #[serde(default)]
+        public_network_access_enabled: bool,
+        #[serde(default)]
+        backup: Option<CosmosDbBackup>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    #[serde(rename="azurerm_cosmosdb_sql_database")]
+    CosmosDbSqlDatabase {
+        name: String,
+        account_ref: String,
+        /// Required when the account is not a sibling.
+        #[serde(default)]
+        resource_group_ref: Option<String>,
+        #[serde(default)]
+        throughput: Option<u32>,
+        #[serde(default)]
+        autoscale_max_throughput: Option<u32>,
+    },
+    #[serde(rename="azurerm_linux_function_app")]
+    LinuxFunctionApp {
+        name: String,
+        resource_group_ref: String,
+        #[serde(default)]
+        location: Option<String>,
+        service_plan_ref: String,
+        storage_account_ref: String,
+        /// Needed only when `storage_account_ref` is not a sibling storage account.
+        #[serde(default)]
+        storage_account_access_key_from: Option<ValueFrom>,
+        runtime: FunctionRuntime,
+        #[serde(default)]
+        app_settings: BTreeMap<String, AppSetting>,
+        #[serde(default="default_true")]
+        https_only: bool,
+        #[serde(default)]
+        identity: Option<Identity>,
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum AccountTier { #[default] Standard, Premium }
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all="UPPERCASE")]
+pub enum ReplicationType { #[default] Lrs, Grs, Ragrs, Zrs, Gzrs, Ragzrs }
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum KeyVaultSku { #[default] Standard, Premium }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkAcls {
+    #[serde(default="default_deny")]
+    pub default_action: String,
+    #[serde(default="default_bypass")]
+    pub bypass: String,
+    #[serde(default)]
+    pub ip_rules: Vec<String>,
+    /// Sibling `azurerm_subnet` names or subnet ids.
+    #[serde(default)]
+    pub subnet_refs: Vec<String>,
+}
+
+fn default_deny() -> String { "Deny".to_string() }
+fn default_bypass() -> String { "AzureServices".to_string() }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubnetDelegation {
+    pub name: String,
+    /// e.g. `Microsoft.Web/serverFarms`.
+    pub service: String,
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NsgRule {
+    pub name: String,
+    pub priority: u32,
+    pub direction: NsgDirection,
+    pub access: NsgAccess,
+    /// `Tcp`, `Udp`, `Icmp`, or `*`.
+    #[serde(default="default_any")]
+    pub protocol: String,
+    #[serde(default)]
+    pub source_ports: Vec<String>,
+    #[serde(default)]
+    pub destination_ports: Vec<String>,
+    #[serde(default)]
+    pub source_prefixes: Vec<String>,
+    #[serde(default)]
+    pub destination_prefixes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum NsgDirection { Inbound, Outbound }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum NsgAccess { Allow, Deny }
+
+fn default_any() -> String { "*".to_string() }
+
+/// The provider wants the singular attribute for one value and the plural for several;
+/// inline rules in JSON must carry both keys, so the unused one is null / empty.
+fn single_or_many(rule: &mut Json, single: &str, many: &str, values: &[String]) {
+    match values {
+        [] => { rule[single] = json!("*"); rule[many] = json!([]); }
+        [v] => { rule[single] = json!(v); rule[many] = json!([]); }
+        vs => { rule[single] = Json::Null; rule[many] = json!(vs); }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsDisk {
+    #[serde(default="default_caching")]
+    pub caching: String,
+    #[serde(default="default_disk_type")]
+    pub storage_account_type: String,
+    #[serde(default)]
+    pub disk_size_gb: Option<u32>,
+}
+
+impl Default for OsDisk {
+    fn default() -> Self { Self { caching: default_caching(), storage_account_type: default_disk_type(), disk_size_gb: None } }
+}
+
+fn default_caching() -> String { "ReadWrite".to_string() }
+fn default_disk_type() -> String { "Premium_LRS".to_string() }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceImage {
+    pub publisher: String,
+    pub offer: String,
+    pub sku: String,
+    #[serde(default="default_latest")]
+    pub version: String,
+}
+
+fn default_latest() -> String { "latest".to_string() }
+
+/// Managed identity block shared by AKS, web apps and function apps.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Identity {
+    #[serde(default, rename="type")]
+    pub identity_type: IdentityType,
+    /// Sibling `azurerm_user_assigned_identity` names or identity ids.
+    #[serde(default)]
+    pub identity_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdentityType {
+    #[default] SystemAssigned,
+    UserAssigned,
+    #[serde(rename="SystemAssigned, UserAssigned")] SystemAndUserAssigned,
+}
+
+impl Identity {
+    fn to_tf_json(&self, ctx: &RenderCtx, owner: &str) -> Result<Json> {
+        let user = self.identity_type != IdentityType::SystemAssigned;
+        if user == self.identity_ids.is_empty() {
+            anyhow::bail!("{}: identity_ids must be set exactly when the identity includes UserAssigned", owner);
+        }
+        let mut block = json!({ "type": self.identity_type });
+        if user {
+            block["identity_ids"] = json!(self.identity_ids.iter().map(|i| ctx.resolve("azurerm_user_assigned_identity", i, "id")).collect::<Vec<_>>());
+        }
+        Ok(json!([block]))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AksNodePool {
+    pub name: String,
+    pub vm_size: String,
+    #[serde(default)]
+    pub node_count: Option<u32>,
+    #[serde(default)]
+    pub autoscaling: Option<AksAutoscaling>,
+    #[serde(default)]
+    pub vnet_subnet_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AksAutoscaling { pub min: u32, pub max: u32 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AksNetworkProfile {
+    /// `azure`, `kubenet` or `none`.
+    pub network_plugin: String,
+    #[serde(default)]
+    pub service_cidr: Option<String>,
+    #[serde(default)]
+    pub dns_service_ip: Option<String>,
+    #[serde(default)]
+    pub pod_cidr: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum OsType { #[default] Linux, Windows, WindowsContainer }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SiteConfig {
+    /// Rendered as the `application_stack` block, e.g. `{ node_version: "20-lts" }`.
+    #[serde(default)]
+    pub application_stack: BTreeMap<String, String>,
+    #[serde(default)]
+    pub always_on: Option<bool>,
+    #[serde(default)]
+    pub health_check_path: Option<String>,
+}
+
+/// A plain app setting, or `{ value_from: ... }` to route it through a sensitive variable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AppSetting { Value(String), Secret { value_from: ValueFrom } }
+
+/// Render app settings, registering a sensitive variable for each secret one.
+fn app_settings(ctx: &mut RenderCtx, type_name: &str, name: &str, settings: &BTreeMap<String, AppSetting>) -> JsonMap<String, Json> {
+    settings.iter().map(|(k, v)| {
+        let value = match v {
+            AppSetting::Value(s) => s.clone(),
+            AppSetting::Secret { value_from } => ctx.secret_var(&secret_var_name(type_name, &format!("{}_{}", name, k)), value_from),
+        };
+        (k.clone(), json!(value))
+    }).collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionRuntime {
+    pub stack: FunctionStack,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum FunctionStack { Node, Python, Dotnet }
+
+impl FunctionRuntime {
+    /// The `application_stack` attribute and its provider-accepted value.
+    fn application_stack(&self, owner: &str) -> Result<(&'static str, String)> {
+        let v = self.version.as_str();
+        Ok(match self.stack {
+            FunctionStack::Node if ["18", "20"].contains(&v) => ("node_version", v.to_string()),
+            FunctionStack::Python if ["3.10", "3.11"].contains(&v) => ("python_version", v.to_string()),
+            FunctionStack::Dotnet if ["6", "6.0", "8", "8.0"].contains(&v) => ("dotnet_version", format!("{}.0", v.trim_end_matches(".0"))),
+            _ => anyhow::bail!("{}: unsupported runtime {:?} {} (node 18/20, python 3.10/3.11, dotnet 6/8)", owner, self.stack, v),
+        })
+    }
+}
+
+fn default_mssql_version() -> String { "12.0".to_string() }
+fn default_tls12() -> String { "1.2".to_string() }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureAdAdministrator {
+    pub login_username: String,
+    pub object_id: String,
+    #[serde(default)]
+    pub azuread_authentication_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AcrSku { Basic, #[default] Standard, Premium }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Georeplication {
+    pub location: String,
+    #[serde(default)]
+    pub zone_redundancy_enabled: bool,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+fn default_law_sku() -> String { "PerGB2018".to_string() }
+
+/// Common built-in roles; anything else is assumed custom and only warned about.
+const BUILTIN_ROLES: &[&str] = &[
+    "Owner", "Contributor", "Reader", "User Access Administrator",
+    "Storage Blob Data Owner", "Storage Blob Data Contributor", "Storage Blob Data Reader",
+    "Storage Queue Data Contributor", "Storage Queue Data Reader",
+    "Key Vault Administrator", "Key Vault Secrets User", "Key Vault Secrets Officer",
+    "Key Vault Crypto User", "Key Vault Crypto Officer", "Key Vault Reader",
+    "AcrPull", "AcrPush", "AcrDelete",
+    "Azure Kubernetes Service Cluster User Role", "Azure Kubernetes Service RBAC Reader",
+    "Azure Kubernetes Service RBAC Writer", "Azure Kubernetes Service RBAC Admin",
+    "Network Contributor", "Virtual Machine Contributor", "Monitoring Reader",
+    "Monitoring Metrics Publisher", "Log Analytics Contributor", "Log Analytics Reader",
+    "Website Contributor", "SQL DB Contributor", "SQL Server Contributor",
+    "Cosmos DB Account Reader Role", "DocumentDB Account Contributor",
+    "Managed Identity Operator", "Service Bus Data Owner", "Service Bus Data Sender", "Service Bus Data Receiver",
+];
+
+fn default_offer_type() -> String { "Standard".to_string() }
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum CosmosDbKind { #[default] GlobalDocumentDB, MongoDB, Parse }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag="level")]
+pub enum ConsistencyPolicy {
+    BoundedStaleness { max_interval_in_seconds: u32, max_staleness_prefix: u32 },
+    Strong,
+    #[default]
+    Session,
+    ConsistentPrefix,
+    Eventual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoLocation {
+    pub location: String,
+    pub failover_priority: u32,
+    #[serde(default)]
+    pub zone_redundant: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag="type")]
+pub enum CosmosDbBackup {
+    Periodic {
+        #[serde(default)]
+        interval_in_minutes: Option<u32>,
+        #[serde(default)]
+        retention_in_hours: Option<u32>,
+        #[serde(default)]
+        storage_redundancy: Option<String>,
+    },
+    Continuous {
+        /// `Continuous7Days` or `Continuous30Days`.
+        #[serde(default)]
+        tier: Option<String>,
+    },
+}
+
+fn default_true() -> bool { true }
+
+fn rg_name(ctx: &RenderCtx, r: &str) -> String {
+    ctx.resolve("azurerm_resource_group", r, "name")
+}
+
+/// `attr` of a sibling named as `<azurerm type>.<name>`, or a literal id starting with `/`.
+fn resource_ref(ctx: &RenderCtx, owner: &str, field: &str, r: &str, attr: &str) -> Result<String> {
+    if r.starts_with('/') { return Ok(r.to_string()); }
+    r.split_once('.')
+        .filter(|(t, _)| t.starts_with("azurerm_"))
+        .and_then(|(t, n)| ctx.sibling(t, n, attr))
+        .ok_or_else(|| anyhow::anyhow!("{}: {} '{}' must be `<azurerm type>.<name>` of a sibling resource or a literal resource id", owner, field, r))
+}
+
+/// An explicit location, or the parent resource group's when it is a sibling.
+fn location(ctx: &RenderCtx, owner: &str, rg_ref: &str, location: &Option<String>) -> Result<String> {
+    match location {
+        Some(l) => Ok(l.clone()),
+        None => ctx.sibling("azurerm_resource_group", rg_ref, "location").ok_or_else(|| anyhow::anyhow!(
+            "{}: location is required when resource_group_ref '{}' is not a sibling resource group", owner, rg_ref)),
+    }
+}
+
+impl AzureResource {
+    /// Terraform type and logical name this resource is declared under.
+    pub fn type_and_name(&self) -> (&'static str, &str) {
+        match self {
+            AzureResource::ResourceGroup { name, .. } => ("azurerm_resource_group", name),
+            AzureResource::StorageAccount { name, .. } => ("azurerm_storage_account", name),
+            AzureResource::KeyVault { name, .. } => ("azurerm_key_vault", name),
+            AzureResource::KeyVaultSecret { name, .. } => ("azurerm_key_vault_secret", name),
+            AzureResource::VirtualNetwork { name, .. } => ("azurerm_virtual_network", name),
+            AzureResource::Subnet { name, .. } => ("azurerm_subnet", name),
+            AzureResource::NetworkSecurityGroup { name, .. } => ("azurerm_network_security_group", name),
+            AzureResource::LinuxVirtualMachine { name, .. } => ("azurerm_linux_virtual_machine", name),
+            AzureResource::KubernetesCluster { name, .. } => ("azurerm_kubernetes_cluster", name),
+            AzureResource::ServicePlan { name, .. } => ("azurerm_service_plan", name),
+            AzureResource::LinuxWebApp { name, .. } => ("azurerm_linux_web_app", name),
+            AzureResource::MssqlServer { name, .. } => ("azurerm_mssql_server", name),
+            AzureResource::MssqlDatabase { name, .. } => ("azurerm_mssql_database", name),
+            AzureResource::ContainerRegistry { name, .. } => ("azurerm_container_registry", name),
+            AzureResource::LogAnalyticsWorkspace { name, .. } => ("azurerm_log_analytics_workspace", name),
+            AzureResource::MonitorDiagnosticSetting { name, .. } => ("azurerm_monitor_diagnostic_setting", name),
+            AzureResource::RoleAssignment { name, .. } => ("azurerm_role_assignment", name),
+            AzureResource::CosmosDbAccount { name, .. } => ("azurerm_cosmosdb_account", name),
+            AzureResource::CosmosDbSqlDatabase { name, .. } => ("azurerm_cosmosdb_sql_database", name),
+            AzureResource::LinuxFunctionApp { name, .. } => ("azurerm_linux_function_app", name),
+        }
+    }
+
+    pub fn to_tf_json(&self, ctx: &mut RenderCtx) -> Result<Json> {
+        Ok(match self {
+            AzureResource::ResourceGroup { name, location, tags } => {
+                let mut body = json!({ "name": name, "location": location });
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_resource_group": { name: body } } })
+            }
+            AzureResource::StorageAccount { name, resource_group_ref, location: loc, account_tier, account_replication_type, min_tls_version, https_traffic_only, allow_blob_public_access, tags } => {
+                let owner = format!("azurerm_storage_account.{}", name);
+                if !(3..=24).contains(&name.len()) || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+                    anyhow::bail!("{}: storage account names must be 3-24 lowercase letters and digits", owner);
+                }
+                let tls = min_tls_version.as_deref().unwrap_or("TLS1_2");
+                if !["TLS1_0", "TLS1_1", "TLS1_2"].contains(&tls) {
+                    anyhow::bail!("{}: min_tls_version '{}' must be TLS1_0, TLS1_1 or TLS1_2", owner, tls);
+                }
+                let mut body = json!({
+                    "name": name,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                    "account_tier": account_tier,
+                    "account_replication_type": account_replication_type,
+                    "min_tls_version": tls,
+                    "https_traffic_only_enabled": https_traffic_only,
+                    "allow_nested_items_to_be_public": allow_blob_public_access,
+                });
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_storage_account": { name: body } } })
+            }
+            AzureResource::KeyVault { name, resource_group_ref, location: loc, tenant_id, sku_name, purge_protection_enabled, soft_delete_retention_days, enable_rbac_authorization, network_acls, tags } => {
+                let owner = format!("azurerm_key_vault.{}", name);
+                let valid = (3..=24).contains(&name.len())
+                    && name.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && !name.ends_with('-') && !name.contains("--")
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+                if !valid {
+                    anyhow::bail!("{}: key vault names must be 3-24 letters, digits and single hyphens, starting with a letter", owner);
+                }
+                let mut o = json!({});
+                let tenant = match tenant_id {
+                    Some(t) => t.clone(),
+                    None => {
+                        o["data"]["azurerm_client_config"]["current"] = json!({});
+                        "${data.azurerm_client_config.current.tenant_id}".to_string()
+                    }
+                };
+                let mut body = json!({
+                    "name": name,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                    "tenant_id": tenant, "sku_name": sku_name,
+                    "purge_protection_enabled": purge_protection_enabled,
+                });
+                if let Some(d) = soft_delete_retention_days {
+                    if !(7..=90).contains(d) {
+                        anyhow::bail!("{}: soft_delete_retention_days {} must be between 7 and 90", owner, d);
+                    }
+                    body["soft_delete_retention_days"] = json!(d);
+                }
+                if let Some(r) = enable_rbac_authorization { body["enable_rbac_authorization"] = json!(r); }
+                if let Some(acl) = network_acls {
+                    if !["Allow", "Deny"].contains(&acl.default_action.as_str()) {
+                        anyhow::bail!("{}: network_acls default_action must be Allow or Deny", owner);
+                    }
+                    if !["AzureServices", "None"].contains(&acl.bypass.as_str()) {
+                        anyhow::bail!("{}: network_acls bypass must be AzureServices or None", owner);
+                    }
+                    let subnets: Vec<_> = acl.subnet_refs.iter().map(|s| ctx.resolve("azurerm_subnet", s, "id")).collect();
+                    body["network_acls"] = json!([{
+                        "default_action": acl.default_action, "bypass": acl.bypass,
+                        "ip_rules": acl.ip_rules, "virtual_network_subnet_ids": subnets,
+                    }]);
+                }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                o["resource"]["azurerm_key_vault"][name] = body;
+                o
+            }
+            AzureResource::KeyVaultSecret { name, key_vault_ref, secret_name, value_from, tags } => {
+                if secret_name.is_empty() || secret_name.len() > 127 || !secret_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                    anyhow::bail!("azurerm_key_vault_secret.{}: secret_name must be 1-127 letters, digits and hyphens", name);
+                }
+                let value = ctx.secret_var(&secret_var_name("azurerm_key_vault_secret", name), value_from);
+                let mut body = json!({
+                    "name": secret_name, "value": value,
+                    "key_vault_id": ctx.resolve("azurerm_key_vault", key_vault_ref, "id"),
+                });
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_key_vault_secret": { name: body } } })
+            }
+            AzureResource::VirtualNetwork { name, resource_group_ref, location: loc, address_space, dns_servers, tags } => {
+                let owner = format!("azurerm_virtual_network.{}", name);
+                if address_space.is_empty() { anyhow::bail!("{}: address_space must not be empty", owner); }
+                for c in address_space { check_cidr(&owner, c)?; }
+                let mut body = json!({
+                    "name": name, "address_space": address_space,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                });
+                if !dns_servers.is_empty() { body["dns_servers"] = json!(dns_servers); }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_virtual_network": { name: body } } })
+            }
+            AzureResource::Subnet { name, vnet_ref, resource_group_ref, address_prefixes, service_endpoints, delegation } => {
+                let owner = format!("azurerm_subnet.{}", name);
+                if address_prefixes.is_empty() { anyhow::bail!("{}: address_prefixes must not be empty", owner); }
+                for c in address_prefixes { check_cidr(&owner, c)?; }
+                // A sibling network knows its own resource group.
+                let rg = match (ctx.sibling("azurerm_virtual_network", vnet_ref, "resource_group_name"), resource_group_ref) {
+                    (_, Some(r)) => rg_name(ctx, r),
+                    (Some(rg), None) => rg,
+                    (None, None) => anyhow::bail!("{}: resource_group_ref is required when vnet_ref '{}' is not a sibling network", owner, vnet_ref),
+                };
+                let mut body = json!({
+                    "name": name, "address_prefixes": address_prefixes, "resource_group_name": rg,
+                    "virtual_network_name": ctx.resolve("azurerm_virtual_network", vnet_ref, "name"),
+                });
+                if !service_endpoints.is_empty() { body["service_endpoints"] = json!(service_endpoints); }
+                if let Some(d) = delegation {
+                    let mut svc = json!({ "name": d.service });
+                    if !d.actions.is_empty() { svc["actions"] = json!(d.actions); }
+                    body["delegation"] = json!([{ "name": d.name, "service_delegation": [svc] }]);
+                }
+                json!({ "resource": { "azurerm_subnet": { name: body } } })
+            }
+            AzureResource::NetworkSecurityGroup { name, resource_group_ref, location: loc, rules, tags } => {
+                let owner = format!("azurerm_network_security_group.{}", name);
+                let mut priorities = BTreeSet::new();
+                let mut names = BTreeSet::new();
+                let mut rendered = Vec::new();
+                for r in rules {
+                    if !(100..=4096).contains(&r.priority) {
+                        anyhow::bail!("{}: rule '{}' priority {} must be between 100 and 4096", owner, r.name, r.priority);
+                    }
+                    // Azure scopes priorities per direction.
+                    if !priorities.insert((r.direction, r.priority)) {
+                        anyhow::bail!("{}: rule '{}' reuses {:?} priority {}", owner, r.name, r.direction, r.priority);
+                    }
+                    if !names.insert(&r.name) {
+                        anyhow::bail!("{}: rule name '{}' is used more than once", owner, r.name);
+                    }
+                    let mut rule = json!({
+                        "name": r.name, "priority": r.priority, "direction": r.direction, "access": r.access,
+                        "protocol": r.protocol, "description": Json::Null,
+                        "source_application_security_group_ids": [], "destination_application_security_group_ids": [],
+                    });
+                    single_or_many(&mut rule, "source_port_range", "source_port_ranges", &r.source_ports);
+                    single_or_many(&mut rule, "destination_port_range", "destination_port_ranges", &r.destination_ports);
+                    single_or_many(&mut rule, "source_address_prefix", "source_address_prefixes", &r.source_prefixes);
+                    single_or_many(&mut rule, "destination_address_prefix", "destination_address_prefixes", &r.destination_prefixes);
+                    rendered.push(rule);
+                }
+                let mut body = json!({
+                    "name": name, "security_rule": rendered,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                });
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_network_security_group": { name: body } } })
+            }
+            AzureResource::LinuxVirtualMachine { name, resource_group_ref, location: loc, size, admin_username, admin_ssh_public_key, admin_password_from, subnet_ref, os_disk, source_image_reference: img, tags } => {
+                let owner = format!("azurerm_linux_virtual_machine.{}", name);
+                if admin_ssh_public_key.is_none() && admin_password_from.is_none() {
+                    anyhow::bail!("{}: set admin_ssh_public_key (or opt into admin_password_from)", owner);
+                }
+                if !["None", "ReadOnly", "ReadWrite"].contains(&os_disk.caching.as_str()) {
+                    anyhow::bail!("{}: os_disk caching '{}' must be None, ReadOnly or ReadWrite", owner, os_disk.caching);
+                }
+                let rg = rg_name(ctx, resource_group_ref);
+                let loc = location(ctx, &owner, resource_group_ref, loc)?;
+                let mut disk = json!({ "caching": os_disk.caching, "storage_account_type": os_disk.storage_account_type });
+                if let Some(s) = os_disk.disk_size_gb { disk["disk_size_gb"] = json!(s); }
+                let mut body = json!({
+                    "name": name, "resource_group_name": rg, "location": loc, "size": size,
+                    "admin_username": admin_username,
+                    "network_interface_ids": [format!("${{azurerm_network_interface.{}.id}}", name)],
+                    "os_disk": [disk],
+                    "source_image_reference": [{ "publisher": img.publisher, "offer": img.offer, "sku": img.sku, "version": img.version }],
+                    "disable_password_authentication": admin_password_from.is_none(),
+                });
+                if let Some(k) = admin_ssh_public_key {
+                    // Public keys are safe to inline; reading the file here surfaces a bad path before plan.
+                    let key = if k.starts_with("ssh-") || k.starts_with("ecdsa-") { k.clone() } else {
+                        std::fs::read_to_string(k).with_context(|| format!("{}: read admin_ssh_public_key {}", owner, k))?.trim().to_string()
+                    };
+                    body["admin_ssh_key"] = json!([{ "username": admin_username, "public_key": key }]);
+                }
+                if let Some(from) = admin_password_from {
+                    body["admin_password"] = json!(ctx.secret_var(&secret_var_name("azurerm_linux_virtual_machine", name), from));
+                }
+                let mut nic = json!({
+                    "name": format!("{}-nic", name), "resource_group_name": rg, "location": loc,
+                    "ip_configuration": [{
+                        "name": "internal", "private_ip_address_allocation": "Dynamic",
+                        "subnet_id": ctx.resolve("azurerm_subnet", subnet_ref, "id"),
+                    }],
+                });
+                if !tags.is_empty() {
+                    body["tags"] = json!(tags);
+                    nic["tags"] = json!(tags);
+                }
+                json!({ "resource": {
+                    "azurerm_linux_virtual_machine": { name: body },
+                    "azurerm_network_interface": { name: nic },
+                } })
+            }
+            AzureResource::KubernetesCluster { name, resource_group_ref, location: loc, dns_prefix, default_node_pool: pool, identity, network_profile, private_cluster_enabled, api_server_authorized_ip_ranges, oidc_issuer_enabled, tags } => {
+                let owner = format!("azurerm_kubernetes_cluster.{}", name);
+                let valid_pool = (1..=12).contains(&pool.name.len())
+                    && pool.name.starts_with(|c: char| c.is_ascii_lowercase())
+                    && pool.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+                if !valid_pool {
+                    anyhow::bail!("{}: default_node_pool name '{}' must be 1-12 lowercase letters and digits, starting with a letter", owner, pool.name);
+                }
+                if *private_cluster_enabled && !api_server_authorized_ip_ranges.is_empty() {
+                    anyhow::bail!("{}: api_server_authorized_ip_ranges cannot be combined with private_cluster_enabled", owner);
+                }
+                let mut np = json!({ "name": pool.name, "vm_size": pool.vm_size });
+                match (pool.node_count, &pool.autoscaling) {
+                    (Some(_), Some(_)) => anyhow::bail!("{}: set node_count or autoscaling on default_node_pool, not both", owner),
+                    (Some(n), None) => np["node_count"] = json!(n),
+                    (None, Some(a)) => {
+                        if a.min > a.max { anyhow::bail!("{}: autoscaling min {} exceeds max {}", owner, a.min, a.max); }
+                        np["auto_scaling_enabled"] = json!(true);
+                        np["min_count"] = json!(a.min);
+                        np["max_count"] = json!(a.max);
+                    }
+                    (None, None) => np["node_count"] = json!(1),
+                }
+                if let Some(s) = &pool.vnet_subnet_ref { np["vnet_subnet_id"] = json!(ctx.resolve("azurerm_subnet", s, "id")); }
+                let mut body = json!({
+                    "name": name,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                    "dns_prefix": dns_prefix.as_deref().unwrap_or(name),
+                    "default_node_pool": [np],
+                    "identity": identity.to_tf_json(ctx, &owner)?,
+                    "private_cluster_enabled": private_cluster_enabled,
+                    "oidc_issuer_enabled": oidc_issuer_enabled,
+                });
+                if !api_server_authorized_ip_ranges.is_empty() {
+                    for r in api_server_authorized_ip_ranges { check_cidr(&owner, r)?; }
+                    body["api_server_access_profile"] = json!([{ "authorized_ip_ranges": api_server_authorized_ip_ranges }]);
+                }
+                if let Some(n) = network_profile {
+                    if !["azure", "kubenet", "none"].contains(&n.network_plugin.as_str()) {
+                        anyhow::bail!("{}: network_plugin '{}' must be azure, kubenet or none", owner, n.network_plugin);
+                    }
+                    let mut p = json!({ "network_plugin": n.network_plugin });
+                    if let Some(c) = &n.service_cidr { check_cidr(&owner, c)?; p["service_cidr"] = json!(c); }
+                    if let Some(ip) = &n.dns_service_ip { p["dns_service_ip"] = json!(ip); }
+                    if let Some(c) = &n.pod_cidr { check_cidr(&owner, c)?; p["pod_cidr"] = json!(c); }
+                    body["network_profile"] = json!([p]);
+                }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_kubernetes_cluster": { name: body } } })
+            }
+            AzureResource::ServicePlan { name, resource_group_ref, location: loc, os_type, sku_name, tags } => {
+                let owner = format!("azurerm_service_plan.{}", name);
+                let mut body = json!({
+                    "name": name, "os_type": os_type, "sku_name": sku_name,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                });
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_service_plan": { name: body } } })
+            }
+            AzureResource::LinuxWebApp { name, resource_group_ref, location: loc, service_plan_ref, site_config, app_settings: settings, https_only, identity, tags } => {
+                let owner = format!("azurerm_linux_web_app.{}", name);
+                let mut site = json!({});
+                if !site_config.application_stack.is_empty() { site["application_stack"] = json!([site_config.application_stack]); }
+                if let Some(a) = site_config.always_on { site["always_on"] = json!(a); }
+                if let Some(p) = &site_config.health_check_path {
+                    if !p.starts_with('/') { anyhow::bail!("{}: health_check_path '{}' must start with '/'", owner, p); }
+                    site["health_check_path"] = json!(p);
+                }
+                let mut body = json!({
+                    "name": name,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                    "service_plan_id": ctx.resolve("azurerm_service_plan", service_plan_ref, "id"),
+                    "site_config": [site],
+                    "https_only": https_only,
+                });
+                if !settings.is_empty() { body["app_settings"] = json!(app_settings(ctx, "azurerm_linux_web_app", name, settings)); }
+                if let Some(i) = identity { body["identity"] = i.to_tf_json(ctx, &owner)?; }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_linux_web_app": { name: body } } })
+            }
+            AzureResource::MssqlServer { name, resource_group_ref, location: loc, version, administrator_login, administrator_password_from, azuread_administrator, minimum_tls_version, public_network_access_enabled, tags } => {
+                let owner = format!("azurerm_mssql_server.{}", name);
+                let valid = (1..=63).contains(&name.len()) && !name.starts_with('-') && !name.ends_with('-')
+                    && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+                if !valid {
+                    anyhow::bail!("{}: server names must be 1-63 lowercase letters, digits and hyphens", owner);
+                }
+                if administrator_login.is_some() != administrator_password_from.is_some() {
+                    anyhow::bail!("{}: administrator_login and administrator_password_from must be set together", owner);
+                }
+                if administrator_login.is_none() && azuread_administrator.is_none() {
+                    anyhow::bail!("{}: set administrator_login or azuread_administrator", owner);
+                }
+                if !["1.0", "1.1", "1.2", "Disabled"].contains(&minimum_tls_version.as_str()) {
+                    anyhow::bail!("{}: minimum_tls_version '{}' must be 1.0, 1.1, 1.2 or Disabled", owner, minimum_tls_version);
+                }
+                let mut body = json!({
+                    "name": name, "version": version,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                    "minimum_tls_version": minimum_tls_version,
+                    "public_network_access_enabled": public_network_access_enabled,
+                });
+                if let (Some(login), Some(from)) = (administrator_login, administrator_password_from) {
+                    body["administrator_login"] = json!(login);
+                    body["administrator_login_password"] = json!(ctx.secret_var(&secret_var_name("azurerm_mssql_server", name), from));
+                }
+                if let Some(ad) = azuread_administrator {
+                    body["azuread_administrator"] = json!([{
+                        "login_username": ad.login_username, "object_id": ad.object_id,
+                        "azuread_authentication_only": ad.azuread_authentication_only,
+                    }]);
+                }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_mssql_server": { name: body } } })
+            }
+            AzureResource::MssqlDatabase { name, server_ref, sku_name, max_size_gb, zone_redundant, tags } => {
+                let mut body = json!({ "name": name, "server_id": ctx.resolve("azurerm_mssql_server", server_ref, "id") });
+                if let Some(s) = sku_name { body["sku_name"] = json!(s); }
+                if let Some(m) = max_size_gb { body["max_size_gb"] = json!(m); }
+                if let Some(z) = zone_redundant { body["zone_redundant"] = json!(z); }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_mssql_database": { name: body } } })
+            }
+            AzureResource::ContainerRegistry { name, resource_group_ref, location: loc, sku, admin_enabled, public_network_access_enabled, zone_redundancy_enabled, georeplications, tags } => {
+                let owner = format!("azurerm_container_registry.{}", name);
+                // Registry names are global and the API only rejects bad ones at apply time.
+                if !(5..=50).contains(&name.len()) || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    anyhow::bail!("{}: registry names must be 5-50 letters and digits (no hyphens)", owner);
+                }
+                if *sku != AcrSku::Premium && (!georeplications.is_empty() || *zone_redundancy_enabled || !public_network_access_enabled) {
+                    anyhow::bail!("{}: georeplications, zone redundancy and disabling public network access require the Premium sku", owner);
+                }
+                let home = location(ctx, &owner, resource_group_ref, loc)?;
+                let mut seen = BTreeSet::new();
+                for g in georeplications {
+                    if g.location == home || !seen.insert(g.location.as_str()) {
+                        anyhow::bail!("{}: georeplication location '{}' duplicates the registry or another replica", owner, g.location);
+                    }
+                }
+                let mut body = json!({
+                    "name": name, "sku": sku,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": home,
+                    "admin_enabled": admin_enabled,
+                    "public_network_access_enabled": public_network_access_enabled,
+                    "zone_redundancy_enabled": zone_redundancy_enabled,
+                });
+                if !georeplications.is_empty() {
+                    body["georeplications"] = json!(georeplications.iter().map(|g| {
+                        let mut r = json!({ "location": g.location, "zone_redundancy_enabled": g.zone_redundancy_enabled });
+                        if !g.tags.is_empty() { r["tags"] = json!(g.tags); }
+                        r
+                    }).collect::<Vec<_>>());
+                }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_container_registry": { name: body } } })
+            }
+            AzureResource::LogAnalyticsWorkspace { name, resource_group_ref, location: loc, sku, retention_in_days, daily_quota_gb, tags } => {
+                let owner = format!("azurerm_log_analytics_workspace.{}", name);
+                let mut body = json!({
+                    "name": name, "sku": sku,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                });
+                if let Some(r) = retention_in_days {
+                    if !(30..=730).contains(r) { anyhow::bail!("{}: retention_in_days {} must be between 30 and 730", owner, r); }
+                    body["retention_in_days"] = json!(r);
+                }
+                if let Some(q) = daily_quota_gb { body["daily_quota_gb"] = json!(q); }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_log_analytics_workspace": { name: body } } })
+            }
+            AzureResource::MonitorDiagnosticSetting { name, target_resource_ref, workspace_ref, enabled_log_categories, metrics_enabled } => {
+                let owner = format!("azurerm_monitor_diagnostic_setting.{}", name);
+                if enabled_log_categories.is_empty() && !metrics_enabled {
+                    anyhow::bail!("{}: enable at least one log category or metrics", owner);
+                }
+                let mut body = json!({
+                    "name": name,
+                    "target_resource_id": resource_ref(ctx, &owner, "target_resource_ref", target_resource_ref, "id")?,
+                    "log_analytics_workspace_id": ctx.resolve("azurerm_log_analytics_workspace", workspace_ref, "id"),
+                });
+                if !enabled_log_categories.is_empty() {
+                    body["enabled_log"] = json!(enabled_log_categories.iter().map(|c| json!({ "category": c })).collect::<Vec<_>>());
+                }
+                if *metrics_enabled { body["metric"] = json!([{ "category": "AllMetrics" }]); }
+                json!({ "resource": { "azurerm_monitor_diagnostic_setting": { name: body } } })
+            }
+            AzureResource::RoleAssignment { name, scope_ref, role_definition_name, principal_id_ref } => {
+                let owner = format!("azurerm_role_assignment.{}", name);
+                if !BUILTIN_ROLES.contains(&role_definition_name.as_str()) {
+                    tracing::warn!("{}: '{}' is not a known built-in role; assuming a custom role definition", owner, role_definition_name);
+                }
+                let principal_id = if principal_id_ref.starts_with("azurerm_") {
+                    resource_ref(ctx, &owner, "principal_id_ref", principal_id_ref, "identity[0].principal_id")?
+                } else {
+                    principal_id_ref.clone()
+                };
+                json!({ "resource": { "azurerm_role_assignment": { name: {
+                    "scope": resource_ref(ctx, &owner, "scope_ref", scope_ref, "id")?,
+                    "role_definition_name": role_definition_name,
+                    "principal_id": principal_id,
+                } } } })
+            }
+            AzureResource::CosmosDbAccount { name, resource_group_ref, location: loc, offer_type, kind, consistency_policy, geo_locations, public_network_access_enabled, backup, tags } => {
+                let owner = format!("azurerm_cosmosdb_account.{}", name);
+                let home = location(ctx, &owner, resource_group_ref, loc)?;
+                let consistency = match consistency_policy {
+                    ConsistencyPolicy::BoundedStaleness { max_interval_in_seconds, max_staleness_prefix } => {
+                        if !(5..=86400).contains(max_interval_in_seconds) || !(10..=2147483647).contains(max_staleness_prefix) {
+                            anyhow::bail!("{}: BoundedStaleness needs max_interval_in_seconds 5-86400 and max_staleness_prefix 10-2147483647", owner);
+                        }
+                        json!({ "consistency_level": "BoundedStaleness", "max_interval_in_seconds": max_interval_in_seconds, "max_staleness_prefix": max_staleness_prefix })
+                    }
+                    ConsistencyPolicy::Strong => json!({ "consistency_level": "Strong" }),
+                    ConsistencyPolicy::Session => json!({ "consistency_level": "Session" }),
+                    ConsistencyPolicy::ConsistentPrefix => json!({ "consistency_level": "ConsistentPrefix" }),
+                    ConsistencyPolicy::Eventual => json!({ "consistency_level": "Eventual" }),
+                };
+                let geo = if geo_locations.is_empty() {
+                    vec![json!({ "location": home, "failover_priority": 0 })]
+                } else {
+                    let priorities: BTreeSet<_> = geo_locations.iter().map(|g| g.failover_priority).collect();
+                    if priorities.len() != geo_locations.len() || !priorities.contains(&0) {
+                        anyhow::bail!("{}: geo_locations need unique failover priorities including 0", owner);
+                    }
+                    geo_locations.iter().map(|g| json!({
+                        "location": g.location, "failover_priority": g.failover_priority, "zone_redundant": g.zone_redundant,
+                    })).collect()
+                };
+                let mut body = json!({
+                    "name": name, "offer_type": offer_type, "kind": kind,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": home,
+                    "consistency_policy": [consistency],
+                    "geo_location": geo,
+                    "public_network_access_enabled": public_network_access_enabled,
+                });
+                if let Some(b) = backup {
+                    let mut block = json!({});
+                    match b {
+                        CosmosDbBackup::Periodic { interval_in_minutes, retention_in_hours, storage_redundancy } => {
+                            block["type"] = json!("Periodic");
+                            if let Some(i) = interval_in_minutes { block["interval_in_minutes"] = json!(i); }
+                            if let Some(r) = retention_in_hours { block["retention_in_hours"] = json!(r); }
+                            if let Some(s) = storage_redundancy { block["storage_redundancy"] = json!(s); }
+                        }
+                        CosmosDbBackup::Continuous { tier } => {
+                            block["type"] = json!("Continuous");
+                            if let Some(t) = tier { block["tier"] = json!(t); }
+                        }
+                    }
+                    body["backup"] = json!([block]);
+                }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_cosmosdb_account": { name: body } } })
+            }
+            AzureResource::CosmosDbSqlDatabase { name, account_ref, resource_group_ref, throughput, autoscale_max_throughput } => {
+                let owner = format!("azurerm_cosmosdb_sql_database.{}", name);
+                let rg = match (ctx.sibling("azurerm_cosmosdb_account", account_ref, "resource_group_name"), resource_group_ref) {
+                    (_, Some(r)) => rg_name(ctx, r),
+                    (Some(r), None) => r,
+                    (None, None) => anyhow::bail!("{}: resource_group_ref is required when account_ref '{}' is not a sibling account", owner, account_ref),
+                };
+                let mut body = json!({
+                    "name": name, "resource_group_name": rg,
+                    "account_name": ctx.resolve("azurerm_cosmosdb_account", account_ref, "name"),
+                });
+                match (throughput, autoscale_max_throughput) {
+                    (Some(_), Some(_)) => anyhow::bail!("{}: throughput and autoscale_max_throughput are mutually exclusive", owner),
+                    (Some(t), None) => {
+                        if *t < 400 { anyhow::bail!("{}: throughput must be at least 400 RU/s", owner); }
+                        body["throughput"] = json!(t);
+                    }
+                    (None, Some(m)) => {
+                        if *m < 1000 || m % 1000 != 0 { anyhow::bail!("{}: autoscale_max_throughput must be a multiple of 1000 RU/s", owner); }
+                        body["autoscale_settings"] = json!([{ "max_throughput": m }]);
+                    }
+                    (None, None) => {}
+                }
+                json!({ "resource": { "azurerm_cosmosdb_sql_database": { name: body } } })
+            }
+            AzureResource::LinuxFunctionApp { name, resource_group_ref, location: loc, service_plan_ref, storage_account_ref, storage_account_access_key_from, runtime, app_settings: settings, https_only, identity, tags } => {
+                let owner = format!("azurerm_linux_function_app.{}", name);
+                let (stack_attr, stack_version) = runtime.application_stack(&owner)?;
+                let access_key = match (ctx.sibling("azurerm_storage_account", storage_account_ref, "primary_access_key"), storage_account_access_key_from) {
+                    (Some(k), _) => k,
+                    (None, Some(from)) => ctx.secret_var(&secret_var_name("azurerm_linux_function_app", &format!("{}_storage_key", name)), from),
+                    (None, None) => anyhow::bail!("{}: storage_account_access_key_from is required when storage_account_ref '{}' is not a sibling storage account", owner, storage_account_ref),
+                };
+                let mut body = json!({
+                    "name": name,
+                    "resource_group_name": rg_name(ctx, resource_group_ref),
+                    "location": location(ctx, &owner, resource_group_ref, loc)?,
+                    "service_plan_id": ctx.resolve("azurerm_service_plan", service_plan_ref, "id"),
+                    "storage_account_name": ctx.resolve("azurerm_storage_account", storage_account_ref, "name"),
+                    "storage_account_access_key": access_key,
+                    "site_config": [{ "application_stack": [{ stack_attr: stack_version }] }],
+                    "https_only": https_only,
+                });
+                if !settings.is_empty() { body["app_settings"] = json!(app_settings(ctx, "azurerm_linux_function_app", name, settings)); }
+                if let Some(i) = identity { body["identity"] = i.to_tf_json(ctx, &owner)?; }
+                if !tags.is_empty() { body["tags"] = json!(tags); }
+                json!({ "resource": { "azurerm_linux_function_app": { name: body } } })
+            }
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,3 +1261,593 @@ impl AzureAnyResource {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2iac_core::test_util::{assert_errors, ctx, with, Captured};
+
+    fn render(siblings: &[(&str, &str)], res: Json) -> Result<Json> {
+        serde_json::from_value::<AzureResource>(res)?.to_tf_json(&mut ctx(siblings))
+    }
+
+    #[test]
+    fn provider_keeps_identity_out_of_the_block() {
+        let p: AzureProvider = serde_json::from_value(json!({
+            "subscription_id": "sub-1", "tenant_id": "tenant-1", "client_id": "app-1",
+            "client_secret_from": "env:ARM_SECRET_SOURCE", "environment": "usgovernment", "skip_provider_registration": true,
+        })).unwrap();
+        assert_eq!(p.to_tf_json(), json!({ "provider": { "azurerm": {
+            "features": {}, "subscription_id": "sub-1", "resource_provider_registrations": "none",
+        } } }));
+        // The secret itself is resolved by the CLI; nothing here names or carries it.
+        assert_eq!(p.runner_env(), [
+            ("ARM_TENANT_ID", "tenant-1".to_string()), ("ARM_CLIENT_ID", "app-1".to_string()), ("ARM_ENVIRONMENT", "usgovernment".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn resource_group() {
+        let tf = render(&[], json!({ "type": "azurerm_resource_group", "name": "app", "location": "westeurope", "tags": { "env": "dev" } })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_resource_group"]["app"], json!({ "name": "app", "location": "westeurope", "tags": { "env": "dev" } }));
+    }
+
+    fn storage_account(extra: Json) -> Json {
+        with(json!({ "type": "azurerm_storage_account", "name": "acmelogs", "resource_group_ref": "app" }), extra)
+    }
+
+    #[test]
+    fn storage_account_secure_defaults_and_sibling_location() {
+        let tf = render(&[("azurerm_resource_group", "app")], storage_account(json!({}))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_storage_account"]["acmelogs"], json!({
+            "name": "acmelogs", "resource_group_name": "${azurerm_resource_group.app.name}",
+            "location": "${azurerm_resource_group.app.location}",
+            "account_tier": "Standard", "account_replication_type": "LRS", "min_tls_version": "TLS1_2",
+            "https_traffic_only_enabled": true, "allow_nested_items_to_be_public": false,
+        }));
+    }
+
+    #[test]
+    fn storage_account_in_an_external_group() {
+        let tf = render(&[], storage_account(json!({ "location": "northeurope", "account_replication_type": "GRS" }))).unwrap();
+        let acct = &tf["resource"]["azurerm_storage_account"]["acmelogs"];
+        assert_eq!(acct["resource_group_name"], "app");
+        assert_eq!(acct["location"], "northeurope");
+        assert_eq!(acct["account_replication_type"], "GRS");
+    }
+
+    #[test]
+    fn storage_account_errors() {
+        let rg = [("azurerm_resource_group", "app")];
+        assert_errors(&storage_account(json!({})), &[
+            (json!({ "name": "Acme-Logs" }), "storage account names must be 3-24 lowercase letters and digits"),
+            (json!({ "name": "ab" }), "storage account names must be 3-24 lowercase letters and digits"),
+            (json!({ "min_tls_version": "TLS1_3" }), "min_tls_version 'TLS1_3' must be TLS1_0, TLS1_1 or TLS1_2"),
+        ], |r| render(&rg, r));
+        let err = render(&[], storage_account(json!({}))).unwrap_err();
+        assert!(err.to_string().contains("location is required when resource_group_ref 'app' is not a sibling resource group"), "{}", err);
+    }
+
+    fn linux_vm(extra: Json) -> Json {
+        with(json!({
+            "type": "azurerm_linux_virtual_machine", "name": "web", "resource_group_ref": "app", "size": "Standard_B2s",
+            "admin_username": "ops", "admin_ssh_public_key": "ssh-ed25519 AAAAC3Nza ops@example", "subnet_ref": "apps",
+            "source_image_reference": { "publisher": "Canonical", "offer": "ubuntu-24_04-lts", "sku": "server" },
+        }), extra)
+    }
+
+    #[test]
+    fn vm_tags_reach_its_nic() {
+        let siblings = [("azurerm_resource_group", "app"), ("azurerm_subnet", "apps")];
+        let tf = render(&siblings, linux_vm(json!({ "tags": { "owner": "web" } }))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_linux_virtual_machine"]["web"]["tags"], json!({ "owner": "web" }));
+        assert_eq!(tf["resource"]["azurerm_network_interface"]["web"]["tags"], json!({ "owner": "web" }));
+
+        let untagged = render(&siblings, linux_vm(json!({}))).unwrap();
+        assert!(untagged["resource"]["azurerm_network_interface"]["web"].get("tags").is_none());
+    }
+
+    const RG: (&str, &str) = ("azurerm_resource_group", "app");
+
+    fn key_vault(extra: Json) -> Json {
+        with(json!({ "type": "azurerm_key_vault", "name": "acme-kv", "resource_group_ref": "app" }), extra)
+    }
+
+    #[test]
+    fn key_vault_defaults_to_the_current_tenant_with_purge_protection() {
+        let tf = render(&[RG, ("azurerm_subnet", "apps")], key_vault(json!({
+            "soft_delete_retention_days": 30, "network_acls": { "ip_rules": ["203.0.113.0/24"], "subnet_refs": ["apps"] },
+        }))).unwrap();
+        assert_eq!(tf["data"]["azurerm_client_config"]["current"], json!({}));
+        assert_eq!(tf["resource"]["azurerm_key_vault"]["acme-kv"], json!({
+            "name": "acme-kv", "resource_group_name": "${azurerm_resource_group.app.name}",
+            "location": "${azurerm_resource_group.app.location}",
+            "tenant_id": "${data.azurerm_client_config.current.tenant_id}", "sku_name": "standard",
+            "purge_protection_enabled": true, "soft_delete_retention_days": 30,
+            "network_acls": [{
+                "default_action": "Deny", "bypass": "AzureServices", "ip_rules": ["203.0.113.0/24"],
+                "virtual_network_subnet_ids": ["${azurerm_subnet.apps.id}"],
+            }],
+        }));
+        let tf = render(&[RG], key_vault(json!({ "tenant_id": "tenant-1" }))).unwrap();
+        assert!(tf.get("data").is_none());
+        assert_eq!(tf["resource"]["azurerm_key_vault"]["acme-kv"]["tenant_id"], "tenant-1");
+    }
+
+    #[test]
+    fn key_vault_errors() {
+        assert_errors(&key_vault(json!({})), &[
+            (json!({ "name": "1kv" }), "key vault names must be 3-24 letters, digits and single hyphens, starting with a letter"),
+            (json!({ "name": "acme--kv" }), "key vault names must be 3-24 letters, digits and single hyphens, starting with a letter"),
+            (json!({ "soft_delete_retention_days": 5 }), "soft_delete_retention_days 5 must be between 7 and 90"),
+            (json!({ "network_acls": { "default_action": "Block" } }), "network_acls default_action must be Allow or Deny"),
+            (json!({ "network_acls": { "bypass": "Everything" } }), "network_acls bypass must be AzureServices or None"),
+        ], |r| render(&[RG], r));
+    }
+
+    fn key_vault_secret(extra: Json) -> Json {
+        with(json!({
+            "type": "azurerm_key_vault_secret", "name": "db-password", "key_vault_ref": "acme-kv",
+            "secret_name": "db-password", "value_from": "env:R2IAC_TEST_DB_PASSWORD",
+        }), extra)
+    }
+
+    #[test]
+    fn key_vault_secret_never_renders_the_secret() {
+        std::env::set_var("R2IAC_TEST_DB_PASSWORD", "plaintext-db-password");
+        let mut ctx = ctx(&[("azurerm_key_vault", "acme-kv")]);
+        let logs = Captured::default();
+        let tf = logs.during(|| serde_json::from_value::<AzureResource>(key_vault_secret(json!({}))).unwrap().to_tf_json(&mut ctx)).unwrap();
+        assert_eq!(tf["resource"]["azurerm_key_vault_secret"]["db-password"], json!({
+            "name": "db-password", "value": "${var.azurerm_key_vault_secret_db_password}",
+            "key_vault_id": "${azurerm_key_vault.acme-kv.id}",
+        }));
+        assert!(ctx.secret_vars().contains_key("azurerm_key_vault_secret_db_password"));
+        for text in [tf.to_string(), logs.text()] {
+            assert!(!text.contains("plaintext-db-password"), "{}", text);
+        }
+    }
+
+    #[test]
+    fn key_vault_secret_errors() {
+        assert_errors(&key_vault_secret(json!({})), &[
+            (json!({ "secret_name": "" }), "secret_name must be 1-127 letters, digits and hyphens"),
+            (json!({ "secret_name": "db_password" }), "secret_name must be 1-127 letters, digits and hyphens"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn vnet_and_subnet_in_it() {
+        let tf = render(&[RG], json!({
+            "type": "azurerm_virtual_network", "name": "core", "resource_group_ref": "app",
+            "address_space": ["10.10.0.0/16"], "dns_servers": ["10.10.0.4"],
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_virtual_network"]["core"], json!({
+            "name": "core", "address_space": ["10.10.0.0/16"], "dns_servers": ["10.10.0.4"],
+            "resource_group_name": "${azurerm_resource_group.app.name}", "location": "${azurerm_resource_group.app.location}",
+        }));
+
+        let tf = render(&[RG, ("azurerm_virtual_network", "core")], json!({
+            "type": "azurerm_subnet", "name": "apps", "vnet_ref": "core", "address_prefixes": ["10.10.1.0/24"],
+            "service_endpoints": ["Microsoft.Storage"],
+            "delegation": { "name": "web", "service": "Microsoft.Web/serverFarms", "actions": ["Microsoft.Network/virtualNetworks/subnets/action"] },
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_subnet"]["apps"], json!({
+            "name": "apps", "address_prefixes": ["10.10.1.0/24"],
+            "resource_group_name": "${azurerm_virtual_network.core.resource_group_name}",
+            "virtual_network_name": "${azurerm_virtual_network.core.name}",
+            "service_endpoints": ["Microsoft.Storage"],
+            "delegation": [{ "name": "web", "service_delegation": [{
+                "name": "Microsoft.Web/serverFarms", "actions": ["Microsoft.Network/virtualNetworks/subnets/action"],
+            }] }],
+        }));
+    }
+
+    #[test]
+    fn vnet_and_subnet_errors() {
+        let vnet = json!({ "type": "azurerm_virtual_network", "name": "core", "resource_group_ref": "app", "address_space": ["10.10.0.0/16"] });
+        assert_errors(&vnet, &[
+            (json!({ "address_space": [] }), "address_space must not be empty"),
+            (json!({ "address_space": ["10.10.0.0"] }), "'10.10.0.0' is not a CIDR block"),
+        ], |r| render(&[RG], r));
+        let subnet = json!({ "type": "azurerm_subnet", "name": "apps", "vnet_ref": "core", "address_prefixes": ["10.10.1.0/24"] });
+        assert_errors(&subnet, &[
+            (json!({ "address_prefixes": [] }), "address_prefixes must not be empty"),
+            (json!({ "address_prefixes": ["10.10.1.0/33"] }), "'10.10.1.0/33'"),
+            (json!({}), "resource_group_ref is required when vnet_ref 'core' is not a sibling network"),
+        ], |r| render(&[], r));
+    }
+
+    fn nsg(rules: Json) -> Json {
+        json!({ "type": "azurerm_network_security_group", "name": "web", "resource_group_ref": "app", "rules": rules })
+    }
+
+    fn nsg_rule(name: &str, priority: u32, direction: &str) -> Json {
+        json!({ "name": name, "priority": priority, "direction": direction, "access": "Allow", "protocol": "Tcp" })
+    }
+
+    #[test]
+    fn nsg_rules_inline_with_singular_or_plural_fields() {
+        let https = with(nsg_rule("https", 100, "Inbound"), json!({ "destination_ports": ["443"], "source_prefixes": ["10.0.0.0/8", "192.168.0.0/16"] }));
+        // Priorities are per direction, so an outbound rule may reuse 100.
+        let tf = render(&[RG], nsg(json!([https, nsg_rule("egress", 100, "Outbound")]))).unwrap();
+        let rules = &tf["resource"]["azurerm_network_security_group"]["web"]["security_rule"];
+        assert_eq!(rules[0], json!({
+            "name": "https", "priority": 100, "direction": "Inbound", "access": "Allow", "protocol": "Tcp", "description": null,
+            "source_application_security_group_ids": [], "destination_application_security_group_ids": [],
+            "source_port_range": "*", "source_port_ranges": [],
+            "destination_port_range": "443", "destination_port_ranges": [],
+            "source_address_prefix": null, "source_address_prefixes": ["10.0.0.0/8", "192.168.0.0/16"],
+            "destination_address_prefix": "*", "destination_address_prefixes": [],
+        }));
+        assert_eq!(rules[1]["direction"], "Outbound");
+    }
+
+    #[test]
+    fn nsg_errors() {
+        assert_errors(&nsg(json!([])), &[
+            (json!({ "rules": [nsg_rule("low", 99, "Inbound")] }), "rule 'low' priority 99 must be between 100 and 4096"),
+            (json!({ "rules": [nsg_rule("high", 4097, "Outbound")] }), "rule 'high' priority 4097 must be between 100 and 4096"),
+            (json!({ "rules": [nsg_rule("a", 200, "Inbound"), nsg_rule("b", 200, "Inbound")] }), "rule 'b' reuses Inbound priority 200"),
+            (json!({ "rules": [nsg_rule("a", 200, "Inbound"), nsg_rule("a", 210, "Outbound")] }), "rule name 'a' is used more than once"),
+        ], |r| render(&[RG], r));
+    }
+
+    #[test]
+    fn vm_uses_ssh_keys_unless_a_password_is_opted_into() {
+        let siblings = [RG, ("azurerm_subnet", "apps")];
+        let mut ctx = ctx(&siblings);
+        let tf = serde_json::from_value::<AzureResource>(linux_vm(json!({ "os_disk": { "disk_size_gb": 64 } }))).unwrap().to_tf_json(&mut ctx).unwrap();
+        assert_eq!(tf["resource"]["azurerm_linux_virtual_machine"]["web"], json!({
+            "name": "web", "resource_group_name": "${azurerm_resource_group.app.name}",
+            "location": "${azurerm_resource_group.app.location}", "size": "Standard_B2s", "admin_username": "ops",
+            "network_interface_ids": ["${azurerm_network_interface.web.id}"],
+            "os_disk": [{ "caching": "ReadWrite", "storage_account_type": "Premium_LRS", "disk_size_gb": 64 }],
+            "source_image_reference": [{ "publisher": "Canonical", "offer": "ubuntu-24_04-lts", "sku": "server", "version": "latest" }],
+            "disable_password_authentication": true,
+            "admin_ssh_key": [{ "username": "ops", "public_key": "ssh-ed25519 AAAAC3Nza ops@example" }],
+        }));
+        assert_eq!(tf["resource"]["azurerm_network_interface"]["web"]["ip_configuration"][0]["subnet_id"], "${azurerm_subnet.apps.id}");
+        assert!(ctx.secret_vars().is_empty());
+
+        let tf = render(&siblings, linux_vm(json!({ "admin_ssh_public_key": null, "admin_password_from": "env:VM_PASSWORD" }))).unwrap();
+        let vm = &tf["resource"]["azurerm_linux_virtual_machine"]["web"];
+        assert_eq!(vm["disable_password_authentication"], false);
+        assert_eq!(vm["admin_password"], "${var.azurerm_linux_virtual_machine_web}");
+        assert!(vm.get("admin_ssh_key").is_none());
+    }
+
+    #[test]
+    fn vm_errors() {
+        assert_errors(&linux_vm(json!({})), &[
+            (json!({ "admin_ssh_public_key": null }), "set admin_ssh_public_key (or opt into admin_password_from)"),
+            (json!({ "os_disk": { "caching": "WriteBack" } }), "os_disk caching 'WriteBack' must be None, ReadOnly or ReadWrite"),
+            (json!({ "admin_ssh_public_key": "/nonexistent/id_ed25519.pub" }), "read admin_ssh_public_key /nonexistent/id_ed25519.pub"),
+        ], |r| render(&[RG, ("azurerm_subnet", "apps")], r));
+    }
+
+    fn aks(extra: Json) -> Json {
+        with(json!({
+            "type": "azurerm_kubernetes_cluster", "name": "main", "resource_group_ref": "app",
+            "default_node_pool": { "name": "system", "vm_size": "Standard_D4s_v5" },
+        }), extra)
+    }
+
+    #[test]
+    fn aks_cluster_with_autoscaling_and_network_profile() {
+        let tf = render(&[RG, ("azurerm_subnet", "nodes")], aks(json!({
+            "default_node_pool": { "name": "system", "vm_size": "Standard_D4s_v5", "autoscaling": { "min": 1, "max": 5 }, "vnet_subnet_ref": "nodes" },
+            "network_profile": { "network_plugin": "azure", "service_cidr": "10.20.0.0/16", "dns_service_ip": "10.20.0.10" },
+            "api_server_authorized_ip_ranges": ["203.0.113.0/24"], "oidc_issuer_enabled": true,
+        }))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_kubernetes_cluster"]["main"], json!({
+            "name": "main", "resource_group_name": "${azurerm_resource_group.app.name}",
+            "location": "${azurerm_resource_group.app.location}", "dns_prefix": "main",
+            "default_node_pool": [{
+                "name": "system", "vm_size": "Standard_D4s_v5", "auto_scaling_enabled": true, "min_count": 1, "max_count": 5,
+                "vnet_subnet_id": "${azurerm_subnet.nodes.id}",
+            }],
+            "identity": [{ "type": "SystemAssigned" }],
+            "private_cluster_enabled": false, "oidc_issuer_enabled": true,
+            "api_server_access_profile": [{ "authorized_ip_ranges": ["203.0.113.0/24"] }],
+            "network_profile": [{ "network_plugin": "azure", "service_cidr": "10.20.0.0/16", "dns_service_ip": "10.20.0.10" }],
+        }));
+        let tf = render(&[RG], aks(json!({}))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_kubernetes_cluster"]["main"]["default_node_pool"][0]["node_count"], 1);
+    }
+
+    #[test]
+    fn aks_errors() {
+        assert_errors(&aks(json!({})), &[
+            (json!({ "default_node_pool": { "name": "System-Pool", "vm_size": "x" } }), "default_node_pool name 'System-Pool' must be 1-12 lowercase letters and digits"),
+            (json!({ "private_cluster_enabled": true, "api_server_authorized_ip_ranges": ["203.0.113.0/24"] }), "api_server_authorized_ip_ranges cannot be combined with private_cluster_enabled"),
+            (json!({ "default_node_pool": { "name": "system", "vm_size": "x", "node_count": 3, "autoscaling": { "min": 1, "max": 3 } } }), "set node_count or autoscaling on default_node_pool, not both"),
+            (json!({ "default_node_pool": { "name": "system", "vm_size": "x", "autoscaling": { "min": 4, "max": 2 } } }), "autoscaling min 4 exceeds max 2"),
+            (json!({ "network_profile": { "network_plugin": "calico" } }), "network_plugin 'calico' must be azure, kubenet or none"),
+            (json!({ "identity": { "type": "UserAssigned" } }), "identity_ids must be set exactly when the identity includes UserAssigned"),
+        ], |r| render(&[RG], r));
+    }
+
+    #[test]
+    fn web_app_on_a_sibling_plan_with_a_secret_setting() {
+        let tf = render(&[RG], json!({
+            "type": "azurerm_service_plan", "name": "web", "resource_group_ref": "app", "sku_name": "P1v3",
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_service_plan"]["web"], json!({
+            "name": "web", "os_type": "Linux", "sku_name": "P1v3",
+            "resource_group_name": "${azurerm_resource_group.app.name}", "location": "${azurerm_resource_group.app.location}",
+        }));
+
+        let mut ctx = ctx(&[RG, ("azurerm_service_plan", "web")]);
+        let tf = serde_json::from_value::<AzureResource>(json!({
+            "type": "azurerm_linux_web_app", "name": "site", "resource_group_ref": "app", "service_plan_ref": "web",
+            "site_config": { "application_stack": { "node_version": "20-lts" }, "always_on": true, "health_check_path": "/healthz" },
+            "app_settings": { "LOG_LEVEL": "info", "API_KEY": { "value_from": "env:SITE_API_KEY" } },
+        })).unwrap().to_tf_json(&mut ctx).unwrap();
+        assert_eq!(tf["resource"]["azurerm_linux_web_app"]["site"], json!({
+            "name": "site", "resource_group_name": "${azurerm_resource_group.app.name}",
+            "location": "${azurerm_resource_group.app.location}", "service_plan_id": "${azurerm_service_plan.web.id}",
+            "site_config": [{ "application_stack": [{ "node_version": "20-lts" }], "always_on": true, "health_check_path": "/healthz" }],
+            "https_only": true,
+            "app_settings": { "API_KEY": "${var.azurerm_linux_web_app_site_API_KEY}", "LOG_LEVEL": "info" },
+        }));
+        assert!(ctx.secret_vars().contains_key("azurerm_linux_web_app_site_API_KEY"));
+    }
+
+    #[test]
+    fn web_app_errors() {
+        let app = json!({ "type": "azurerm_linux_web_app", "name": "site", "resource_group_ref": "app", "service_plan_ref": "web" });
+        assert_errors(&app, &[
+            (json!({ "site_config": { "health_check_path": "healthz" } }), "health_check_path 'healthz' must start with '/'"),
+            (json!({ "identity": { "type": "SystemAssigned", "identity_ids": ["ops"] } }), "identity_ids must be set exactly when the identity includes UserAssigned"),
+        ], |r| render(&[RG], r));
+        let plan = json!({ "type": "azurerm_service_plan", "name": "web", "resource_group_ref": "ext", "sku_name": "B1" });
+        assert_errors(&plan, &[
+            (json!({}), "location is required when resource_group_ref 'ext' is not a sibling resource group"),
+        ], |r| render(&[], r));
+    }
+
+    fn mssql_server(extra: Json) -> Json {
+        with(json!({
+            "type": "azurerm_mssql_server", "name": "acme-sql", "resource_group_ref": "app",
+            "administrator_login": "sqladmin", "administrator_password_from": "env:SQL_ADMIN_PASSWORD",
+        }), extra)
+    }
+
+    #[test]
+    fn mssql_server_and_database() {
+        let tf = render(&[RG], mssql_server(json!({}))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_mssql_server"]["acme-sql"], json!({
+            "name": "acme-sql", "version": "12.0",
+            "resource_group_name": "${azurerm_resource_group.app.name}", "location": "${azurerm_resource_group.app.location}",
+            "minimum_tls_version": "1.2", "public_network_access_enabled": false,
+            "administrator_login": "sqladmin", "administrator_login_password": "${var.azurerm_mssql_server_acme_sql}",
+        }));
+        let tf = render(&[RG], mssql_server(json!({
+            "administrator_login": null, "administrator_password_from": null,
+            "azuread_administrator": { "login_username": "dba-group", "object_id": "00000000-0000-0000-0000-000000000001", "azuread_authentication_only": true },
+        }))).unwrap();
+        let server = &tf["resource"]["azurerm_mssql_server"]["acme-sql"];
+        assert!(server.get("administrator_login_password").is_none());
+        assert_eq!(server["azuread_administrator"][0]["azuread_authentication_only"], true);
+
+        let tf = render(&[("azurerm_mssql_server", "acme-sql")], json!({
+            "type": "azurerm_mssql_database", "name": "orders", "server_ref": "acme-sql", "sku_name": "S1", "max_size_gb": 50,
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_mssql_database"]["orders"], json!({
+            "name": "orders", "server_id": "${azurerm_mssql_server.acme-sql.id}", "sku_name": "S1", "max_size_gb": 50,
+        }));
+    }
+
+    #[test]
+    fn mssql_server_errors() {
+        assert_errors(&mssql_server(json!({})), &[
+            (json!({ "name": "Acme_SQL" }), "server names must be 1-63 lowercase letters, digits and hyphens"),
+            (json!({ "name": "-acme" }), "server names must be 1-63 lowercase letters, digits and hyphens"),
+            (json!({ "administrator_password_from": null }), "administrator_login and administrator_password_from must be set together"),
+            (json!({ "administrator_login": null, "administrator_password_from": null }), "set administrator_login or azuread_administrator"),
+            (json!({ "minimum_tls_version": "1.3" }), "minimum_tls_version '1.3' must be 1.0, 1.1, 1.2 or Disabled"),
+        ], |r| render(&[RG], r));
+    }
+
+    fn registry(extra: Json) -> Json {
+        with(json!({ "type": "azurerm_container_registry", "name": "acmeimages", "resource_group_ref": "app", "location": "westeurope" }), extra)
+    }
+
+    #[test]
+    fn premium_registry_with_georeplications() {
+        let tf = render(&[RG], registry(json!({
+            "sku": "Premium", "public_network_access_enabled": false,
+            "georeplications": [{ "location": "northeurope", "zone_redundancy_enabled": true }],
+        }))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_container_registry"]["acmeimages"], json!({
+            "name": "acmeimages", "sku": "Premium",
+            "resource_group_name": "${azurerm_resource_group.app.name}", "location": "westeurope",
+            "admin_enabled": false, "public_network_access_enabled": false, "zone_redundancy_enabled": false,
+            "georeplications": [{ "location": "northeurope", "zone_redundancy_enabled": true }],
+        }));
+    }
+
+    #[test]
+    fn registry_errors() {
+        assert_errors(&registry(json!({})), &[
+            (json!({ "name": "acme-images" }), "registry names must be 5-50 letters and digits (no hyphens)"),
+            (json!({ "name": "acr" }), "registry names must be 5-50 letters and digits (no hyphens)"),
+            (json!({ "georeplications": [{ "location": "northeurope" }] }), "require the Premium sku"),
+            (json!({ "public_network_access_enabled": false }), "require the Premium sku"),
+            (json!({ "sku": "Premium", "georeplications": [{ "location": "westeurope" }] }), "georeplication location 'westeurope' duplicates the registry or another replica"),
+            (json!({ "sku": "Premium", "georeplications": [{ "location": "uksouth" }, { "location": "uksouth" }] }), "georeplication location 'uksouth' duplicates"),
+        ], |r| render(&[RG], r));
+    }
+
+    #[test]
+    fn log_analytics_workspace_and_diagnostic_setting() {
+        let tf = render(&[RG], json!({
+            "type": "azurerm_log_analytics_workspace", "name": "ops", "resource_group_ref": "app", "retention_in_days": 90, "daily_quota_gb": -1.0,
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_log_analytics_workspace"]["ops"], json!({
+            "name": "ops", "sku": "PerGB2018",
+            "resource_group_name": "${azurerm_resource_group.app.name}", "location": "${azurerm_resource_group.app.location}",
+            "retention_in_days": 90, "daily_quota_gb": -1.0,
+        }));
+
+        let tf = render(&[("azurerm_key_vault", "acme-kv"), ("azurerm_log_analytics_workspace", "ops")], json!({
+            "type": "azurerm_monitor_diagnostic_setting", "name": "kv-audit", "target_resource_ref": "azurerm_key_vault.acme-kv",
+            "workspace_ref": "ops", "enabled_log_categories": ["AuditEvent"],
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_monitor_diagnostic_setting"]["kv-audit"], json!({
+            "name": "kv-audit", "target_resource_id": "${azurerm_key_vault.acme-kv.id}",
+            "log_analytics_workspace_id": "${azurerm_log_analytics_workspace.ops.id}",
+            "enabled_log": [{ "category": "AuditEvent" }], "metric": [{ "category": "AllMetrics" }],
+        }));
+    }
+
+    #[test]
+    fn log_analytics_and_diagnostic_setting_errors() {
+        let law = json!({ "type": "azurerm_log_analytics_workspace", "name": "ops", "resource_group_ref": "app" });
+        assert_errors(&law, &[
+            (json!({ "retention_in_days": 7 }), "retention_in_days 7 must be between 30 and 730"),
+            (json!({ "retention_in_days": 731 }), "retention_in_days 731 must be between 30 and 730"),
+        ], |r| render(&[RG], r));
+        let diag = json!({
+            "type": "azurerm_monitor_diagnostic_setting", "name": "kv-audit", "target_resource_ref": "azurerm_key_vault.acme-kv", "workspace_ref": "ops",
+        });
+        assert_errors(&diag, &[
+            (json!({ "metrics_enabled": false }), "enable at least one log category or metrics"),
+            (json!({ "target_resource_ref": "acme-kv" }), "target_resource_ref 'acme-kv' must be `<azurerm type>.<name>` of a sibling resource or a literal resource id"),
+            (json!({ "target_resource_ref": "azurerm_key_vault.other" }), "target_resource_ref 'azurerm_key_vault.other'"),
+        ], |r| render(&[("azurerm_key_vault", "acme-kv")], r));
+    }
+
+    fn role_assignment(extra: Json) -> Json {
+        with(json!({
+            "type": "azurerm_role_assignment", "name": "site-reads-secrets", "scope_ref": "azurerm_key_vault.acme-kv",
+            "role_definition_name": "Key Vault Secrets User", "principal_id_ref": "azurerm_linux_web_app.site",
+        }), extra)
+    }
+
+    #[test]
+    fn role_assignment_resolves_sibling_scope_and_principal() {
+        let siblings = [("azurerm_key_vault", "acme-kv"), ("azurerm_linux_web_app", "site")];
+        let logs = Captured::default();
+        let tf = logs.during(|| render(&siblings, role_assignment(json!({})))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_role_assignment"]["site-reads-secrets"], json!({
+            "scope": "${azurerm_key_vault.acme-kv.id}", "role_definition_name": "Key Vault Secrets User",
+            "principal_id": "${azurerm_linux_web_app.site.identity[0].principal_id}",
+        }));
+        assert!(logs.text().is_empty(), "{}", logs.text());
+
+        // Literal scopes and object ids pass through; unknown roles are allowed but warned about.
+        let logs = Captured::default();
+        let tf = logs.during(|| render(&[], role_assignment(json!({
+            "scope_ref": "/subscriptions/sub-1", "role_definition_name": "Acme Deployer", "principal_id_ref": "00000000-0000-0000-0000-000000000002",
+        })))).unwrap();
+        let ra = &tf["resource"]["azurerm_role_assignment"]["site-reads-secrets"];
+        assert_eq!(ra["scope"], "/subscriptions/sub-1");
+        assert_eq!(ra["principal_id"], "00000000-0000-0000-0000-000000000002");
+        let text = logs.text();
+        assert!(text.contains("WARN") && text.contains("azurerm_role_assignment.site-reads-secrets: 'Acme Deployer' is not a known built-in role"), "{}", text);
+    }
+
+    #[test]
+    fn role_assignment_errors() {
+        assert_errors(&role_assignment(json!({})), &[
+            (json!({ "scope_ref": "acme-kv" }), "scope_ref 'acme-kv' must be `<azurerm type>.<name>` of a sibling resource or a literal resource id"),
+            (json!({ "principal_id_ref": "azurerm_linux_web_app.other" }), "principal_id_ref 'azurerm_linux_web_app.other'"),
+        ], |r| render(&[("azurerm_key_vault", "acme-kv"), ("azurerm_linux_web_app", "site")], r));
+    }
+
+    fn cosmos(extra: Json) -> Json {
+        with(json!({ "type": "azurerm_cosmosdb_account", "name": "acme-docs", "resource_group_ref": "app", "location": "westeurope" }), extra)
+    }
+
+    #[test]
+    fn cosmos_account_and_sql_database() {
+        let tf = render(&[RG], cosmos(json!({}))).unwrap();
+        assert_eq!(tf["resource"]["azurerm_cosmosdb_account"]["acme-docs"], json!({
+            "name": "acme-docs", "offer_type": "Standard", "kind": "GlobalDocumentDB",
+            "resource_group_name": "${azurerm_resource_group.app.name}", "location": "westeurope",
+            "consistency_policy": [{ "consistency_level": "Session" }],
+            "geo_location": [{ "location": "westeurope", "failover_priority": 0 }],
+            "public_network_access_enabled": false,
+        }));
+        let tf = render(&[RG], cosmos(json!({
+            "consistency_policy": { "level": "BoundedStaleness", "max_interval_in_seconds": 300, "max_staleness_prefix": 100000 },
+            "geo_locations": [{ "location": "westeurope", "failover_priority": 0 }, { "location": "northeurope", "failover_priority": 1, "zone_redundant": true }],
+            "backup": { "type": "Continuous", "tier": "Continuous7Days" },
+        }))).unwrap();
+        let acct = &tf["resource"]["azurerm_cosmosdb_account"]["acme-docs"];
+        assert_eq!(acct["consistency_policy"], json!([{ "consistency_level": "BoundedStaleness", "max_interval_in_seconds": 300, "max_staleness_prefix": 100000 }]));
+        assert_eq!(acct["geo_location"][1], json!({ "location": "northeurope", "failover_priority": 1, "zone_redundant": true }));
+        assert_eq!(acct["backup"], json!([{ "type": "Continuous", "tier": "Continuous7Days" }]));
+
+        let tf = render(&[("azurerm_cosmosdb_account", "acme-docs")], json!({
+            "type": "azurerm_cosmosdb_sql_database", "name": "orders", "account_ref": "acme-docs", "autoscale_max_throughput": 4000,
+        })).unwrap();
+        assert_eq!(tf["resource"]["azurerm_cosmosdb_sql_database"]["orders"], json!({
+            "name": "orders", "resource_group_name": "${azurerm_cosmosdb_account.acme-docs.resource_group_name}",
+            "account_name": "${azurerm_cosmosdb_account.acme-docs.name}", "autoscale_settings": [{ "max_throughput": 4000 }],
+        }));
+    }
+
+    #[test]
+    fn cosmos_errors() {
+        assert_errors(&cosmos(json!({})), &[
+            (json!({ "consistency_policy": { "level": "BoundedStaleness", "max_interval_in_seconds": 1, "max_staleness_prefix": 100 } }), "BoundedStaleness needs max_interval_in_seconds 5-86400"),
+            (json!({ "geo_locations": [{ "location": "westeurope", "failover_priority": 1 }] }), "geo_locations need unique failover priorities including 0"),
+            (json!({ "geo_locations": [{ "location": "westeurope", "failover_priority": 0 }, { "location": "uksouth", "failover_priority": 0 }] }), "geo_locations need unique failover priorities including 0"),
+        ], |r| render(&[RG], r));
+        let db = json!({ "type": "azurerm_cosmosdb_sql_database", "name": "orders", "account_ref": "acme-docs" });
+        assert_errors(&db, &[
+            (json!({ "throughput": 400, "autoscale_max_throughput": 1000 }), "throughput and autoscale_max_throughput are mutually exclusive"),
+            (json!({ "throughput": 100 }), "throughput must be at least 400 RU/s"),
+            (json!({ "autoscale_max_throughput": 1500 }), "autoscale_max_throughput must be a multiple of 1000 RU/s"),
+        ], |r| render(&[("azurerm_cosmosdb_account", "acme-docs")], r));
+        assert_errors(&db, &[
+            (json!({}), "resource_group_ref is required when account_ref 'acme-docs' is not a sibling account"),
+        ], |r| render(&[], r));
+    }
+
+    fn function_app(extra: Json) -> Json {
+        with(json!({
+            "type": "azurerm_linux_function_app", "name": "jobs", "resource_group_ref": "app", "service_plan_ref": "web",
+            "storage_account_ref": "acmejobs", "runtime": { "stack": "python", "version": "3.11" },
+        }), extra)
+    }
+
+    #[test]
+    fn function_app_wires_the_sibling_storage_key() {
+        let mut sibling = ctx(&[RG, ("azurerm_service_plan", "web"), ("azurerm_storage_account", "acmejobs")]);
+        let tf = serde_json::from_value::<AzureResource>(function_app(json!({}))).unwrap().to_tf_json(&mut sibling).unwrap();
+        assert_eq!(tf["resource"]["azurerm_linux_function_app"]["jobs"], json!({
+            "name": "jobs", "resource_group_name": "${azurerm_resource_group.app.name}",
+            "location": "${azurerm_resource_group.app.location}", "service_plan_id": "${azurerm_service_plan.web.id}",
+            "storage_account_name": "${azurerm_storage_account.acmejobs.name}",
+            "storage_account_access_key": "${azurerm_storage_account.acmejobs.primary_access_key}",
+            "site_config": [{ "application_stack": [{ "python_version": "3.11" }] }],
+            "https_only": true,
+        }));
+        assert!(sibling.secret_vars().is_empty());
+
+        // An external account's key comes in as a sensitive variable; dotnet versions normalise to `N.0`.
+        let mut external = ctx(&[RG]);
+        let tf = serde_json::from_value::<AzureResource>(function_app(json!({
+            "storage_account_ref": "sharedjobs", "storage_account_access_key_from": "env:JOBS_STORAGE_KEY",
+            "runtime": { "stack": "dotnet", "version": "8" },
+        }))).unwrap().to_tf_json(&mut external).unwrap();
+        let app = &tf["resource"]["azurerm_linux_function_app"]["jobs"];
+        assert_eq!(app["storage_account_name"], "sharedjobs");
+        assert_eq!(app["storage_account_access_key"], "${var.azurerm_linux_function_app_jobs_storage_key}");
+        assert_eq!(app["site_config"][0]["application_stack"][0]["dotnet_version"], "8.0");
+        assert!(external.secret_vars().contains_key("azurerm_linux_function_app_jobs_storage_key"));
+    }
+
+    #[test]
+    fn function_app_errors() {
+        assert_errors(&function_app(json!({})), &[
+            (json!({ "runtime": { "stack": "node", "version": "16" } }), "unsupported runtime Node 16 (node 18/20, python 3.10/3.11, dotnet 6/8)"),
+            (json!({ "runtime": { "stack": "python", "version": "3.8" } }), "unsupported runtime Python 3.8"),
+            (json!({ "storage_account_ref": "sharedjobs" }), "storage_account_access_key_from is required when storage_account_ref 'sharedjobs' is not a sibling storage account"),
+        ], |r| render(&[RG, ("azurerm_storage_account", "acmejobs")], r));
+    }
+}
@@ -2,15 +2,18 @@ use anyhow::{Result, Context};
 use clap::{Parser, Subcommand, ValueEnum};
 use serde::Deserialize;
 use serde_json::{json, Value as Json};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use tracing_subscriber::fmt::format::FmtSpan;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, SecretString};
 use std::process::{Command, Stdio};
+use std::time::Duration;
 
-use r2iac_policy::Policy;
+use r2iac_core::{RenderCtx, ValueFrom};
+use r2iac_policy::{Exemptions, Policy, PolicyReport};
 use r2iac_tfcompat as tfc;
-use r2iac_aws::{AwsProvider, AwsResource, AwsAnyResource};
-use r2iac_azure::{AzureProvider, AzureAnyResource};
+use r2iac_aws::{AwsProviders, AwsResource, AwsAnyResource};
+use r2iac_azure::{AzureProvider, AzureResource, AzureAnyResource};
 use r2iac_gcp::{GcpProvider, GcpResource, GcpAnyResource};
 use r2iac_cfn as cfn;
 
@@ -19,7 +22,7 @@ use r2iac_cfn as cfn;
 struct Cli {
     /// Config file (YAML or .yml.age)
     #[arg(short, long, global = true)]
-    file: PathBuf,
+    file: Option<PathBuf>,
 
     /// Output directory
     #[arg(short, long, default_value="out", global = true)]
@@ -29,10 +32,95 @@ struct Cli {
     #[arg(long, value_enum, default_value_t=Runner::Auto, global = true)]
     runner: Runner,
 
-    /// Allow unencrypted buckets
+    /// Policy rules file (YAML)
+    #[arg(long, global = true)]
+    policy_file: Option<PathBuf>,
+
+    /// Directory of Rego policies whose `deny` rules are evaluated with `opa eval`
+    #[cfg(feature = "opa")]
+    #[arg(long, global = true)]
+    opa_policy_dir: Option<PathBuf>,
+
+    /// Also write the policy report as JSON or SARIF 2.1.0
+    #[arg(long, value_enum, global = true)]
+    policy_output: Option<PolicyFormat>,
+
+    /// Where --policy-output goes (default: stdout); a plan-time report replaces the config-time one
+    #[arg(long, global = true, requires = "policy_output")]
+    policy_output_file: Option<PathBuf>,
+
+    /// Skip every built-in encryption check (shorthand for `required_encryption: []`)
     #[arg(long, default_value_t=false, global = true)]
     allow_unencrypted: bool,
 
+    /// Allow buckets without a public access block
+    #[arg(long, default_value_t=false, global = true)]
+    allow_public_buckets: bool,
+
+    /// Allow world-open security group, firewall and NSG rules beyond the policy's allowed ports
+    #[arg(long, default_value_t=false, global = true)]
+    allow_open_ingress: bool,
+
+    /// Allow IAM grants of roles/owner and subscription-wide Owner role assignments
+    #[arg(long, default_value_t=false, global = true)]
+    allow_owner_grants: bool,
+
+    /// Allow Cloud SQL instances, Azure SQL servers and Cosmos DB accounts with public access
+    #[arg(long, default_value_t=false, global = true)]
+    allow_public_sql: bool,
+
+    /// Allow key vaults without purge protection
+    #[arg(long, default_value_t=false, global = true)]
+    allow_no_purge_protection: bool,
+
+    /// Allow password authentication on Linux VMs
+    #[arg(long, default_value_t=false, global = true)]
+    allow_password_auth: bool,
+
+    /// Treat policy warnings as failures
+    #[arg(long, default_value_t=false, global = true)]
+    strict: bool,
+
+    /// Set a stack variable, as name=value; repeatable and applied over --var-file
+    #[arg(long = "var", global = true)]
+    vars: Vec<String>,
+
+    /// YAML or JSON file of stack variable values
+    #[arg(long, global = true)]
+    var_file: Option<PathBuf>,
+
+    /// Terraform workspace to select (creating it if missing) before plan, apply and destroy
+    #[arg(long, global = true)]
+    workspace: Option<String>,
+
+    /// Only plan, apply or destroy this resource address (and what it depends on); repeatable
+    #[arg(long = "target", global = true)]
+    targets: Vec<String>,
+
+    /// Concurrent resource operations for plan, apply and destroy
+    #[arg(long, global = true)]
+    parallelism: Option<u32>,
+
+    /// Wait this long for a state lock, e.g. 30s, 5m or 500ms
+    #[arg(long, global = true, value_parser = parse_duration)]
+    lock_timeout: Option<Duration>,
+
+    /// Plan against state as recorded, without refreshing it first
+    #[arg(long, default_value_t=false, global = true)]
+    no_refresh: bool,
+
+    /// Pass runner output straight to the terminal instead of through the JSON log, keeping color and prompts
+    #[arg(long, default_value_t=false, global = true)]
+    raw_output: bool,
+
+    /// Offer to go ahead when a plan exceeds max_resources_changed/max_resources_destroyed
+    #[arg(long, default_value_t=false, global = true)]
+    force: bool,
+
+    /// Require versioning on every S3 bucket
+    #[arg(long, default_value_t=false, global = true)]
+    require_versioning: bool,
+
     /// AGE identities (optional, for .age files)
     #[arg(long="age-identity", global = true)]
     age_ids: Vec<PathBuf>,
@@ -44,10 +132,24 @@ struct Cli {
 #[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
 enum Runner { Auto, Terraform, Tofu }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum PolicyFormat { Json, Sarif }
+
+/// How policy reports are enforced and where their machine-readable copy goes.
+struct PolicyOutput { strict: bool, format: Option<PolicyFormat>, file: Option<PathBuf>, stack: String }
+
 #[derive(Subcommand, Debug)] enum Cmd {
     Init,
-    Plan,
-    Apply,
+    Plan {
+        /// Exit 0 when the plan is empty and 2 when it has changes, as terraform does
+        #[arg(long)] detailed_exitcode: bool,
+        /// Keep the plan at this path for a later `apply --plan`
+        #[arg(long, conflicts_with = "detailed_exitcode")] save: Option<PathBuf>,
+    },
+    Apply {
+        /// Apply a plan saved by `plan --save` instead of planning afresh
+        #[arg(long)] plan: Option<PathBuf>,
+    },
     Destroy,
     AwsConfigure {
         #[arg(long)] profile: Option<String>,
@@ -64,24 +166,135 @@ enum Runner { Auto, Terraform, Tofu }
         #[arg(long)] stack: Option<String>,
         #[arg(short='f', long="file")] file: Option<PathBuf>,
         #[arg(short='o', long="out")] out: Option<PathBuf>,
-    }
+    },
+    /// Bring an existing object under management, then show the plan against it
+    Import {
+        /// Resource address in the rendered config, e.g. aws_s3_bucket.logs
+        #[arg(long)] address: String,
+        /// Provider-specific id of the existing object
+        #[arg(long)] id: String,
+    },
+    /// Print outputs from state, sensitive ones redacted
+    Output {
+        /// Print JSON instead of `name = value` lines
+        #[arg(long)] json: bool,
+        #[arg(long)] show_sensitive: bool,
+        name: Option<String>,
+    },
+    /// Reconcile state with the real infrastructure, reporting what drifted; changes nothing else
+    Refresh {
+        /// Only report drift; leave state as it is
+        #[arg(long)] report_only: bool,
+    },
+    /// Render and check the stack, then have the runner validate it; exits 2 when only validation fails
+    Validate,
+    /// Inspect or rewrite state without touching real infrastructure
+    State {
+        #[command(subcommand)] cmd: StateCmd,
+    },
+    /// Work on the policy file itself
+    Policy {
+        #[command(subcommand)] cmd: PolicyCmd,
+    },
 }
 
+#[derive(Subcommand, Debug)] enum StateCmd {
+    /// Print every address in state
+    List,
+    /// Stop managing resources; the real objects are left in place
+    Rm {
+        #[arg(required = true)] addresses: Vec<String>,
+        /// Skip the confirmation prompt
+        #[arg(long)] yes: bool,
+    },
+    /// Rename a resource in state so the plan does not destroy and recreate it
+    Mv {
+        from: String,
+        to: String,
+    },
+}
+
+#[derive(Subcommand, Debug)] enum PolicyCmd {
+    /// Check every stack in a directory against its `<stack>.expected.json` report
+    Test {
+        #[arg(long)] fixtures: PathBuf,
+    },
+}
+
+#[derive(Deserialize)]
+struct Stack {
+    project: Option<String>,
+    /// Semver constraint on terraform/tofu, e.g. ">= 1.5, < 2".
+    #[serde(default)] required_runner_version: Option<String>,
+    #[serde(default)] backend: Option<tfc::Backend>,
+    /// Set on the terraform/tofu process only, e.g. AWS_PROFILE or credentials from value_from.
+    #[serde(default)] environment: BTreeMap<String, EnvValue>,
+    #[serde(default)] variables: Vec<Variable>,
+    #[serde(default)] outputs: Vec<Output>,
+    provider: Providers,
+    resources: Vec<Resource>,
+}
+/// An `environment` entry: a literal, or `{ value_from: age:PATH }` etc. for anything secret.
 #[derive(Deserialize)]
-struct Stack { project: Option<String>, provider: Providers, resources: Vec<Resource> }
+#[serde(untagged, expecting = "a string, or { value_from: env:VAR, file:PATH or age:PATH }")]
+enum EnvValue {
+    Literal(String),
+    Secret { value_from: ValueFrom },
+}
+
+/// A root-module input, set through --var/--var-file or its default.
+#[derive(Deserialize)]
+struct Variable {
+    name: String,
+    /// A Terraform type expression such as `string` or `list(string)`.
+    #[serde(default, rename="type")] type_expr: Option<String>,
+    #[serde(default)] default: Option<Json>,
+    #[serde(default)] sensitive: bool,
+    #[serde(default)] description: Option<String>,
+}
+#[derive(Deserialize)]
+struct Output {
+    name: String,
+    /// `<type>.<name>.<attribute>`, or an explicit `${...}` expression.
+    value: String,
+    #[serde(default)] sensitive: bool,
+    #[serde(default)] description: Option<String>,
+}
 #[derive(Deserialize)] struct Providers { 
-    #[serde(default)] aws: Option<AwsProvider>,
+    #[serde(default)] aws: Option<AwsProviders>,
     #[serde(default)] azurerm: Option<AzureProvider>,
     #[serde(default)] google: Option<GcpProvider>,
 }
 #[derive(Deserialize, Clone)]
 #[serde(tag="cloud")]
 enum Resource { 
-    #[serde(rename="aws")]   Aws   { #[serde(flatten)] res: AwsResource },
-    #[serde(rename="aws_any")] AwsAny { #[serde(flatten)] res: AwsAnyResource },
-    #[serde(rename="azure")] Azure { #[serde(flatten)] res: AzureAnyResource },
-    #[serde(rename="gcp")]   Gcp   { #[serde(flatten)] res: GcpResource },
-    #[serde(rename="gcp_any")] GcpAny { #[serde(flatten)] res: GcpAnyResource },
+    #[serde(rename="aws")]   Aws   { #[serde(default)] provider_alias: Option<String>, #[serde(default)] exempt_policies: Vec<String>, #[serde(flatten)] res: AwsResource },
+    #[serde(rename="aws_any")] AwsAny { #[serde(default)] provider_alias: Option<String>, #[serde(default)] exempt_policies: Vec<String>, #[serde(flatten)] res: AwsAnyResource },
+    #[serde(rename="azure")] Azure { #[serde(default)] exempt_policies: Vec<String>, #[serde(flatten)] res: AzureAnyResource },
+    #[serde(rename="azure_typed")] AzureTyped { #[serde(default)] exempt_policies: Vec<String>, #[serde(flatten)] res: AzureResource },
+    #[serde(rename="gcp")]   Gcp   { #[serde(default)] exempt_policies: Vec<String>, #[serde(flatten)] res: GcpResource },
+    #[serde(rename="gcp_any")] GcpAny { #[serde(default)] exempt_policies: Vec<String>, #[serde(flatten)] res: GcpAnyResource },
+}
+
+impl Resource {
+    fn type_and_name(&self) -> (&str, &str) {
+        match self {
+            Resource::Aws { res, .. } => res.type_and_name(),
+            Resource::AwsAny { res, .. } => (&res.type_name, &res.name),
+            Resource::Azure { res, .. } => (&res.type_name, &res.name),
+            Resource::AzureTyped { res, .. } => res.type_and_name(),
+            Resource::Gcp { res, .. } => res.type_and_name(),
+            Resource::GcpAny { res, .. } => (&res.type_name, &res.name),
+        }
+    }
+
+    fn exempt_policies(&self) -> &[String] {
+        match self {
+            Resource::Aws { exempt_policies, .. } | Resource::AwsAny { exempt_policies, .. }
+            | Resource::Azure { exempt_policies, .. } | Resource::AzureTyped { exempt_policies, .. }
+            | Resource::Gcp { exempt_policies, .. } | Resource::GcpAny { exempt_policies, .. } => exempt_policies,
+        }
+    }
 }
 
 fn merge(mut a: Json, b: Json) -> Json {
@@ -100,6 +313,93 @@ fn merge(mut a: Json, b: Json) -> Json {
     }
 }
 
+/// Pins every resource and data source in a rendered fragment to an aliased AWS provider,
+/// so companions (versioning, policies, ...) land in the same region as their parent.
+fn with_provider_alias(mut frag: Json, alias: &str) -> Json {
+    for section in ["resource", "data"] {
+        if let Some(types) = frag.get_mut(section).and_then(|s| s.as_object_mut()) {
+            for body in types.values_mut().filter_map(|t| t.as_object_mut()).flat_map(|t| t.values_mut()) {
+                body["provider"] = json!(format!("aws.{}", alias));
+            }
+        }
+    }
+    frag
+}
+
+/// Logs warnings, prints the violation table, and fails on any deny.
+fn enforce_policy(mut report: PolicyReport, out: &PolicyOutput) -> Result<()> {
+    if out.strict { report = report.strict(); }
+    if let Some(format) = out.format {
+        let doc = match format {
+            PolicyFormat::Json => serde_json::to_value(report.clone().sorted())?,
+            PolicyFormat::Sarif => report.to_sarif(Some(&out.stack)),
+        };
+        let text = serde_json::to_string_pretty(&doc)?;
+        match &out.file {
+            Some(p) => std::fs::write(p, text).with_context(|| format!("write {}", p.display()))?,
+            None => { use std::io::Write; writeln!(std::io::stdout(), "{}", text)?; }
+        }
+    }
+    for v in report.warnings() {
+        tracing::warn!(rule = %v.rule, resource = %v.resource, "{}", v.message);
+    }
+    if !report.violations.is_empty() || !report.exempted.is_empty() { eprintln!("{}", report); }
+    if report.denies().next().is_some() { anyhow::bail!("policy check failed: {}", report.summary()); }
+    Ok(())
+}
+
+/// Like [`enforce_policy`], but `--force` lets the user confirm past `blast-radius` denies.
+fn enforce_plan_policy(mut report: PolicyReport, out: &PolicyOutput, force: bool) -> Result<()> {
+    let over: Vec<_> = report.violations.iter().filter(|v| v.rule == "blast-radius").map(|v| v.message.clone()).collect();
+    if force && !over.is_empty() {
+        use std::io::IsTerminal;
+        if !std::io::stdin().is_terminal() { anyhow::bail!("--force needs an interactive terminal to confirm: {}", over.join(" ")); }
+        for m in &over { eprintln!("{}", m); }
+        if !confirm("Continue with this plan anyway?")? { anyhow::bail!("aborted: plan exceeds the blast-radius limits"); }
+        report.waive("blast-radius");
+    }
+    enforce_policy(report, out)
+}
+
+/// Whether the user typed 'yes'; callers check for a terminal first so scripts fail instead of hanging.
+fn confirm(prompt: &str) -> Result<bool> {
+    use std::io::{BufRead, Write};
+    eprint!("{} Type 'yes' to confirm: ", prompt);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim() == "yes")
+}
+
+/// A number with an `ms`, `s`, `m` or `h` suffix; bare numbers are seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().map_err(|_| format!("'{}' is not a duration like 30s or 5m", s))?;
+    Ok(match unit {
+        "ms" => Duration::from_millis(n),
+        "" | "s" => Duration::from_secs(n),
+        "m" => Duration::from_secs(n * 60),
+        "h" => Duration::from_secs(n * 3600),
+        _ => return Err(format!("'{}' has an unknown unit; use ms, s, m or h", s)),
+    })
+}
+
+/// `addr`, less any instance key, must be a resource the rendered config declares.
+fn check_address(known: &[String], addr: &str) -> Result<()> {
+    let base = addr.split('[').next().unwrap_or_default();
+    if known.iter().any(|a| a == base) { return Ok(()); }
+    let ty = base.split('.').next().unwrap_or_default();
+    let near: Vec<_> = known.iter().filter(|a| a.starts_with(base) || a.split('.').next() == Some(ty)).map(String::as_str).collect();
+    if !near.is_empty() { anyhow::bail!("{} is not a resource in the rendered config; did you mean: {}", addr, near.join(", ")); }
+    anyhow::bail!("{} is not a resource in the rendered config; known addresses: {}", addr, if known.is_empty() { "none".to_string() } else { known.join(", ") });
+}
+
+/// `addr` names a tracked instance, or a whole resource whose instances are tracked.
+fn in_state(state: &[String], addr: &str) -> bool {
+    state.iter().any(|s| s == addr || s.strip_prefix(addr).is_some_and(|rest| rest.starts_with('[')))
+}
+
 fn ensure_type_prefix(prefix: &str, type_name: &str) -> Result<()> {
     if !type_name.starts_with(prefix) {
         anyhow::bail!("resource type '{}' must start with '{}'", type_name, prefix);
@@ -110,16 +410,306 @@ fn ensure_type_prefix(prefix: &str, type_name: &str) -> Result<()> {
     Ok(())
 }
 
+fn read_secret(from: &ValueFrom, ids: &[age::x25519::Identity]) -> Result<SecretString> {
+    let value = match from {
+        ValueFrom::Env(var) => std::env::var(var).with_context(|| format!("environment variable {} is not set", var))?,
+        ValueFrom::File(p) => std::fs::read_to_string(p).with_context(|| format!("read {}", p.display()))?,
+        ValueFrom::Age(p) => {
+            let f = std::fs::File::open(p).with_context(|| format!("open {}", p.display()))?;
+            let dec = r2iac_crypto::decrypt_age_bytes(std::io::BufReader::new(f), ids)?;
+            String::from_utf8(dec.expose_secret().clone()).with_context(|| format!("{} is not UTF-8", p.display()))?
+        }
+    };
+    // Files written by editors usually end in a newline that isn't part of the secret.
+    Ok(SecretString::new(value.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn load_stack(path: &Path, ids: &[age::x25519::Identity]) -> Result<Stack> {
+    let cfg = if path.extension().and_then(|s| s.to_str()) == Some("age") {
+        let f = std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+        let dec = r2iac_crypto::decrypt_age_bytes(std::io::BufReader::new(f), ids)?;
+        serde_yaml::from_slice(dec.expose_secret())?
+    } else {
+        serde_yaml::from_slice(&std::fs::read(path).with_context(|| format!("read {}", path.display()))?)?
+    };
+    Ok(cfg)
+}
+
+/// Builds tf.json for a stack. In `cfn_mode` only providers are rendered, since the
+/// resources are CloudFormation-typed.
+fn render(cfg: &Stack, cfn_mode: bool) -> Result<(Json, RenderCtx)> {
+    let mut tf = json!({ "terraform": { "required_providers": {} } });
+    if let Some(b) = &cfg.backend { tf = merge(tf, b.to_tf_json()?); }
+    if let Some(p) = &cfg.provider.aws {
+        tf["terraform"]["required_providers"]["aws"] = json!({ "source": "hashicorp/aws", "version": "~> 5.0" });
+        tf = merge(tf, p.to_tf_json()?);
+    }
+    if let Some(p) = &cfg.provider.azurerm {
+        // 4.x attribute names throughout; see CHANGELOG.md for moving azure_any stacks off 3.x.
+        tf["terraform"]["required_providers"]["azurerm"] = json!({ "source": "hashicorp/azurerm", "version": "~> 4.0" });
+        tf = merge(tf, p.to_tf_json());
+    }
+    if let Some(p) = &cfg.provider.google {
+        tf["terraform"]["required_providers"]["google"] = json!({ "source": "hashicorp/google", "version": ">= 5.0" });
+        tf = merge(tf, p.to_tf_json()?);
+    }
+    let mut ctx = RenderCtx::default();
+    for r in &cfg.resources {
+        let (type_name, name) = r.type_and_name();
+        ctx.declare(type_name, name);
+        if let Resource::Gcp { res, .. } = r {
+            if let Some((parent_type, parent)) = res.parent_ref() { ctx.declare_child_of(parent_type, parent); }
+        }
+    }
+    for r in cfg.resources.clone().into_iter().filter(|_| !cfn_mode) {
+        match r {
+            Resource::Aws { .. } | Resource::AwsAny { .. } => {
+                let (type_name, name) = r.type_and_name();
+                let owner = format!("{}.{}", type_name, name);
+                let (frag, alias) = match &r {
+                    Resource::Aws { res, provider_alias, .. } => (res.to_tf_json(&mut ctx)?, provider_alias),
+                    Resource::AwsAny { res, provider_alias, .. } => {
+                        ensure_type_prefix("aws_", &res.type_name)?;
+                        (res.to_tf_json(), provider_alias)
+                    }
+                    _ => unreachable!(),
+                };
+                tf = merge(tf, match alias {
+                    Some(a) if cfg.provider.aws.as_ref().is_some_and(|p| p.has_alias(a)) => with_provider_alias(frag, a),
+                    Some(a) => anyhow::bail!("{}: provider_alias '{}' is not defined under provider.aws", owner, a),
+                    None => frag,
+                });
+            },
+            Resource::Azure { res, .. } => { ensure_type_prefix("azurerm_", &res.type_name)?; tf = merge(tf, res.to_tf_json()); },
+            Resource::AzureTyped { res, .. } => { tf = merge(tf, res.to_tf_json(&mut ctx)?); },
+            Resource::Gcp { res, .. } => { tf = merge(tf, res.to_tf_json(&mut ctx)?); },
+            Resource::GcpAny { res, .. } => { ensure_type_prefix("google_", &res.type_name)?; tf = merge(tf, res.to_tf_json()); },
+        }
+    }
+
+    for var in ctx.secret_vars().keys() {
+        tf["variable"][var] = json!({ "type": "string", "sensitive": true });
+    }
+    for v in &cfg.variables {
+        if tf.pointer(&format!("/variable/{}", v.name)).is_some() {
+            anyhow::bail!("variables: '{}' is already declared{}", v.name, if ctx.secret_vars().contains_key(&v.name) { " for a resource secret" } else { "" });
+        }
+        let mut body = json!({});
+        if let Some(t) = &v.type_expr { body["type"] = json!(t); }
+        if let Some(d) = &v.default { body["default"] = d.clone(); }
+        if let Some(d) = &v.description { body["description"] = json!(d); }
+        if v.sensitive { body["sensitive"] = json!(true); }
+        tf["variable"][&v.name] = body;
+    }
+    for o in &cfg.outputs {
+        let value = if o.value.contains("${") { o.value.clone() } else {
+            let mut parts = o.value.splitn(3, '.');
+            let (Some(t), Some(n), Some(_)) = (parts.next(), parts.next(), parts.next()) else {
+                anyhow::bail!("outputs.{}: value '{}' must be <type>.<name>.<attribute> or a ${{...}} expression", o.name, o.value);
+            };
+            if tf.pointer(&format!("/resource/{}/{}", t, n)).is_none() && !cfn_mode {
+                anyhow::bail!("outputs.{}: no resource {}.{} in this stack", o.name, t, n);
+            }
+            format!("${{{}}}", o.value)
+        };
+        let mut body = json!({ "value": value });
+        if let Some(d) = &o.description { body["description"] = json!(d); }
+        if o.sensitive { body["sensitive"] = json!(true); }
+        tf["output"][&o.name] = body;
+    }
+    Ok((tf, ctx))
+}
+
+/// Values for the stack's variables from `var_file` and then `vars`, split into the
+/// non-sensitive ones for the tfvars file and `TF_VAR_` environment for sensitive ones.
+fn variable_values(cfg: &Stack, var_file: Option<&Path>, vars: &[String]) -> Result<(BTreeMap<String, Json>, BTreeMap<String, SecretString>)> {
+    let mut given: BTreeMap<String, Json> = BTreeMap::new();
+    if let Some(p) = var_file {
+        let text = std::fs::read_to_string(p).with_context(|| format!("read {}", p.display()))?;
+        given = serde_yaml::from_str(&text).with_context(|| format!("--var-file {}: expected a YAML or JSON map", p.display()))?;
+    }
+    for kv in vars {
+        let (k, v) = kv.split_once('=').with_context(|| format!("--var {}: expected name=value", kv))?;
+        let declared = cfg.variables.iter().find(|d| d.name == k);
+        // Only string-typed values stay verbatim; numbers, lists and maps are parsed.
+        let value = match declared.and_then(|d| d.type_expr.as_deref()) {
+            Some("string") | None => json!(v),
+            Some(_) => serde_yaml::from_str(v).with_context(|| format!("--var {}: value is not valid YAML/JSON", k))?,
+        };
+        given.insert(k.to_string(), value);
+    }
+    let (mut tfvars, mut env) = (BTreeMap::new(), BTreeMap::new());
+    for (k, v) in given {
+        let Some(declared) = cfg.variables.iter().find(|d| d.name == k) else {
+            let known: Vec<_> = cfg.variables.iter().map(|d| d.name.as_str()).collect();
+            anyhow::bail!("variable '{}' is not declared under variables (declared: {})", k, if known.is_empty() { "none".to_string() } else { known.join(", ") });
+        };
+        if declared.sensitive {
+            let text = match v { Json::String(s) => s, v => v.to_string() };
+            env.insert(format!("TF_VAR_{}", k), SecretString::new(text));
+        } else {
+            tfvars.insert(k, v);
+        }
+    }
+    Ok((tfvars, env))
+}
+
+/// Sets everything the runner process gets beyond r2iac's own environment, and only on it:
+/// provider settings, then the stack's `environment`, which wins a clash. Secrets, whether
+/// the provider's, `environment` entries or sensitive vars, resolve only when `needs_secrets`.
+fn runner_env(cfg: &Stack, ctx: &RenderCtx, ids: &[age::x25519::Identity], needs_secrets: bool, opts: &mut tfc::RunOptions) -> Result<()> {
+    if let Some(p) = &cfg.provider.google { opts.env.extend(p.env.clone()); }
+    if let Some(p) = &cfg.provider.azurerm {
+        opts.env.extend(p.runner_env().into_iter().map(|(k, v)| (k.to_string(), v)));
+        if let (Some(from), true) = (&p.client_secret_from, needs_secrets) {
+            let secret = read_secret(from, ids).context("resolve provider.azurerm.client_secret_from")?;
+            opts.secret_env.insert("ARM_CLIENT_SECRET".to_string(), secret);
+        }
+    }
+    for (k, v) in &cfg.environment {
+        if k.is_empty() || k.contains(['=', '\0']) { anyhow::bail!("environment: '{}' is not a valid variable name", k); }
+        match v {
+            EnvValue::Literal(s) => { opts.secret_env.remove(k); opts.env.insert(k.clone(), s.clone()); }
+            EnvValue::Secret { value_from } => {
+                opts.env.remove(k);
+                if !needs_secrets { continue; }
+                let value = read_secret(value_from, ids).with_context(|| format!("resolve environment.{}", k))?;
+                opts.secret_env.insert(k.clone(), value);
+            }
+        }
+    }
+    if !needs_secrets { return Ok(()); }
+    for (var, from) in ctx.secret_vars() {
+        let value = read_secret(from, ids).with_context(|| format!("resolve secret for var.{}", var))?;
+        opts.secret_env.insert(format!("TF_VAR_{}", var), value);
+    }
+    Ok(())
+}
+
+/// Anything that talks to providers or remote state may need secrets; init reaches the backend.
+fn needs_secrets(cmd: &Cmd) -> bool {
+    matches!(cmd, Cmd::Init | Cmd::Plan { .. } | Cmd::Apply { .. } | Cmd::Destroy | Cmd::Import { .. } | Cmd::Output { .. } | Cmd::State { .. } | Cmd::Refresh { .. })
+}
+
+/// `name = value` lines or JSON on stdout; one bare value when `name` is given.
+fn print_outputs(outputs: &BTreeMap<String, Json>, name: Option<&str>, as_json: bool, show_sensitive: bool) -> Result<()> {
+    use std::io::Write;
+    let shown = |entry: &Json| -> Json {
+        let sensitive = entry.get("sensitive") == Some(&Json::Bool(true));
+        // JSON is for scripts, which asked for the values; people see a placeholder.
+        if sensitive && !show_sensitive && !as_json { json!("<sensitive>") } else { entry.get("value").cloned().unwrap_or(Json::Null) }
+    };
+    let mut stdout = std::io::stdout();
+    if let Some(n) = name {
+        let entry = outputs.get(n).with_context(|| format!("no output '{}' (known: {})", n, outputs.keys().cloned().collect::<Vec<_>>().join(", ")))?;
+        match shown(entry) {
+            Json::String(s) if !as_json => writeln!(stdout, "{}", s)?,
+            v => writeln!(stdout, "{}", serde_json::to_string_pretty(&v)?)?,
+        }
+        return Ok(());
+    }
+    if as_json {
+        let all: BTreeMap<_, _> = outputs.iter().map(|(k, e)| (k, shown(e))).collect();
+        writeln!(stdout, "{}", serde_json::to_string_pretty(&all)?)?;
+    } else {
+        for (k, e) in outputs { writeln!(stdout, "{} = {}", k, shown(e))?; }
+    }
+    Ok(())
+}
+
+fn collect_exemptions(cfg: &Stack) -> Exemptions {
+    let mut exemptions = Exemptions::new();
+    for r in &cfg.resources {
+        let (type_name, name) = r.type_and_name();
+        if r.exempt_policies().is_empty() { continue; }
+        exemptions.entry((type_name.to_string(), name.to_string())).or_default().extend(r.exempt_policies().iter().cloned());
+    }
+    exemptions
+}
+
+/// The built-in checks on rendered tf.json, plus OPA when a policy directory is given.
+fn check_policy(policy: &Policy, tf: &Json, exemptions: &Exemptions, opa_policy_dir: Option<&Path>) -> Result<PolicyReport> {
+    let mut report = policy.check_tf_json(tf, exemptions);
+    if let Some(dir) = opa_policy_dir { report.merge(check_opa(policy, tf, dir, exemptions)?); }
+    Ok(report)
+}
+
+#[cfg(feature = "opa")]
+fn check_opa(policy: &Policy, tf: &Json, dir: &Path, exemptions: &Exemptions) -> Result<PolicyReport> {
+    policy.check_with_opa(tf, dir, exemptions)
+}
+
+#[cfg(not(feature = "opa"))]
+fn check_opa(_: &Policy, _: &Json, _: &Path, _: &Exemptions) -> Result<PolicyReport> {
+    anyhow::bail!("this r2iac was built without the 'opa' feature")
+}
+
+/// Renders each `*.yml`/`*.yaml` under `dir`, checks it, and compares the report with the
+/// `<stem>.expected.json` beside it, printing a diff for every mismatch.
+fn policy_test(policy: &Policy, dir: &Path, opa_policy_dir: Option<&Path>, ids: &[age::x25519::Identity]) -> Result<()> {
+    let mut stacks: Vec<PathBuf> = std::fs::read_dir(dir).with_context(|| format!("read fixtures dir {}", dir.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yml" | "yaml")))
+        .collect();
+    stacks.sort();
+    if stacks.is_empty() { anyhow::bail!("no .yml/.yaml fixtures in {}", dir.display()); }
+    let mut failed = 0;
+    for path in &stacks {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let expected_path = path.with_file_name(format!("{}.expected.json", stem));
+        let outcome = (|| -> Result<Vec<String>> {
+            let cfg = load_stack(path, ids)?;
+            let (tf, _) = render(&cfg, false)?;
+            let exemptions = collect_exemptions(&cfg);
+            policy.check_exemptions(&exemptions)?;
+            let report = check_policy(policy, &tf, &exemptions, opa_policy_dir)?;
+            // A missing file shows the current report, ready to review and save as the expectation.
+            let Ok(text) = std::fs::read_to_string(&expected_path) else {
+                anyhow::bail!("no {}; this fixture currently produces:\n{}", expected_path.display(), serde_json::to_string_pretty(&report.sorted())?);
+            };
+            let expected: PolicyReport = serde_json::from_str(&text).with_context(|| format!("parse {}", expected_path.display()))?;
+            Ok(report.diff(&expected))
+        })();
+        match outcome {
+            Ok(diff) if diff.is_empty() => eprintln!("ok    {}", path.display()),
+            Ok(diff) => { failed += 1; eprintln!("FAIL  {}\n{}", path.display(), diff.join("\n")); }
+            Err(e) => { failed += 1; eprintln!("FAIL  {}: {:#}", path.display(), e); }
+        }
+    }
+    if failed > 0 { anyhow::bail!("{} of {} policy fixtures failed", failed, stacks.len()); }
+    Ok(())
+}
+
+impl Cli {
+    /// `--opa-policy-dir`, which only exists in builds with the `opa` feature.
+    fn opa_policy_dir(&self) -> Option<&Path> {
+        #[cfg(feature = "opa")]
+        return self.opa_policy_dir.as_deref();
+        #[cfg(not(feature = "opa"))]
+        None
+    }
+}
+
 fn main() -> Result<()> {
-    tracing_subscriber::fmt().json().with_span_events(FmtSpan::CLOSE).init();
+    // Logs go to stderr so stdout carries only command output: outputs, state, reports.
+    tracing_subscriber::fmt().json().with_span_events(FmtSpan::CLOSE).with_writer(std::io::stderr).init();
     let cli = Cli::parse();
-    let policy = Policy::new(cli.allow_unencrypted);
+    let base = match &cli.policy_file { Some(p) => Policy::from_file(p)?, None => Policy::default() };
+    let mut policy = Policy { require_versioning: cli.require_versioning, allow_public_buckets: cli.allow_public_buckets, allow_open_ingress: cli.allow_open_ingress, allow_owner_grants: cli.allow_owner_grants, allow_public_sql: cli.allow_public_sql, allow_no_purge_protection: cli.allow_no_purge_protection, allow_password_auth: cli.allow_password_auth, ..base };
+    if cli.allow_unencrypted { policy.rules.required_encryption = Some(vec![]); }
+
+    if let Some(w) = &cli.workspace { r2iac_tfcompat::check_workspace_name(w)?; }
+    let mut ids = Vec::new();
+    for p in &cli.age_ids { ids.extend(r2iac_crypto::load_identities(p)?); }
+    if let Cmd::Policy { cmd: PolicyCmd::Test { fixtures } } = &cli.cmd {
+        return policy_test(&policy, fixtures, cli.opa_policy_dir(), &ids);
+    }
 
     // Load stack (no passphrase AGE in this MVP)
     let effective_file: PathBuf = match &cli.cmd {
         Cmd::CfnDeploy { file: Some(f), .. } => f.clone(),
         Cmd::CfnDelete { file: Some(f), .. } => f.clone(),
-        _ => cli.file.clone(),
+        _ => cli.file.clone().context("--file is required")?,
     };
     let effective_out: PathBuf = match &cli.cmd {
         Cmd::CfnDeploy { out: Some(p), .. } => p.clone(),
@@ -127,45 +717,37 @@ fn main() -> Result<()> {
         _ => cli.out.clone(),
     };
 
-    let cfg: Stack = if effective_file.extension().and_then(|s| s.to_str()) == Some("age") {
-        let mut ids = Vec::new();
-        for p in &cli.age_ids { ids.extend(r2iac_crypto::load_identities(p)?); }
-        let f = std::fs::File::open(&effective_file)?;
-        let dec = r2iac_crypto::decrypt_age_bytes(std::io::BufReader::new(f), &ids)?;
-        serde_yaml::from_slice(dec.expose_secret())?
-    } else {
-        serde_yaml::from_slice(&std::fs::read(&effective_file)?)?
+    let cfg = load_stack(&effective_file, &ids)?;
+    // CloudFormation commands take CFN-typed resources, checked separately before deploy.
+    let cfn_mode = matches!(cli.cmd, Cmd::CfnDeploy { .. } | Cmd::CfnDelete { .. });
+    let (tf, ctx) = render(&cfg, cfn_mode)?;
+
+    // Policy
+    let exemptions = collect_exemptions(&cfg);
+    policy.check_exemptions(&exemptions)?;
+    let policy_out = PolicyOutput {
+        strict: cli.strict, format: cli.policy_output, file: cli.policy_output_file.clone(),
+        stack: effective_file.to_string_lossy().into_owned(),
     };
+    enforce_policy(check_policy(&policy, &tf, &exemptions, cli.opa_policy_dir())?, &policy_out)?;
 
-    // Build tf.json
-    let mut tf = json!({ "terraform": { "required_providers": {} } });
-    if cfg.provider.aws.is_some() {
-        tf["terraform"]["required_providers"]["aws"] = json!({ "source": "hashicorp/aws", "version": "~> 5.0" });
-        tf = merge(tf, cfg.provider.aws.as_ref().unwrap().to_tf_json());
-    }
-    if cfg.provider.azurerm.is_some() {
-        tf["terraform"]["required_providers"]["azurerm"] = json!({ "source": "hashicorp/azurerm", "version": ">= 3.0" });
-        tf = merge(tf, cfg.provider.azurerm.as_ref().unwrap().to_tf_json());
+    // Secret values reach the runner only through its environment, never main.tf.json.
+    let (tfvars, var_env) = variable_values(&cfg, cli.var_file.as_deref(), &cli.vars)?;
+    if !cli.targets.is_empty() {
+        let known = r2iac_tfcompat::resource_addresses(&tf);
+        for t in &cli.targets { check_address(&known, t)?; }
     }
-    if cfg.provider.google.is_some() {
-        tf["terraform"]["required_providers"]["google"] = json!({ "source": "hashicorp/google", "version": ">= 5.0" });
-        tf = merge(tf, cfg.provider.google.as_ref().unwrap().to_tf_json());
-    }
-    for r in cfg.resources.clone() {
-        match r {
-            Resource::Aws { res } => { tf = merge(tf, res.to_tf_json()); },
-            Resource::AwsAny { res } => { ensure_type_prefix("aws_", &res.type_name)?; tf = merge(tf, res.to_tf_json()); },
-            Resource::Azure { res } => { ensure_type_prefix("azurerm_", &res.type_name)?; tf = merge(tf, res.to_tf_json()); },
-            Resource::Gcp { res } => { tf = merge(tf, res.to_tf_json()); },
-            Resource::GcpAny { res } => { ensure_type_prefix("google_", &res.type_name)?; tf = merge(tf, res.to_tf_json()); },
-        }
+    let mut run_opts = tfc::RunOptions {
+        parallelism: cli.parallelism, lock_timeout: cli.lock_timeout, refresh: !cli.no_refresh,
+        secret_env: var_env, raw_output: cli.raw_output, targets: cli.targets.clone(), ..Default::default()
+    };
+    if !matches!(cli.cmd, Cmd::AwsConfigure { .. } | Cmd::CfnDeploy { .. } | Cmd::CfnDelete { .. }) {
+        runner_env(&cfg, &ctx, &ids, needs_secrets(&cli.cmd), &mut run_opts)?;
     }
 
-    // Policy
-    policy.check_tf_json(&tf)?;
-
     // Write + run
     r2iac_tfcompat::write_tf_json(&tf, &effective_out)?;
+    r2iac_tfcompat::write_tfvars_json(&tfvars, &effective_out)?;
     let r = match cli.runner {
         Runner::Terraform => Some(tfc::Runner::Terraform),
         Runner::Tofu      => Some(tfc::Runner::Tofu),
@@ -174,29 +756,60 @@ fn main() -> Result<()> {
 
     match cli.cmd {
       Cmd::Init    => { 
-          let runner = r2iac_tfcompat::pick_runner(r)?;
-          r2iac_tfcompat::run_init(runner, &effective_out)?; 
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?; 
       },
-      Cmd::Plan    => { 
-          let runner = r2iac_tfcompat::pick_runner(r)?;
-          r2iac_tfcompat::run_init(runner, &effective_out)?; 
-          r2iac_tfcompat::run_plan(runner, &effective_out)?; 
+      Cmd::Plan { detailed_exitcode, save } => { 
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?; 
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          let mut outcome = None;
+          match &save {
+              Some(p) => r2iac_tfcompat::run_plan_save(runner, &effective_out, p, &run_opts)?,
+              None if detailed_exitcode => outcome = Some(r2iac_tfcompat::run_plan_detailed(runner, &effective_out, &run_opts)?),
+              None => r2iac_tfcompat::run_plan_with(runner, &effective_out, &run_opts)?,
+          }
+          let plan_path = save.unwrap_or_else(|| effective_out.join(tfc::PLAN_FILE));
+          let plan = tfc::PlanSummary::from_plan_json(r2iac_tfcompat::show_saved_plan_json(runner, &effective_out, &plan_path, &run_opts)?)?;
+          eprintln!("{}", plan);
+          enforce_plan_policy(policy.check_plan_json(&plan.plan, &exemptions), &policy_out, cli.force)?;
+          // Policy failures still exit 1 above; 2 is reserved for a clean plan with changes.
+          if outcome == Some(tfc::PlanOutcome::Changes) { std::process::exit(2); }
       },
-      Cmd::Apply   => { 
-          let runner = r2iac_tfcompat::pick_runner(r)?;
-          r2iac_tfcompat::run_init(runner, &effective_out)?; 
-          r2iac_tfcompat::run_apply(runner, &effective_out)?; 
+      Cmd::Apply { plan: saved } => { 
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?; 
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          if let Some(p) = &saved {
+              r2iac_tfcompat::check_saved_plan(&effective_out, p)?;
+              // Checked again: the policy file may have changed since the plan was reviewed.
+              let plan = tfc::PlanSummary::from_plan_json(r2iac_tfcompat::show_saved_plan_json(runner, &effective_out, p, &run_opts)?)?;
+              eprintln!("{}", plan);
+              enforce_plan_policy(policy.check_plan_json(&plan.plan, &exemptions), &policy_out, cli.force)?;
+              r2iac_tfcompat::run_apply_saved(runner, &effective_out, p, &run_opts)?;
+          } else {
+              // Computed values and provider defaults only exist in the plan; deny before anything changes.
+              let plan = r2iac_tfcompat::run_plan_json(runner, &effective_out, &run_opts)?;
+              eprintln!("{}", plan);
+              enforce_plan_policy(policy.check_plan_json(&plan.plan, &exemptions), &policy_out, cli.force)?;
+              r2iac_tfcompat::run_apply_plan(runner, &effective_out, &run_opts)?; 
+          }
       },
       Cmd::Destroy => { 
-          let runner = r2iac_tfcompat::pick_runner(r)?;
-          r2iac_tfcompat::run_init(runner, &effective_out)?; 
-          r2iac_tfcompat::run_destroy(runner, &effective_out)?; 
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?; 
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          // Same blast-radius check as apply: plan the deletions, check them, then apply that plan.
+          let plan = r2iac_tfcompat::run_plan_destroy_json(runner, &effective_out, &run_opts)?;
+          eprintln!("{}", plan);
+          enforce_plan_policy(policy.check_plan_json(&plan.plan, &exemptions), &policy_out, cli.force)?;
+          r2iac_tfcompat::run_apply_plan(runner, &effective_out, &run_opts)?;
       },
       Cmd::AwsConfigure { profile, access_key_id, secret_access_key, region } => {
           let aws = which::which("aws").context("'aws' CLI not found in PATH. Install AWS CLI v2.")?;
           match (access_key_id, secret_access_key, region) {
               (Some(ak), Some(sk), Some(rg)) => {
-                  let mut run = |args: &[&str]| -> Result<()> {
+                  let run = |args: &[&str]| -> Result<()> {
                       let st = Command::new(&aws).args(args).status().context("spawn aws configure set")?;
                       if !st.success() { anyhow::bail!("aws configure set failed: {:?}", args); }
                       Ok(())
@@ -217,25 +830,248 @@ fn main() -> Result<()> {
               }
           }
       },
-      Cmd::CfnDeploy { stack: stack_opt } => {
+      Cmd::CfnDeploy { stack: stack_opt, .. } => {
           let stack_name = stack_opt.or(cfg.project.clone()).unwrap_or_else(|| "r2iac-stack".to_string());
           // Reuse the same tf JSON as a CFN template if user supplied CFN-structured input instead.
           // For now, assume the YAML is already a CFN template under `resources` keyed map.
           let mut resources = std::collections::BTreeMap::new();
           for r in cfg.resources.into_iter() {
-              if let Resource::AwsAny { res } = r { resources.insert(res.name.clone(), cfn::CfnAnyResource { name: res.name.clone(), type_name: res.type_name, properties: res.properties }); }
+              if let Resource::AwsAny { res, .. } = r { resources.insert(res.name.clone(), cfn::CfnAnyResource { name: res.name.clone(), type_name: res.type_name, properties: res.properties }); }
               else { continue; }
           }
           let tpl = cfn::CfnTemplate { version: Some("2010-09-09".to_string()), description: Some("r2iac generated CFN".to_string()), resources };
+          enforce_policy(policy.check_cfn_template(&tpl, &exemptions), &policy_out)?;
           let tpl_json = serde_json::to_value(tpl)?;
-          let region = cfg.provider.aws.as_ref().map(|p| p.region.as_str());
+          let region = cfg.provider.aws.as_ref().and_then(|p| p.default_provider()).map(|p| p.region.as_str());
           cfn::deploy_stack(&stack_name, &tpl_json, region)?
       },
-      Cmd::CfnDelete { stack: stack_opt } => {
+      Cmd::CfnDelete { stack: stack_opt, .. } => {
           let stack_name = stack_opt.or(cfg.project.clone()).unwrap_or_else(|| "r2iac-stack".to_string());
-          let region = cfg.provider.aws.as_ref().map(|p| p.region.as_str());
+          let region = cfg.provider.aws.as_ref().and_then(|p| p.default_provider()).map(|p| p.region.as_str());
           cfn::delete_stack(&stack_name, region)?
       },
+      Cmd::Import { address, id } => {
+          // The block must be in the config for import to attach state to it.
+          check_address(&r2iac_tfcompat::resource_addresses(&tf), &address)?;
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?;
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          r2iac_tfcompat::run_import(runner, &effective_out, &address, &id, &run_opts)?;
+          // Whatever the plan still wants to change is where the config and the real object disagree.
+          eprintln!("{}", r2iac_tfcompat::run_plan_json(runner, &effective_out, &run_opts)?);
+      },
+      Cmd::Output { json, show_sensitive, name } => {
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?;
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          let outputs = r2iac_tfcompat::run_output(runner, &effective_out, &run_opts)?;
+          print_outputs(&outputs, name.as_deref(), json, show_sensitive)?;
+      },
+      Cmd::Refresh { report_only } => {
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?;
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          let plan = r2iac_tfcompat::run_plan_refresh_only_json(runner, &effective_out, &run_opts)?;
+          eprintln!("{}", tfc::DriftReport(&plan.drift));
+          // A refresh-only plan can only rewrite state, so there is no policy to check before applying it.
+          if !report_only && !plan.drift.is_empty() { r2iac_tfcompat::run_apply_plan(runner, &effective_out, &run_opts)?; }
+      },
+      Cmd::Validate => {
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_no_backend(runner, &effective_out, &run_opts)?;
+          let result = r2iac_tfcompat::run_validate(runner, &effective_out, &run_opts)?;
+          let tf_text = serde_json::to_string_pretty(&tf)?;
+          for d in &result.diagnostics {
+              eprintln!("{}", d);
+              let addr = d.range.as_ref().filter(|r| r.filename == tfc::TF_FILE).and_then(|r| tfc::address_at_line(&tf_text, r.start.line));
+              let from = addr.and_then(|a| cfg.resources.iter().enumerate().find(|(_, res)| { let (t, n) = res.type_and_name(); a == format!("{}.{}", t, n) }));
+              if let Some((i, res)) = from { eprintln!("  from resources[{}] '{}' in {}", i, res.type_and_name().1, effective_file.display()); }
+          }
+          eprintln!("Validation: {} errors, {} warnings.", result.error_count, result.warning_count);
+          // Render and policy failures exit 1 as errors above, so CI can tell them apart.
+          if !result.valid { std::process::exit(2); }
+      },
+      Cmd::State { cmd } => {
+          let runner = r2iac_tfcompat::pick_runner_matching(r, cfg.required_runner_version.as_deref())?;
+          r2iac_tfcompat::run_init_with(runner, &effective_out, &run_opts)?;
+          if let Some(w) = &cli.workspace { r2iac_tfcompat::run_workspace_select(runner, &effective_out, w, &run_opts)?; }
+          let state = r2iac_tfcompat::run_state_list(runner, &effective_out, &run_opts)?;
+          match cmd {
+              StateCmd::List => {
+                  use std::io::Write;
+                  let mut stdout = std::io::stdout().lock();
+                  for a in &state { writeln!(stdout, "{}", a)?; }
+              }
+              StateCmd::Rm { addresses, yes } => {
+                  if let Some(a) = addresses.iter().find(|a| !in_state(&state, a)) { anyhow::bail!("{} is not in state", a); }
+                  if !yes {
+                      use std::io::IsTerminal;
+                      if !std::io::stdin().is_terminal() { anyhow::bail!("state rm needs --yes when not run from a terminal"); }
+                      for a in &addresses { eprintln!("{}", a); }
+                      if !confirm("These will no longer be managed, and the real objects will be left running.")? { anyhow::bail!("aborted: state rm"); }
+                  }
+                  r2iac_tfcompat::run_state_rm(runner, &effective_out, &addresses, &run_opts)?;
+              }
+              StateCmd::Mv { from, to } => {
+                  if !in_state(&state, &from) { anyhow::bail!("{} is not in state", from); }
+                  if in_state(&state, &to) { anyhow::bail!("{} is already in state; move or remove it first", to); }
+                  r2iac_tfcompat::run_state_mv(runner, &effective_out, &from, &to, &run_opts)?;
+              }
+          }
+      },
+      Cmd::Policy { .. } => unreachable!("handled before the stack is loaded"),
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_BUCKET: &str = "project: fx
+provider: { aws: { region: us-east-1 } }
+resources:
+  - { cloud: aws, type: aws_s3_bucket, name: site, bucket: acme-site, block_public_access: false }
+";
+
+    fn expected(message: &str) -> String {
+        serde_json::to_string(&json!({ "violations": [{
+            "rule": "public-access", "severity": "deny", "resource": "aws_s3_bucket.site", "message": message,
+        }] })).unwrap()
+    }
+
+    #[test]
+    fn policy_test_compares_fixtures_with_expected_reports() {
+        let dir = std::env::temp_dir().join(format!("r2iac-policy-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("public.yml"), PUBLIC_BUCKET).unwrap();
+        let policy = Policy::default();
+
+        let err = policy_test(&policy, &dir, None, &[]).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 1 policy fixtures failed");
+
+        std::fs::write(dir.join("public.expected.json"),
+            expected("Policy: S3 bucket 'site' must block public access (pass --allow-public-buckets to override).")).unwrap();
+        policy_test(&policy, &dir, None, &[]).unwrap();
+
+        std::fs::write(dir.join("public.expected.json"), expected("something else")).unwrap();
+        assert!(policy_test(&policy, &dir, None, &[]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn policy_test_needs_fixtures() {
+        let dir = std::env::temp_dir().join(format!("r2iac-policy-test-empty-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let err = policy_test(&Policy::default(), &dir, None, &[]).unwrap_err();
+        assert!(err.to_string().starts_with("no .yml/.yaml fixtures in"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn example_policy_fixtures_pass() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/policy-fixtures");
+        policy_test(&Policy::default(), &dir, None, &[]).unwrap();
+    }
+
+    #[test]
+    fn azure_client_secret_only_reaches_the_runner_env() {
+        let dir = std::env::temp_dir().join(format!("r2iac-azure-secret-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("secret"), "s3cr3t-value\n").unwrap();
+        let cfg: Stack = serde_yaml::from_str(&format!("project: fx
+provider:
+  azurerm: {{ subscription_id: sub-1, tenant_id: tenant-1, client_id: app-1, client_secret_from: 'file:{}' }}
+resources: []
+", dir.join("secret").display())).unwrap();
+
+        let (tf, ctx) = render(&cfg, false).unwrap();
+        let provider = serde_json::to_string(&tf["provider"]["azurerm"]).unwrap();
+        assert!(!provider.contains("s3cr3t-value") && !provider.contains("client_secret"), "{}", provider);
+        assert!(!serde_json::to_string(&tf).unwrap().contains("s3cr3t-value"));
+
+        let mut opts = tfc::RunOptions::default();
+        runner_env(&cfg, &ctx, &[], true, &mut opts).unwrap();
+        assert_eq!(opts.secret_env["ARM_CLIENT_SECRET"].expose_secret(), "s3cr3t-value");
+        assert_eq!(opts.env.get("ARM_CLIENT_ID").map(String::as_str), Some("app-1"));
+        assert!(opts.env.values().all(|v| !v.contains("s3cr3t-value")));
+        assert_eq!(opts.secret_env.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn secrets_resolve_only_when_needed() {
+        let cfg: Stack = serde_yaml::from_str("project: fx
+provider: { aws: { region: us-east-1 } }
+environment:
+  TF_LOG: info
+  GITHUB_TOKEN: { value_from: 'env:R2IAC_TEST_UNSET_TOKEN' }
+resources: []
+").unwrap();
+        let (_, ctx) = render(&cfg, false).unwrap();
+        let mut opts = tfc::RunOptions::default();
+        runner_env(&cfg, &ctx, &[], false, &mut opts).unwrap();
+        assert_eq!(opts.env.get("TF_LOG").map(String::as_str), Some("info"));
+        assert!(opts.secret_env.is_empty() && !opts.env.contains_key("GITHUB_TOKEN"));
+
+        let err = runner_env(&cfg, &ctx, &[], true, &mut tfc::RunOptions::default()).unwrap_err();
+        assert_eq!(format!("{:#}", err), "resolve environment.GITHUB_TOKEN: environment variable R2IAC_TEST_UNSET_TOKEN is not set: environment variable not found");
+
+        // Backend credentials are needed from init on; validate runs init without the backend.
+        assert!(needs_secrets(&Cmd::Init));
+        assert!(!needs_secrets(&Cmd::Validate));
+    }
+
+    #[test]
+    fn backend_renders_beside_required_providers() {
+        let cfg: Stack = serde_yaml::from_str("project: fx
+backend: { type: s3, bucket: tf-state, key: fx/terraform.tfstate, region: us-east-1, dynamodb_table: tf-locks }
+provider: { aws: { region: us-east-1 } }
+resources: []
+").unwrap();
+        let (tf, _) = render(&cfg, false).unwrap();
+        assert_eq!(tf["terraform"]["required_providers"]["aws"]["source"], "hashicorp/aws");
+        assert_eq!(tf["terraform"]["backend"]["s3"], json!({
+            "bucket": "tf-state", "key": "fx/terraform.tfstate", "region": "us-east-1", "dynamodb_table": "tf-locks", "encrypt": true,
+        }));
+
+        let cfg: Stack = serde_yaml::from_str("project: fx
+backend: { type: gcs, prefix: fx }
+provider: {}
+resources: []
+").unwrap();
+        assert_eq!(render(&cfg, false).unwrap_err().to_string(), "backend.gcs: bucket is required");
+    }
+
+    #[test]
+    fn provider_alias_pins_the_resource_and_its_companions() {
+        let stack = |alias: &str| serde_yaml::from_str::<Stack>(&format!("project: fx
+provider:
+  aws:
+    - {{ region: us-east-1 }}
+    - {{ region: eu-west-1, alias: eu }}
+resources:
+  - {{ cloud: aws, type: aws_s3_bucket, name: replica, bucket: fx-replica, versioning: true, provider_alias: {} }}
+  - {{ cloud: aws, type: aws_s3_bucket, name: primary, bucket: fx-primary }}
+", alias)).unwrap();
+        let (tf, _) = render(&stack("eu"), false).unwrap();
+        for t in ["aws_s3_bucket", "aws_s3_bucket_versioning", "aws_s3_bucket_public_access_block", "aws_s3_bucket_server_side_encryption_configuration"] {
+            assert_eq!(tf["resource"][t]["replica"]["provider"], "aws.eu", "{}", t);
+        }
+        assert!(tf["resource"]["aws_s3_bucket"]["primary"].get("provider").is_none());
+        assert_eq!(tf["provider"]["aws"][1], json!({ "region": "eu-west-1", "alias": "eu" }));
+
+        assert_eq!(render(&stack("ap"), false).unwrap_err().to_string(),
+            "aws_s3_bucket.replica: provider_alias 'ap' is not defined under provider.aws");
+    }
+
+    #[test]
+    fn environment_value_from_is_checked_on_load() {
+        let err = serde_yaml::from_str::<Stack>("project: fx
+provider: {}
+environment: { TOKEN: { value_from: 'vault:x' } }
+resources: []
+").err().unwrap();
+        assert!(err.to_string().contains("a string, or { value_from: env:VAR, file:PATH or age:PATH }"), "{}", err);
+    }
+}
@@ -0,0 +1,64 @@
+//! Runs the built binary against a fake runner and checks that stdout carries only command output.
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A scratch directory holding `stack.yml` and a `terraform` script that prints noise on
+/// every command except `output -json`, which prints `outputs`.
+fn fixture(test: &str, stack: &str, outputs: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("r2iac-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("bin")).unwrap();
+    std::fs::write(dir.join("stack.yml"), stack).unwrap();
+    let script = dir.join("bin/terraform");
+    std::fs::write(&script, format!(
+        "#!/bin/sh\ncase \"$*\" in\n  *'output -json'*) cat <<'JSON'\n{}\nJSON\n;;\n  *) echo 'Initializing the backend...'; echo 'Terraform has been successfully initialized!' >&2 ;;\nesac\n",
+        outputs,
+    )).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    dir
+}
+
+fn r2iac(dir: &Path, args: &[&str]) -> Output {
+    let path = format!("{}:{}", dir.join("bin").display(), std::env::var("PATH").unwrap_or_default());
+    Command::new(env!("CARGO_BIN_EXE_r2iac"))
+        .current_dir(dir)
+        .env("PATH", path)
+        .args(["--runner", "terraform", "--file", "stack.yml", "--out", "out"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+const STACK: &str = "project: t\nprovider:\n  aws: { region: us-east-1 }\nresources: []\n";
+
+#[test]
+fn output_json_is_one_document() {
+    let dir = fixture("output-json", STACK, r#"{ "url": { "sensitive": false, "type": "string", "value": "https://example.com" } }"#);
+    let o = r2iac(&dir, &["output", "--json"]);
+    assert!(o.status.success(), "{}", String::from_utf8_lossy(&o.stderr));
+    let doc: serde_json::Value = serde_json::from_slice(&o.stdout).expect("stdout is a single JSON document");
+    assert_eq!(doc, serde_json::json!({ "url": "https://example.com" }));
+    // The init output was logged, just not on stdout.
+    assert!(String::from_utf8_lossy(&o.stderr).contains("successfully initialized"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sarif_report_on_stdout_parses() {
+    let stack = "project: t\nprovider:\n  aws: { region: us-east-1 }\nresources:\n  - { cloud: aws_any, type: aws_ebs_volume, name: logs, availability_zone: us-east-1a, size: 50 }\n";
+    let dir = fixture("sarif", stack, "{}");
+    // A warning too, since warnings are logged as well as reported.
+    std::fs::write(dir.join("policy.yml"), "required_tags: [owner]\nseverity: { required-tags: warn }\n").unwrap();
+    let o = r2iac(&dir, &["--policy-file", "policy.yml", "--policy-output", "sarif", "plan"]);
+    assert!(!o.status.success());
+    let sarif: serde_json::Value = serde_json::from_slice(&o.stdout).expect("stdout is only the SARIF log");
+    assert_eq!(sarif["version"], "2.1.0");
+    let rules: Vec<_> = sarif["runs"][0]["results"].as_array().unwrap().iter().map(|r| (r["ruleId"].clone(), r["level"].clone())).collect();
+    assert_eq!(rules, [("encryption".into(), "error".into()), ("required-tags".into(), "warning".into())]);
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains("\"level\":\"WARN\"") && stderr.contains("policy check failed"), "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
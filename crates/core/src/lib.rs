@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use serde::{Serialize, Deserialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 use thiserror::Error;
 use petgraph::graph::DiGraph;
 use petgraph::algo::toposort;
@@ -69,3 +70,187 @@ pub async fn apply_all(resources: &[Box<dyn Resource>], plan: &[(String, Op)]) -
     }
     Ok(())
 }
+
+/// Stack-wide view a resource gets while rendering: which logical resources
+/// exist, so `*_ref` fields can tell a sibling apart from a literal identifier.
+#[derive(Debug, Clone, Default)]
+pub struct RenderCtx {
+    declared: BTreeSet<(String, String)>,
+    parents: BTreeSet<(String, String)>,
+    cidrs: BTreeMap<String, Vec<(String, String)>>,
+    secrets: BTreeMap<String, ValueFrom>,
+}
+
+impl RenderCtx {
+    pub fn declare(&mut self, type_name: &str, name: &str) {
+        self.declared.insert((type_name.to_string(), name.to_string()));
+    }
+
+    pub fn has(&self, type_name: &str, name: &str) -> bool {
+        self.declared.contains(&(type_name.to_string(), name.to_string()))
+    }
+
+    /// Record that some resource in the stack hangs off `type_name.name`.
+    pub fn declare_child_of(&mut self, type_name: &str, name: &str) {
+        self.parents.insert((type_name.to_string(), name.to_string()));
+    }
+
+    pub fn has_children(&self, type_name: &str, name: &str) -> bool {
+        self.parents.contains(&(type_name.to_string(), name.to_string()))
+    }
+
+    /// Record that `owner` uses `cidr` within `scope` (e.g. one VPC), failing when it
+    /// overlaps a block claimed there by an earlier resource.
+    pub fn claim_cidr(&mut self, scope: &str, owner: &str, cidr: &str) -> anyhow::Result<()> {
+        let (v6, start, end) = cidr_bounds(owner, cidr)?;
+        let claimed = self.cidrs.entry(scope.to_string()).or_default();
+        for (other, other_cidr) in claimed.iter() {
+            let (other_v6, other_start, other_end) = cidr_bounds(other, other_cidr)?;
+            if v6 == other_v6 && start <= other_end && other_start <= end {
+                anyhow::bail!("{}: '{}' overlaps '{}' of {} in {}", owner, cidr, other_cidr, other, scope);
+            }
+        }
+        claimed.push((owner.to_string(), cidr.to_string()));
+        Ok(())
+    }
+
+    /// `${type_name.r.attr}` when `r` names a sibling of that type.
+    pub fn sibling(&self, type_name: &str, r: &str, attr: &str) -> Option<String> {
+        self.has(type_name, r).then(|| format!("${{{}.{}.{}}}", type_name, r, attr))
+    }
+
+    /// Like [`RenderCtx::sibling`], but falls back to `r` as a literal value.
+    pub fn resolve(&self, type_name: &str, r: &str, attr: &str) -> String {
+        self.sibling(type_name, r, attr).unwrap_or_else(|| r.to_string())
+    }
+
+    /// Register a sensitive variable fed from `from` and return its interpolation.
+    /// The value itself never enters the rendered document; the CLI resolves it and
+    /// hands it to the runner as `TF_VAR_<var>`.
+    pub fn secret_var(&mut self, var: &str, from: &ValueFrom) -> String {
+        self.secrets.insert(var.to_string(), from.clone());
+        format!("${{var.{}}}", var)
+    }
+
+    pub fn secret_vars(&self) -> &BTreeMap<String, ValueFrom> { &self.secrets }
+}
+
+/// Sensitive variable name for a secret-bearing resource; `_` only, so it is a
+/// valid `TF_VAR_` suffix as well.
+pub fn secret_var_name(type_name: &str, name: &str) -> String {
+    format!("{}_{}", type_name, name).replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+}
+
+/// Where a secret value is read from: `env:VAR`, `file:PATH`, or `age:PATH`
+/// (decrypted with the configured identities).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from="String", into="String")]
+pub enum ValueFrom {
+    Env(String),
+    File(PathBuf),
+    Age(PathBuf),
+}
+
+impl TryFrom<String> for ValueFrom {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.split_once(':') {
+            Some(("env", v)) if !v.is_empty() => Ok(ValueFrom::Env(v.to_string())),
+            Some(("file", p)) if !p.is_empty() => Ok(ValueFrom::File(PathBuf::from(p))),
+            Some(("age", p)) if !p.is_empty() => Ok(ValueFrom::Age(PathBuf::from(p))),
+            _ => Err(format!("value_from '{}' must be env:VAR, file:PATH or age:PATH", s)),
+        }
+    }
+}
+
+impl From<ValueFrom> for String {
+    fn from(v: ValueFrom) -> Self {
+        match v {
+            ValueFrom::Env(v) => format!("env:{}", v),
+            ValueFrom::File(p) => format!("file:{}", p.display()),
+            ValueFrom::Age(p) => format!("age:{}", p.display()),
+        }
+    }
+}
+
+/// Parse `addr/prefix` as an IPv4 or IPv6 network; `owner` prefixes errors.
+/// Host bits must be zero, matching what the providers accept.
+pub fn check_cidr(owner: &str, cidr: &str) -> anyhow::Result<()> {
+    cidr_bounds(owner, cidr).map(|_| ())
+}
+
+/// First and last address of a checked CIDR block, and whether it is IPv6.
+fn cidr_bounds(owner: &str, cidr: &str) -> anyhow::Result<(bool, u128, u128)> {
+    use std::net::IpAddr;
+    let (addr, prefix) = cidr.split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("{}: '{}' is not a CIDR block (expected addr/prefix)", owner, cidr))?;
+    let addr: IpAddr = addr.parse()
+        .map_err(|_| anyhow::anyhow!("{}: '{}' has an invalid address", owner, cidr))?;
+    let prefix: u8 = prefix.parse()
+        .map_err(|_| anyhow::anyhow!("{}: '{}' has an invalid prefix length", owner, cidr))?;
+    let (bits, max) = match addr {
+        IpAddr::V4(a) => (u32::from(a) as u128, 32),
+        IpAddr::V6(a) => (u128::from(a), 128),
+    };
+    if prefix > max {
+        anyhow::bail!("{}: '{}' prefix length must be <= {}", owner, cidr, max);
+    }
+    let host_bits = u32::from(max - prefix);
+    let host_mask = if host_bits == 0 { 0 } else { u128::MAX >> (128 - host_bits) };
+    if bits & host_mask != 0 {
+        anyhow::bail!("{}: '{}' has host bits set", owner, cidr);
+    }
+    Ok((max == 128, bits, bits | host_mask))
+}
+
+/// Shared by the provider crates' render tests, through the `test-util` feature.
+#[cfg(feature = "test-util")]
+pub mod test_util {
+    use super::RenderCtx;
+    use serde_json::Value as Json;
+
+    /// A context with each `(type, name)` declared, as the rest of a stack would be.
+    pub fn ctx(siblings: &[(&str, &str)]) -> RenderCtx {
+        let mut ctx = RenderCtx::default();
+        for (t, n) in siblings { ctx.declare(t, n); }
+        ctx
+    }
+
+    /// `res` with the fields of `extra` added or replaced.
+    pub fn with(mut res: Json, extra: Json) -> Json {
+        res.as_object_mut().expect("resource is a mapping").extend(extra.as_object().expect("extra is a mapping").clone());
+        res
+    }
+
+    /// Renders `res` with each case's fields added and asserts it fails with an error naming
+    /// the resource (`<type>.<name>: `) and containing the case's message.
+    #[track_caller]
+    pub fn assert_errors<T: std::fmt::Debug>(res: &Json, cases: &[(Json, &str)], render: impl Fn(Json) -> anyhow::Result<T>) {
+        for (extra, msg) in cases {
+            let res = with(res.clone(), extra.clone());
+            let owner = format!("{}.{}: ", res["type"].as_str().unwrap_or_default(), res["name"].as_str().unwrap_or_default());
+            let err = format!("{:#}", render(res).expect_err(&extra.to_string()));
+            assert!(err.starts_with(&owner) && err.contains(msg), "{} with {}: {}", owner, extra, err);
+        }
+    }
+
+    /// A `MakeWriter` collecting everything a test's subscriber writes.
+    #[derive(Clone, Default)]
+    pub struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.lock().unwrap().write(buf) }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    impl Captured {
+        /// Runs `f` with every event at any level formatted into this buffer.
+        pub fn during<T>(&self, f: impl FnOnce() -> T) -> T {
+            let writer = self.clone();
+            let subscriber = tracing_subscriber::fmt().with_max_level(tracing::Level::TRACE).with_writer(move || writer.clone()).finish();
+            tracing::subscriber::with_default(subscriber, f)
+        }
+
+        pub fn text(&self) -> String { String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned() }
+    }
+}
@@ -1,13 +1,37 @@
+use anyhow::{Context, Result};
+use r2iac_core::{check_cidr, secret_var_name, RenderCtx, ValueFrom};
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value as Json, Map as JsonMap};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GcpProvider { pub project: String, pub region: Option<String> }
+pub struct GcpProvider {
+    pub project: String,
+    pub region: Option<String>,
+    #[serde(default)] pub zone: Option<String>,
+    /// Service-account key file; rendered as an absolute path since the runner works in the output dir.
+    #[serde(default)] pub credentials: Option<PathBuf>,
+    #[serde(default)] pub impersonate_service_account: Option<String>,
+    #[serde(default)] pub billing_project: Option<String>,
+    /// Extra environment (e.g. `GOOGLE_APPLICATION_CREDENTIALS`) the CLI sets for the runner.
+    #[serde(default)] pub env: BTreeMap<String, String>,
+}
 impl GcpProvider {
-    pub fn to_tf_json(&self) -> Json {
+    pub fn to_tf_json(&self) -> Result<Json> {
         let mut body = json!({ "project": self.project });
         if let Some(r) = &self.region { body["region"] = json!(r); }
-        json!({ "provider": { "google": body } })
+        if let Some(z) = &self.zone { body["zone"] = json!(z); }
+        if let Some(c) = &self.credentials {
+            let path = std::fs::canonicalize(c).with_context(|| format!("provider.google: credentials file {} not found", c.display()))?;
+            body["credentials"] = json!(path.display().to_string());
+        }
+        if let Some(sa) = &self.impersonate_service_account { body["impersonate_service_account"] = json!(sa); }
+        if let Some(b) = &self.billing_project {
+            body["billing_project"] = json!(b);
+            body["user_project_override"] = json!(true);
+        }
+        Ok(json!({ "provider": { "google": body } }))
     }
 }
 
@@ -15,28 +39,1282 @@ impl GcpProvider {
 #[serde(tag="type")]
 pub enum GcpResource {
     #[serde(rename="google_storage_bucket")]
-    StorageBucket { name: String, location: String, force_destroy: Option<bool> },
+    StorageBucket {
+        name: String,
+        location: String,
+        force_destroy: Option<bool>,
+        #[serde(default)]
+        versioning: Option<bool>,
+        #[serde(default="default_some_true")]
+        uniform_bucket_level_access: Option<bool>,
+        #[serde(default)]
+        lifecycle_rules: Vec<GcsLifecycleRule>,
+        #[serde(default)]
+        encryption: Option<GcsEncryption>,
+        /// `enforced` (the default) or `inherited`.
+        #[serde(default)]
+        public_access_prevention: Option<String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
     #[serde(rename="google_kms_key_ring")]
     KmsKeyRing { name: String, location: String },
     #[serde(rename="google_secret_manager_secret")]
-    SecretManagerSecret { name: String, replication: String },
+    SecretManagerSecret {
+        name: String,
+        #[serde(default)]
+        replication: SecretReplication,
+        /// Sibling `google_kms_crypto_key` name, or a full key id; applied to every replica.
+        #[serde(default)]
+        kms_key_ref: Option<String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_secret_manager_secret_version")]
+    SecretManagerSecretVersion {
+        name: String,
+        secret_ref: String,
+        /// The payload is passed to Terraform as a sensitive variable, never inlined.
+        value_from: ValueFrom,
+    },
+    #[serde(rename="google_kms_crypto_key")]
+    KmsCryptoKey {
+        name: String,
+        /// Sibling `google_kms_key_ring` name, or a full `projects/.../keyRings/...` id.
+        key_ring_ref: String,
+        #[serde(default)]
+        rotation_period: Option<String>,
+        #[serde(default)]
+        purpose: Option<KmsKeyPurpose>,
+        /// Required for asymmetric purposes, e.g. `EC_SIGN_P256_SHA256`.
+        #[serde(default)]
+        algorithm: Option<String>,
+        #[serde(default)]
+        protection_level: Option<KmsProtectionLevel>,
+        #[serde(default)]
+        destroy_scheduled_duration: Option<String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_compute_instance")]
+    ComputeInstance {
+        name: String,
+        machine_type: String,
+        zone: String,
+        boot_disk: BootDisk,
+        network_interface: NetworkInterface,
+        #[serde(default)]
+        service_account: Option<InstanceServiceAccount>,
+        #[serde(default)]
+        metadata: BTreeMap<String, String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_compute_network")]
+    ComputeNetwork {
+        name: String,
+        #[serde(default)]
+        auto_create_subnetworks: Option<bool>,
+        #[serde(default)]
+        routing_mode: Option<RoutingMode>,
+        #[serde(default)]
+        mtu: Option<u32>,
+    },
+    #[serde(rename="google_compute_subnetwork")]
+    ComputeSubnetwork {
+        name: String,
+        network_ref: String,
+        ip_cidr_range: String,
+        #[serde(default)]
+        region: Option<String>,
+        #[serde(default)]
+        private_ip_google_access: Option<bool>,
+        /// Alias ranges, e.g. for GKE pods and services.
+        #[serde(default)]
+        secondary_ranges: Vec<SecondaryRange>,
+    },
+    #[serde(rename="google_compute_firewall")]
+    ComputeFirewall {
+        name: String,
+        network_ref: String,
+        #[serde(default)]
+        direction: FirewallDirection,
+        #[serde(default)]
+        priority: Option<u32>,
+        #[serde(default)]
+        source_ranges: Vec<String>,
+        #[serde(default)]
+        destination_ranges: Vec<String>,
+        #[serde(default)]
+        target_tags: Vec<String>,
+        #[serde(default)]
+        allow: Vec<FirewallRule>,
+        #[serde(default)]
+        deny: Vec<FirewallRule>,
+    },
+    #[serde(rename="google_service_account")]
+    ServiceAccount {
+        name: String,
+        account_id: String,
+        #[serde(default)]
+        display_name: Option<String>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    #[serde(rename="google_project_iam_member")]
+    ProjectIamMember {
+        name: String,
+        role: String,
+        /// `user:...`/`group:...` etc., or the name of a sibling service account.
+        member: String,
+        /// Defaults to the provider's project.
+        #[serde(default)]
+        project: Option<String>,
+    },
+    #[serde(rename="google_service_account_iam_member")]
+    ServiceAccountIamMember {
+        name: String,
+        service_account_ref: String,
+        role: String,
+        member: String,
+    },
+    #[serde(rename="google_pubsub_topic")]
+    PubSubTopic {
+        name: String,
+        #[serde(default)]
+        kms_key_ref: Option<String>,
+        #[serde(default)]
+        message_retention_duration: Option<String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_pubsub_subscription")]
+    PubSubSubscription {
+        name: String,
+        topic_ref: String,
+        #[serde(default)]
+        ack_deadline_seconds: Option<u32>,
+        /// Makes this a push subscription; pull otherwise.
+        #[serde(default)]
+        push_endpoint: Option<String>,
+        #[serde(default)]
+        dead_letter: Option<DeadLetter>,
+        #[serde(default)]
+        retain_acked_messages: Option<bool>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_bigquery_dataset")]
+    BigQueryDataset {
+        name: String,
+        dataset_id: String,
+        #[serde(default)]
+        location: Option<String>,
+        #[serde(default)]
+        default_table_expiration_ms: Option<u64>,
+        #[serde(default)]
+        default_kms_key_ref: Option<String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+        #[serde(default)]
+        delete_contents_on_destroy: Option<bool>,
+    },
+    #[serde(rename="google_bigquery_table")]
+    BigQueryTable {
+        name: String,
+        dataset_ref: String,
+        table_id: String,
+        /// Written as YAML, rendered as the JSON string the provider expects.
+        #[serde(default)]
+        schema: Vec<BqField>,
+        #[serde(default)]
+        time_partitioning: Option<TimePartitioning>,
+        #[serde(default)]
+        clustering: Vec<String>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_cloud_run_v2_service")]
+    CloudRunService {
+        name: String,
+        location: String,
+        image: String,
+        #[serde(default)]
+        env: Vec<CloudRunEnv>,
+        #[serde(default)]
+        cpu: Option<String>,
+        #[serde(default)]
+        memory: Option<String>,
+        #[serde(default)]
+        min_instances: Option<u32>,
+        #[serde(default)]
+        max_instances: Option<u32>,
+        #[serde(default)]
+        ingress: Option<CloudRunIngress>,
+        #[serde(default)]
+        service_account: Option<String>,
+        /// Also grant `roles/run.invoker` to `allUsers`.
+        #[serde(default)]
+        allow_unauthenticated: bool,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_container_cluster")]
+    GkeCluster {
+        name: String,
+        location: String,
+        #[serde(default)]
+        network_ref: Option<String>,
+        #[serde(default)]
+        subnetwork_ref: Option<String>,
+        #[serde(default)]
+        release_channel: Option<ReleaseChannel>,
+        #[serde(default)]
+        workload_identity: bool,
+        #[serde(default)]
+        private_cluster: Option<PrivateCluster>,
+        /// Defaults to true when a `google_container_node_pool` in the stack targets this cluster.
+        #[serde(default)]
+        remove_default_node_pool: Option<bool>,
+        /// Rendered as `resource_labels`, which GKE also applies to the cluster's nodes and disks.
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_container_node_pool")]
+    GkeNodePool {
+        name: String,
+        cluster_ref: String,
+        machine_type: String,
+        #[serde(default)]
+        node_count: Option<u32>,
+        #[serde(default)]
+        autoscaling: Option<NodeAutoscaling>,
+        #[serde(default)]
+        disk_size_gb: Option<u32>,
+        #[serde(default)]
+        service_account_ref: Option<String>,
+        #[serde(default)]
+        oauth_scopes: Vec<String>,
+    },
+    #[serde(rename="google_sql_database_instance")]
+    CloudSqlInstance {
+        name: String,
+        database_version: String,
+        region: String,
+        tier: String,
+        #[serde(default)]
+        disk_size: Option<u32>,
+        #[serde(default)]
+        disk_autoresize: Option<bool>,
+        #[serde(default)]
+        availability_type: Option<AvailabilityType>,
+        #[serde(default)]
+        backup: Option<SqlBackup>,
+        #[serde(default)]
+        ip_configuration: SqlIpConfiguration,
+        #[serde(default)]
+        deletion_protection: Option<bool>,
+        #[serde(default)]
+        root_password_from: Option<ValueFrom>,
+    },
+    #[serde(rename="google_artifact_registry_repository")]
+    ArtifactRegistryRepository {
+        name: String,
+        repository_id: String,
+        location: String,
+        format: RepositoryFormat,
+        #[serde(default)]
+        kms_key_ref: Option<String>,
+        #[serde(default)]
+        cleanup_policies: Vec<CleanupPolicy>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_dns_managed_zone")]
+    DnsManagedZone {
+        name: String,
+        dns_name: String,
+        #[serde(default)]
+        visibility: DnsVisibility,
+        #[serde(default)]
+        private_visibility_network_refs: Vec<String>,
+        #[serde(default)]
+        dnssec: bool,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+    #[serde(rename="google_dns_record_set")]
+    DnsRecordSet {
+        name: String,
+        zone_ref: String,
+        record_name: String,
+        record_type: DnsType,
+        #[serde(default="default_ttl")]
+        ttl: u32,
+        rrdatas: Vec<String>,
+    },
+    #[serde(rename="google_storage_bucket_iam_member")]
+    StorageBucketIamMember {
+        name: String,
+        bucket_ref: String,
+        role: String,
+        member: String,
+    },
+    #[serde(rename="google_redis_instance")]
+    RedisInstance {
+        name: String,
+        #[serde(default)]
+        tier: RedisTier,
+        memory_size_gb: u32,
+        #[serde(default)]
+        region: Option<String>,
+        #[serde(default)]
+        authorized_network_ref: Option<String>,
+        #[serde(default)]
+        redis_version: Option<String>,
+        #[serde(default="default_true")]
+        auth_enabled: bool,
+        #[serde(default)]
+        transit_encryption_mode: TransitEncryptionMode,
+        #[serde(default)]
+        persistence: Option<RedisPersistence>,
+        /// STANDARD_HA only.
+        #[serde(default)]
+        replica_count: Option<u32>,
+        #[serde(default)]
+        read_replicas_enabled: bool,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum KmsKeyPurpose { EncryptDecrypt, AsymmetricSign, AsymmetricDecrypt, Mac }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum KmsProtectionLevel { Software, Hsm, External }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootDisk {
+    pub image: String,
+    #[serde(default)]
+    pub size: Option<u32>,
+    #[serde(default, rename="type")]
+    pub disk_type: Option<String>,
+    /// Sibling `google_kms_crypto_key` name, or a full key id.
+    #[serde(default)]
+    pub kms_key_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    #[serde(default)]
+    pub network_ref: Option<String>,
+    #[serde(default)]
+    pub subnetwork_ref: Option<String>,
+    /// Attach an ephemeral external IP (an empty `access_config` block).
+    #[serde(default)]
+    pub public_ip: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceServiceAccount {
+    pub email: String,
+    #[serde(default="default_scopes")]
+    pub scopes: Vec<String>,
+}
+
+fn default_scopes() -> Vec<String> { vec!["cloud-platform".to_string()] }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum RoutingMode { Regional, Global }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecondaryRange { pub range_name: String, pub ip_cidr_range: String }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum FirewallDirection { #[default] Ingress, Egress }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirewallRule {
+    pub protocol: String,
+    /// Ports or ranges like `"443"` / `"8000-8080"`; empty means all ports.
+    #[serde(default)]
+    pub ports: Vec<String>,
+}
+
+/// Expand a sibling service account name to its member string; anything else must
+/// already be a principal like `user:alice@example.com`.
+fn iam_member(ctx: &RenderCtx, owner: &str, member: &str) -> Result<String> {
+    if let Some(email) = ctx.sibling("google_service_account", member, "email") {
+        return Ok(format!("serviceAccount:{}", email));
+    }
+    if member.contains(':') || member == "allUsers" || member == "allAuthenticatedUsers" {
+        return Ok(member.to_string());
+    }
+    anyhow::bail!("{}: member '{}' is neither a principal (user:, group:, serviceAccount:, ...) nor a sibling service account", owner, member)
+}
+
+fn check_iam_role(owner: &str, role: &str) -> Result<()> {
+    if !role.starts_with("roles/") && !role.starts_with("projects/") {
+        anyhow::bail!("{}: role '{}' must start with 'roles/' or 'projects/'", owner, role);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub topic_ref: String,
+    #[serde(default)]
+    pub max_delivery_attempts: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BqField {
+    pub name: String,
+    #[serde(rename="type")]
+    pub field_type: String,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub description: Option<String>,
+    /// Children of a RECORD/STRUCT field.
+    #[serde(default, skip_serializing_if="Vec::is_empty")]
+    pub fields: Vec<BqField>,
+}
+
+const BQ_TYPES: &[&str] = &[
+    "STRING", "BYTES", "INTEGER", "INT64", "FLOAT", "FLOAT64", "NUMERIC", "BIGNUMERIC", "BOOLEAN", "BOOL",
+    "TIMESTAMP", "DATE", "TIME", "DATETIME", "GEOGRAPHY", "JSON", "INTERVAL", "RANGE", "RECORD", "STRUCT",
+];
+
+fn check_bq_fields(owner: &str, fields: &[BqField]) -> Result<()> {
+    let mut seen = BTreeSet::new();
+    for f in fields {
+        if !seen.insert(f.name.to_ascii_lowercase()) {
+            anyhow::bail!("{}: schema field '{}' is defined more than once", owner, f.name);
+        }
+        if !BQ_TYPES.contains(&f.field_type.as_str()) {
+            anyhow::bail!("{}: schema field '{}' has unknown type '{}'", owner, f.name, f.field_type);
+        }
+        if let Some(m) = &f.mode {
+            if !["NULLABLE", "REQUIRED", "REPEATED"].contains(&m.as_str()) {
+                anyhow::bail!("{}: schema field '{}' has unknown mode '{}'", owner, f.name, m);
+            }
+        }
+        let record = f.field_type == "RECORD" || f.field_type == "STRUCT";
+        if record == f.fields.is_empty() {
+            anyhow::bail!("{}: schema field '{}' must list fields if and only if it is a RECORD/STRUCT", owner, f.name);
+        }
+        check_bq_fields(owner, &f.fields)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimePartitioning {
+    #[serde(rename="type")]
+    pub partition_type: PartitionType,
+    /// Column to partition on; ingestion time when unset.
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub expiration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all="UPPERCASE")]
+pub enum PartitionType { Hour, Day, Month, Year }
+
+/// A container env var: a literal `value`, or a Secret Manager `secret_ref`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudRunEnv {
+    pub name: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Sibling `google_secret_manager_secret` name, or a secret id.
+    #[serde(default)]
+    pub secret_ref: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum CloudRunIngress {
+    #[serde(rename="INGRESS_TRAFFIC_ALL")] All,
+    #[serde(rename="INGRESS_TRAFFIC_INTERNAL_ONLY")] InternalOnly,
+    #[serde(rename="INGRESS_TRAFFIC_INTERNAL_LOAD_BALANCER")] InternalLoadBalancer,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum ReleaseChannel { Unspecified, Rapid, Regular, Stable }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateCluster {
+    #[serde(default="default_true")]
+    pub enable_private_nodes: bool,
+    /// The control plane's /28.
+    pub master_ipv4_cidr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeAutoscaling { pub min: u32, pub max: u32 }
+
+fn default_true() -> bool { true }
+
+/// `auto`, or `{ user_managed: [locations] }`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all="snake_case")]
+pub enum SecretReplication { #[default] Auto, UserManaged(Vec<String>) }
+
+fn default_some_true() -> Option<bool> { Some(true) }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsLifecycleRule {
+    pub action: GcsLifecycleAction,
+    #[serde(default)]
+    pub age: Option<u32>,
+    #[serde(default)]
+    pub num_newer_versions: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag="type")]
+pub enum GcsLifecycleAction { Delete, SetStorageClass { storage_class: String } }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsEncryption {
+    /// Sibling `google_kms_crypto_key` name, or a full key id.
+    pub default_kms_key_ref: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum AvailabilityType { Zonal, Regional }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlBackup {
+    #[serde(default="default_true")]
+    pub enabled: bool,
+    /// WAL archiving on Postgres, binary logging on MySQL.
+    #[serde(default)]
+    pub point_in_time_recovery: bool,
+    /// `HH:MM` in UTC.
+    #[serde(default)]
+    pub start_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SqlIpConfiguration {
+    #[serde(default)]
+    pub public_ip: bool,
+    #[serde(default)]
+    pub authorized_networks: Vec<String>,
+    /// Sibling `google_compute_network` name, or a network id.
+    #[serde(default)]
+    pub private_network_ref: Option<String>,
+}
+
+const SQL_VERSIONS: &[&str] = &[
+    "POSTGRES_9_6", "POSTGRES_10", "POSTGRES_11", "POSTGRES_12", "POSTGRES_13", "POSTGRES_14", "POSTGRES_15",
+    "POSTGRES_16", "POSTGRES_17", "MYSQL_5_6", "MYSQL_5_7", "MYSQL_8_0", "MYSQL_8_4",
+];
+
+/// Known engine identifiers, plus MySQL 8.0 minor pins such as `MYSQL_8_0_36`.
+fn check_sql_version(owner: &str, v: &str) -> Result<()> {
+    let minor = v.strip_prefix("MYSQL_8_0_").is_some_and(|m| !m.is_empty() && m.chars().all(|c| c.is_ascii_digit()));
+    if !SQL_VERSIONS.contains(&v) && !minor && !v.starts_with("SQLSERVER_") {
+        anyhow::bail!("{}: unknown database_version '{}' (expected e.g. POSTGRES_16 or MYSQL_8_0)", owner, v);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum RepositoryFormat { Docker, Maven, Npm, Python, Apt, Yum, Go, Kfp, Generic }
+
+/// One of `keep_most_recent: N` or `delete_older_than: "2592000s"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupPolicy {
+    /// Defaults to `keep-most-recent` / `delete-older-than`.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub keep_most_recent: Option<u32>,
+    #[serde(default)]
+    pub delete_older_than: Option<String>,
+    /// `TAGGED`, `UNTAGGED` or `ANY` (the default).
+    #[serde(default)]
+    pub tag_state: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum DnsVisibility { #[default] Public, Private }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DnsType { A, AAAA, CNAME, MX, TXT, NS, SRV, CAA, PTR }
+
+fn default_ttl() -> u32 { 300 }
+
+/// Cloud DNS wants fully-qualified names; the missing trailing dot is the usual apply failure.
+fn fqdn(s: &str) -> String {
+    if s.ends_with('.') { s.to_string() } else { format!("{}.", s) }
+}
+
+/// Normalize one rrdata for its record type: qualify hostnames, and quote TXT
+/// values, splitting them into 255-byte strings.
+fn dns_rrdata(owner: &str, t: DnsType, v: &str) -> Result<String> {
+    Ok(match t {
+        DnsType::A if v.parse::<std::net::Ipv4Addr>().is_err() => anyhow::bail!("{}: '{}' is not an IPv4 address", owner, v),
+        DnsType::AAAA if v.parse::<std::net::Ipv6Addr>().is_err() => anyhow::bail!("{}: '{}' is not an IPv6 address", owner, v),
+        DnsType::CNAME | DnsType::NS | DnsType::PTR => fqdn(v),
+        DnsType::MX => match v.split_once(' ') {
+            Some((prio, host)) if prio.parse::<u16>().is_ok() => format!("{} {}", prio, fqdn(host.trim())),
+            _ => anyhow::bail!("{}: MX record '{}' must be '<priority> <host>'", owner, v),
+        },
+        DnsType::TXT if v.starts_with('"') => v.to_string(),
+        DnsType::TXT => {
+            // Split on char boundaries first so neither UTF-8 nor an escape is cut in half.
+            let mut chunks = vec![String::new()];
+            for c in v.chars() {
+                if chunks.last().unwrap().len() + c.len_utf8() > 255 { chunks.push(String::new()); }
+                chunks.last_mut().unwrap().push(c);
+            }
+            chunks.iter().map(|c| format!("\"{}\"", c.replace('\\', "\\\\").replace('"', "\\\""))).collect::<Vec<_>>().join(" ")
+        }
+        _ => v.to_string(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum RedisTier { #[default] Basic, StandardHa }
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all="SCREAMING_SNAKE_CASE")]
+pub enum TransitEncryptionMode { #[default] ServerAuthentication, Disabled }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisPersistence {
+    /// `ONE_HOUR`, `SIX_HOURS`, `TWELVE_HOURS` or `TWENTY_FOUR_HOURS`.
+    pub rdb_snapshot_period: String,
+}
+
+/// GCP durations are whole seconds with an `s` suffix, e.g. `7776000s`.
+fn duration_secs(owner: &str, field: &str, s: &str) -> Result<u64> {
+    s.strip_suffix('s').and_then(|n| n.parse().ok()).ok_or_else(|| anyhow::anyhow!(
+        "{}: {} '{}' must be a number of seconds ending in 's', e.g. '86400s'", owner, field, s))
 }
 
 impl GcpResource {
-    pub fn to_tf_json(&self) -> Json {
+    /// Terraform type and logical name this resource is declared under.
+    pub fn type_and_name(&self) -> (&'static str, &str) {
         match self {
-            GcpResource::StorageBucket { name, location, force_destroy } => {
-                let mut body = json!({ "name": name, "location": location });
+            GcpResource::StorageBucket { name, .. } => ("google_storage_bucket", name),
+            GcpResource::KmsKeyRing { name, .. } => ("google_kms_key_ring", name),
+            GcpResource::SecretManagerSecret { name, .. } => ("google_secret_manager_secret", name),
+            GcpResource::SecretManagerSecretVersion { name, .. } => ("google_secret_manager_secret_version", name),
+            GcpResource::KmsCryptoKey { name, .. } => ("google_kms_crypto_key", name),
+            GcpResource::ComputeInstance { name, .. } => ("google_compute_instance", name),
+            GcpResource::ComputeNetwork { name, .. } => ("google_compute_network", name),
+            GcpResource::ComputeSubnetwork { name, .. } => ("google_compute_subnetwork", name),
+            GcpResource::ComputeFirewall { name, .. } => ("google_compute_firewall", name),
+            GcpResource::ServiceAccount { name, .. } => ("google_service_account", name),
+            GcpResource::ProjectIamMember { name, .. } => ("google_project_iam_member", name),
+            GcpResource::ServiceAccountIamMember { name, .. } => ("google_service_account_iam_member", name),
+            GcpResource::PubSubTopic { name, .. } => ("google_pubsub_topic", name),
+            GcpResource::PubSubSubscription { name, .. } => ("google_pubsub_subscription", name),
+            GcpResource::BigQueryDataset { name, .. } => ("google_bigquery_dataset", name),
+            GcpResource::BigQueryTable { name, .. } => ("google_bigquery_table", name),
+            GcpResource::CloudRunService { name, .. } => ("google_cloud_run_v2_service", name),
+            GcpResource::GkeCluster { name, .. } => ("google_container_cluster", name),
+            GcpResource::GkeNodePool { name, .. } => ("google_container_node_pool", name),
+            GcpResource::CloudSqlInstance { name, .. } => ("google_sql_database_instance", name),
+            GcpResource::ArtifactRegistryRepository { name, .. } => ("google_artifact_registry_repository", name),
+            GcpResource::DnsManagedZone { name, .. } => ("google_dns_managed_zone", name),
+            GcpResource::DnsRecordSet { name, .. } => ("google_dns_record_set", name),
+            GcpResource::StorageBucketIamMember { name, .. } => ("google_storage_bucket_iam_member", name),
+            GcpResource::RedisInstance { name, .. } => ("google_redis_instance", name),
+        }
+    }
+
+    /// The resource this one is attached to, for parents that render differently
+    /// once they have children (see [`RenderCtx::has_children`]).
+    pub fn parent_ref(&self) -> Option<(&'static str, &str)> {
+        match self {
+            GcpResource::GkeNodePool { cluster_ref, .. } => Some(("google_container_cluster", cluster_ref)),
+            _ => None,
+        }
+    }
+
+    pub fn to_tf_json(&self, ctx: &mut RenderCtx) -> Result<Json> {
+        Ok(match self {
+            GcpResource::StorageBucket { name, location, force_destroy, versioning, uniform_bucket_level_access, lifecycle_rules, encryption, public_access_prevention, labels } => {
+                let owner = format!("google_storage_bucket.{}", name);
+                let prevention = public_access_prevention.as_deref().unwrap_or("enforced");
+                if prevention != "enforced" && prevention != "inherited" {
+                    anyhow::bail!("{}: public_access_prevention '{}' must be 'enforced' or 'inherited'", owner, prevention);
+                }
+                let mut body = json!({ "name": name, "location": location, "public_access_prevention": prevention });
                 if let Some(f) = force_destroy { body["force_destroy"] = json!(f); }
+                if let Some(u) = uniform_bucket_level_access { body["uniform_bucket_level_access"] = json!(u); }
+                if let Some(v) = versioning { body["versioning"] = json!([{ "enabled": v }]); }
+                if !lifecycle_rules.is_empty() {
+                    let mut rules = Vec::new();
+                    for r in lifecycle_rules {
+                        let mut condition = json!({});
+                        if let Some(a) = r.age { condition["age"] = json!(a); }
+                        if let Some(n) = r.num_newer_versions { condition["num_newer_versions"] = json!(n); }
+                        if condition.as_object().unwrap().is_empty() {
+                            anyhow::bail!("{}: lifecycle rule needs age or num_newer_versions", owner);
+                        }
+                        let action = match &r.action {
+                            GcsLifecycleAction::Delete => json!({ "type": "Delete" }),
+                            GcsLifecycleAction::SetStorageClass { storage_class } => json!({ "type": "SetStorageClass", "storage_class": storage_class }),
+                        };
+                        rules.push(json!({ "action": [action], "condition": [condition] }));
+                    }
+                    body["lifecycle_rule"] = json!(rules);
+                }
+                if let Some(e) = encryption {
+                    body["encryption"] = json!([{ "default_kms_key_name": ctx.resolve("google_kms_crypto_key", &e.default_kms_key_ref, "id") }]);
+                }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
                 json!({ "resource": { "google_storage_bucket": { name: body } } })
             }
             GcpResource::KmsKeyRing { name, location } => json!({
                 "resource": { "google_kms_key_ring": { name: { "name": name, "location": location } } }
             }),
-            GcpResource::SecretManagerSecret { name, replication } => json!({
-                "resource": { "google_secret_manager_secret": { name: { "name": name, "replication": replication } } }
-            }),
-        }
+            GcpResource::SecretManagerSecret { name, replication, kms_key_ref, labels } => {
+                let cmek = |mut block: Json| {
+                    if let Some(k) = kms_key_ref {
+                        block["customer_managed_encryption"] = json!([{ "kms_key_name": ctx.resolve("google_kms_crypto_key", k, "id") }]);
+                    }
+                    block
+                };
+                let replication = match replication {
+                    SecretReplication::Auto => json!({ "auto": [cmek(json!({}))] }),
+                    SecretReplication::UserManaged(locations) => {
+                        if locations.is_empty() {
+                            anyhow::bail!("google_secret_manager_secret.{}: user_managed replication needs at least one location", name);
+                        }
+                        json!({ "user_managed": [{ "replicas": locations.iter().map(|l| cmek(json!({ "location": l }))).collect::<Vec<_>>() }] })
+                    }
+                };
+                let mut body = json!({ "secret_id": name, "replication": [replication] });
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_secret_manager_secret": { name: body } } })
+            }
+            GcpResource::SecretManagerSecretVersion { name, secret_ref, value_from } => {
+                let data = ctx.secret_var(&secret_var_name("google_secret_manager_secret_version", name), value_from);
+                json!({ "resource": { "google_secret_manager_secret_version": { name: {
+                    "secret": ctx.resolve("google_secret_manager_secret", secret_ref, "id"),
+                    "secret_data": data,
+                } } } })
+            }
+            GcpResource::KmsCryptoKey { name, key_ring_ref, rotation_period, purpose, algorithm, protection_level, destroy_scheduled_duration, labels } => {
+                let owner = format!("google_kms_crypto_key.{}", name);
+                let purpose = purpose.unwrap_or(KmsKeyPurpose::EncryptDecrypt);
+                let mut body = json!({
+                    "name": name,
+                    "key_ring": ctx.resolve("google_kms_key_ring", key_ring_ref, "id"),
+                    "purpose": purpose,
+                });
+                if let Some(p) = rotation_period {
+                    // Cloud KMS only rotates symmetric keys automatically.
+                    if purpose != KmsKeyPurpose::EncryptDecrypt {
+                        anyhow::bail!("{}: rotation_period is only supported for ENCRYPT_DECRYPT keys", owner);
+                    }
+                    if duration_secs(&owner, "rotation_period", p)? < 86400 {
+                        anyhow::bail!("{}: rotation_period '{}' must be at least 86400s", owner, p);
+                    }
+                    body["rotation_period"] = json!(p);
+                }
+                if let Some(d) = destroy_scheduled_duration {
+                    duration_secs(&owner, "destroy_scheduled_duration", d)?;
+                    body["destroy_scheduled_duration"] = json!(d);
+                }
+                let algorithm = match (algorithm, purpose) {
+                    (Some(a), _) => Some(a.as_str()),
+                    (None, KmsKeyPurpose::EncryptDecrypt) => protection_level.map(|_| "GOOGLE_SYMMETRIC_ENCRYPTION"),
+                    (None, _) => anyhow::bail!("{}: algorithm is required unless purpose is ENCRYPT_DECRYPT", owner),
+                };
+                if let Some(a) = algorithm {
+                    let mut tpl = json!({ "algorithm": a });
+                    if let Some(l) = protection_level { tpl["protection_level"] = json!(l); }
+                    body["version_template"] = tpl;
+                }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_kms_crypto_key": { name: body } } })
+            }
+            GcpResource::ComputeInstance { name, machine_type, zone, boot_disk, network_interface, service_account, metadata, labels } => {
+                let owner = format!("google_compute_instance.{}", name);
+                let mut params = json!({ "image": boot_disk.image });
+                if let Some(s) = boot_disk.size { params["size"] = json!(s); }
+                if let Some(t) = &boot_disk.disk_type { params["type"] = json!(t); }
+                // The key sits on boot_disk itself, not in initialize_params.
+                let mut disk = json!({ "initialize_params": params });
+                if let Some(k) = &boot_disk.kms_key_ref { disk["kms_key_self_link"] = json!(ctx.resolve("google_kms_crypto_key", k, "id")); }
+                let nic = network_interface;
+                if nic.network_ref.is_none() && nic.subnetwork_ref.is_none() {
+                    anyhow::bail!("{}: network_interface needs network_ref or subnetwork_ref", owner);
+                }
+                let mut iface = json!({});
+                if let Some(n) = &nic.network_ref { iface["network"] = json!(ctx.resolve("google_compute_network", n, "self_link")); }
+                if let Some(s) = &nic.subnetwork_ref { iface["subnetwork"] = json!(ctx.resolve("google_compute_subnetwork", s, "self_link")); }
+                if nic.public_ip { iface["access_config"] = json!([{}]); }
+                let mut body = json!({
+                    "name": name, "machine_type": machine_type, "zone": zone,
+                    "boot_disk": [disk], "network_interface": [iface],
+                });
+                if let Some(sa) = service_account {
+                    body["service_account"] = json!([{ "email": ctx.resolve("google_service_account", &sa.email, "email"), "scopes": sa.scopes }]);
+                }
+                if !metadata.is_empty() { body["metadata"] = json!(metadata); }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_compute_instance": { name: body } } })
+            }
+            GcpResource::ComputeNetwork { name, auto_create_subnetworks, routing_mode, mtu } => {
+                let mut body = json!({ "name": name });
+                if let Some(a) = auto_create_subnetworks { body["auto_create_subnetworks"] = json!(a); }
+                if let Some(m) = routing_mode { body["routing_mode"] = json!(m); }
+                if let Some(m) = mtu {
+                    if !(1300..=8896).contains(m) {
+                        anyhow::bail!("google_compute_network.{}: mtu {} must be between 1300 and 8896", name, m);
+                    }
+                    body["mtu"] = json!(m);
+                }
+                json!({ "resource": { "google_compute_network": { name: body } } })
+            }
+            GcpResource::ComputeSubnetwork { name, network_ref, ip_cidr_range, region, private_ip_google_access, secondary_ranges } => {
+                let owner = format!("google_compute_subnetwork.{}", name);
+                check_cidr(&owner, ip_cidr_range)?;
+                let mut seen = BTreeSet::new();
+                for r in secondary_ranges {
+                    check_cidr(&owner, &r.ip_cidr_range)?;
+                    if !seen.insert(&r.range_name) {
+                        anyhow::bail!("{}: secondary range name '{}' is used more than once", owner, r.range_name);
+                    }
+                }
+                let mut body = json!({
+                    "name": name, "ip_cidr_range": ip_cidr_range,
+                    "network": ctx.resolve("google_compute_network", network_ref, "id"),
+                });
+                if let Some(r) = region { body["region"] = json!(r); }
+                if let Some(p) = private_ip_google_access { body["private_ip_google_access"] = json!(p); }
+                if !secondary_ranges.is_empty() {
+                    body["secondary_ip_range"] = json!(secondary_ranges.iter()
+                        .map(|r| json!({ "range_name": r.range_name, "ip_cidr_range": r.ip_cidr_range }))
+                        .collect::<Vec<_>>());
+                }
+                json!({ "resource": { "google_compute_subnetwork": { name: body } } })
+            }
+            GcpResource::ComputeFirewall { name, network_ref, direction, priority, source_ranges, destination_ranges, target_tags, allow, deny } => {
+                let owner = format!("google_compute_firewall.{}", name);
+                match direction {
+                    FirewallDirection::Ingress if !destination_ranges.is_empty() =>
+                        anyhow::bail!("{}: INGRESS rules cannot set destination_ranges", owner),
+                    FirewallDirection::Egress if !source_ranges.is_empty() =>
+                        anyhow::bail!("{}: EGRESS rules cannot set source_ranges", owner),
+                    _ => {}
+                }
+                match (allow.is_empty(), deny.is_empty()) {
+                    (true, true) => anyhow::bail!("{}: set at least one allow or deny entry", owner),
+                    (false, false) => anyhow::bail!("{}: allow and deny cannot be combined in one rule", owner),
+                    _ => {}
+                }
+                if let Some(p) = priority {
+                    if *p > 65535 { anyhow::bail!("{}: priority {} must be between 0 and 65535", owner, p); }
+                }
+                for r in source_ranges.iter().chain(destination_ranges) { check_cidr(&owner, r)?; }
+                let rules = |rs: &[FirewallRule]| rs.iter().map(|r| {
+                    let mut b = json!({ "protocol": r.protocol });
+                    if !r.ports.is_empty() { b["ports"] = json!(r.ports); }
+                    b
+                }).collect::<Vec<_>>();
+                let mut body = json!({
+                    "name": name, "direction": direction,
+                    "network": ctx.resolve("google_compute_network", network_ref, "id"),
+                });
+                if let Some(p) = priority { body["priority"] = json!(p); }
+                if !source_ranges.is_empty() { body["source_ranges"] = json!(source_ranges); }
+                if !destination_ranges.is_empty() { body["destination_ranges"] = json!(destination_ranges); }
+                if !target_tags.is_empty() { body["target_tags"] = json!(target_tags); }
+                if !allow.is_empty() { body["allow"] = json!(rules(allow)); }
+                if !deny.is_empty() { body["deny"] = json!(rules(deny)); }
+                json!({ "resource": { "google_compute_firewall": { name: body } } })
+            }
+            GcpResource::ServiceAccount { name, account_id, display_name, description } => {
+                // 6-30 chars, lowercase letters, digits and hyphens, starting with a letter.
+                let valid = (6..=30).contains(&account_id.len())
+                    && account_id.starts_with(|c: char| c.is_ascii_lowercase())
+                    && !account_id.ends_with('-')
+                    && account_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+                if !valid {
+                    anyhow::bail!("google_service_account.{}: account_id '{}' must be 6-30 lowercase letters, digits or hyphens, starting with a letter", name, account_id);
+                }
+                let mut body = json!({ "account_id": account_id });
+                if let Some(d) = display_name { body["display_name"] = json!(d); }
+                if let Some(d) = description { body["description"] = json!(d); }
+                json!({ "resource": { "google_service_account": { name: body } } })
+            }
+            GcpResource::ProjectIamMember { name, role, member, project } => {
+                let owner = format!("google_project_iam_member.{}", name);
+                check_iam_role(&owner, role)?;
+                let mut o = json!({});
+                let project = match project {
+                    Some(p) => p.clone(),
+                    None => {
+                        o["data"]["google_client_config"]["current"] = json!({});
+                        "${data.google_client_config.current.project}".to_string()
+                    }
+                };
+                o["resource"]["google_project_iam_member"][name] = json!({
+                    "project": project, "role": role, "member": iam_member(ctx, &owner, member)?,
+                });
+                o
+            }
+            GcpResource::ServiceAccountIamMember { name, service_account_ref, role, member } => {
+                let owner = format!("google_service_account_iam_member.{}", name);
+                check_iam_role(&owner, role)?;
+                json!({ "resource": { "google_service_account_iam_member": { name: {
+                    "service_account_id": ctx.resolve("google_service_account", service_account_ref, "name"),
+                    "role": role, "member": iam_member(ctx, &owner, member)?,
+                } } } })
+            }
+            GcpResource::PubSubTopic { name, kms_key_ref, message_retention_duration, labels } => {
+                let mut body = json!({ "name": name });
+                if let Some(k) = kms_key_ref { body["kms_key_name"] = json!(ctx.resolve("google_kms_crypto_key", k, "id")); }
+                if let Some(d) = message_retention_duration {
+                    let owner = format!("google_pubsub_topic.{}", name);
+                    if !(600..=2678400).contains(&duration_secs(&owner, "message_retention_duration", d)?) {
+                        anyhow::bail!("{}: message_retention_duration '{}' must be between 600s and 2678400s (31 days)", owner, d);
+                    }
+                    body["message_retention_duration"] = json!(d);
+                }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_pubsub_topic": { name: body } } })
+            }
+            GcpResource::PubSubSubscription { name, topic_ref, ack_deadline_seconds, push_endpoint, dead_letter, retain_acked_messages, labels } => {
+                let owner = format!("google_pubsub_subscription.{}", name);
+                let mut body = json!({ "name": name, "topic": ctx.resolve("google_pubsub_topic", topic_ref, "id") });
+                if let Some(a) = ack_deadline_seconds {
+                    if !(10..=600).contains(a) {
+                        anyhow::bail!("{}: ack_deadline_seconds {} must be between 10 and 600", owner, a);
+                    }
+                    body["ack_deadline_seconds"] = json!(a);
+                }
+                if let Some(e) = push_endpoint { body["push_config"] = json!([{ "push_endpoint": e }]); }
+                if let Some(dl) = dead_letter {
+                    let mut policy = json!({ "dead_letter_topic": ctx.resolve("google_pubsub_topic", &dl.topic_ref, "id") });
+                    if let Some(m) = dl.max_delivery_attempts {
+                        if !(5..=100).contains(&m) {
+                            anyhow::bail!("{}: max_delivery_attempts {} must be between 5 and 100", owner, m);
+                        }
+                        policy["max_delivery_attempts"] = json!(m);
+                    }
+                    body["dead_letter_policy"] = json!([policy]);
+                }
+                if let Some(r) = retain_acked_messages { body["retain_acked_messages"] = json!(r); }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_pubsub_subscription": { name: body } } })
+            }
+            GcpResource::BigQueryDataset { name, dataset_id, location, default_table_expiration_ms, default_kms_key_ref, labels, delete_contents_on_destroy } => {
+                let mut body = json!({ "dataset_id": dataset_id });
+                if let Some(l) = location { body["location"] = json!(l); }
+                if let Some(e) = default_table_expiration_ms {
+                    // BigQuery rejects anything shorter than an hour.
+                    if *e < 3_600_000 {
+                        anyhow::bail!("google_bigquery_dataset.{}: default_table_expiration_ms must be at least 3600000", name);
+                    }
+                    body["default_table_expiration_ms"] = json!(e);
+                }
+                if let Some(k) = default_kms_key_ref {
+                    body["default_encryption_configuration"] = json!([{ "kms_key_name": ctx.resolve("google_kms_crypto_key", k, "id") }]);
+                }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                if let Some(d) = delete_contents_on_destroy { body["delete_contents_on_destroy"] = json!(d); }
+                json!({ "resource": { "google_bigquery_dataset": { name: body } } })
+            }
+            GcpResource::BigQueryTable { name, dataset_ref, table_id, schema, time_partitioning, clustering, labels } => {
+                let owner = format!("google_bigquery_table.{}", name);
+                check_bq_fields(&owner, schema)?;
+                let top_level = |col: &str| schema.iter().any(|f| f.name == col);
+                let mut body = json!({
+                    "dataset_id": ctx.resolve("google_bigquery_dataset", dataset_ref, "dataset_id"),
+                    "table_id": table_id,
+                });
+                if !schema.is_empty() { body["schema"] = json!(serde_json::to_string(schema)?); }
+                if let Some(tp) = time_partitioning {
+                    let mut p = json!({ "type": tp.partition_type });
+                    if let Some(f) = &tp.field {
+                        if !schema.is_empty() && !top_level(f) {
+                            anyhow::bail!("{}: time_partitioning field '{}' is not in the schema", owner, f);
+                        }
+                        p["field"] = json!(f);
+                    }
+                    if let Some(e) = tp.expiration_ms { p["expiration_ms"] = json!(e); }
+                    body["time_partitioning"] = json!([p]);
+                }
+                if !clustering.is_empty() {
+                    if clustering.len() > 4 {
+                        anyhow::bail!("{}: clustering allows at most 4 columns", owner);
+                    }
+                    if let Some(c) = clustering.iter().find(|c| !schema.is_empty() && !top_level(c)) {
+                        anyhow::bail!("{}: clustering column '{}' is not in the schema", owner, c);
+                    }
+                    body["clustering"] = json!(clustering);
+                }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_bigquery_table": { name: body } } })
+            }
+            GcpResource::CloudRunService { name, location, image, env, cpu, memory, min_instances, max_instances, ingress, service_account, allow_unauthenticated, labels } => {
+                let owner = format!("google_cloud_run_v2_service.{}", name);
+                let mut vars = Vec::new();
+                for e in env {
+                    vars.push(match (&e.value, &e.secret_ref) {
+                        (Some(v), None) => json!({ "name": e.name, "value": v }),
+                        (None, Some(s)) => json!({ "name": e.name, "value_source": [{ "secret_key_ref": [{
+                            "secret": ctx.resolve("google_secret_manager_secret", s, "secret_id"),
+                            "version": e.version.as_deref().unwrap_or("latest"),
+                        }] }] }),
+                        _ => anyhow::bail!("{}: env '{}' needs exactly one of value or secret_ref", owner, e.name),
+                    });
+                }
+                let mut container = json!({ "image": image });
+                if !vars.is_empty() { container["env"] = json!(vars); }
+                let mut limits = json!({});
+                if let Some(c) = cpu { limits["cpu"] = json!(c); }
+                if let Some(m) = memory { limits["memory"] = json!(m); }
+                if !limits.as_object().unwrap().is_empty() { container["resources"] = json!([{ "limits": limits }]); }
+                let mut template = json!({ "containers": [container] });
+                if let (Some(lo), Some(hi)) = (min_instances, max_instances) {
+                    if lo > hi { anyhow::bail!("{}: min_instances {} exceeds max_instances {}", owner, lo, hi); }
+                }
+                let mut scaling = json!({});
+                if let Some(n) = min_instances { scaling["min_instance_count"] = json!(n); }
+                if let Some(n) = max_instances { scaling["max_instance_count"] = json!(n); }
+                if !scaling.as_object().unwrap().is_empty() { template["scaling"] = json!([scaling]); }
+                if let Some(sa) = service_account { template["service_account"] = json!(ctx.resolve("google_service_account", sa, "email")); }
+                let mut body = json!({ "name": name, "location": location, "template": [template] });
+                if let Some(i) = ingress { body["ingress"] = json!(i); }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                let mut o = json!({ "resource": { "google_cloud_run_v2_service": { name: body } } });
+                if *allow_unauthenticated {
+                    o["resource"]["google_cloud_run_v2_service_iam_member"][name] = json!({
+                        "name": format!("${{google_cloud_run_v2_service.{}.name}}", name),
+                        "location": format!("${{google_cloud_run_v2_service.{}.location}}", name),
+                        "role": "roles/run.invoker", "member": "allUsers",
+                    });
+                }
+                o
+            }
+            GcpResource::GkeCluster { name, location, network_ref, subnetwork_ref, release_channel, workload_identity, private_cluster, remove_default_node_pool, labels } => {
+                let owner = format!("google_container_cluster.{}", name);
+                let mut body = json!({ "name": name, "location": location });
+                // The default pool can't be omitted, only created small and dropped after the cluster is up.
+                if remove_default_node_pool.unwrap_or_else(|| ctx.has_children("google_container_cluster", name)) {
+                    body["remove_default_node_pool"] = json!(true);
+                    body["initial_node_count"] = json!(1);
+                }
+                if let Some(n) = network_ref { body["network"] = json!(ctx.resolve("google_compute_network", n, "id")); }
+                if let Some(s) = subnetwork_ref { body["subnetwork"] = json!(ctx.resolve("google_compute_subnetwork", s, "id")); }
+                if let Some(c) = release_channel { body["release_channel"] = json!([{ "channel": c }]); }
+                let mut o = json!({});
+                if *workload_identity {
+                    o["data"]["google_client_config"]["current"] = json!({});
+                    body["workload_identity_config"] = json!([{ "workload_pool": "${data.google_client_config.current.project}.svc.id.goog" }]);
+                }
+                if let Some(p) = private_cluster {
+                    check_cidr(&owner, &p.master_ipv4_cidr)?;
+                    if !p.master_ipv4_cidr.ends_with("/28") {
+                        anyhow::bail!("{}: master_ipv4_cidr '{}' must be a /28", owner, p.master_ipv4_cidr);
+                    }
+                    body["private_cluster_config"] = json!([{
+                        "enable_private_nodes": p.enable_private_nodes,
+                        "master_ipv4_cidr_block": p.master_ipv4_cidr,
+                    }]);
+                }
+                if !labels.is_empty() { body["resource_labels"] = json!(labels); }
+                o["resource"]["google_container_cluster"][name] = body;
+                o
+            }
+            GcpResource::GkeNodePool { name, cluster_ref, machine_type, node_count, autoscaling, disk_size_gb, service_account_ref, oauth_scopes } => {
+                let owner = format!("google_container_node_pool.{}", name);
+                let mut body = json!({ "name": name, "cluster": ctx.resolve("google_container_cluster", cluster_ref, "id") });
+                match (node_count, autoscaling) {
+                    (Some(_), Some(_)) => anyhow::bail!("{}: set node_count or autoscaling, not both", owner),
+                    (Some(n), None) => body["node_count"] = json!(n),
+                    (None, Some(a)) => {
+                        if a.min > a.max { anyhow::bail!("{}: autoscaling min {} exceeds max {}", owner, a.min, a.max); }
+                        body["autoscaling"] = json!([{ "min_node_count": a.min, "max_node_count": a.max }]);
+                    }
+                    (None, None) => {}
+                }
+                let mut node = json!({ "machine_type": machine_type });
+                if let Some(d) = disk_size_gb { node["disk_size_gb"] = json!(d); }
+                if let Some(sa) = service_account_ref { node["service_account"] = json!(ctx.resolve("google_service_account", sa, "email")); }
+                if !oauth_scopes.is_empty() { node["oauth_scopes"] = json!(oauth_scopes); }
+                body["node_config"] = json!([node]);
+                json!({ "resource": { "google_container_node_pool": { name: body } } })
+            }
+            GcpResource::CloudSqlInstance { name, database_version, region, tier, disk_size, disk_autoresize, availability_type, backup, ip_configuration, deletion_protection, root_password_from } => {
+                let owner = format!("google_sql_database_instance.{}", name);
+                check_sql_version(&owner, database_version)?;
+                let mut settings = json!({ "tier": tier });
+                if let Some(d) = disk_size { settings["disk_size"] = json!(d); }
+                if let Some(a) = disk_autoresize { settings["disk_autoresize"] = json!(a); }
+                if let Some(a) = availability_type { settings["availability_type"] = json!(a); }
+                if let Some(b) = backup {
+                    let mut cfg = json!({ "enabled": b.enabled });
+                    if b.point_in_time_recovery {
+                        let flag = if database_version.starts_with("POSTGRES_") { "point_in_time_recovery_enabled" }
+                            else if database_version.starts_with("MYSQL_") { "binary_log_enabled" }
+                            else { anyhow::bail!("{}: point_in_time_recovery is only supported for Postgres and MySQL", owner) };
+                        cfg[flag] = json!(true);
+                    }
+                    if let Some(t) = &b.start_time {
+                        let valid = t.split_once(':').is_some_and(|(h, m)| {
+                            h.len() == 2 && m.len() == 2 && h.parse::<u8>().is_ok_and(|h| h < 24) && m.parse::<u8>().is_ok_and(|m| m < 60)
+                        });
+                        if !valid { anyhow::bail!("{}: backup start_time '{}' must be HH:MM", owner, t); }
+                        cfg["start_time"] = json!(t);
+                    }
+                    settings["backup_configuration"] = json!([cfg]);
+                }
+                let ip = ip_configuration;
+                // The provider turns public IP on when ip_configuration is missing, so always render it.
+                if !ip.public_ip && ip.private_network_ref.is_none() {
+                    anyhow::bail!("{}: enable public_ip or set private_network_ref, otherwise the instance is unreachable", owner);
+                }
+                if !ip.public_ip && !ip.authorized_networks.is_empty() {
+                    anyhow::bail!("{}: authorized_networks require public_ip", owner);
+                }
+                let mut ipc = json!({ "ipv4_enabled": ip.public_ip });
+                if let Some(n) = &ip.private_network_ref { ipc["private_network"] = json!(ctx.resolve("google_compute_network", n, "id")); }
+                if !ip.authorized_networks.is_empty() {
+                    for n in &ip.authorized_networks { check_cidr(&owner, n)?; }
+                    ipc["authorized_networks"] = json!(ip.authorized_networks.iter().map(|n| json!({ "value": n })).collect::<Vec<_>>());
+                }
+                settings["ip_configuration"] = json!([ipc]);
+                let mut body = json!({ "name": name, "database_version": database_version, "region": region, "settings": [settings] });
+                if let Some(d) = deletion_protection { body["deletion_protection"] = json!(d); }
+                if let Some(from) = root_password_from {
+                    body["root_password"] = json!(ctx.secret_var(&secret_var_name("google_sql_database_instance", name), from));
+                }
+                json!({ "resource": { "google_sql_database_instance": { name: body } } })
+            }
+            GcpResource::ArtifactRegistryRepository { name, repository_id, location, format, kms_key_ref, cleanup_policies, labels } => {
+                let owner = format!("google_artifact_registry_repository.{}", name);
+                let mut body = json!({ "repository_id": repository_id, "location": location, "format": format });
+                if let Some(k) = kms_key_ref { body["kms_key_name"] = json!(ctx.resolve("google_kms_crypto_key", k, "id")); }
+                let mut ids = BTreeSet::new();
+                let mut policies = Vec::new();
+                for p in cleanup_policies {
+                    let (default_id, mut policy) = match (p.keep_most_recent, &p.delete_older_than) {
+                        (Some(n), None) => ("keep-most-recent", json!({ "action": "KEEP", "most_recent_versions": [{ "keep_count": n }] })),
+                        (None, Some(d)) => {
+                            duration_secs(&owner, "delete_older_than", d)?;
+                            ("delete-older-than", json!({ "action": "DELETE", "condition": [{ "older_than": d }] }))
+                        }
+                        _ => anyhow::bail!("{}: each cleanup policy needs exactly one of keep_most_recent or delete_older_than", owner),
+                    };
+                    if let Some(t) = &p.tag_state {
+                        if !["TAGGED", "UNTAGGED", "ANY"].contains(&t.as_str()) {
+                            anyhow::bail!("{}: tag_state '{}' must be TAGGED, UNTAGGED or ANY", owner, t);
+                        }
+                        if p.keep_most_recent.is_some() {
+                            anyhow::bail!("{}: tag_state only applies to delete_older_than policies", owner);
+                        }
+                        policy["condition"][0]["tag_state"] = json!(t);
+                    }
+                    let id = p.id.as_deref().unwrap_or(default_id);
+                    if !ids.insert(id) {
+                        anyhow::bail!("{}: cleanup policy id '{}' is used more than once; set id explicitly", owner, id);
+                    }
+                    policy["id"] = json!(id);
+                    policies.push(policy);
+                }
+                if !policies.is_empty() { body["cleanup_policies"] = json!(policies); }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_artifact_registry_repository": { name: body } } })
+            }
+            GcpResource::DnsManagedZone { name, dns_name, visibility, private_visibility_network_refs, dnssec, labels } => {
+                let owner = format!("google_dns_managed_zone.{}", name);
+                let mut body = json!({ "name": name, "dns_name": fqdn(dns_name), "visibility": visibility });
+                match visibility {
+                    DnsVisibility::Private => {
+                        if private_visibility_network_refs.is_empty() {
+                            anyhow::bail!("{}: private zones need at least one private_visibility_network_refs entry", owner);
+                        }
+                        if *dnssec { anyhow::bail!("{}: dnssec is only supported on public zones", owner); }
+                        let networks: Vec<_> = private_visibility_network_refs.iter()
+                            .map(|n| json!({ "network_url": ctx.resolve("google_compute_network", n, "id") })).collect();
+                        body["private_visibility_config"] = json!([{ "networks": networks }]);
+                    }
+                    DnsVisibility::Public if !private_visibility_network_refs.is_empty() =>
+                        anyhow::bail!("{}: private_visibility_network_refs requires visibility: private", owner),
+                    DnsVisibility::Public => {}
+                }
+                if *dnssec { body["dnssec_config"] = json!([{ "state": "on" }]); }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_dns_managed_zone": { name: body } } })
+            }
+            GcpResource::DnsRecordSet { name, zone_ref, record_name, record_type, ttl, rrdatas } => {
+                let owner = format!("google_dns_record_set.{}", name);
+                if rrdatas.is_empty() { anyhow::bail!("{}: rrdatas must not be empty", owner); }
+                if *record_type == DnsType::CNAME && rrdatas.len() > 1 {
+                    anyhow::bail!("{}: a CNAME record takes exactly one rrdata", owner);
+                }
+                let rrdatas = rrdatas.iter().map(|v| dns_rrdata(&owner, *record_type, v)).collect::<Result<Vec<_>>>()?;
+                json!({ "resource": { "google_dns_record_set": { name: {
+                    "name": fqdn(record_name), "type": record_type, "ttl": ttl, "rrdatas": rrdatas,
+                    "managed_zone": ctx.resolve("google_dns_managed_zone", zone_ref, "name"),
+                } } } })
+            }
+            GcpResource::StorageBucketIamMember { name, bucket_ref, role, member } => {
+                let owner = format!("google_storage_bucket_iam_member.{}", name);
+                if !role.starts_with("roles/storage.") {
+                    anyhow::bail!("{}: role '{}' must be a roles/storage.* role", owner, role);
+                }
+                json!({ "resource": { "google_storage_bucket_iam_member": { name: {
+                    "bucket": ctx.resolve("google_storage_bucket", bucket_ref, "name"),
+                    "role": role, "member": iam_member(ctx, &owner, member)?,
+                } } } })
+            }
+            GcpResource::RedisInstance { name, tier, memory_size_gb, region, authorized_network_ref, redis_version, auth_enabled, transit_encryption_mode, persistence, replica_count, read_replicas_enabled, labels } => {
+                let owner = format!("google_redis_instance.{}", name);
+                if *tier == RedisTier::Basic && (replica_count.is_some() || *read_replicas_enabled) {
+                    anyhow::bail!("{}: BASIC tier instances cannot have replicas; use STANDARD_HA", owner);
+                }
+                let mut body = json!({
+                    "name": name, "tier": tier, "memory_size_gb": memory_size_gb,
+                    "auth_enabled": auth_enabled, "transit_encryption_mode": transit_encryption_mode,
+                });
+                if let Some(r) = region { body["region"] = json!(r); }
+                if let Some(n) = authorized_network_ref { body["authorized_network"] = json!(ctx.resolve("google_compute_network", n, "id")); }
+                if let Some(v) = redis_version { body["redis_version"] = json!(v); }
+                if let Some(n) = replica_count {
+                    if !(1..=5).contains(n) { anyhow::bail!("{}: replica_count {} must be between 1 and 5", owner, n); }
+                    body["replica_count"] = json!(n);
+                }
+                if *read_replicas_enabled { body["read_replicas_mode"] = json!("READ_REPLICAS_ENABLED"); }
+                if let Some(p) = persistence {
+                    if !["ONE_HOUR", "SIX_HOURS", "TWELVE_HOURS", "TWENTY_FOUR_HOURS"].contains(&p.rdb_snapshot_period.as_str()) {
+                        anyhow::bail!("{}: rdb_snapshot_period '{}' must be ONE_HOUR, SIX_HOURS, TWELVE_HOURS or TWENTY_FOUR_HOURS", owner, p.rdb_snapshot_period);
+                    }
+                    body["persistence_config"] = json!([{ "persistence_mode": "RDB", "rdb_snapshot_period": p.rdb_snapshot_period }]);
+                }
+                if !labels.is_empty() { body["labels"] = json!(labels); }
+                json!({ "resource": { "google_redis_instance": { name: body } } })
+            }
+        })
     }
 }
 
@@ -65,3 +1343,750 @@ impl GcpAnyResource {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r2iac_core::test_util::{assert_errors, ctx, with, Captured};
+
+    fn render(siblings: &[(&str, &str)], res: Json) -> Result<Json> {
+        serde_json::from_value::<GcpResource>(res)?.to_tf_json(&mut ctx(siblings))
+    }
+
+    #[test]
+    fn provider_block_with_relative_credentials() {
+        // Relative to the working directory, which the runner will not share.
+        let key = format!("r2iac-test-credentials-{}.json", std::process::id());
+        std::fs::write(&key, "{}").unwrap();
+        let p: GcpProvider = serde_json::from_value(json!({
+            "project": "acme-prod", "region": "europe-west1", "zone": "europe-west1-b", "credentials": key,
+            "impersonate_service_account": "deployer@acme-prod.iam.gserviceaccount.com", "billing_project": "acme-billing",
+        })).unwrap();
+        let tf = p.to_tf_json();
+        let absolute = std::fs::canonicalize(&key).unwrap();
+        std::fs::remove_file(&key).unwrap();
+        assert_eq!(tf.unwrap(), json!({ "provider": { "google": {
+            "project": "acme-prod", "region": "europe-west1", "zone": "europe-west1-b",
+            "credentials": absolute.display().to_string(),
+            "impersonate_service_account": "deployer@acme-prod.iam.gserviceaccount.com",
+            "billing_project": "acme-billing", "user_project_override": true,
+        } } }));
+        assert!(absolute.is_absolute());
+    }
+
+    #[test]
+    fn provider_credentials_must_exist() {
+        let p: GcpProvider = serde_json::from_value(json!({ "project": "acme-prod", "credentials": "missing/sa-key.json" })).unwrap();
+        assert_eq!(format!("{}", p.to_tf_json().unwrap_err()), "provider.google: credentials file missing/sa-key.json not found");
+        let p: GcpProvider = serde_json::from_value(json!({ "project": "acme-prod" })).unwrap();
+        assert_eq!(p.to_tf_json().unwrap(), json!({ "provider": { "google": { "project": "acme-prod" } } }));
+    }
+
+    fn secret(extra: Json) -> Json {
+        with(json!({ "type": "google_secret_manager_secret", "name": "db-password" }), extra)
+    }
+
+    #[test]
+    fn secret_id_and_replication() {
+        let tf = render(&[], secret(json!({ "labels": { "owner": "data" } }))).unwrap();
+        assert_eq!(tf["resource"]["google_secret_manager_secret"]["db-password"], json!({
+            "secret_id": "db-password", "replication": [{ "auto": [{}] }], "labels": { "owner": "data" },
+        }));
+
+        let tf = render(&[("google_kms_crypto_key", "secrets")], secret(json!({
+            "replication": { "user_managed": ["europe-west1", "europe-west4"] }, "kms_key_ref": "secrets",
+        }))).unwrap();
+        let cmek = json!([{ "kms_key_name": "${google_kms_crypto_key.secrets.id}" }]);
+        assert_eq!(tf["resource"]["google_secret_manager_secret"]["db-password"]["replication"], json!([{ "user_managed": [{ "replicas": [
+            { "location": "europe-west1", "customer_managed_encryption": cmek },
+            { "location": "europe-west4", "customer_managed_encryption": cmek },
+        ] }] }]));
+
+        let tf = render(&[], secret(json!({ "kms_key_ref": "projects/p/locations/global/keyRings/r/cryptoKeys/k" }))).unwrap();
+        assert_eq!(tf["resource"]["google_secret_manager_secret"]["db-password"]["replication"], json!([{ "auto": [{
+            "customer_managed_encryption": [{ "kms_key_name": "projects/p/locations/global/keyRings/r/cryptoKeys/k" }],
+        }] }]));
+    }
+
+    #[test]
+    fn secret_errors() {
+        assert_errors(&secret(json!({})), &[
+            (json!({ "replication": { "user_managed": [] } }), "user_managed replication needs at least one location"),
+        ], |r| render(&[], r));
+        let err = render(&[], secret(json!({ "replication": "manual" }))).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `manual`"), "{}", err);
+    }
+
+    #[test]
+    fn secret_version_never_renders_the_secret() {
+        std::env::set_var("R2IAC_TEST_GCP_SECRET", "plaintext-gcp-secret-value");
+        for src in ["env:R2IAC_TEST_GCP_SECRET", "file:/run/secrets/db-password", "age:secrets/db-password.age"] {
+            let mut ctx = ctx(&[("google_secret_manager_secret", "db-password")]);
+            let logs = Captured::default();
+            let tf = logs.during(|| serde_json::from_value::<GcpResource>(json!({
+                "type": "google_secret_manager_secret_version", "name": "db-password-v1", "secret_ref": "db-password", "value_from": src,
+            })).unwrap().to_tf_json(&mut ctx)).unwrap();
+            let out = serde_json::to_string(&tf).unwrap();
+            assert!(!out.contains(src) && !out.contains(src.split_once(':').unwrap().1), "{}", out);
+            for text in [out, logs.text()] {
+                assert!(!text.contains("plaintext-gcp-secret-value"), "{}", text);
+            }
+            assert_eq!(tf["resource"]["google_secret_manager_secret_version"]["db-password-v1"], json!({
+                "secret": "${google_secret_manager_secret.db-password.id}",
+                "secret_data": "${var.google_secret_manager_secret_version_db_password_v1}",
+            }));
+            assert_eq!(String::from(ctx.secret_vars()["google_secret_manager_secret_version_db_password_v1"].clone()), src);
+        }
+    }
+
+    fn crypto_key(extra: Json) -> Json {
+        with(json!({ "type": "google_kms_crypto_key", "name": "data", "key_ring_ref": "main" }), extra)
+    }
+
+    #[test]
+    fn kms_crypto_key_symmetric_with_rotation() {
+        let tf = render(&[("google_kms_key_ring", "main")], crypto_key(json!({
+            "rotation_period": "7776000s", "protection_level": "HSM", "destroy_scheduled_duration": "86400s",
+        }))).unwrap();
+        assert_eq!(tf["resource"]["google_kms_crypto_key"]["data"], json!({
+            "name": "data", "key_ring": "${google_kms_key_ring.main.id}", "purpose": "ENCRYPT_DECRYPT",
+            "rotation_period": "7776000s", "destroy_scheduled_duration": "86400s",
+            "version_template": { "algorithm": "GOOGLE_SYMMETRIC_ENCRYPTION", "protection_level": "HSM" },
+        }));
+    }
+
+    #[test]
+    fn kms_crypto_key_asymmetric_needs_algorithm() {
+        let tf = render(&[], crypto_key(json!({ "purpose": "ASYMMETRIC_SIGN", "algorithm": "EC_SIGN_P256_SHA256" }))).unwrap();
+        assert_eq!(tf["resource"]["google_kms_crypto_key"]["data"]["version_template"], json!({ "algorithm": "EC_SIGN_P256_SHA256" }));
+        let err = render(&[], crypto_key(json!({ "purpose": "ASYMMETRIC_SIGN" }))).unwrap_err();
+        assert!(err.to_string().contains("algorithm is required unless purpose is ENCRYPT_DECRYPT"), "{}", err);
+    }
+
+    #[test]
+    fn kms_crypto_key_rotation_errors() {
+        assert_errors(&crypto_key(json!({})), &[
+            (json!({ "rotation_period": "3600s" }), "rotation_period '3600s' must be at least 86400s"),
+            (json!({ "rotation_period": "90d" }), "must be a number of seconds ending in 's'"),
+            (json!({ "rotation_period": "86400s", "purpose": "MAC", "algorithm": "HMAC_SHA256" }), "only supported for ENCRYPT_DECRYPT keys"),
+            (json!({ "destroy_scheduled_duration": "1 day" }), "destroy_scheduled_duration '1 day'"),
+        ], |r| render(&[], r));
+    }
+
+    fn instance(nic: Json) -> Json {
+        json!({
+            "type": "google_compute_instance", "name": "vm", "machine_type": "e2-small", "zone": "europe-west1-b",
+            "boot_disk": { "image": "debian-cloud/debian-12", "size": 20, "type": "pd-balanced", "kms_key_ref": "disk" },
+            "network_interface": nic,
+        })
+    }
+
+    #[test]
+    fn compute_instance_blocks() {
+        let res = with(instance(json!({ "subnetwork_ref": "app", "public_ip": true })), json!({
+            "service_account": { "email": "runner" }, "metadata": { "enable-oslogin": "TRUE" }, "labels": { "env": "dev" },
+        }));
+        let tf = render(&[("google_kms_crypto_key", "disk"), ("google_compute_subnetwork", "app"), ("google_service_account", "runner")], res).unwrap();
+        assert_eq!(tf["resource"]["google_compute_instance"]["vm"], json!({
+            "name": "vm", "machine_type": "e2-small", "zone": "europe-west1-b",
+            "boot_disk": [{
+                "initialize_params": { "image": "debian-cloud/debian-12", "size": 20, "type": "pd-balanced" },
+                "kms_key_self_link": "${google_kms_crypto_key.disk.id}",
+            }],
+            "network_interface": [{ "subnetwork": "${google_compute_subnetwork.app.self_link}", "access_config": [{}] }],
+            "service_account": [{ "email": "${google_service_account.runner.email}", "scopes": ["cloud-platform"] }],
+            "metadata": { "enable-oslogin": "TRUE" }, "labels": { "env": "dev" },
+        }));
+    }
+
+    #[test]
+    fn compute_instance_private_by_default() {
+        let tf = render(&[("google_compute_network", "vpc")], instance(json!({ "network_ref": "vpc" }))).unwrap();
+        assert_eq!(tf["resource"]["google_compute_instance"]["vm"]["network_interface"], json!([{ "network": "${google_compute_network.vpc.self_link}" }]));
+    }
+
+    #[test]
+    fn compute_instance_needs_a_network() {
+        let err = render(&[], instance(json!({ "public_ip": true }))).unwrap_err();
+        assert!(err.to_string().contains("google_compute_instance.vm: network_interface needs network_ref or subnetwork_ref"), "{}", err);
+    }
+
+    fn cloud_run(extra: Json) -> Json {
+        with(json!({ "type": "google_cloud_run_v2_service", "name": "api", "location": "europe-west1", "image": "gcr.io/acme/api:1" }), extra)
+    }
+
+    #[test]
+    fn cloud_run_service_with_secret_env_and_public_invoker() {
+        let tf = render(&[("google_secret_manager_secret", "db"), ("google_service_account", "api")], cloud_run(json!({
+            "env": [{ "name": "MODE", "value": "prod" }, { "name": "DB_PASSWORD", "secret_ref": "db" }],
+            "cpu": "1", "memory": "512Mi", "min_instances": 0, "max_instances": 3,
+            "ingress": "INGRESS_TRAFFIC_ALL", "service_account": "api", "allow_unauthenticated": true,
+        }))).unwrap();
+        let r = &tf["resource"];
+        assert_eq!(r["google_cloud_run_v2_service"]["api"], json!({
+            "name": "api", "location": "europe-west1", "ingress": "INGRESS_TRAFFIC_ALL",
+            "template": [{
+                "containers": [{
+                    "image": "gcr.io/acme/api:1",
+                    "env": [
+                        { "name": "MODE", "value": "prod" },
+                        { "name": "DB_PASSWORD", "value_source": [{ "secret_key_ref": [{
+                            "secret": "${google_secret_manager_secret.db.secret_id}", "version": "latest",
+                        }] }] },
+                    ],
+                    "resources": [{ "limits": { "cpu": "1", "memory": "512Mi" } }],
+                }],
+                "scaling": [{ "min_instance_count": 0, "max_instance_count": 3 }],
+                "service_account": "${google_service_account.api.email}",
+            }],
+        }));
+        assert_eq!(r["google_cloud_run_v2_service_iam_member"]["api"]["member"], "allUsers");
+        assert_eq!(r["google_cloud_run_v2_service_iam_member"]["api"]["role"], "roles/run.invoker");
+    }
+
+    #[test]
+    fn cloud_run_service_private_by_default() {
+        let tf = render(&[], cloud_run(json!({}))).unwrap();
+        assert!(tf["resource"].get("google_cloud_run_v2_service_iam_member").is_none());
+        assert_eq!(tf["resource"]["google_cloud_run_v2_service"]["api"]["template"], json!([{ "containers": [{ "image": "gcr.io/acme/api:1" }] }]));
+    }
+
+    #[test]
+    fn cloud_run_service_errors() {
+        assert_errors(&cloud_run(json!({})), &[
+            (json!({ "env": [{ "name": "X" }] }), "env 'X' needs exactly one of value or secret_ref"),
+            (json!({ "env": [{ "name": "X", "value": "a", "secret_ref": "s" }] }), "env 'X' needs exactly one of value or secret_ref"),
+            (json!({ "min_instances": 4, "max_instances": 2 }), "min_instances 4 exceeds max_instances 2"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn dns_zone_names_get_a_trailing_dot_once() {
+        for dns_name in ["example.com", "example.com."] {
+            let tf = render(&[], json!({ "type": "google_dns_managed_zone", "name": "pub", "dns_name": dns_name, "dnssec": true })).unwrap();
+            assert_eq!(tf["resource"]["google_dns_managed_zone"]["pub"], json!({
+                "name": "pub", "dns_name": "example.com.", "visibility": "public", "dnssec_config": [{ "state": "on" }],
+            }));
+        }
+    }
+
+    #[test]
+    fn dns_private_zone_networks() {
+        let tf = render(&[("google_compute_network", "vpc")], json!({
+            "type": "google_dns_managed_zone", "name": "int", "dns_name": "internal.", "visibility": "private",
+            "private_visibility_network_refs": ["vpc"],
+        })).unwrap();
+        assert_eq!(tf["resource"]["google_dns_managed_zone"]["int"]["private_visibility_config"],
+            json!([{ "networks": [{ "network_url": "${google_compute_network.vpc.id}" }] }]));
+    }
+
+    #[test]
+    fn dns_zone_visibility_errors() {
+        assert_errors(&json!({ "type": "google_dns_managed_zone", "name": "z", "dns_name": "example.com" }), &[
+            (json!({ "visibility": "private" }), "private zones need at least one private_visibility_network_refs entry"),
+            (json!({ "visibility": "private", "private_visibility_network_refs": ["vpc"], "dnssec": true }), "dnssec is only supported on public zones"),
+            (json!({ "private_visibility_network_refs": ["vpc"] }), "private_visibility_network_refs requires visibility: private"),
+        ], |r| render(&[], r));
+    }
+
+    fn record_set(record_type: &str, rrdatas: Json) -> Json {
+        json!({ "type": "google_dns_record_set", "name": "r", "zone_ref": "pub", "record_name": "www.example.com", "record_type": record_type, "rrdatas": rrdatas })
+    }
+
+    #[test]
+    fn dns_record_rrdatas_are_normalized() {
+        let zone = [("google_dns_managed_zone", "pub")];
+        let rrdatas = |t: &str, v: Json| render(&zone, record_set(t, v)).unwrap()["resource"]["google_dns_record_set"]["r"]["rrdatas"].clone();
+        assert_eq!(rrdatas("CNAME", json!(["web.example.net"])), json!(["web.example.net."]));
+        assert_eq!(rrdatas("CNAME", json!(["web.example.net."])), json!(["web.example.net."]));
+        assert_eq!(rrdatas("MX", json!(["10 mail.example.com"])), json!(["10 mail.example.com."]));
+        assert_eq!(rrdatas("TXT", json!(["v=spf1 -all"])), json!(["\"v=spf1 -all\""]));
+        assert_eq!(rrdatas("TXT", json!([format!("{}b", "a".repeat(255))])), json!([format!("\"{}\" \"b\"", "a".repeat(255))]));
+        let tf = render(&zone, record_set("A", json!(["192.0.2.1"]))).unwrap();
+        assert_eq!(tf["resource"]["google_dns_record_set"]["r"], json!({
+            "name": "www.example.com.", "type": "A", "ttl": 300, "rrdatas": ["192.0.2.1"],
+            "managed_zone": "${google_dns_managed_zone.pub.name}",
+        }));
+    }
+
+    #[test]
+    fn dns_record_errors() {
+        for (t, rrdatas, msg) in [
+            ("A", json!([]), "rrdatas must not be empty"),
+            ("A", json!(["not-an-ip"]), "'not-an-ip' is not an IPv4 address"),
+            ("AAAA", json!(["192.0.2.1"]), "'192.0.2.1' is not an IPv6 address"),
+            ("CNAME", json!(["a.example.", "b.example."]), "a CNAME record takes exactly one rrdata"),
+            ("MX", json!(["mail.example.com"]), "must be '<priority> <host>'"),
+        ] {
+            let err = render(&[], record_set(t, rrdatas)).unwrap_err();
+            assert!(err.to_string().starts_with("google_dns_record_set.r: "), "{}", err);
+            assert!(err.to_string().contains(msg), "{}", err);
+        }
+    }
+
+    fn bucket_member(role: &str, member: &str) -> Json {
+        json!({ "type": "google_storage_bucket_iam_member", "name": "read", "bucket_ref": "assets", "role": role, "member": member })
+    }
+
+    #[test]
+    fn bucket_iam_member_resolves_bucket_and_service_account() {
+        let tf = render(&[("google_storage_bucket", "assets"), ("google_service_account", "cdn")], bucket_member("roles/storage.objectViewer", "cdn")).unwrap();
+        assert_eq!(tf["resource"]["google_storage_bucket_iam_member"]["read"], json!({
+            "bucket": "${google_storage_bucket.assets.name}", "role": "roles/storage.objectViewer",
+            "member": "serviceAccount:${google_service_account.cdn.email}",
+        }));
+        let tf = render(&[], bucket_member("roles/storage.objectViewer", "group:eng@example.com")).unwrap();
+        assert_eq!(tf["resource"]["google_storage_bucket_iam_member"]["read"]["bucket"], "assets");
+        assert_eq!(tf["resource"]["google_storage_bucket_iam_member"]["read"]["member"], "group:eng@example.com");
+    }
+
+    #[test]
+    fn bucket_iam_member_errors() {
+        let err = render(&[], bucket_member("roles/viewer", "allUsers")).unwrap_err();
+        assert!(err.to_string().contains("role 'roles/viewer' must be a roles/storage.* role"), "{}", err);
+        let err = render(&[], bucket_member("roles/storage.objectViewer", "eng@example.com")).unwrap_err();
+        assert!(err.to_string().contains("member 'eng@example.com' is neither a principal"), "{}", err);
+    }
+
+    #[test]
+    fn gke_labels_render_as_resource_labels() {
+        let tf = render(&[], json!({ "type": "google_container_cluster", "name": "main", "location": "europe-west1", "labels": { "owner": "platform" } })).unwrap();
+        let cluster = &tf["resource"]["google_container_cluster"]["main"];
+        assert_eq!(cluster["resource_labels"], json!({ "owner": "platform" }));
+        assert!(cluster.get("labels").is_none());
+    }
+
+    #[test]
+    fn network_and_subnetwork() {
+        let tf = render(&[], json!({ "type": "google_compute_network", "name": "vpc", "auto_create_subnetworks": false, "routing_mode": "GLOBAL", "mtu": 1460 })).unwrap();
+        assert_eq!(tf["resource"]["google_compute_network"]["vpc"], json!({ "name": "vpc", "auto_create_subnetworks": false, "routing_mode": "GLOBAL", "mtu": 1460 }));
+        let tf = render(&[("google_compute_network", "vpc")], json!({
+            "type": "google_compute_subnetwork", "name": "gke", "network_ref": "vpc", "ip_cidr_range": "10.0.0.0/20",
+            "region": "europe-west1", "private_ip_google_access": true,
+            "secondary_ranges": [{ "range_name": "pods", "ip_cidr_range": "10.4.0.0/14" }, { "range_name": "services", "ip_cidr_range": "10.8.0.0/20" }],
+        })).unwrap();
+        assert_eq!(tf["resource"]["google_compute_subnetwork"]["gke"], json!({
+            "name": "gke", "ip_cidr_range": "10.0.0.0/20", "network": "${google_compute_network.vpc.id}",
+            "region": "europe-west1", "private_ip_google_access": true,
+            "secondary_ip_range": [{ "range_name": "pods", "ip_cidr_range": "10.4.0.0/14" }, { "range_name": "services", "ip_cidr_range": "10.8.0.0/20" }],
+        }));
+    }
+
+    #[test]
+    fn network_and_subnetwork_errors() {
+        assert_errors(&json!({ "type": "google_compute_network", "name": "vpc" }), &[
+            (json!({ "mtu": 1200 }), "mtu 1200 must be between 1300 and 8896"),
+            (json!({ "mtu": 9000 }), "mtu 9000 must be between 1300 and 8896"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "google_compute_subnetwork", "name": "gke", "network_ref": "vpc", "ip_cidr_range": "10.0.0.0/20" }), &[
+            (json!({ "ip_cidr_range": "10.0.0.0" }), "'10.0.0.0' is not a CIDR block"),
+            (json!({ "ip_cidr_range": "10.0.1.0/20" }), "'10.0.1.0/20' has host bits set"),
+            (json!({ "secondary_ranges": [{ "range_name": "pods", "ip_cidr_range": "10.4.0.0/33" }] }), "prefix length must be <= 32"),
+            (json!({ "secondary_ranges": [
+                { "range_name": "pods", "ip_cidr_range": "10.4.0.0/14" }, { "range_name": "pods", "ip_cidr_range": "10.8.0.0/20" },
+            ] }), "secondary range name 'pods' is used more than once"),
+        ], |r| render(&[], r));
+    }
+
+    fn firewall(extra: Json) -> Json {
+        with(json!({ "type": "google_compute_firewall", "name": "web", "network_ref": "vpc", "allow": [{ "protocol": "tcp", "ports": ["443", "8000-8080"] }] }), extra)
+    }
+
+    #[test]
+    fn firewall_ingress_and_egress() {
+        let tf = render(&[("google_compute_network", "vpc")], firewall(json!({
+            "priority": 900, "source_ranges": ["10.0.0.0/8"], "target_tags": ["web"],
+        }))).unwrap();
+        assert_eq!(tf["resource"]["google_compute_firewall"]["web"], json!({
+            "name": "web", "direction": "INGRESS", "network": "${google_compute_network.vpc.id}", "priority": 900,
+            "source_ranges": ["10.0.0.0/8"], "target_tags": ["web"],
+            "allow": [{ "protocol": "tcp", "ports": ["443", "8000-8080"] }],
+        }));
+        let tf = render(&[], firewall(json!({
+            "direction": "EGRESS", "destination_ranges": ["0.0.0.0/0"], "allow": [], "deny": [{ "protocol": "all" }],
+        }))).unwrap();
+        assert_eq!(tf["resource"]["google_compute_firewall"]["web"], json!({
+            "name": "web", "direction": "EGRESS", "network": "vpc", "destination_ranges": ["0.0.0.0/0"], "deny": [{ "protocol": "all" }],
+        }));
+    }
+
+    #[test]
+    fn firewall_errors() {
+        assert_errors(&firewall(json!({})), &[
+            (json!({ "destination_ranges": ["10.0.0.0/8"] }), "INGRESS rules cannot set destination_ranges"),
+            (json!({ "direction": "EGRESS", "source_ranges": ["10.0.0.0/8"] }), "EGRESS rules cannot set source_ranges"),
+            (json!({ "allow": [] }), "set at least one allow or deny entry"),
+            (json!({ "deny": [{ "protocol": "udp" }] }), "allow and deny cannot be combined in one rule"),
+            (json!({ "priority": 70000 }), "priority 70000 must be between 0 and 65535"),
+            (json!({ "source_ranges": ["10.0.0.1/8"] }), "'10.0.0.1/8' has host bits set"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn service_account_and_iam_members() {
+        let tf = render(&[], json!({ "type": "google_service_account", "name": "ci", "account_id": "ci-deployer", "display_name": "CI", "description": "Deploys from CI" })).unwrap();
+        assert_eq!(tf["resource"]["google_service_account"]["ci"], json!({ "account_id": "ci-deployer", "display_name": "CI", "description": "Deploys from CI" }));
+
+        let member = json!({ "type": "google_project_iam_member", "name": "ci-run", "role": "roles/run.admin", "member": "ci" });
+        let tf = render(&[("google_service_account", "ci")], member.clone()).unwrap();
+        assert_eq!(tf, json!({
+            "data": { "google_client_config": { "current": {} } },
+            "resource": { "google_project_iam_member": { "ci-run": {
+                "project": "${data.google_client_config.current.project}", "role": "roles/run.admin",
+                "member": "serviceAccount:${google_service_account.ci.email}",
+            } } },
+        }));
+        let tf = render(&[], with(member, json!({ "project": "acme-prod", "member": "group:eng@example.com", "role": "projects/acme-prod/roles/deployer" }))).unwrap();
+        assert!(tf.get("data").is_none());
+        assert_eq!(tf["resource"]["google_project_iam_member"]["ci-run"], json!({
+            "project": "acme-prod", "role": "projects/acme-prod/roles/deployer", "member": "group:eng@example.com",
+        }));
+
+        let tf = render(&[("google_service_account", "ci")], json!({
+            "type": "google_service_account_iam_member", "name": "ci-wi", "service_account_ref": "ci",
+            "role": "roles/iam.workloadIdentityUser", "member": "serviceAccount:acme-prod.svc.id.goog[ci/runner]",
+        })).unwrap();
+        assert_eq!(tf["resource"]["google_service_account_iam_member"]["ci-wi"], json!({
+            "service_account_id": "${google_service_account.ci.name}", "role": "roles/iam.workloadIdentityUser",
+            "member": "serviceAccount:acme-prod.svc.id.goog[ci/runner]",
+        }));
+    }
+
+    #[test]
+    fn service_account_and_iam_errors() {
+        assert_errors(&json!({ "type": "google_service_account", "name": "ci", "account_id": "ci-deployer" }), &[
+            (json!({ "account_id": "ci" }), "account_id 'ci' must be 6-30"),
+            (json!({ "account_id": "a-very-long-service-account-id-x" }), "must be 6-30"),
+            (json!({ "account_id": "1-deployer" }), "starting with a letter"),
+            (json!({ "account_id": "ci-deployer-" }), "account_id 'ci-deployer-'"),
+            (json!({ "account_id": "CI_Deployer" }), "account_id 'CI_Deployer'"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "google_project_iam_member", "name": "ci-run", "role": "roles/run.admin", "member": "user:ci@example.com" }), &[
+            (json!({ "role": "run.admin" }), "role 'run.admin' must start with 'roles/' or 'projects/'"),
+            (json!({ "member": "ci" }), "member 'ci' is neither a principal"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "google_service_account_iam_member", "name": "ci-wi", "service_account_ref": "ci", "role": "roles/iam.serviceAccountUser", "member": "user:ci@example.com" }), &[
+            (json!({ "role": "iam.serviceAccountUser" }), "role 'iam.serviceAccountUser' must start with"),
+            (json!({ "member": "ci@example.com" }), "member 'ci@example.com' is neither a principal"),
+        ], |r| render(&[], r));
+    }
+
+    #[test]
+    fn pubsub_topic_and_subscription() {
+        let tf = render(&[("google_kms_crypto_key", "events")], json!({
+            "type": "google_pubsub_topic", "name": "orders", "kms_key_ref": "events", "message_retention_duration": "86400s", "labels": { "team": "shop" },
+        })).unwrap();
+        assert_eq!(tf["resource"]["google_pubsub_topic"]["orders"], json!({
+            "name": "orders", "kms_key_name": "${google_kms_crypto_key.events.id}", "message_retention_duration": "86400s", "labels": { "team": "shop" },
+        }));
+        let tf = render(&[("google_pubsub_topic", "orders"), ("google_pubsub_topic", "orders-dead")], json!({
+            "type": "google_pubsub_subscription", "name": "billing", "topic_ref": "orders", "ack_deadline_seconds": 60,
+            "push_endpoint": "https://billing.example.com/push", "dead_letter": { "topic_ref": "orders-dead", "max_delivery_attempts": 10 },
+            "retain_acked_messages": true,
+        })).unwrap();
+        assert_eq!(tf["resource"]["google_pubsub_subscription"]["billing"], json!({
+            "name": "billing", "topic": "${google_pubsub_topic.orders.id}", "ack_deadline_seconds": 60,
+            "push_config": [{ "push_endpoint": "https://billing.example.com/push" }],
+            "dead_letter_policy": [{ "dead_letter_topic": "${google_pubsub_topic.orders-dead.id}", "max_delivery_attempts": 10 }],
+            "retain_acked_messages": true,
+        }));
+    }
+
+    #[test]
+    fn pubsub_errors() {
+        assert_errors(&json!({ "type": "google_pubsub_topic", "name": "orders" }), &[
+            (json!({ "message_retention_duration": "300s" }), "message_retention_duration '300s' must be between 600s and 2678400s"),
+            (json!({ "message_retention_duration": "2678401s" }), "must be between 600s and 2678400s"),
+            (json!({ "message_retention_duration": "7d" }), "must be a number of seconds ending in 's'"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "google_pubsub_subscription", "name": "billing", "topic_ref": "orders" }), &[
+            (json!({ "ack_deadline_seconds": 5 }), "ack_deadline_seconds 5 must be between 10 and 600"),
+            (json!({ "ack_deadline_seconds": 601 }), "ack_deadline_seconds 601 must be between 10 and 600"),
+            (json!({ "dead_letter": { "topic_ref": "dead", "max_delivery_attempts": 4 } }), "max_delivery_attempts 4 must be between 5 and 100"),
+            (json!({ "dead_letter": { "topic_ref": "dead", "max_delivery_attempts": 101 } }), "max_delivery_attempts 101 must be between 5 and 100"),
+        ], |r| render(&[], r));
+    }
+
+    fn bq_table(extra: Json) -> Json {
+        with(json!({
+            "type": "google_bigquery_table", "name": "events", "dataset_ref": "analytics", "table_id": "events",
+            "schema": [
+                { "name": "ts", "type": "TIMESTAMP", "mode": "REQUIRED" },
+                { "name": "user_id", "type": "STRING" },
+                { "name": "payload", "type": "RECORD", "fields": [{ "name": "kind", "type": "STRING" }] },
+            ],
+        }), extra)
+    }
+
+    #[test]
+    fn bigquery_dataset_and_table() {
+        let tf = render(&[("google_kms_crypto_key", "bq")], json!({
+            "type": "google_bigquery_dataset", "name": "analytics", "dataset_id": "analytics", "location": "EU",
+            "default_table_expiration_ms": 3600000, "default_kms_key_ref": "bq", "delete_contents_on_destroy": false,
+        })).unwrap();
+        assert_eq!(tf["resource"]["google_bigquery_dataset"]["analytics"], json!({
+            "dataset_id": "analytics", "location": "EU", "default_table_expiration_ms": 3600000,
+            "default_encryption_configuration": [{ "kms_key_name": "${google_kms_crypto_key.bq.id}" }], "delete_contents_on_destroy": false,
+        }));
+        let tf = render(&[("google_bigquery_dataset", "analytics")], bq_table(json!({
+            "time_partitioning": { "type": "DAY", "field": "ts", "expiration_ms": 7776000000u64 }, "clustering": ["user_id"],
+        }))).unwrap();
+        let table = &tf["resource"]["google_bigquery_table"]["events"];
+        assert_eq!(table["dataset_id"], "${google_bigquery_dataset.analytics.dataset_id}");
+        assert_eq!(table["time_partitioning"], json!([{ "type": "DAY", "field": "ts", "expiration_ms": 7776000000u64 }]));
+        assert_eq!(table["clustering"], json!(["user_id"]));
+        // The provider takes the schema as a JSON string, without unset optional keys.
+        assert_eq!(serde_json::from_str::<Json>(table["schema"].as_str().unwrap()).unwrap(), json!([
+            { "name": "ts", "type": "TIMESTAMP", "mode": "REQUIRED" },
+            { "name": "user_id", "type": "STRING" },
+            { "name": "payload", "type": "RECORD", "fields": [{ "name": "kind", "type": "STRING" }] },
+        ]));
+    }
+
+    #[test]
+    fn bigquery_errors() {
+        assert_errors(&json!({ "type": "google_bigquery_dataset", "name": "analytics", "dataset_id": "analytics" }), &[
+            (json!({ "default_table_expiration_ms": 60000 }), "default_table_expiration_ms must be at least 3600000"),
+        ], |r| render(&[], r));
+        assert_errors(&bq_table(json!({})), &[
+            (json!({ "schema": [{ "name": "id", "type": "STRING" }, { "name": "ID", "type": "STRING" }] }), "schema field 'ID' is defined more than once"),
+            (json!({ "schema": [{ "name": "id", "type": "VARCHAR" }] }), "schema field 'id' has unknown type 'VARCHAR'"),
+            (json!({ "schema": [{ "name": "id", "type": "STRING", "mode": "OPTIONAL" }] }), "schema field 'id' has unknown mode 'OPTIONAL'"),
+            (json!({ "schema": [{ "name": "payload", "type": "RECORD" }] }), "schema field 'payload' must list fields if and only if it is a RECORD/STRUCT"),
+            (json!({ "schema": [{ "name": "id", "type": "STRING", "fields": [{ "name": "x", "type": "STRING" }] }] }), "schema field 'id' must list fields"),
+            (json!({ "schema": [{ "name": "payload", "type": "RECORD", "fields": [{ "name": "kind", "type": "TEXT" }] }] }), "schema field 'kind' has unknown type 'TEXT'"),
+            (json!({ "time_partitioning": { "type": "DAY", "field": "created_at" } }), "time_partitioning field 'created_at' is not in the schema"),
+            (json!({ "time_partitioning": { "type": "DAY", "field": "kind" } }), "time_partitioning field 'kind' is not in the schema"),
+            (json!({ "clustering": ["ts", "user_id", "payload", "ts", "user_id"] }), "clustering allows at most 4 columns"),
+            (json!({ "clustering": ["country"] }), "clustering column 'country' is not in the schema"),
+        ], |r| render(&[], r));
+    }
+
+    fn gke(extra: Json) -> Json {
+        with(json!({ "type": "google_container_cluster", "name": "main", "location": "europe-west1" }), extra)
+    }
+
+    #[test]
+    fn gke_cluster_private_with_workload_identity() {
+        let tf = render(&[("google_compute_network", "vpc"), ("google_compute_subnetwork", "gke")], gke(json!({
+            "network_ref": "vpc", "subnetwork_ref": "gke", "release_channel": "REGULAR", "workload_identity": true,
+            "private_cluster": { "master_ipv4_cidr": "172.16.0.0/28" },
+        }))).unwrap();
+        assert_eq!(tf, json!({
+            "data": { "google_client_config": { "current": {} } },
+            "resource": { "google_container_cluster": { "main": {
+                "name": "main", "location": "europe-west1",
+                "network": "${google_compute_network.vpc.id}", "subnetwork": "${google_compute_subnetwork.gke.id}",
+                "release_channel": [{ "channel": "REGULAR" }],
+                "workload_identity_config": [{ "workload_pool": "${data.google_client_config.current.project}.svc.id.goog" }],
+                "private_cluster_config": [{ "enable_private_nodes": true, "master_ipv4_cidr_block": "172.16.0.0/28" }],
+            } } },
+        }));
+    }
+
+    #[test]
+    fn gke_default_node_pool_is_removed_once_the_cluster_has_pools() {
+        let cluster = |c: &mut RenderCtx, extra: Json| serde_json::from_value::<GcpResource>(gke(extra)).unwrap().to_tf_json(c).unwrap();
+        let mut alone = ctx(&[]);
+        let tf = cluster(&mut alone, json!({}));
+        assert!(tf["resource"]["google_container_cluster"]["main"].get("remove_default_node_pool").is_none());
+
+        let mut pooled = ctx(&[("google_container_cluster", "main")]);
+        pooled.declare_child_of("google_container_cluster", "main");
+        let tf = cluster(&mut pooled, json!({}));
+        assert_eq!(tf["resource"]["google_container_cluster"]["main"]["remove_default_node_pool"], true);
+        assert_eq!(tf["resource"]["google_container_cluster"]["main"]["initial_node_count"], 1);
+        let tf = cluster(&mut pooled, json!({ "remove_default_node_pool": false }));
+        assert!(tf["resource"]["google_container_cluster"]["main"].get("remove_default_node_pool").is_none());
+    }
+
+    #[test]
+    fn gke_node_pool() {
+        let pool = json!({ "type": "google_container_node_pool", "name": "general", "cluster_ref": "main", "machine_type": "e2-standard-4" });
+        let res: GcpResource = serde_json::from_value(pool.clone()).unwrap();
+        assert_eq!(res.parent_ref(), Some(("google_container_cluster", "main")));
+        let tf = render(&[("google_container_cluster", "main"), ("google_service_account", "nodes")], with(pool.clone(), json!({
+            "autoscaling": { "min": 1, "max": 5 }, "disk_size_gb": 100, "service_account_ref": "nodes", "oauth_scopes": ["cloud-platform"],
+        }))).unwrap();
+        assert_eq!(tf["resource"]["google_container_node_pool"]["general"], json!({
+            "name": "general", "cluster": "${google_container_cluster.main.id}",
+            "autoscaling": [{ "min_node_count": 1, "max_node_count": 5 }],
+            "node_config": [{
+                "machine_type": "e2-standard-4", "disk_size_gb": 100,
+                "service_account": "${google_service_account.nodes.email}", "oauth_scopes": ["cloud-platform"],
+            }],
+        }));
+        let tf = render(&[], with(pool, json!({ "node_count": 3 }))).unwrap();
+        assert_eq!(tf["resource"]["google_container_node_pool"]["general"]["node_count"], 3);
+        assert!(tf["resource"]["google_container_node_pool"]["general"].get("autoscaling").is_none());
+    }
+
+    #[test]
+    fn gke_errors() {
+        assert_errors(&gke(json!({})), &[
+            (json!({ "private_cluster": { "master_ipv4_cidr": "172.16.0.0/24" } }), "master_ipv4_cidr '172.16.0.0/24' must be a /28"),
+            (json!({ "private_cluster": { "master_ipv4_cidr": "172.16.0.8/28" } }), "'172.16.0.8/28' has host bits set"),
+        ], |r| render(&[], r));
+        assert_errors(&json!({ "type": "google_container_node_pool", "name": "general", "cluster_ref": "main", "machine_type": "e2-standard-4" }), &[
+            (json!({ "node_count": 3, "autoscaling": { "min": 1, "max": 5 } }), "set node_count or autoscaling, not both"),
+            (json!({ "autoscaling": { "min": 6, "max": 5 } }), "autoscaling min 6 exceeds max 5"),
+        ], |r| render(&[], r));
+    }
+
+    fn bucket(extra: Json) -> Json {
+        with(json!({ "type": "google_storage_bucket", "name": "assets", "location": "EU" }), extra)
+    }
+
+    #[test]
+    fn storage_bucket() {
+        let tf = render(&[], bucket(json!({}))).unwrap();
+        assert_eq!(tf["resource"]["google_storage_bucket"]["assets"], json!({
+            "name": "assets", "location": "EU", "public_access_prevention": "enforced", "uniform_bucket_level_access": true,
+        }));
+        let tf = render(&[("google_kms_crypto_key", "assets")], bucket(json!({
+            "force_destroy": true, "versioning": true, "public_access_prevention": "inherited", "encryption": { "default_kms_key_ref": "assets" },
+            "lifecycle_rules": [
+                { "action": { "type": "SetStorageClass", "storage_class": "NEARLINE" }, "age": 30 },
+                { "action": { "type": "Delete" }, "num_newer_versions": 3 },
+            ],
+            "labels": { "env": "prod" },
+        }))).unwrap();
+        assert_eq!(tf["resource"]["google_storage_bucket"]["assets"], json!({
+            "name": "assets", "location": "EU", "public_access_prevention": "inherited", "force_destroy": true,
+            "uniform_bucket_level_access": true, "versioning": [{ "enabled": true }],
+            "lifecycle_rule": [
+                { "action": [{ "type": "SetStorageClass", "storage_class": "NEARLINE" }], "condition": [{ "age": 30 }] },
+                { "action": [{ "type": "Delete" }], "condition": [{ "num_newer_versions": 3 }] },
+            ],
+            "encryption": [{ "default_kms_key_name": "${google_kms_crypto_key.assets.id}" }],
+            "labels": { "env": "prod" },
+        }));
+    }
+
+    #[test]
+    fn storage_bucket_errors() {
+        assert_errors(&bucket(json!({})), &[
+            (json!({ "public_access_prevention": "unspecified" }), "public_access_prevention 'unspecified' must be 'enforced' or 'inherited'"),
+            (json!({ "lifecycle_rules": [{ "action": { "type": "Delete" } }] }), "lifecycle rule needs age or num_newer_versions"),
+        ], |r| render(&[], r));
+    }
+
+    fn sql(extra: Json) -> Json {
+        with(json!({
+            "type": "google_sql_database_instance", "name": "main", "database_version": "POSTGRES_16", "region": "europe-west1",
+            "tier": "db-custom-2-7680", "ip_configuration": { "private_network_ref": "vpc" },
+        }), extra)
+    }
+
+    #[test]
+    fn cloud_sql_instance() {
+        let mut c = ctx(&[("google_compute_network", "vpc")]);
+        let tf = serde_json::from_value::<GcpResource>(sql(json!({
+            "disk_size": 50, "disk_autoresize": true, "availability_type": "REGIONAL", "deletion_protection": true,
+            "backup": { "point_in_time_recovery": true, "start_time": "03:30" }, "root_password_from": "env:DB_ROOT_PASSWORD",
+        }))).unwrap().to_tf_json(&mut c).unwrap();
+        assert_eq!(tf["resource"]["google_sql_database_instance"]["main"], json!({
+            "name": "main", "database_version": "POSTGRES_16", "region": "europe-west1", "deletion_protection": true,
+            "root_password": "${var.google_sql_database_instance_main}",
+            "settings": [{
+                "tier": "db-custom-2-7680", "disk_size": 50, "disk_autoresize": true, "availability_type": "REGIONAL",
+                "backup_configuration": [{ "enabled": true, "point_in_time_recovery_enabled": true, "start_time": "03:30" }],
+                "ip_configuration": [{ "ipv4_enabled": false, "private_network": "${google_compute_network.vpc.id}" }],
+            }],
+        }));
+        assert_eq!(String::from(c.secret_vars()["google_sql_database_instance_main"].clone()), "env:DB_ROOT_PASSWORD");
+
+        let tf = render(&[], sql(json!({
+            "database_version": "MYSQL_8_0_36", "backup": { "point_in_time_recovery": true },
+            "ip_configuration": { "public_ip": true, "authorized_networks": ["203.0.113.0/24"] },
+        }))).unwrap();
+        let settings = &tf["resource"]["google_sql_database_instance"]["main"]["settings"][0];
+        assert_eq!(settings["backup_configuration"], json!([{ "enabled": true, "binary_log_enabled": true }]));
+        assert_eq!(settings["ip_configuration"], json!([{ "ipv4_enabled": true, "authorized_networks": [{ "value": "203.0.113.0/24" }] }]));
+    }
+
+    #[test]
+    fn cloud_sql_errors() {
+        assert_errors(&sql(json!({})), &[
+            (json!({ "database_version": "POSTGRES_8" }), "unknown database_version 'POSTGRES_8'"),
+            (json!({ "database_version": "MYSQL_8_0_" }), "unknown database_version 'MYSQL_8_0_'"),
+            (json!({ "database_version": "SQLSERVER_2022_STANDARD", "backup": { "point_in_time_recovery": true } }), "point_in_time_recovery is only supported for Postgres and MySQL"),
+            (json!({ "backup": { "start_time": "3:30" } }), "backup start_time '3:30' must be HH:MM"),
+            (json!({ "backup": { "start_time": "24:00" } }), "backup start_time '24:00' must be HH:MM"),
+            (json!({ "ip_configuration": {} }), "enable public_ip or set private_network_ref"),
+            (json!({ "ip_configuration": { "private_network_ref": "vpc", "authorized_networks": ["203.0.113.0/24"] } }), "authorized_networks require public_ip"),
+            (json!({ "ip_configuration": { "public_ip": true, "authorized_networks": ["203.0.113.1/24"] } }), "'203.0.113.1/24' has host bits set"),
+        ], |r| render(&[], r));
+    }
+
+    fn registry(policies: Json) -> Json {
+        json!({
+            "type": "google_artifact_registry_repository", "name": "images", "repository_id": "images", "location": "europe-west1",
+            "format": "DOCKER", "cleanup_policies": policies,
+        })
+    }
+
+    #[test]
+    fn artifact_registry_cleanup_policies() {
+        let tf = render(&[("google_kms_crypto_key", "ar")], with(registry(json!([
+            { "keep_most_recent": 10 },
+            { "delete_older_than": "2592000s", "tag_state": "UNTAGGED" },
+            { "id": "old-tagged", "delete_older_than": "7776000s", "tag_state": "TAGGED" },
+        ])), json!({ "kms_key_ref": "ar", "labels": { "team": "platform" } }))).unwrap();
+        assert_eq!(tf["resource"]["google_artifact_registry_repository"]["images"], json!({
+            "repository_id": "images", "location": "europe-west1", "format": "DOCKER", "kms_key_name": "${google_kms_crypto_key.ar.id}",
+            "cleanup_policies": [
+                { "id": "keep-most-recent", "action": "KEEP", "most_recent_versions": [{ "keep_count": 10 }] },
+                { "id": "delete-older-than", "action": "DELETE", "condition": [{ "older_than": "2592000s", "tag_state": "UNTAGGED" }] },
+                { "id": "old-tagged", "action": "DELETE", "condition": [{ "older_than": "7776000s", "tag_state": "TAGGED" }] },
+            ],
+            "labels": { "team": "platform" },
+        }));
+    }
+
+    #[test]
+    fn artifact_registry_errors() {
+        assert_errors(&registry(json!([])), &[
+            (json!({ "cleanup_policies": [{}] }), "each cleanup policy needs exactly one of keep_most_recent or delete_older_than"),
+            (json!({ "cleanup_policies": [{ "keep_most_recent": 5, "delete_older_than": "86400s" }] }), "needs exactly one of"),
+            (json!({ "cleanup_policies": [{ "delete_older_than": "30d" }] }), "delete_older_than '30d' must be a number of seconds"),
+            (json!({ "cleanup_policies": [{ "delete_older_than": "86400s", "tag_state": "untagged" }] }), "tag_state 'untagged' must be TAGGED, UNTAGGED or ANY"),
+            (json!({ "cleanup_policies": [{ "keep_most_recent": 5, "tag_state": "TAGGED" }] }), "tag_state only applies to delete_older_than policies"),
+            (json!({ "cleanup_policies": [{ "delete_older_than": "86400s" }, { "delete_older_than": "604800s" }] }), "cleanup policy id 'delete-older-than' is used more than once"),
+        ], |r| render(&[], r));
+    }
+
+    fn redis(extra: Json) -> Json {
+        with(json!({ "type": "google_redis_instance", "name": "cache", "memory_size_gb": 5 }), extra)
+    }
+
+    #[test]
+    fn redis_instance() {
+        let tf = render(&[], redis(json!({}))).unwrap();
+        assert_eq!(tf["resource"]["google_redis_instance"]["cache"], json!({
+            "name": "cache", "tier": "BASIC", "memory_size_gb": 5, "auth_enabled": true, "transit_encryption_mode": "SERVER_AUTHENTICATION",
+        }));
+        let tf = render(&[("google_compute_network", "vpc")], redis(json!({
+            "tier": "STANDARD_HA", "region": "europe-west1", "authorized_network_ref": "vpc", "redis_version": "REDIS_7_2",
+            "replica_count": 2, "read_replicas_enabled": true, "persistence": { "rdb_snapshot_period": "SIX_HOURS" }, "labels": { "env": "prod" },
+        }))).unwrap();
+        assert_eq!(tf["resource"]["google_redis_instance"]["cache"], json!({
+            "name": "cache", "tier": "STANDARD_HA", "memory_size_gb": 5, "auth_enabled": true, "transit_encryption_mode": "SERVER_AUTHENTICATION",
+            "region": "europe-west1", "authorized_network": "${google_compute_network.vpc.id}", "redis_version": "REDIS_7_2",
+            "replica_count": 2, "read_replicas_mode": "READ_REPLICAS_ENABLED",
+            "persistence_config": [{ "persistence_mode": "RDB", "rdb_snapshot_period": "SIX_HOURS" }],
+            "labels": { "env": "prod" },
+        }));
+    }
+
+    #[test]
+    fn redis_errors() {
+        assert_errors(&redis(json!({})), &[
+            (json!({ "replica_count": 1 }), "BASIC tier instances cannot have replicas"),
+            (json!({ "read_replicas_enabled": true }), "BASIC tier instances cannot have replicas"),
+            (json!({ "tier": "STANDARD_HA", "replica_count": 0 }), "replica_count 0 must be between 1 and 5"),
+            (json!({ "tier": "STANDARD_HA", "replica_count": 6 }), "replica_count 6 must be between 1 and 5"),
+            (json!({ "persistence": { "rdb_snapshot_period": "DAILY" } }), "rdb_snapshot_period 'DAILY' must be ONE_HOUR"),
+        ], |r| render(&[], r));
+    }
+}
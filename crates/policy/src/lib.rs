@@ -1,24 +1,1762 @@
+use anyhow::{Context, Result};
+use r2iac_cfn::CfnTemplate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
 
+/// Resource types with a built-in encryption (or in-transit TLS) check.
+pub const ENCRYPTION_TYPES: &[&str] = &[
+    "aws_s3_bucket", "aws_kinesis_stream", "aws_efs_file_system", "aws_instance", "aws_ebs_volume", "aws_db_instance",
+    "google_redis_instance", "azurerm_storage_account", "azurerm_linux_web_app", "azurerm_linux_function_app", "azurerm_mssql_server",
+];
 
-use anyhow::Result;
-use serde_json::Value as Json;
+/// Every rule id with its default severity; a policy file may override either way.
+pub const RULES: &[(&str, Severity)] = &[
+    ("encryption", Severity::Deny),
+    ("cmek", Severity::Deny),
+    ("public-access", Severity::Deny),
+    ("versioning", Severity::Deny),
+    ("public-sql", Severity::Deny),
+    ("purge-protection", Severity::Deny),
+    ("password-auth", Severity::Deny),
+    ("acr-admin", Severity::Warn),
+    ("owner-grants", Severity::Deny),
+    ("open-ingress", Severity::Deny),
+    ("required-tags", Severity::Deny),
+    ("denied-resource-types", Severity::Deny),
+    ("allowed-resource-types", Severity::Deny),
+    ("allowed-regions", Severity::Deny),
+    ("naming", Severity::Deny),
+    ("key-rotation", Severity::Deny),
+    ("opa", Severity::Deny),
+    ("blast-radius", Severity::Deny),
+    ("instance-size", Severity::Deny),
+    ("secret-recovery", Severity::Deny),
+    ("ephemeral", Severity::Deny),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum Severity { #[default] Deny, Warn }
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Violation {
+    pub rule: String,
+    pub severity: Severity,
+    /// `<type>.<name>`, or `provider.<name>[.<alias>]` for provider blocks.
+    pub resource: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyReport {
+    pub violations: Vec<Violation>,
+    /// Violations skipped through a resource's `exempt_policies`, kept for auditing.
+    #[serde(default)]
+    pub exempted: Vec<Violation>,
+}
+
+/// Rule ids skipped per `(resource type, logical name)`.
+pub type Exemptions = BTreeMap<(String, String), BTreeSet<String>>;
+
+impl PolicyReport {
+    pub fn denies(&self) -> impl Iterator<Item = &Violation> {
+        self.violations.iter().filter(|v| v.severity == Severity::Deny)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Violation> {
+        self.violations.iter().filter(|v| v.severity == Severity::Warn)
+    }
+
+    /// Moves violations on exempted `(type, name)` pairs into `exempted`.
+    fn exempt(mut self, exemptions: &Exemptions) -> Self {
+        let exempt = |v: &Violation| v.resource.split_once('.').is_some_and(|(t, n)| {
+            exemptions.get(&(t.to_string(), n.to_string())).is_some_and(|r| r.contains(&v.rule))
+        });
+        let exempted;
+        (exempted, self.violations) = self.violations.into_iter().partition(exempt);
+        self.exempted.extend(exempted);
+        self
+    }
+
+    /// Folds another report, e.g. from OPA, into this one.
+    pub fn merge(&mut self, other: PolicyReport) {
+        self.violations.extend(other.violations);
+        self.exempted.extend(other.exempted);
+    }
+
+    /// Moves every violation of `rule` into `exempted`, e.g. once a user confirmed an override.
+    pub fn waive(&mut self, rule: &str) {
+        let waived;
+        (waived, self.violations) = std::mem::take(&mut self.violations).into_iter().partition(|v| v.rule == rule);
+        self.exempted.extend(waived);
+    }
+
+    /// Promotes every warning to a deny, as `--strict` does.
+    pub fn strict(mut self) -> Self {
+        for v in &mut self.violations { v.severity = Severity::Deny; }
+        self
+    }
+
+    /// Orders both lists so serialized reports compare and diff stably.
+    pub fn sorted(mut self) -> Self {
+        self.violations.sort();
+        self.exempted.sort();
+        self
+    }
+
+    /// `-` lines for violations `expected` has and this report lacks, `+` lines for the reverse;
+    /// empty when they match regardless of order.
+    pub fn diff(&self, expected: &PolicyReport) -> Vec<String> {
+        let line = |sign: char, exempt: bool, v: &Violation| {
+            let kind = if exempt { "exempt".to_string() } else { v.severity.to_string() };
+            format!("{} {:<6} {} {}: {}", sign, kind, v.rule, v.resource, v.message)
+        };
+        let mut out = Vec::new();
+        for (exempt, actual, expected) in [(false, &self.violations, &expected.violations), (true, &self.exempted, &expected.exempted)] {
+            let (actual, expected): (BTreeSet<_>, BTreeSet<_>) = (actual.iter().collect(), expected.iter().collect());
+            out.extend(expected.difference(&actual).map(|v| line('-', exempt, v)));
+            out.extend(actual.difference(&expected).map(|v| line('+', exempt, v)));
+        }
+        out
+    }
+
+    /// A SARIF 2.1.0 log: denies are errors, warnings warnings, and exemptions suppressed results.
+    /// Each result's logical location is the resource address; `artifact` (the stack file)
+    /// becomes the physical location code scanning needs to place it.
+    pub fn to_sarif(&self, artifact: Option<&str>) -> Json {
+        let level = |s: Severity| match s { Severity::Deny => "error", Severity::Warn => "warning" };
+        let rules: BTreeMap<&str, Severity> = self.violations.iter().chain(&self.exempted).map(|v| (v.rule.as_str(), v.severity)).collect();
+        let result = |v: &Violation, exempt: bool| {
+            let mut location = serde_json::json!({ "logicalLocations": [{ "fullyQualifiedName": v.resource, "kind": "resource" }] });
+            if let Some(uri) = artifact { location["physicalLocation"] = serde_json::json!({ "artifactLocation": { "uri": uri } }); }
+            let mut r = serde_json::json!({
+                "ruleId": v.rule, "level": level(v.severity), "message": { "text": v.message }, "locations": [location],
+            });
+            if exempt { r["suppressions"] = serde_json::json!([{ "kind": "inSource", "justification": "exempt_policies" }]); }
+            r
+        };
+        serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": {
+                    "name": "r2iac", "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|(id, s)| serde_json::json!({ "id": id, "defaultConfiguration": { "level": level(*s) } })).collect::<Vec<_>>(),
+                } },
+                "results": self.violations.iter().map(|v| result(v, false)).chain(self.exempted.iter().map(|v| result(v, true))).collect::<Vec<_>>(),
+            }],
+        })
+    }
+
+    /// One line such as `3 policy violations (2 deny, 1 warn)`.
+    pub fn summary(&self) -> String {
+        let n = self.violations.len();
+        let mut s = format!("{} policy violation{} ({} deny, {} warn)", n, if n == 1 { "" } else { "s" }, self.denies().count(), self.warnings().count());
+        if !self.exempted.is_empty() { s.push_str(&format!(", {} exempted", self.exempted.len())); }
+        s
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self { Severity::Deny => "deny", Severity::Warn => "warn" })
+    }
+}
+
+/// An aligned table of every violation, denies first and exemptions last, followed by the summary.
+impl fmt::Display for PolicyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows: Vec<_> = self.violations.iter().map(|v| (v.severity.to_string(), v)).collect();
+        rows.sort_by(|(_, a), (_, b)| (a.severity, &a.resource, &a.rule).cmp(&(b.severity, &b.resource, &b.rule)));
+        rows.extend(self.exempted.iter().map(|v| ("exempt".to_string(), v)));
+        let rule_w = rows.iter().map(|(_, v)| v.rule.len()).chain([4]).max().unwrap_or_default();
+        let res_w = rows.iter().map(|(_, v)| v.resource.len()).chain([8]).max().unwrap_or_default();
+        writeln!(f, "{:<8}  {:<rule_w$}  {:<res_w$}  MESSAGE", "SEVERITY", "RULE", "RESOURCE")?;
+        for (severity, v) in rows {
+            writeln!(f, "{:<8}  {:<rule_w$}  {:<res_w$}  {}", severity, v.rule, v.resource, v.message)?;
+        }
+        write!(f, "{}", self.summary())
+    }
+}
+
+/// Rules loaded from a policy file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRules {
+    /// Types whose built-in encryption check applies; every one of [`ENCRYPTION_TYPES`] when unset.
+    #[serde(default)]
+    pub required_encryption: Option<Vec<String>>,
+    /// Keys every taggable resource needs with a non-empty value; AWS provider `default_tags` count.
+    #[serde(default)]
+    pub required_tags: Vec<String>,
+    /// Globs such as `aws_iam_*`.
+    #[serde(default)]
+    pub denied_resource_types: Vec<String>,
+    /// Globs; when set, every other type is rejected and `denied_resource_types` is ignored.
+    #[serde(default)]
+    pub allowed_resource_types: Vec<String>,
+    /// Checked against provider regions and literal resource `region`/`location` values.
+    #[serde(default)]
+    pub allowed_regions: Vec<String>,
+    /// Skip the `key-rotation` checks on KMS keys.
+    #[serde(default)]
+    pub allow_no_key_rotation: bool,
+    /// Resource-type globs to the pattern their `bucket`/`name` (or logical name) must match.
+    #[serde(default)]
+    pub naming: BTreeMap<String, Pattern>,
+    /// Secret Manager secrets must be encrypted with a customer-managed key.
+    #[serde(default)]
+    pub require_cmek: bool,
+    /// `<type>.<attribute>` to the value globs it may take, e.g. `aws_instance.instance_type: [t3.*]`;
+    /// an empty list bans the type.
+    #[serde(default)]
+    pub allowed_sizes: BTreeMap<String, Vec<String>>,
+    /// Most resources one plan may create or update; replacements count here and as destroys.
+    #[serde(default)]
+    pub max_resources_changed: Option<usize>,
+    /// Most resources one plan may destroy.
+    #[serde(default)]
+    pub max_resources_destroyed: Option<usize>,
+    #[serde(default)]
+    pub open_ingress: OpenIngress,
+    /// Checks on arbitrary resource attributes, reported under their own `id`.
+    #[serde(default)]
+    pub custom_rules: Vec<CustomRule>,
+    /// Keyed by rule id; anything unlisted keeps its [`RULES`] default.
+    #[serde(default)]
+    pub severity: BTreeMap<String, Severity>,
+}
+
+/// An AWS rule shared by the Terraform and CloudFormation checks.
+struct AwsRule {
+    rule: &'static str,
+    cfn_type: &'static str,
+    /// e.g. `S3 bucket`.
+    label: &'static str,
+    /// Completes "Policy: <label> '<name>' ...".
+    problem: &'static str,
+    enabled: fn(&Policy) -> bool,
+    /// Whether a CloudFormation resource's properties pass; the Terraform side needs
+    /// cross-resource lookups and lives in `check_tf_json`.
+    cfn_ok: fn(&Json) -> bool,
+}
+
+const S3_ENCRYPTION: AwsRule = AwsRule {
+    rule: "encryption", cfn_type: "AWS::S3::Bucket", label: "S3 bucket",
+    problem: "requires encryption (SSE-S3 or KMS)",
+    enabled: |p| p.requires_encryption("aws_s3_bucket"),
+    cfn_ok: |p| p.pointer("/BucketEncryption/ServerSideEncryptionConfiguration").and_then(|c| c.as_array()).is_some_and(|c| !c.is_empty()),
+};
+const S3_PUBLIC_ACCESS: AwsRule = AwsRule {
+    rule: "public-access", cfn_type: "AWS::S3::Bucket", label: "S3 bucket",
+    problem: "must block public access (pass --allow-public-buckets to override)",
+    enabled: |p| !p.allow_public_buckets,
+    cfn_ok: |p| ["BlockPublicAcls", "BlockPublicPolicy", "IgnorePublicAcls", "RestrictPublicBuckets"]
+        .iter().all(|f| p.pointer(&format!("/PublicAccessBlockConfiguration/{}", f)) == Some(&Json::Bool(true))),
+};
+const S3_VERSIONING: AwsRule = AwsRule {
+    rule: "versioning", cfn_type: "AWS::S3::Bucket", label: "S3 bucket",
+    problem: "requires versioning to be enabled",
+    enabled: |p| p.require_versioning,
+    cfn_ok: |p| p.pointer("/VersioningConfiguration/Status").and_then(|s| s.as_str()) == Some("Enabled"),
+};
+const KINESIS_ENCRYPTION: AwsRule = AwsRule {
+    rule: "encryption", cfn_type: "AWS::Kinesis::Stream", label: "Kinesis stream",
+    problem: "requires KMS encryption",
+    enabled: |p| p.requires_encryption("aws_kinesis_stream"),
+    cfn_ok: |p| p.pointer("/StreamEncryption/EncryptionType").and_then(|e| e.as_str()) == Some("KMS"),
+};
+const EFS_ENCRYPTION: AwsRule = AwsRule {
+    rule: "encryption", cfn_type: "AWS::EFS::FileSystem", label: "EFS file system",
+    problem: "requires encryption",
+    enabled: |p| p.requires_encryption("aws_efs_file_system"),
+    cfn_ok: |p| p.get("Encrypted") == Some(&Json::Bool(true)),
+};
+const EC2_ENCRYPTION: AwsRule = AwsRule {
+    rule: "encryption", cfn_type: "AWS::EC2::Instance", label: "EC2 instance",
+    problem: "requires encrypted root and EBS block devices",
+    enabled: |p| p.requires_encryption("aws_instance"),
+    cfn_ok: |p| !p.get("BlockDeviceMappings").and_then(|b| b.as_array()).into_iter().flatten()
+        .any(|d| d.pointer("/Ebs/Encrypted") == Some(&Json::Bool(false))),
+};
+const EBS_ENCRYPTION: AwsRule = AwsRule {
+    rule: "encryption", cfn_type: "AWS::EC2::Volume", label: "EBS volume",
+    problem: "requires encrypted = true",
+    enabled: |p| p.requires_encryption("aws_ebs_volume"),
+    cfn_ok: |p| p.get("Encrypted") == Some(&Json::Bool(true)),
+};
+const RDS_ENCRYPTION: AwsRule = AwsRule {
+    rule: "encryption", cfn_type: "AWS::RDS::DBInstance", label: "RDS instance",
+    problem: "requires storage_encrypted = true",
+    enabled: |p| p.requires_encryption("aws_db_instance"),
+    cfn_ok: |p| p.get("StorageEncrypted") == Some(&Json::Bool(true)),
+};
+const KMS_ROTATION: AwsRule = AwsRule {
+    rule: "key-rotation", cfn_type: "AWS::KMS::Key", label: "KMS key",
+    problem: "must enable key rotation",
+    enabled: |p| !p.rules.allow_no_key_rotation,
+    cfn_ok: |p| p.get("KeySpec").and_then(|s| s.as_str()).is_some_and(|s| s != "SYMMETRIC_DEFAULT")
+        || p.get("EnableKeyRotation") == Some(&Json::Bool(true)),
+};
+const KMS_DELETION_WINDOW: AwsRule = AwsRule {
+    rule: "key-rotation", cfn_type: "AWS::KMS::Key", label: "KMS key",
+    problem: "must keep a deletion window of at least 7 days",
+    enabled: |p| !p.rules.allow_no_key_rotation,
+    cfn_ok: |p| p.get("PendingWindowInDays").and_then(|d| d.as_u64()).is_none_or(|d| d >= 7),
+};
+const AWS_RULES: &[AwsRule] = &[
+    S3_ENCRYPTION, S3_PUBLIC_ACCESS, S3_VERSIONING, KINESIS_ENCRYPTION, EFS_ENCRYPTION,
+    EC2_ENCRYPTION, EBS_ENCRYPTION, RDS_ENCRYPTION, KMS_ROTATION, KMS_DELETION_WINDOW,
+];
+
+/// A regex compiled when the policy file loads, so a bad pattern fails early.
+#[derive(Debug, Clone)]
+pub struct Pattern(pub Regex);
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        Regex::new(&s).map(Pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Port lists for the `open-ingress` rule.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct OpenIngress {
+    /// Ports security groups and firewalls may open to the whole internet.
+    pub allowed_ports: BTreeSet<u32>,
+    /// Ports an NSG must not open to any source.
+    pub sensitive_ports: BTreeSet<u32>,
+}
+
+impl Default for OpenIngress {
+    fn default() -> Self {
+        Self { allowed_ports: [80, 443].into(), sensitive_ports: [22, 3389, 5432, 3306].into() }
+    }
+}
+
+impl OpenIngress {
+    /// Whether every port of `from..=to` may be world-open.
+    fn allows(&self, (from, to): (u32, u32)) -> bool {
+        self.allowed_ports.range(from..=to).count() as u32 == to.saturating_sub(from) + 1
+    }
+
+    fn sensitive_in(&self, (from, to): (u32, u32)) -> Vec<u32> {
+        self.sensitive_ports.range(from..=to).copied().collect()
+    }
+}
+
+/// `22`, `8000-8080` or `*`, as GCP and Azure spell ports.
+fn port_range(s: &str) -> Option<(u32, u32)> {
+    if s == "*" { return Some((0, 65535)); }
+    match s.split_once('-') {
+        Some((a, b)) => Some((a.trim().parse().ok()?, b.trim().parse().ok()?)),
+        None => s.trim().parse().ok().map(|p| (p, p)),
+    }
+}
+
+fn show_ports((from, to): (u32, u32)) -> String {
+    match (from, to) {
+        (0, 65535) => "all ports".to_string(),
+        (f, t) if f == t => format!("port {}", f),
+        (f, t) => format!("ports {}-{}", f, t),
+    }
+}
+
+/// A policy-file check of one attribute on every resource of matching type.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRule {
+    pub id: String,
+    /// Glob such as `aws_db_*`.
+    pub resource_type: String,
+    /// A JSON pointer (`/settings/0/tier`) or dotted path (`settings.tier`); dotted paths
+    /// step through single-element block arrays on their own.
+    pub path: String,
+    pub op: CustomOp,
+    /// Required by every op except `present` and `absent`; a regex for `matches`.
+    #[serde(default)]
+    pub value: Option<Json>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Replaces the generated description of what the rule expects.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomOp { Eq, Ne, Gte, Lte, Present, Absent, Matches }
+
+impl CustomRule {
+    fn validate(&self) -> Result<()> {
+        if RULES.iter().any(|(r, _)| *r == self.id) {
+            anyhow::bail!("custom rule '{}': id clashes with a built-in rule", self.id);
+        }
+        match (self.op, &self.value) {
+            (CustomOp::Present | CustomOp::Absent, _) => {}
+            (op, None) => anyhow::bail!("custom rule '{}': op {:?} needs a value", self.id, op),
+            (CustomOp::Gte | CustomOp::Lte, Some(v)) if !v.is_number() => anyhow::bail!("custom rule '{}': {:?} needs a numeric value", self.id, self.op),
+            (CustomOp::Matches, Some(v)) => {
+                let re = v.as_str().with_context(|| format!("custom rule '{}': matches needs a string value", self.id))?;
+                Regex::new(re).with_context(|| format!("custom rule '{}'", self.id))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `None` when the rule holds; otherwise what was expected, used in the violation message.
+    fn failure(&self, actual: Option<&Json>) -> Option<String> {
+        let value = self.value.as_ref().unwrap_or(&Json::Null);
+        // 7 and 7.0 deserialize to different numbers but should compare equal.
+        let eq = |a: &Json| match (a.as_f64(), value.as_f64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == value,
+        };
+        let ok = match (self.op, actual) {
+            (CustomOp::Present, a) => a.is_some(),
+            (CustomOp::Absent, a) => a.is_none(),
+            (CustomOp::Ne, a) => !a.is_some_and(eq),
+            (_, None) => false,
+            (CustomOp::Eq, Some(a)) => eq(a),
+            (CustomOp::Gte, Some(a)) => a.as_f64().zip(value.as_f64()).is_some_and(|(a, b)| a >= b),
+            (CustomOp::Lte, Some(a)) => a.as_f64().zip(value.as_f64()).is_some_and(|(a, b)| a <= b),
+            (CustomOp::Matches, Some(a)) => {
+                let text = a.as_str().map(str::to_string).unwrap_or_else(|| a.to_string());
+                value.as_str().and_then(|re| Regex::new(re).ok()).is_some_and(|re| re.is_match(&text))
+            }
+        };
+        if ok { return None; }
+        Some(self.message.clone().unwrap_or_else(|| match self.op {
+            CustomOp::Present => format!("{} must be set", self.path),
+            CustomOp::Absent => format!("{} must not be set", self.path),
+            CustomOp::Eq => format!("{} must equal {}", self.path, value),
+            CustomOp::Ne => format!("{} must not equal {}", self.path, value),
+            CustomOp::Gte => format!("{} must be at least {}", self.path, value),
+            CustomOp::Lte => format!("{} must be at most {}", self.path, value),
+            CustomOp::Matches => format!("{} must match {}", self.path, value),
+        }))
+    }
+}
+
+/// Resolves a [`CustomRule::path`]; anything that does not resolve is `None`.
+fn lookup<'a>(body: &'a Json, path: &str) -> Option<&'a Json> {
+    if path.starts_with('/') { return body.pointer(path); }
+    path.split('.').try_fold(body, |v, seg| match (v, seg.parse::<usize>()) {
+        (Json::Array(a), Ok(i)) => a.get(i),
+        (Json::Array(a), Err(_)) if a.len() == 1 => a[0].get(seg),
+        (v, _) => v.get(seg),
+    })
+}
+
+/// Types that take no `tags`/`labels` argument, on top of [`UNTAGGABLE_SUFFIXES`].
+const UNTAGGABLE: &[&str] = &[
+    "aws_iam_role_policy", "aws_iam_user_policy", "aws_iam_group_policy",
+    "aws_s3_bucket_policy", "aws_s3_bucket_public_access_block", "aws_s3_bucket_versioning",
+    "aws_s3_bucket_server_side_encryption_configuration", "aws_s3_bucket_lifecycle_configuration",
+    "aws_s3_bucket_notification", "aws_s3_bucket_ownership_controls", "aws_kms_alias", "aws_route",
+    "aws_security_group_rule", "aws_lambda_permission", "aws_sns_topic_subscription", "aws_sqs_queue_policy",
+    "aws_lb_target_group_attachment", "aws_route53_record", "aws_ecr_lifecycle_policy",
+    "aws_cloudwatch_event_target", "aws_efs_mount_target", "aws_apigatewayv2_route", "aws_apigatewayv2_integration",
+    "aws_cloudfront_origin_access_control", "aws_secretsmanager_secret_version",
+    "google_compute_firewall", "google_compute_network", "google_compute_subnetwork", "google_compute_route",
+    "google_service_account", "google_kms_key_ring", "google_secret_manager_secret_version",
+    "google_dns_record_set", "google_container_node_pool", "google_sql_database_instance", "google_sql_database",
+    "azurerm_subnet", "azurerm_role_assignment", "azurerm_monitor_diagnostic_setting",
+    "azurerm_cosmosdb_sql_database", "azurerm_key_vault_access_policy",
+];
+const UNTAGGABLE_SUFFIXES: &[&str] = &["_policy_attachment", "_iam_member", "_iam_binding", "_iam_policy", "_association"];
+
+fn taggable(type_name: &str) -> bool {
+    !UNTAGGABLE.contains(&type_name) && !UNTAGGABLE_SUFFIXES.iter().any(|s| type_name.ends_with(s))
+}
+
+/// `default_tags` of the AWS provider a resource renders against, aliased or not.
+fn aws_default_tags<'a>(tf: &'a Json, body: &Json) -> Option<&'a serde_json::Map<String, Json>> {
+    let alias = body.get("provider").and_then(|p| p.as_str()).and_then(|p| p.strip_prefix("aws."));
+    let providers = match tf.pointer("/provider/aws")? {
+        Json::Array(a) => a.iter().collect(),
+        p => vec![p],
+    };
+    providers.into_iter()
+        .find(|p| p.get("alias").and_then(|a| a.as_str()) == alias)?
+        .pointer("/default_tags/0/tags")?.as_object()
+}
+
+/// `*` matches any run of characters; everything else is literal.
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = value.strip_prefix(parts.next().unwrap_or_default()) else { return false };
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for p in middle {
+        match rest.find(p) {
+            Some(i) => rest = &rest[i + p.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
 
 /// Simple plan-time checks (expand later).
-pub struct Policy { pub allow_unencrypted: bool }
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    pub rules: PolicyRules,
+    /// Every S3 bucket must have an `aws_s3_bucket_versioning` with status Enabled.
+    pub require_versioning: bool,
+    /// Buckets may go without a fully-enabled public access block.
+    pub allow_public_buckets: bool,
+    /// Security groups, firewalls and NSGs may admit the internet beyond [`OpenIngress`].
+    pub allow_open_ingress: bool,
+    /// IAM members and bindings may grant `roles/owner`, and role assignments subscription-wide Owner.
+    pub allow_owner_grants: bool,
+    /// Cloud SQL instances, Azure SQL servers and Cosmos DB accounts may be reachable publicly.
+    pub allow_public_sql: bool,
+    /// Key vaults may be created without purge protection.
+    pub allow_no_purge_protection: bool,
+    /// Linux VMs may accept password logins.
+    pub allow_password_auth: bool,
+}
 
 impl Policy {
-    pub fn new(allow_unencrypted: bool) -> Self { Self { allow_unencrypted } }
+    pub fn new(allow_unencrypted: bool) -> Self {
+        let mut policy = Self::default();
+        if allow_unencrypted { policy.rules.required_encryption = Some(vec![]); }
+        policy
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("read policy file {}", path.display()))?;
+        let rules: PolicyRules = serde_yaml::from_str(&text).with_context(|| format!("policy file {}", path.display()))?;
+        for t in rules.required_encryption.iter().flatten() {
+            if !ENCRYPTION_TYPES.contains(&t.as_str()) {
+                anyhow::bail!("policy file {}: required_encryption: no built-in encryption check for '{}'", path.display(), t);
+            }
+        }
+        for rule in rules.severity.keys() {
+            if !RULES.iter().any(|(r, _)| r == rule) {
+                anyhow::bail!("policy file {}: severity: unknown rule '{}'", path.display(), rule);
+            }
+        }
+        for key in rules.allowed_sizes.keys() {
+            if !key.split_once('.').is_some_and(|(t, a)| !t.is_empty() && !a.is_empty()) {
+                anyhow::bail!("policy file {}: allowed_sizes: '{}' must be <resource type>.<attribute>", path.display(), key);
+            }
+        }
+        let mut ids = BTreeSet::new();
+        for c in &rules.custom_rules {
+            c.validate().with_context(|| format!("policy file {}", path.display()))?;
+            if !ids.insert(&c.id) {
+                anyhow::bail!("policy file {}: custom rule '{}' is defined twice", path.display(), c.id);
+            }
+        }
+        Ok(Self { rules, ..Self::default() })
+    }
+
+    /// Rejects exemptions naming a rule that does not exist, built-in or custom.
+    pub fn check_exemptions(&self, exemptions: &Exemptions) -> Result<()> {
+        for ((type_name, name), rules) in exemptions {
+            let known = |r: &String| RULES.iter().any(|(id, _)| id == r) || self.rules.custom_rules.iter().any(|c| &c.id == r);
+            if let Some(rule) = rules.iter().find(|r| !known(r)) {
+                anyhow::bail!("{}.{}: exempt_policies: unknown rule '{}'", type_name, name, rule);
+            }
+        }
+        Ok(())
+    }
 
-    pub fn check_tf_json(&self, tf: &Json) -> Result<()> {
+    fn requires_encryption(&self, type_name: &str) -> bool {
+        self.rules.required_encryption.as_ref().is_none_or(|t| t.iter().any(|t| t == type_name))
+    }
+
+    /// The policy file's severity for `rule`, else its built-in default.
+    pub fn severity(&self, rule: &str) -> Severity {
+        self.rules.severity.get(rule).copied()
+            .or_else(|| RULES.iter().find(|(r, _)| *r == rule).map(|(_, s)| *s))
+            .unwrap_or_default()
+    }
+
+    fn flag(&self, report: &mut PolicyReport, rule: &'static str, resource: String, message: String) {
+        report.violations.push(Violation { rule: rule.to_string(), severity: self.severity(rule), resource, message });
+    }
+
+    fn flag_aws(&self, report: &mut PolicyReport, r: &AwsRule, resource: String, name: &str) {
+        self.flag(report, r.rule, resource, format!("Policy: {} '{}' {}.", r.label, name, r.problem));
+    }
+
+    /// The built-in AWS rules and the resource-type lists, applied to a CloudFormation
+    /// template. Violations are reported against `<CFN type>.<logical id>`.
+    pub fn check_cfn_template(&self, tpl: &CfnTemplate, exemptions: &Exemptions) -> PolicyReport {
+        let mut report = PolicyReport::default();
+        for (logical_id, res) in &tpl.resources {
+            let resource = format!("{}.{}", res.type_name, logical_id);
+            // Accept both the bare property map and one nested under `Properties`.
+            let props = res.properties.get("Properties").filter(|p| p.is_object()).cloned()
+                .unwrap_or_else(|| Json::Object(res.properties.clone()));
+            for r in AWS_RULES.iter().filter(|r| r.cfn_type == res.type_name && (r.enabled)(self)) {
+                if !(r.cfn_ok)(&props) {
+                    self.flag(&mut report, r.rule, resource.clone(), format!("Policy: {} '{}' ({}) {}.", r.label, logical_id, r.cfn_type, r.problem));
+                }
+            }
+            let denied = if !self.rules.allowed_resource_types.is_empty() {
+                (!self.rules.allowed_resource_types.iter().any(|p| glob_match(p, &res.type_name))).then_some("allowed-resource-types")
+            } else {
+                self.rules.denied_resource_types.iter().any(|p| glob_match(p, &res.type_name)).then_some("denied-resource-types")
+            };
+            if let Some(rule) = denied {
+                self.flag(&mut report, rule, resource.clone(), format!("Policy: {} '{}' is a resource type not permitted by {}.", res.type_name, logical_id, rule));
+            }
+        }
+        report.exempt(exemptions)
+    }
+
+    pub fn check_tf_json(&self, tf: &Json, exemptions: &Exemptions) -> PolicyReport {
+        let mut report = PolicyReport::default();
         if let Some(res) = tf.get("resource").and_then(|r| r.get("aws_s3_bucket")) {
-            for (_name, bucket) in res.as_object().unwrap().iter() {
+            let sse = tf.get("resource").and_then(|r| r.get("aws_s3_bucket_server_side_encryption_configuration"));
+            for (name, bucket) in res.as_object().unwrap().iter() {
+                let bucket_ref = Json::String(format!("${{aws_s3_bucket.{}.id}}", name));
+                // The inline shapes predate provider v5 but are still accepted from hand-written JSON.
                 let has_enc = bucket.get("bucket_encryption").is_some()
-                    || bucket.get("server_side_encryption_configuration").is_some();
-                if !has_enc && !self.allow_unencrypted {
-                    anyhow::bail!("Policy: S3 bucket requires encryption (SSE-S3 or KMS).");
+                    || bucket.get("server_side_encryption_configuration").is_some()
+                    || sse.and_then(|s| s.as_object()).is_some_and(|s| s.values().any(|s| {
+                        s.get("bucket") == Some(&bucket_ref) && s.get("rule").is_some()
+                    }));
+                if !has_enc && (S3_ENCRYPTION.enabled)(self) {
+                    self.flag_aws(&mut report, &S3_ENCRYPTION, format!("aws_s3_bucket.{}", name), name);
+                }
+            }
+            if (S3_PUBLIC_ACCESS.enabled)(self) {
+                let blocks = tf.get("resource").and_then(|r| r.get("aws_s3_bucket_public_access_block"));
+                for name in res.as_object().unwrap().keys() {
+                    let bucket_ref = Json::String(format!("${{aws_s3_bucket.{}.id}}", name));
+                    let blocked = blocks.and_then(|b| b.as_object()).is_some_and(|b| b.values().any(|b| {
+                        b.get("bucket") == Some(&bucket_ref)
+                            && ["block_public_acls", "block_public_policy", "ignore_public_acls", "restrict_public_buckets"]
+                                .iter().all(|f| b.get(*f) == Some(&Json::Bool(true)))
+                    }));
+                    if !blocked {
+                        self.flag_aws(&mut report, &S3_PUBLIC_ACCESS, format!("aws_s3_bucket.{}", name), name);
+                    }
+                }
+            }
+            if (S3_VERSIONING.enabled)(self) {
+                let versioning = tf.get("resource").and_then(|r| r.get("aws_s3_bucket_versioning"));
+                for name in res.as_object().unwrap().keys() {
+                    let bucket_ref = Json::String(format!("${{aws_s3_bucket.{}.id}}", name));
+                    let enabled = versioning.and_then(|v| v.as_object()).is_some_and(|v| v.values().any(|v| {
+                        v.get("bucket") == Some(&bucket_ref)
+                            && v.pointer("/versioning_configuration/status").and_then(|s| s.as_str()) == Some("Enabled")
+                    }));
+                    if !enabled {
+                        self.flag_aws(&mut report, &S3_VERSIONING, format!("aws_s3_bucket.{}", name), name);
+                    }
                 }
             }
         }
-        Ok(())
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("google_storage_bucket")) {
+            for (name, bucket) in res.as_object().unwrap().iter() {
+                // GCS always encrypts at rest; an encryption block without a key is a broken CMEK setup.
+                let blocks = match bucket.get("encryption") {
+                    Some(Json::Array(a)) => a.iter().collect(),
+                    Some(e) => vec![e],
+                    None => vec![],
+                };
+                if blocks.iter().any(|e| e.get("default_kms_key_name").and_then(|k| k.as_str()).is_none_or(|k| k.is_empty())) {
+                    self.flag(&mut report, "cmek", format!("google_storage_bucket.{}", name), format!("Policy: GCS bucket '{}' has an encryption block without default_kms_key_name.", name));
+                }
+                let enforced = bucket.get("public_access_prevention").and_then(|p| p.as_str()) == Some("enforced")
+                    && bucket.get("uniform_bucket_level_access") == Some(&Json::Bool(true));
+                if !enforced && !self.allow_public_buckets {
+                    self.flag(&mut report, "public-access", format!("google_storage_bucket.{}", name), format!("Policy: GCS bucket '{}' must enforce public access prevention and uniform bucket-level access (pass --allow-public-buckets to override).", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_storage_account")) {
+            for (name, acct) in res.as_object().unwrap().iter() {
+                // azurerm v4 renamed both flags; read either spelling and fall back to provider defaults.
+                let flag = |new: &str, old: &str| acct.get(new).or_else(|| acct.get(old)).and_then(|v| v.as_bool());
+                let https = flag("https_traffic_only_enabled", "enable_https_traffic_only").unwrap_or(true);
+                let tls = acct.get("min_tls_version").and_then(|t| t.as_str()).unwrap_or("TLS1_2");
+                if (!https || tls < "TLS1_2") && self.requires_encryption("azurerm_storage_account") {
+                    self.flag(&mut report, "encryption", format!("azurerm_storage_account.{}", name), format!("Policy: storage account '{}' must require HTTPS and TLS1_2 (found https_only={}, min_tls_version={}).", name, https, tls));
+                }
+                let public = flag("allow_nested_items_to_be_public", "allow_blob_public_access").unwrap_or(true);
+                if public && !self.allow_public_buckets {
+                    self.flag(&mut report, "public-access", format!("azurerm_storage_account.{}", name), format!("Policy: storage account '{}' allows public blob access (pass --allow-public-buckets to override).", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("google_sql_database_instance")) {
+            for (name, inst) in res.as_object().unwrap().iter() {
+                // ipv4_enabled defaults to true, so only an explicit false counts as private.
+                let ip = inst.pointer("/settings/0/ip_configuration/0").or_else(|| inst.pointer("/settings/ip_configuration"));
+                let public = ip.and_then(|i| i.get("ipv4_enabled")) != Some(&Json::Bool(false));
+                if public && !self.allow_public_sql {
+                    self.flag(&mut report, "public-sql", format!("google_sql_database_instance.{}", name), format!("Policy: Cloud SQL instance '{}' has a public IP (pass --allow-public-sql to override).", name));
+                }
+            }
+        }
+        if !self.allow_public_buckets {
+            for type_name in ["google_storage_bucket_iam_member", "google_storage_bucket_iam_binding"] {
+                let Some(res) = tf.get("resource").and_then(|r| r.get(type_name)) else { continue };
+                for (name, grant) in res.as_object().unwrap().iter() {
+                    let members = grant.get("member").into_iter()
+                        .chain(grant.get("members").and_then(|m| m.as_array()).into_iter().flatten());
+                    if let Some(m) = members.filter_map(|m| m.as_str()).find(|m| *m == "allUsers" || *m == "allAuthenticatedUsers") {
+                        self.flag(&mut report, "public-access", format!("{}.{}", type_name, name), format!("Policy: {} '{}' makes a GCS bucket public via {} (pass --allow-public-buckets to override).", type_name, name, m));
+                    }
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("google_redis_instance")) {
+            for (name, redis) in res.as_object().unwrap().iter() {
+                // Both default to off on the API side.
+                let auth = redis.get("auth_enabled") == Some(&Json::Bool(true));
+                let tls = redis.get("transit_encryption_mode").and_then(|m| m.as_str()) == Some("SERVER_AUTHENTICATION");
+                let secured = auth && tls;
+                if !secured && self.requires_encryption("google_redis_instance") {
+                    self.flag(&mut report, "encryption", format!("google_redis_instance.{}", name), format!("Policy: Memorystore instance '{}' requires auth_enabled and SERVER_AUTHENTICATION transit encryption.", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_key_vault")) {
+            for (name, vault) in res.as_object().unwrap().iter() {
+                if vault.get("purge_protection_enabled") != Some(&Json::Bool(true)) && !self.allow_no_purge_protection {
+                    self.flag(&mut report, "purge-protection", format!("azurerm_key_vault.{}", name), format!("Policy: key vault '{}' requires purge protection (pass --allow-no-purge-protection to override).", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_linux_virtual_machine")) {
+            for (name, vm) in res.as_object().unwrap().iter() {
+                // The provider defaults to keys only, so only an explicit false opens passwords.
+                if vm.get("disable_password_authentication") == Some(&Json::Bool(false)) && !self.allow_password_auth {
+                    self.flag(&mut report, "password-auth", format!("azurerm_linux_virtual_machine.{}", name), format!("Policy: Linux VM '{}' enables password authentication (pass --allow-password-auth to override).", name));
+                }
+            }
+        }
+        for type_name in ["azurerm_linux_web_app", "azurerm_linux_function_app"] {
+            let Some(res) = tf.get("resource").and_then(|r| r.get(type_name)) else { continue };
+            for (name, app) in res.as_object().unwrap().iter() {
+                if app.get("https_only") != Some(&Json::Bool(true)) && self.requires_encryption(type_name) {
+                    self.flag(&mut report, "encryption", format!("{}.{}", type_name, name), format!("Policy: {} '{}' must set https_only.", type_name, name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_container_registry")) {
+            for (name, acr) in res.as_object().unwrap().iter() {
+                // Shared admin credentials are an anti-pattern but not a hard failure.
+                if acr.get("admin_enabled") == Some(&Json::Bool(true)) {
+                    self.flag(&mut report, "acr-admin", format!("azurerm_container_registry.{}", name), format!("Policy: container registry '{}' enables the admin user; prefer role assignments.", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_cosmosdb_account")) {
+            for (name, acct) in res.as_object().unwrap().iter() {
+                // An account without the flag accepts traffic from any network.
+                if acct.get("public_network_access_enabled") != Some(&Json::Bool(false)) && !self.allow_public_sql {
+                    self.flag(&mut report, "public-sql", format!("azurerm_cosmosdb_account.{}", name), format!("Policy: Cosmos DB account '{}' allows public network access (pass --allow-public-sql to override).", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_role_assignment")) {
+            for (name, ra) in res.as_object().unwrap().iter() {
+                let scope = ra.get("scope").and_then(|s| s.as_str()).unwrap_or_default();
+                let subscription = scope.strip_prefix("/subscriptions/").is_some_and(|s| !s.trim_end_matches('/').contains('/'))
+                    || scope.starts_with("${data.azurerm_subscription.");
+                if subscription && ra.get("role_definition_name").and_then(|r| r.as_str()) == Some("Owner") && !self.allow_owner_grants {
+                    self.flag(&mut report, "owner-grants", format!("azurerm_role_assignment.{}", name), format!("Policy: role assignment '{}' grants Owner at subscription scope (pass --allow-owner-grants to override).", name));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("azurerm_mssql_server")) {
+            for (name, server) in res.as_object().unwrap().iter() {
+                // Firewall rules still apply, but the server gets a public endpoint unless this is off.
+                if server.get("public_network_access_enabled") != Some(&Json::Bool(false)) && !self.allow_public_sql {
+                    self.flag(&mut report, "public-sql", format!("azurerm_mssql_server.{}", name), format!("Policy: Azure SQL server '{}' allows public network access (pass --allow-public-sql to override).", name));
+                }
+                let tls = server.get("minimum_tls_version").and_then(|t| t.as_str()).unwrap_or("1.2");
+                if tls != "1.2" && self.requires_encryption("azurerm_mssql_server") {
+                    self.flag(&mut report, "encryption", format!("azurerm_mssql_server.{}", name), format!("Policy: Azure SQL server '{}' must require TLS 1.2 (found '{}').", name, tls));
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("aws_kinesis_stream")) {
+            for (name, stream) in res.as_object().unwrap().iter() {
+                let kms = stream.get("encryption_type").and_then(|e| e.as_str()) == Some("KMS");
+                if !kms && (KINESIS_ENCRYPTION.enabled)(self) {
+                    self.flag_aws(&mut report, &KINESIS_ENCRYPTION, format!("aws_kinesis_stream.{}", name), name);
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("aws_efs_file_system")) {
+            for (name, fs) in res.as_object().unwrap().iter() {
+                // EFS defaults to unencrypted, so a missing flag counts as off.
+                let encrypted = fs.get("encrypted") == Some(&Json::Bool(true));
+                if !encrypted && (EFS_ENCRYPTION.enabled)(self) {
+                    self.flag_aws(&mut report, &EFS_ENCRYPTION, format!("aws_efs_file_system.{}", name), name);
+                }
+            }
+        }
+        if let Some(res) = tf.get("resource").and_then(|r| r.get("aws_instance")) {
+            for (name, inst) in res.as_object().unwrap().iter() {
+                // Nested blocks may be rendered as a single object or a list of them.
+                let devices = |block: &str| match inst.get(block) {
+                    Some(Json::Array(a)) => a.iter().collect(),
+                    Some(d) => vec![d],
+                    None => vec![],
+                };
+                // A root volume left unspecified is created unencrypted.
+                let root = devices("root_block_device");
+                let unencrypted = root.is_empty()
+                    || root.iter().chain(&devices("ebs_block_device")).any(|d| d.get("encrypted") != Some(&Json::Bool(true)));
+                if unencrypted && (EC2_ENCRYPTION.enabled)(self) {
+                    self.flag_aws(&mut report, &EC2_ENCRYPTION, format!("aws_instance.{}", name), name);
+                }
+            }
+        }
+        for (rule, type_name, attr) in [(&EBS_ENCRYPTION, "aws_ebs_volume", "encrypted"), (&RDS_ENCRYPTION, "aws_db_instance", "storage_encrypted")] {
+            if !(rule.enabled)(self) { continue; }
+            for (name, body) in tf.pointer(&format!("/resource/{}", type_name)).and_then(|r| r.as_object()).into_iter().flatten() {
+                // Both default to unencrypted when the flag is missing.
+                if body.get(attr) != Some(&Json::Bool(true)) {
+                    self.flag_aws(&mut report, rule, format!("{}.{}", type_name, name), name);
+                }
+            }
+        }
+        if (KMS_ROTATION.enabled)(self) {
+            for (name, key) in tf.pointer("/resource/aws_kms_key").and_then(|r| r.as_object()).into_iter().flatten() {
+                // Only symmetric encryption keys support automatic rotation.
+                let symmetric = key.get("customer_master_key_spec").or_else(|| key.get("key_spec"))
+                    .and_then(|s| s.as_str()).is_none_or(|s| s == "SYMMETRIC_DEFAULT");
+                if symmetric && key.get("enable_key_rotation") != Some(&Json::Bool(true)) {
+                    self.flag_aws(&mut report, &KMS_ROTATION, format!("aws_kms_key.{}", name), name);
+                }
+                if key.get("deletion_window_in_days").and_then(|d| d.as_u64()).is_some_and(|d| d < 7) {
+                    self.flag_aws(&mut report, &KMS_DELETION_WINDOW, format!("aws_kms_key.{}", name), name);
+                }
+            }
+            for (name, key) in tf.pointer("/resource/google_kms_crypto_key").and_then(|r| r.as_object()).into_iter().flatten() {
+                let symmetric = key.get("purpose").and_then(|p| p.as_str()).is_none_or(|p| p == "ENCRYPT_DECRYPT");
+                let rotates = key.get("rotation_period").and_then(|p| p.as_str()).is_some_and(|p| !p.is_empty());
+                if symmetric && !rotates {
+                    self.flag(&mut report, "key-rotation", format!("google_kms_crypto_key.{}", name), format!("Policy: KMS crypto key '{}' must set rotation_period.", name));
+                }
+            }
+        }
+        if !self.allow_open_ingress {
+            let oi = &self.rules.open_ingress;
+            let allowed = oi.allowed_ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+            let hint = format!("world-open ports allowed: {}; pass --allow-open-ingress to override", if allowed.is_empty() { "none" } else { &allowed });
+            let strs = |v: Option<&Json>| v.and_then(|v| v.as_array()).into_iter().flatten().filter_map(|s| s.as_str()).map(str::to_string).collect::<Vec<_>>();
+            let world = |cidrs: &[String]| cidrs.iter().find(|c| *c == "0.0.0.0/0" || *c == "::/0").cloned();
+            // Inline `ingress` blocks and standalone ingress rules share the attribute names.
+            let mut aws_rules = Vec::new();
+            for (name, sg) in tf.pointer("/resource/aws_security_group").and_then(|r| r.as_object()).into_iter().flatten() {
+                for (i, rule) in sg.get("ingress").and_then(|r| r.as_array()).into_iter().flatten().enumerate() {
+                    aws_rules.push((format!("aws_security_group.{}", name), format!("security group '{}' ingress rule {}", name, i), rule));
+                }
+            }
+            for (name, rule) in tf.pointer("/resource/aws_security_group_rule").and_then(|r| r.as_object()).into_iter().flatten() {
+                if rule.get("type").and_then(|t| t.as_str()) == Some("ingress") {
+                    aws_rules.push((format!("aws_security_group_rule.{}", name), format!("security group rule '{}'", name), rule));
+                }
+            }
+            for (resource, what, rule) in aws_rules {
+                let cidrs = [strs(rule.get("cidr_blocks")), strs(rule.get("ipv6_cidr_blocks"))].concat();
+                let Some(cidr) = world(&cidrs) else { continue };
+                let protocol = rule.get("protocol").and_then(|p| p.as_str()).unwrap_or("-1");
+                let port = |k: &str| rule.get(k).and_then(|p| p.as_u64()).unwrap_or_default() as u32;
+                let (range, proto) = match protocol {
+                    "-1" | "all" => ((0, 65535), "all traffic".to_string()),
+                    // ICMP type/code travel in the port fields and are not ports.
+                    "icmp" | "1" | "icmpv6" | "58" => continue,
+                    p => ((port("from_port"), port("to_port")), format!("{} {}", p, show_ports((port("from_port"), port("to_port"))))),
+                };
+                if !oi.allows(range) {
+                    self.flag(&mut report, "open-ingress", resource, format!("Policy: {} opens {} to {} ({}).", what, proto, cidr, hint));
+                }
+            }
+            for (name, fw) in tf.pointer("/resource/google_compute_firewall").and_then(|r| r.as_object()).into_iter().flatten() {
+                let ingress = fw.get("direction").and_then(|d| d.as_str()).unwrap_or("INGRESS") == "INGRESS";
+                let Some(cidr) = world(&strs(fw.get("source_ranges"))).filter(|_| ingress) else { continue };
+                for allow in fw.get("allow").and_then(|a| a.as_array()).into_iter().flatten() {
+                    let protocol = allow.get("protocol").and_then(|p| p.as_str()).unwrap_or("all");
+                    if protocol == "icmp" { continue; }
+                    // No ports means every port.
+                    let ports = strs(allow.get("ports"));
+                    let ranges = if ports.is_empty() { vec![Some((0, 65535))] } else { ports.iter().map(|p| port_range(p)).collect() };
+                    for range in ranges.into_iter().map(|r| r.unwrap_or((0, 65535))).filter(|r| !oi.allows(*r)) {
+                        self.flag(&mut report, "open-ingress", format!("google_compute_firewall.{}", name),
+                            format!("Policy: firewall '{}' allows {} {} from {} ({}).", name, protocol, show_ports(range), cidr, hint));
+                    }
+                }
+            }
+            let mut nsg_rules = Vec::new();
+            for (name, nsg) in tf.pointer("/resource/azurerm_network_security_group").and_then(|r| r.as_object()).into_iter().flatten() {
+                for rule in nsg.get("security_rule").and_then(|r| r.as_array()).into_iter().flatten() {
+                    nsg_rules.push((format!("azurerm_network_security_group.{}", name), format!("network security group '{}'", name), rule));
+                }
+            }
+            for (name, rule) in tf.pointer("/resource/azurerm_network_security_rule").and_then(|r| r.as_object()).into_iter().flatten() {
+                nsg_rules.push((format!("azurerm_network_security_rule.{}", name), format!("network security rule '{}'", name), rule));
+            }
+            for (resource, what, rule) in nsg_rules {
+                let is = |k: &str, v: &str| rule.get(k).and_then(|d| d.as_str()) == Some(v);
+                if !is("direction", "Inbound") || !is("access", "Allow") { continue; }
+                let sources = [rule.get("source_address_prefix").and_then(|s| s.as_str()).map(str::to_string).into_iter().collect(), strs(rule.get("source_address_prefixes"))].concat();
+                if !sources.iter().any(|s| matches!(s.as_str(), "*" | "0.0.0.0/0" | "Internet")) { continue; }
+                let ports = [rule.get("destination_port_range").and_then(|s| s.as_str()).map(str::to_string).into_iter().collect(), strs(rule.get("destination_port_ranges"))].concat();
+                let hit: BTreeSet<u32> = ports.iter().filter_map(|p| port_range(p)).flat_map(|r| oi.sensitive_in(r)).collect();
+                if !hit.is_empty() {
+                    let rule_name = rule.get("name").and_then(|n| n.as_str()).unwrap_or("?");
+                    let plural = if hit.len() == 1 { "" } else { "s" };
+                    let hit = hit.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+                    self.flag(&mut report, "open-ingress", resource, format!("Policy: {} rule '{}' allows any source on sensitive port{} {} (pass --allow-open-ingress to override).", what, rule_name, plural, hit));
+                }
+            }
+        }
+        if !self.allow_owner_grants {
+            let types = tf.get("resource").and_then(|r| r.as_object()).into_iter().flatten()
+                .filter(|(t, _)| t.starts_with("google_") && (t.ends_with("_iam_member") || t.ends_with("_iam_binding")));
+            for (type_name, res) in types {
+                for (name, grant) in res.as_object().into_iter().flatten() {
+                    if grant.get("role").and_then(|r| r.as_str()) == Some("roles/owner") {
+                        self.flag(&mut report, "owner-grants", format!("{}.{}", type_name, name), format!("Policy: {} '{}' grants roles/owner (pass --allow-owner-grants to override).", type_name, name));
+                    }
+                }
+            }
+        }
+        let resources = tf.get("resource").and_then(|r| r.as_object()).into_iter().flatten();
+        // The allow list wins when both are set.
+        let (rule, outside) = if !self.rules.allowed_resource_types.is_empty() {
+            ("allowed-resource-types", resources.clone()
+                .filter(|(t, _)| !self.rules.allowed_resource_types.iter().any(|p| glob_match(p, t))).collect::<Vec<_>>())
+        } else {
+            ("denied-resource-types", resources.clone()
+                .filter(|(t, _)| self.rules.denied_resource_types.iter().any(|p| glob_match(p, t))).collect())
+        };
+        for (type_name, res) in outside {
+            for name in res.as_object().into_iter().flatten().map(|(n, _)| n) {
+                self.flag(&mut report, rule, format!("{}.{}", type_name, name), format!("Policy: {} '{}' is a resource type not permitted by {}.", type_name, name, rule));
+            }
+        }
+        for (type_name, res) in resources.clone() {
+            for (glob, Pattern(re)) in self.rules.naming.iter().filter(|(g, _)| glob_match(g, type_name)) {
+                for (name, body) in res.as_object().into_iter().flatten() {
+                    let value = ["bucket", "name"].iter().filter_map(|a| body.get(*a).and_then(|v| v.as_str()))
+                        .find(|v| !v.contains("${")).unwrap_or(name);
+                    if !re.is_match(value) {
+                        self.flag(&mut report, "naming", format!("{}.{}", type_name, name),
+                            format!("Policy: {} '{}' is named '{}', which does not match {} pattern '{}'.", type_name, name, value, glob, re.as_str()));
+                    }
+                }
+            }
+        }
+        for (type_name, res) in resources {
+            if self.rules.required_tags.is_empty() || !taggable(type_name) { continue; }
+            for (name, body) in res.as_object().into_iter().flatten() {
+                // GKE clusters call their labels `resource_labels`.
+                let tags = ["tags", "labels", "resource_labels"].iter().find_map(|k| body.get(*k)).and_then(|t| t.as_object());
+                let defaults = if type_name.starts_with("aws_") { aws_default_tags(tf, body) } else { None };
+                let present = |k: &str| [tags, defaults].iter().flatten().any(|t| t.get(k).is_some_and(|v| match v {
+                    Json::String(s) => !s.trim().is_empty(),
+                    v => !v.is_null(),
+                }));
+                let missing: Vec<_> = self.rules.required_tags.iter()
+                    .filter(|k| !present(k)).map(|k| k.as_str()).collect();
+                if !missing.is_empty() {
+                    self.flag(&mut report, "required-tags", format!("{}.{}", type_name, name), format!("Policy: {} '{}' is missing required tags: {}.", type_name, name, missing.join(", ")));
+                }
+            }
+        }
+        if !self.rules.allowed_regions.is_empty() {
+            // Azure spells the same region "West Europe" or "westeurope"; GCS uses upper case.
+            let norm = |r: &str| r.replace(' ', "").to_ascii_lowercase();
+            let allowed: Vec<_> = self.rules.allowed_regions.iter().map(|r| norm(r)).collect();
+            let providers = tf.get("provider").and_then(|p| p.as_object()).into_iter().flatten();
+            for (provider, blocks) in providers {
+                let blocks = match blocks {
+                    Json::Array(a) => a.iter().collect(),
+                    b => vec![b],
+                };
+                for block in blocks {
+                    let Some(region) = block.get("region").and_then(|r| r.as_str()) else { continue };
+                    if !allowed.contains(&norm(region)) {
+                        let label = match block.get("alias").and_then(|a| a.as_str()) {
+                            Some(a) => format!("{}.{}", provider, a),
+                            None => provider.clone(),
+                        };
+                        self.flag(&mut report, "allowed-regions", format!("provider.{}", label), format!("Policy: provider {} region '{}' is not in allowed_regions.", label, region));
+                    }
+                }
+            }
+            let resources = tf.get("resource").and_then(|r| r.as_object()).into_iter().flatten();
+            for (type_name, res) in resources {
+                for (name, body) in res.as_object().into_iter().flatten() {
+                    for attr in ["region", "location"] {
+                        // Interpolations resolve to a provider or parent that is checked on its own.
+                        let Some(value) = body.get(attr).and_then(|v| v.as_str()).filter(|v| !v.contains("${")) else { continue };
+                        if !allowed.contains(&norm(value)) {
+                            self.flag(&mut report, "allowed-regions", format!("{}.{}", type_name, name), format!("Policy: resource {} '{}' {} '{}' is not in allowed_regions.", type_name, name, attr, value));
+                        }
+                    }
+                }
+            }
+        }
+        for (name, secret) in tf.pointer("/resource/aws_secretsmanager_secret").and_then(|r| r.as_object()).into_iter().flatten() {
+            let resource = format!("aws_secretsmanager_secret.{}", name);
+            // AWS defaults to a 30-day window; 0 deletes immediately.
+            match secret.get("recovery_window_in_days").and_then(|d| d.as_u64()) {
+                Some(0) => self.flag(&mut report, "ephemeral", resource.clone(), format!("Policy: secret '{}' sets recovery_window_in_days = 0; mark throwaway secrets with exempt_policies: [ephemeral].", name)),
+                Some(d) if d < 7 => self.flag(&mut report, "secret-recovery", resource.clone(), format!("Policy: secret '{}' recovery_window_in_days is {}, below the minimum of 7.", name, d)),
+                _ => {}
+            }
+            if secret.get("force_delete_without_recovery") == Some(&Json::Bool(true)) {
+                self.flag(&mut report, "ephemeral", resource.clone(), format!("Policy: secret '{}' sets force_delete_without_recovery; mark throwaway secrets with exempt_policies: [ephemeral].", name));
+            }
+            let kms = secret.get("kms_key_id").and_then(|k| k.as_str()).unwrap_or_default();
+            if kms.is_empty() || kms.starts_with("alias/aws/") || kms.contains(":alias/aws/") {
+                self.flag(&mut report, "cmek", resource, format!("Policy: secret '{}' must set kms_key_id to a customer-managed key, not the AWS-managed aws/secretsmanager key.", name));
+            }
+        }
+        if self.rules.require_cmek {
+            for (name, secret) in tf.pointer("/resource/google_secret_manager_secret").and_then(|r| r.as_object()).into_iter().flatten() {
+                let replication = secret.pointer("/replication/0");
+                let blocks: Vec<_> = match replication.and_then(|r| r.pointer("/user_managed/0/replicas")) {
+                    Some(Json::Array(replicas)) => replicas.iter().collect(),
+                    _ => replication.and_then(|r| r.pointer("/auto/0")).into_iter().collect(),
+                };
+                let cmek = !blocks.is_empty() && blocks.iter().all(|b| {
+                    b.pointer("/customer_managed_encryption/0/kms_key_name").and_then(|k| k.as_str()).is_some_and(|k| !k.is_empty())
+                });
+                if !cmek {
+                    self.flag(&mut report, "cmek", format!("google_secret_manager_secret.{}", name), format!("Policy: secret '{}' must use customer_managed_encryption on every replica (require_cmek).", name));
+                }
+            }
+        }
+        for (key, allowed) in &self.rules.allowed_sizes {
+            let Some((type_name, attr)) = key.split_once('.') else { continue };
+            for (name, body) in tf.pointer(&format!("/resource/{}", type_name)).and_then(|r| r.as_object()).into_iter().flatten() {
+                let resource = format!("{}.{}", type_name, name);
+                if allowed.is_empty() {
+                    self.flag(&mut report, "instance-size", resource, format!("Policy: {} '{}' is not allowed: allowed_sizes lists no {} values.", type_name, name, attr));
+                    continue;
+                }
+                // Interpolated sizes are only known at plan time, where this runs again.
+                let Some(value) = body.get(attr).and_then(|v| v.as_str()).filter(|v| !v.contains("${")) else { continue };
+                if !allowed.iter().any(|p| glob_match(p, value)) {
+                    self.flag(&mut report, "instance-size", resource, format!("Policy: {} '{}' {} '{}' is not in allowed_sizes ({}).", type_name, name, attr, value, allowed.join(", ")));
+                }
+            }
+        }
+        for rule in &self.rules.custom_rules {
+            let resources = tf.get("resource").and_then(|r| r.as_object()).into_iter().flatten();
+            for (type_name, res) in resources.filter(|(t, _)| glob_match(&rule.resource_type, t)) {
+                for (name, body) in res.as_object().into_iter().flatten() {
+                    let actual = lookup(body, &rule.path);
+                    let Some(expected) = rule.failure(actual) else { continue };
+                    let found = actual.map_or("absent".to_string(), |a| a.to_string());
+                    report.violations.push(Violation {
+                        rule: rule.id.clone(), severity: rule.severity, resource: format!("{}.{}", type_name, name),
+                        message: format!("Policy: {} '{}' {} (found {}).", type_name, name, expected, found),
+                    });
+                }
+            }
+        }
+        report.exempt(exemptions)
+    }
+
+    /// Runs [`Policy::check_tf_json`] over the planned state from `show -json`: each managed
+    /// `resource_changes[].change.after` that survives the plan becomes a resource body.
+    /// Attributes still unknown at plan time fall back to the configuration's reference, so
+    /// cross-resource checks see `${aws_s3_bucket.x.id}` as they would in the rendered config.
+    pub fn check_plan_json(&self, plan: &Json, exemptions: &Exemptions) -> PolicyReport {
+        let expressions: BTreeMap<&str, &Json> = plan.pointer("/configuration/root_module/resources")
+            .and_then(|r| r.as_array()).into_iter().flatten()
+            .filter_map(|r| Some((r.get("address")?.as_str()?, r.get("expressions")?)))
+            .collect();
+        let mut tf = serde_json::json!({ "resource": {} });
+        for rc in plan.get("resource_changes").and_then(|r| r.as_array()).into_iter().flatten() {
+            let (Some(type_name), Some(name)) = (rc.get("type").and_then(|t| t.as_str()), rc.get("name").and_then(|n| n.as_str())) else { continue };
+            if rc.get("mode").and_then(|m| m.as_str()) != Some("managed") { continue; }
+            let Some(mut body) = rc.pointer("/change/after").and_then(|a| a.as_object()).cloned() else { continue };
+            let config = expressions.get(format!("{}.{}", type_name, name).as_str());
+            for (attr, _) in rc.pointer("/change/after_unknown").and_then(|u| u.as_object()).into_iter().flatten().filter(|(_, u)| **u == Json::Bool(true)) {
+                let reference = config.and_then(|c| c.pointer(&format!("/{}/references/0", attr))).and_then(|r| r.as_str());
+                if let Some(r) = reference { body.insert(attr.clone(), Json::String(format!("${{{}}}", r))); }
+            }
+            // Provider-level default tags and labels only show up in these merged views.
+            for (merged, attr) in [("tags_all", "tags"), ("terraform_labels", "labels")] {
+                if let Some(m) = body.get(merged).filter(|m| m.is_object()).cloned() { body.insert(attr.to_string(), m); }
+            }
+            let name = match rc.get("index") {
+                Some(Json::Number(i)) => format!("{}[{}]", name, i),
+                Some(Json::String(k)) => format!("{}[\"{}\"]", name, k),
+                _ => name.to_string(),
+            };
+            tf["resource"][type_name][name] = Json::Object(body);
+        }
+        let mut report = self.check_tf_json(&tf, exemptions);
+        let (mut changed, mut destroyed) = (Vec::new(), Vec::new());
+        for rc in plan.get("resource_changes").and_then(|r| r.as_array()).into_iter().flatten() {
+            let address = rc.get("address").and_then(|a| a.as_str()).unwrap_or("?");
+            let actions: Vec<_> = rc.pointer("/change/actions").and_then(|a| a.as_array()).into_iter().flatten().filter_map(|a| a.as_str()).collect();
+            if actions.iter().any(|a| *a == "create" || *a == "update") { changed.push(address); }
+            if actions.contains(&"delete") { destroyed.push(address); }
+        }
+        for (what, limit, addresses) in [("changes", self.rules.max_resources_changed, changed), ("destroys", self.rules.max_resources_destroyed, destroyed)] {
+            let Some(limit) = limit.filter(|l| addresses.len() > *l) else { continue };
+            let setting = if what == "changes" { "max_resources_changed" } else { "max_resources_destroyed" };
+            let mut shown = addresses.iter().take(5).copied().collect::<Vec<_>>().join(", ");
+            if addresses.len() > 5 { shown.push_str(&format!(" and {} more", addresses.len() - 5)); }
+            self.flag(&mut report, "blast-radius", format!("plan.{}", what),
+                format!("Policy: plan {} {} resources, over {} of {} ({}; pass --force to override).", what, addresses.len(), setting, limit, shown));
+        }
+        report
+    }
+}
+
+#[cfg(feature = "opa")]
+impl Policy {
+    /// Evaluates the `deny` set of every top-level package under `policy_dir` with `opa eval`,
+    /// feeding the rendered tf.json as input. Each message becomes an `opa` violation; objects
+    /// may carry `msg` and `resource` keys, and `exempt_policies: [opa]` waives them per resource.
+    pub fn check_with_opa(&self, tf: &Json, policy_dir: &Path, exemptions: &Exemptions) -> Result<PolicyReport> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+        let opa = which::which("opa").context("'opa' not found in PATH. Install it from https://www.openpolicyagent.org/docs/latest/#running-opa or drop --opa-policy-dir.")?;
+        let mut child = Command::new(opa)
+            .args(["eval", "--format", "json", "--stdin-input", "--data"]).arg(policy_dir).arg("data[pkg].deny[msg]")
+            .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+            .spawn().context("spawn opa eval")?;
+        child.stdin.take().unwrap().write_all(&serde_json::to_vec(tf)?)?;
+        let out = child.wait_with_output()?;
+        if !out.status.success() {
+            anyhow::bail!("opa eval failed: {}", String::from_utf8_lossy(&out.stderr).trim());
+        }
+        let result: Json = serde_json::from_slice(&out.stdout).context("parse opa eval output")?;
+        let mut report = PolicyReport::default();
+        for r in result.get("result").and_then(|r| r.as_array()).into_iter().flatten() {
+            let pkg = r.pointer("/bindings/pkg").and_then(|p| p.as_str()).unwrap_or("unknown");
+            let msg = r.pointer("/bindings/msg").unwrap_or(&Json::Null);
+            let (text, resource) = match msg {
+                Json::String(s) => (s.clone(), "-".to_string()),
+                m => (
+                    m.get("msg").and_then(|s| s.as_str()).map(str::to_string).unwrap_or_else(|| m.to_string()),
+                    m.get("resource").and_then(|s| s.as_str()).unwrap_or("-").to_string(),
+                ),
+            };
+            self.flag(&mut report, "opa", resource, format!("OPA {}: {}", pkg, text));
+        }
+        Ok(report.exempt(exemptions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// `(rule, resource)` of every violation `policy` finds in `tf`, with no exemptions.
+    fn flagged(policy: &Policy, tf: Json) -> Vec<(String, String)> {
+        policy.check_tf_json(&tf, &Exemptions::new()).violations.into_iter().map(|v| (v.rule, v.resource)).collect()
+    }
+
+    fn flagged_rule(policy: &Policy, tf: Json, rule: &str) -> Vec<String> {
+        flagged(policy, tf).into_iter().filter(|(r, _)| r == rule).map(|(_, res)| res).collect()
+    }
+
+    fn s3_bucket(blocks: Json) -> Json {
+        json!({ "resource": {
+            "aws_s3_bucket": { "logs": { "bucket": "acme-logs" } },
+            "aws_s3_bucket_server_side_encryption_configuration": { "logs": {
+                "bucket": "${aws_s3_bucket.logs.id}", "rule": { "apply_server_side_encryption_by_default": { "sse_algorithm": "AES256" } },
+            } },
+            "aws_s3_bucket_public_access_block": blocks,
+        } })
+    }
+
+    #[test]
+    fn s3_public_access_block_required() {
+        let full = json!({ "logs": {
+            "bucket": "${aws_s3_bucket.logs.id}",
+            "block_public_acls": true, "block_public_policy": true, "ignore_public_acls": true, "restrict_public_buckets": true,
+        } });
+        assert!(flagged_rule(&Policy::default(), s3_bucket(full.clone()), "public-access").is_empty());
+
+        let mut partial = full;
+        partial["logs"]["restrict_public_buckets"] = json!(false);
+        assert_eq!(flagged_rule(&Policy::default(), s3_bucket(partial), "public-access"), ["aws_s3_bucket.logs"]);
+        assert_eq!(flagged_rule(&Policy::default(), s3_bucket(json!({})), "public-access"), ["aws_s3_bucket.logs"]);
+
+        let allow = Policy { allow_public_buckets: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, s3_bucket(json!({})), "public-access").is_empty());
+    }
+
+    #[test]
+    fn s3_encryption_resource_satisfies_encryption_check() {
+        // Regression: buckets rendered with a separate SSE resource (provider v5) used to be denied.
+        assert!(flagged_rule(&Policy::default(), s3_bucket(json!({})), "encryption").is_empty());
+
+        let mut tf = s3_bucket(json!({}));
+        let sse = tf["resource"].as_object_mut().unwrap().remove("aws_s3_bucket_server_side_encryption_configuration").unwrap();
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "encryption"), ["aws_s3_bucket.logs"]);
+
+        // The legacy inline block still counts; an SSE resource for another bucket does not.
+        let mut inline = tf.clone();
+        inline["resource"]["aws_s3_bucket"]["logs"]["server_side_encryption_configuration"] = sse["logs"]["rule"].clone();
+        assert!(flagged_rule(&Policy::default(), inline, "encryption").is_empty());
+        let mut other = sse;
+        other["logs"]["bucket"] = json!("${aws_s3_bucket.other.id}");
+        tf["resource"]["aws_s3_bucket_server_side_encryption_configuration"] = other;
+        assert_eq!(flagged_rule(&Policy::default(), tf, "encryption"), ["aws_s3_bucket.logs"]);
+    }
+
+    #[test]
+    fn public_gcs_grants_are_denied() {
+        let tf = json!({ "resource": {
+            "google_storage_bucket_iam_member": {
+                "team": { "bucket": "assets", "role": "roles/storage.objectViewer", "member": "group:eng@example.com" },
+                "anyone": { "bucket": "assets", "role": "roles/storage.objectViewer", "member": "allUsers" },
+            },
+            "google_storage_bucket_iam_binding": {
+                "signed-in": { "bucket": "assets", "role": "roles/storage.objectViewer", "members": ["user:a@example.com", "allAuthenticatedUsers"] },
+            },
+        } });
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "public-access"),
+            ["google_storage_bucket_iam_member.anyone", "google_storage_bucket_iam_binding.signed-in"]);
+        let allow = Policy { allow_public_buckets: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, tf, "public-access").is_empty());
+    }
+
+    fn storage_account(acct: Json) -> Json {
+        json!({ "resource": { "azurerm_storage_account": { "logs": acct } } })
+    }
+
+    #[test]
+    fn azure_storage_account_https_tls_and_public_access() {
+        let secure = json!({ "https_traffic_only_enabled": true, "min_tls_version": "TLS1_2", "allow_nested_items_to_be_public": false });
+        assert!(flagged(&Policy::default(), storage_account(secure.clone())).is_empty());
+
+        let mut weak = secure.clone();
+        weak["min_tls_version"] = json!("TLS1_0");
+        assert_eq!(flagged_rule(&Policy::default(), storage_account(weak), "encryption"), ["azurerm_storage_account.logs"]);
+        // The azurerm v3 spelling is still read.
+        let legacy = json!({ "enable_https_traffic_only": false, "allow_blob_public_access": false });
+        assert_eq!(flagged_rule(&Policy::default(), storage_account(legacy), "encryption"), ["azurerm_storage_account.logs"]);
+
+        // Provider defaults: public blob access is allowed unless turned off.
+        assert_eq!(flagged_rule(&Policy::default(), storage_account(json!({})), "public-access"), ["azurerm_storage_account.logs"]);
+        let allow = Policy { allow_public_buckets: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, storage_account(json!({})), "public-access").is_empty());
+    }
+
+    fn gcs_bucket(bucket: Json) -> Json {
+        json!({ "resource": { "google_storage_bucket": { "logs": bucket } } })
+    }
+
+    #[test]
+    fn gcs_bucket_uniform_access_and_public_access_prevention() {
+        let locked = json!({ "name": "acme-logs", "uniform_bucket_level_access": true, "public_access_prevention": "enforced" });
+        assert!(flagged(&Policy::default(), gcs_bucket(locked.clone())).is_empty());
+
+        for (attr, value) in [("uniform_bucket_level_access", json!(false)), ("public_access_prevention", json!("inherited"))] {
+            let mut open = locked.clone();
+            open[attr] = value;
+            assert_eq!(flagged_rule(&Policy::default(), gcs_bucket(open), "public-access"), ["google_storage_bucket.logs"], "{}", attr);
+        }
+        // Both default to off in the provider.
+        assert_eq!(flagged_rule(&Policy::default(), gcs_bucket(json!({ "name": "acme-logs" })), "public-access"), ["google_storage_bucket.logs"]);
+        let allow = Policy { allow_public_buckets: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, gcs_bucket(json!({ "name": "acme-logs" })), "public-access").is_empty());
+
+        let mut no_key = locked;
+        no_key["encryption"] = json!([{}]);
+        assert_eq!(flagged_rule(&Policy::default(), gcs_bucket(no_key), "cmek"), ["google_storage_bucket.logs"]);
+    }
+
+    #[test]
+    fn report_diff_ignores_order() {
+        let v = |rule: &str, resource: &str| Violation { rule: rule.into(), severity: Severity::Deny, resource: resource.into(), message: "m".into() };
+        let actual = PolicyReport { violations: vec![v("encryption", "aws_s3_bucket.a"), v("versioning", "aws_s3_bucket.a")], exempted: vec![] };
+        let same = PolicyReport { violations: vec![v("versioning", "aws_s3_bucket.a"), v("encryption", "aws_s3_bucket.a")], exempted: vec![] };
+        assert!(actual.diff(&same).is_empty());
+
+        let expected = PolicyReport { violations: vec![v("encryption", "aws_s3_bucket.a")], exempted: vec![v("versioning", "aws_s3_bucket.a")] };
+        assert_eq!(actual.diff(&expected), [
+            "+ deny   versioning aws_s3_bucket.a: m",
+            "- exempt versioning aws_s3_bucket.a: m",
+        ]);
+    }
+
+    #[test]
+    fn sarif_2_1_0_shape() {
+        let v = |rule: &str, severity, resource: &str| Violation { rule: rule.into(), severity, resource: resource.into(), message: format!("{} on {}", rule, resource) };
+        let report = PolicyReport {
+            violations: vec![v("encryption", Severity::Deny, "aws_ebs_volume.logs"), v("acr-admin", Severity::Warn, "azurerm_container_registry.main")],
+            exempted: vec![v("versioning", Severity::Deny, "aws_s3_bucket.tmp")],
+        };
+        let sarif = report.to_sarif(Some("stack.yml"));
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "r2iac");
+        assert_eq!(run["tool"]["driver"]["rules"], json!([
+            { "id": "acr-admin", "defaultConfiguration": { "level": "warning" } },
+            { "id": "encryption", "defaultConfiguration": { "level": "error" } },
+            { "id": "versioning", "defaultConfiguration": { "level": "error" } },
+        ]));
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], json!({
+            "ruleId": "encryption", "level": "error", "message": { "text": "encryption on aws_ebs_volume.logs" },
+            "locations": [{
+                "logicalLocations": [{ "fullyQualifiedName": "aws_ebs_volume.logs", "kind": "resource" }],
+                "physicalLocation": { "artifactLocation": { "uri": "stack.yml" } },
+            }],
+        }));
+        assert_eq!((&results[1]["ruleId"], &results[1]["level"]), (&json!("acr-admin"), &json!("warning")));
+        assert!(results[1].get("suppressions").is_none());
+        assert_eq!(results[2]["suppressions"], json!([{ "kind": "inSource", "justification": "exempt_policies" }]));
+
+        // Without a stack file there is only the logical location.
+        let bare = report.to_sarif(None);
+        assert!(bare["runs"][0]["results"][0]["locations"][0].get("physicalLocation").is_none());
+    }
+
+    fn with_rules(rules: PolicyRules) -> Policy {
+        Policy { rules, ..Policy::default() }
+    }
+
+    #[test]
+    fn required_tags() {
+        let policy = with_rules(PolicyRules { required_tags: vec!["owner".into(), "env".into()], ..Default::default() });
+        let tf = json!({ "resource": {
+            "azurerm_key_vault": { "ok": { "tags": { "owner": "data", "env": "prod" } }, "partial": { "tags": { "owner": "data", "env": " " } } },
+            "google_pubsub_topic": { "bare": {} },
+            "google_container_cluster": { "gke": { "resource_labels": { "owner": "platform", "env": "prod" } } },
+            // No tag argument at all, so nothing to flag.
+            "aws_cloudwatch_event_target": { "t": {} },
+            "google_project_iam_member": { "m": {} },
+        } });
+        assert_eq!(flagged_rule(&policy, tf.clone(), "required-tags"), ["azurerm_key_vault.partial", "google_pubsub_topic.bare"]);
+        let report = policy.check_tf_json(&tf, &Exemptions::new());
+        let bare = report.violations.iter().find(|v| v.resource == "google_pubsub_topic.bare").unwrap();
+        assert_eq!(bare.message, "Policy: google_pubsub_topic 'bare' is missing required tags: owner, env.");
+
+        assert!(flagged_rule(&Policy::default(), tf, "required-tags").is_empty());
+    }
+
+    #[test]
+    fn required_tags_from_aws_default_tags() {
+        let policy = with_rules(PolicyRules { required_tags: vec!["owner".into()], ..Default::default() });
+        let tf = json!({
+            "provider": { "aws": [
+                { "region": "us-east-1", "default_tags": [{ "tags": { "owner": "platform" } }] },
+                { "region": "eu-west-1", "alias": "eu" },
+            ] },
+            "resource": { "aws_sqs_queue": {
+                "jobs": { "name": "jobs" },
+                "eu-jobs": { "name": "eu-jobs", "provider": "aws.eu" },
+                "eu-tagged": { "name": "eu-tagged", "provider": "aws.eu", "tags": { "owner": "platform" } },
+            } },
+        });
+        assert_eq!(flagged_rule(&policy, tf, "required-tags"), ["aws_sqs_queue.eu-jobs"]);
+    }
+
+    #[test]
+    fn open_ingress_on_aws_security_groups() {
+        let rule = |port: u32, cidr: &str| json!({ "protocol": "tcp", "from_port": port, "to_port": port, "cidr_blocks": [cidr] });
+        let tf = json!({ "resource": {
+            "aws_security_group": {
+                "web": { "ingress": [rule(443, "0.0.0.0/0"), rule(22, "10.0.0.0/8"), { "protocol": "icmp", "from_port": 8, "to_port": 0, "cidr_blocks": ["0.0.0.0/0"] }] },
+                "ssh": { "ingress": [rule(22, "0.0.0.0/0")] },
+                "any": { "ingress": [{ "protocol": "-1", "from_port": 0, "to_port": 0, "ipv6_cidr_blocks": ["::/0"] }] },
+            },
+            "aws_security_group_rule": {
+                "db-in": { "type": "ingress", "protocol": "tcp", "from_port": 5432, "to_port": 5432, "cidr_blocks": ["0.0.0.0/0"] },
+                "out": { "type": "egress", "protocol": "-1", "from_port": 0, "to_port": 0, "cidr_blocks": ["0.0.0.0/0"] },
+            },
+        } });
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "open-ingress"),
+            ["aws_security_group.any", "aws_security_group.ssh", "aws_security_group_rule.db-in"]);
+        let report = Policy::default().check_tf_json(&tf, &Exemptions::new());
+        assert_eq!(report.violations.iter().find(|v| v.resource == "aws_security_group.ssh").unwrap().message,
+            "Policy: security group 'ssh' ingress rule 0 opens tcp port 22 to 0.0.0.0/0 (world-open ports allowed: 80, 443; pass --allow-open-ingress to override).");
+
+        let postgres = with_rules(PolicyRules { open_ingress: OpenIngress { allowed_ports: [443, 5432].into(), ..OpenIngress::default() }, ..Default::default() });
+        assert_eq!(flagged_rule(&postgres, tf.clone(), "open-ingress"), ["aws_security_group.any", "aws_security_group.ssh"]);
+        let allow = Policy { allow_open_ingress: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, tf, "open-ingress").is_empty());
+    }
+
+    #[test]
+    fn open_ingress_on_gcp_firewalls() {
+        let tf = json!({ "resource": { "google_compute_firewall": {
+            "web": { "source_ranges": ["0.0.0.0/0"], "allow": [{ "protocol": "tcp", "ports": ["443", "8000-8080"] }, { "protocol": "icmp" }] },
+            "all": { "source_ranges": ["0.0.0.0/0"], "allow": [{ "protocol": "udp" }] },
+            "internal": { "source_ranges": ["10.0.0.0/8"], "allow": [{ "protocol": "tcp", "ports": ["22"] }] },
+            "egress": { "direction": "EGRESS", "source_ranges": ["0.0.0.0/0"], "allow": [{ "protocol": "tcp" }] },
+        } } });
+        let report = Policy::default().check_tf_json(&tf, &Exemptions::new());
+        let messages: Vec<_> = report.violations.iter().filter(|v| v.rule == "open-ingress").map(|v| v.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Policy: firewall 'all' allows udp all ports from 0.0.0.0/0 (world-open ports allowed: 80, 443; pass --allow-open-ingress to override).",
+            "Policy: firewall 'web' allows tcp ports 8000-8080 from 0.0.0.0/0 (world-open ports allowed: 80, 443; pass --allow-open-ingress to override).",
+        ]);
+        let allow = Policy { allow_open_ingress: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, tf, "open-ingress").is_empty());
+    }
+
+    #[test]
+    fn open_ingress_on_sensitive_nsg_ports() {
+        let rule = |name: &str, access: &str, source: &str, ports: &str| json!({
+            "name": name, "direction": "Inbound", "access": access,
+            "source_address_prefix": source, "destination_port_range": ports,
+        });
+        let tf = json!({ "resource": {
+            "azurerm_network_security_group": { "web": { "security_rule": [
+                rule("https", "Allow", "*", "443"),
+                rule("mgmt", "Allow", "Internet", "20-3389"),
+                rule("vpn-ssh", "Allow", "10.0.0.0/8", "22"),
+                rule("no-rdp", "Deny", "*", "3389"),
+            ] } },
+            "azurerm_network_security_rule": { "db": rule("db", "Allow", "0.0.0.0/0", "5432") },
+        } });
+        let report = Policy::default().check_tf_json(&tf, &Exemptions::new());
+        let messages: Vec<_> = report.violations.iter().filter(|v| v.rule == "open-ingress").map(|v| v.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Policy: network security group 'web' rule 'mgmt' allows any source on sensitive ports 22, 3306, 3389 (pass --allow-open-ingress to override).",
+            "Policy: network security rule 'db' rule 'db' allows any source on sensitive port 5432 (pass --allow-open-ingress to override).",
+        ]);
+        let allow = Policy { allow_open_ingress: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, tf, "open-ingress").is_empty());
+    }
+
+    #[test]
+    fn owner_grants() {
+        let assignment = |scope: &str, role: &str| json!({ "scope": scope, "role_definition_name": role, "principal_id": "p" });
+        let tf = json!({ "resource": {
+            "google_project_iam_member": {
+                "admin": { "project": "acme", "role": "roles/owner", "member": "user:a@example.com" },
+                "viewer": { "project": "acme", "role": "roles/viewer", "member": "user:a@example.com" },
+            },
+            "google_storage_bucket_iam_binding": { "owners": { "bucket": "b", "role": "roles/owner", "members": ["group:g@example.com"] } },
+            "azurerm_role_assignment": {
+                "sub-owner": assignment("/subscriptions/sub-1", "Owner"),
+                "data-owner": assignment("${data.azurerm_subscription.current.id}", "Owner"),
+                "rg-owner": assignment("/subscriptions/sub-1/resourceGroups/app", "Owner"),
+                "sub-reader": assignment("/subscriptions/sub-1", "Reader"),
+            },
+        } });
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "owner-grants"), [
+            "azurerm_role_assignment.data-owner", "azurerm_role_assignment.sub-owner",
+            "google_project_iam_member.admin", "google_storage_bucket_iam_binding.owners",
+        ]);
+        let allow = Policy { allow_owner_grants: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, tf, "owner-grants").is_empty());
+    }
+
+    #[test]
+    fn public_sql() {
+        let tf = json!({ "resource": {
+            "google_sql_database_instance": {
+                "private": { "settings": [{ "ip_configuration": [{ "ipv4_enabled": false, "private_network": "n" }] }] },
+                "public": { "settings": [{ "tier": "db-f1-micro" }] },
+            },
+            "azurerm_mssql_server": {
+                "private": { "public_network_access_enabled": false },
+                "public": { "public_network_access_enabled": true },
+            },
+            "azurerm_cosmosdb_account": { "private": { "public_network_access_enabled": false }, "default": {} },
+        } });
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "public-sql"), [
+            "google_sql_database_instance.public", "azurerm_cosmosdb_account.default", "azurerm_mssql_server.public",
+        ]);
+        let allow = Policy { allow_public_sql: true, ..Policy::default() };
+        assert!(flagged_rule(&allow, tf, "public-sql").is_empty());
+    }
+
+    #[test]
+    fn purge_protection_and_password_auth() {
+        let tf = json!({ "resource": {
+            "azurerm_key_vault": { "kept": { "purge_protection_enabled": true }, "purgeable": { "purge_protection_enabled": false }, "unset": {} },
+            "azurerm_linux_virtual_machine": { "keys": { "disable_password_authentication": true }, "default": {}, "password": { "disable_password_authentication": false } },
+        } });
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "purge-protection"), ["azurerm_key_vault.purgeable", "azurerm_key_vault.unset"]);
+        assert_eq!(flagged_rule(&Policy::default(), tf.clone(), "password-auth"), ["azurerm_linux_virtual_machine.password"]);
+        let allow = Policy { allow_no_purge_protection: true, allow_password_auth: true, ..Policy::default() };
+        assert!(flagged(&allow, tf).is_empty());
+    }
+
+    #[test]
+    fn naming_patterns() {
+        let policy: PolicyRules = serde_yaml::from_str("naming: { 'aws_s3_*': '^acme-[a-z-]+$', azurerm_key_vault: '^kv-' }").unwrap();
+        let policy = with_rules(policy);
+        let tf = json!({ "resource": {
+            "aws_s3_bucket": { "logs": { "bucket": "acme-logs" }, "tmp": { "bucket": "scratch" }, "acme-derived": { "bucket": "${var.bucket}" } },
+            "azurerm_key_vault": { "main": { "name": "kv-main", "purge_protection_enabled": true }, "kv-other": { "name": "vault", "purge_protection_enabled": true } },
+        } });
+        assert_eq!(flagged_rule(&policy, tf.clone(), "naming"), ["aws_s3_bucket.tmp", "azurerm_key_vault.kv-other"]);
+        let report = policy.check_tf_json(&tf, &Exemptions::new());
+        assert_eq!(report.violations.iter().find(|v| v.rule == "naming" && v.resource == "aws_s3_bucket.tmp").unwrap().message,
+            "Policy: aws_s3_bucket 'tmp' is named 'scratch', which does not match aws_s3_* pattern '^acme-[a-z-]+$'.");
+        assert!(flagged_rule(&Policy::default(), tf, "naming").is_empty());
+    }
+
+    #[test]
+    fn allowed_regions() {
+        let policy = with_rules(PolicyRules { allowed_regions: vec!["eu-west-1".into(), "West Europe".into()], ..Default::default() });
+        let tf = json!({
+            "provider": {
+                "aws": [{ "region": "eu-west-1" }, { "region": "us-east-1", "alias": "us" }],
+                "azurerm": { "features": {} },
+            },
+            "resource": {
+                "azurerm_resource_group": { "eu": { "location": "westeurope" }, "us": { "location": "East US" } },
+                "azurerm_storage_account": { "logs": { "location": "${azurerm_resource_group.eu.location}" } },
+            },
+        });
+        assert_eq!(flagged_rule(&policy, tf.clone(), "allowed-regions"), ["provider.aws.us", "azurerm_resource_group.us"]);
+        assert!(flagged_rule(&Policy::default(), tf, "allowed-regions").is_empty());
+    }
+
+    #[test]
+    fn allowed_and_denied_resource_types() {
+        let tf = json!({ "resource": { "aws_iam_user": { "ci": {} }, "aws_sqs_queue": { "jobs": {} }, "aws_iam_role": { "app": {} } } });
+        let denied = with_rules(PolicyRules { denied_resource_types: vec!["aws_iam_user*".into()], ..Default::default() });
+        assert_eq!(flagged_rule(&denied, tf.clone(), "denied-resource-types"), ["aws_iam_user.ci"]);
+        // The allow list wins when both are set.
+        let allowed = with_rules(PolicyRules { allowed_resource_types: vec!["aws_sqs_*".into()], denied_resource_types: vec!["aws_sqs_*".into()], ..Default::default() });
+        assert_eq!(flagged_rule(&allowed, tf.clone(), "allowed-resource-types"), ["aws_iam_role.app", "aws_iam_user.ci"]);
+        assert!(flagged_rule(&allowed, tf.clone(), "denied-resource-types").is_empty());
+        assert!(flagged(&Policy::default(), tf).is_empty());
+    }
+
+    #[test]
+    fn allowed_sizes() {
+        let policy = with_rules(PolicyRules {
+            allowed_sizes: [("aws_instance.instance_type".to_string(), vec!["t3.*".to_string(), "m5.large".to_string()]), ("aws_db_instance.instance_class".to_string(), vec![])].into(),
+            required_encryption: Some(vec![]),
+            ..Default::default()
+        });
+        let tf = json!({ "resource": {
+            "aws_instance": { "small": { "instance_type": "t3.micro" }, "big": { "instance_type": "m5.24xlarge" }, "later": { "instance_type": "${var.size}" } },
+            "aws_db_instance": { "db": { "instance_class": "db.t3.micro" } },
+        } });
+        assert_eq!(flagged_rule(&policy, tf.clone(), "instance-size"), ["aws_db_instance.db", "aws_instance.big"]);
+        let report = policy.check_tf_json(&tf, &Exemptions::new());
+        assert_eq!(report.violations.iter().find(|v| v.resource == "aws_instance.big").unwrap().message,
+            "Policy: aws_instance 'big' instance_type 'm5.24xlarge' is not in allowed_sizes (t3.*, m5.large).");
+        assert!(flagged_rule(&Policy::default(), tf, "instance-size").is_empty());
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules: PolicyRules = serde_yaml::from_str("
+custom_rules:
+  - { id: sql-tier, resource_type: google_sql_*, path: settings.tier, op: matches, value: '^db-custom-' }
+  - { id: backups, resource_type: aws_db_instance, path: /backup_retention_period, op: gte, value: 7, severity: warn }
+  - { id: no-public-ip, resource_type: aws_instance, path: associate_public_ip_address, op: ne, value: true, message: must not have a public IP }
+").unwrap();
+        let policy = with_rules(rules);
+        let tf = json!({ "resource": {
+            "google_sql_database_instance": { "ok": { "settings": [{ "tier": "db-custom-2-7680" }] }, "small": { "settings": [{ "tier": "db-f1-micro" }] } },
+            "aws_db_instance": { "ok": { "backup_retention_period": 7.0 }, "short": { "backup_retention_period": 1 }, "unset": {} },
+            "aws_instance": { "private": {}, "public": { "associate_public_ip_address": true } },
+        } });
+        let report = policy.check_tf_json(&tf, &Exemptions::new());
+        let custom: Vec<_> = report.violations.iter().filter(|v| !RULES.iter().any(|(r, _)| *r == v.rule))
+            .map(|v| (v.rule.as_str(), v.severity, v.resource.as_str(), v.message.as_str())).collect();
+        assert_eq!(custom, [
+            ("sql-tier", Severity::Deny, "google_sql_database_instance.small", "Policy: google_sql_database_instance 'small' settings.tier must match \"^db-custom-\" (found \"db-f1-micro\")."),
+            ("backups", Severity::Warn, "aws_db_instance.short", "Policy: aws_db_instance 'short' /backup_retention_period must be at least 7 (found 1)."),
+            ("backups", Severity::Warn, "aws_db_instance.unset", "Policy: aws_db_instance 'unset' /backup_retention_period must be at least 7 (found absent)."),
+            ("no-public-ip", Severity::Deny, "aws_instance.public", "Policy: aws_instance 'public' must not have a public IP (found true)."),
+        ]);
+    }
+
+    #[test]
+    fn blast_radius_from_plan_json() {
+        let change = |address: &str, actions: &[&str]| {
+            let (type_name, name) = address.split_once('.').unwrap();
+            json!({ "address": address, "mode": "managed", "type": type_name, "name": name, "change": { "actions": actions, "after": {} } })
+        };
+        let plan = json!({ "resource_changes": [
+            change("aws_sqs_queue.a", &["create"]), change("aws_sqs_queue.b", &["update"]),
+            change("aws_sqs_queue.c", &["delete", "create"]), change("aws_sqs_queue.d", &["delete"]),
+            change("aws_sqs_queue.e", &["no-op"]),
+        ] });
+        let policy = with_rules(PolicyRules { max_resources_changed: Some(2), max_resources_destroyed: Some(2), ..Default::default() });
+        let report = policy.check_plan_json(&plan, &Exemptions::new());
+        let blast: Vec<_> = report.violations.iter().filter(|v| v.rule == "blast-radius").map(|v| (v.resource.as_str(), v.message.as_str())).collect();
+        assert_eq!(blast, [("plan.changes", "Policy: plan changes 3 resources, over max_resources_changed of 2 (aws_sqs_queue.a, aws_sqs_queue.b, aws_sqs_queue.c; pass --force to override).")]);
+
+        let roomy = with_rules(PolicyRules { max_resources_changed: Some(3), max_resources_destroyed: Some(1), ..Default::default() });
+        let blast: Vec<_> = roomy.check_plan_json(&plan, &Exemptions::new()).violations.into_iter().filter(|v| v.rule == "blast-radius").map(|v| v.resource).collect();
+        assert_eq!(blast, ["plan.destroys"]);
+        assert!(Policy::default().check_plan_json(&plan, &Exemptions::new()).violations.iter().all(|v| v.rule != "blast-radius"));
+    }
+
+    #[test]
+    fn plan_json_checks_planned_state() {
+        // The SSE configuration's bucket is unknown until apply; the config reference stands in.
+        let plan = json!({
+            "resource_changes": [
+                { "address": "aws_s3_bucket.logs", "mode": "managed", "type": "aws_s3_bucket", "name": "logs",
+                  "change": { "actions": ["create"], "after": { "bucket": "acme-logs", "tags_all": { "owner": "data" } } } },
+                { "address": "aws_s3_bucket_server_side_encryption_configuration.logs", "mode": "managed", "type": "aws_s3_bucket_server_side_encryption_configuration", "name": "logs",
+                  "change": { "actions": ["create"], "after": { "rule": [{}] }, "after_unknown": { "bucket": true } } },
+                { "address": "aws_ebs_volume.data[0]", "mode": "managed", "type": "aws_ebs_volume", "name": "data", "index": 0,
+                  "change": { "actions": ["create"], "after": { "encrypted": false } } },
+                { "address": "data.aws_caller_identity.me", "mode": "data", "type": "aws_caller_identity", "name": "me", "change": { "actions": ["read"], "after": {} } },
+            ],
+            "configuration": { "root_module": { "resources": [
+                { "address": "aws_s3_bucket_server_side_encryption_configuration.logs", "expressions": { "bucket": { "references": ["aws_s3_bucket.logs.id", "aws_s3_bucket.logs"] } } },
+            ] } },
+        });
+        let policy = Policy { allow_public_buckets: true, rules: PolicyRules { required_tags: vec!["owner".into()], ..Default::default() }, ..Policy::default() };
+        let report = policy.check_plan_json(&plan, &Exemptions::new());
+        let found: Vec<_> = report.violations.iter().map(|v| (v.rule.as_str(), v.resource.as_str())).collect();
+        assert_eq!(found, [("encryption", "aws_ebs_volume.data[0]"), ("required-tags", "aws_ebs_volume.data[0]")]);
+    }
+
+    #[test]
+    fn exemptions_are_reported_not_dropped() {
+        let tf = json!({ "resource": { "aws_ebs_volume": { "scratch": {}, "data": {} } } });
+        let exemptions: Exemptions = [(("aws_ebs_volume".to_string(), "scratch".to_string()), ["encryption".to_string()].into())].into();
+        let report = Policy::default().check_tf_json(&tf, &exemptions);
+        assert_eq!(report.violations.iter().map(|v| v.resource.as_str()).collect::<Vec<_>>(), ["aws_ebs_volume.data"]);
+        assert_eq!(report.exempted.iter().map(|v| (v.rule.as_str(), v.resource.as_str())).collect::<Vec<_>>(), [("encryption", "aws_ebs_volume.scratch")]);
+        assert_eq!(report.summary(), "1 policy violation (1 deny, 0 warn), 1 exempted");
+
+        Policy::default().check_exemptions(&exemptions).unwrap();
+        let typo: Exemptions = [(("aws_s3_bucket".to_string(), "site".to_string()), ["public-access".to_string(), "s3-encryption".to_string()].into())].into();
+        assert_eq!(Policy::default().check_exemptions(&typo).unwrap_err().to_string(), "aws_s3_bucket.site: exempt_policies: unknown rule 's3-encryption'");
+        let custom: PolicyRules = serde_yaml::from_str("custom_rules: [{ id: s3-encryption, resource_type: aws_s3_bucket, path: bucket, op: present }]").unwrap();
+        with_rules(custom).check_exemptions(&typo).unwrap();
+    }
+
+    #[test]
+    fn cfn_templates_get_the_aws_rules() {
+        let tpl: CfnTemplate = serde_json::from_value(json!({ "Resources": {
+            "Logs": { "name": "Logs", "Type": "AWS::S3::Bucket", "properties": {
+                "BucketEncryption": { "ServerSideEncryptionConfiguration": [{ "ServerSideEncryptionByDefault": { "SSEAlgorithm": "AES256" } }] },
+                "PublicAccessBlockConfiguration": { "BlockPublicAcls": true, "BlockPublicPolicy": true, "IgnorePublicAcls": true, "RestrictPublicBuckets": true },
+            } },
+            "Site": { "name": "Site", "Type": "AWS::S3::Bucket", "properties": { "Properties": {} } },
+            "Key": { "name": "Key", "Type": "AWS::KMS::Key", "properties": { "EnableKeyRotation": true, "PendingWindowInDays": 3 } },
+            "Ci": { "name": "Ci", "Type": "AWS::IAM::User" },
+        } })).unwrap();
+        let policy = with_rules(PolicyRules { denied_resource_types: vec!["AWS::IAM::*".into()], ..Default::default() });
+        let found = |p: &Policy, e: &Exemptions| p.check_cfn_template(&tpl, e).violations.into_iter().map(|v| (v.rule, v.resource)).collect::<Vec<_>>();
+        assert_eq!(found(&policy, &Exemptions::new()), [
+            ("denied-resource-types".to_string(), "AWS::IAM::User.Ci".to_string()),
+            ("key-rotation".to_string(), "AWS::KMS::Key.Key".to_string()),
+            ("encryption".to_string(), "AWS::S3::Bucket.Site".to_string()),
+            ("public-access".to_string(), "AWS::S3::Bucket.Site".to_string()),
+        ]);
+        let report = policy.check_cfn_template(&tpl, &Exemptions::new());
+        assert_eq!(report.violations[1].message, "Policy: KMS key 'Key' (AWS::KMS::Key) must keep a deletion window of at least 7 days.");
+
+        let exempt: Exemptions = [(("AWS::S3::Bucket".to_string(), "Site".to_string()), ["public-access".to_string()].into())].into();
+        let relaxed = with_rules(PolicyRules { allow_no_key_rotation: true, ..Default::default() });
+        assert_eq!(found(&relaxed, &exempt), [("encryption".to_string(), "AWS::S3::Bucket.Site".to_string())]);
+    }
+
+    #[test]
+    fn secrets_manager_recovery_and_cmek() {
+        let key = "arn:aws:kms:us-east-1:111122223333:key/abcd";
+        let secret = |extra: Json| {
+            let mut s = json!({ "name": "s", "kms_key_id": key });
+            s.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            s
+        };
+        let tf = json!({ "resource": { "aws_secretsmanager_secret": {
+            "ok": secret(json!({})),
+            "week": secret(json!({ "recovery_window_in_days": 7 })),
+            "short": secret(json!({ "recovery_window_in_days": 3 })),
+            "instant": secret(json!({ "recovery_window_in_days": 0 })),
+            "forced": secret(json!({ "force_delete_without_recovery": true })),
+            "aws-key": secret(json!({ "kms_key_id": "alias/aws/secretsmanager" })),
+            "no-key": { "name": "s" },
+        } } });
+        let policy = Policy::default();
+        assert_eq!(flagged_rule(&policy, tf.clone(), "secret-recovery"), ["aws_secretsmanager_secret.short"]);
+        assert_eq!(flagged_rule(&policy, tf.clone(), "ephemeral"), ["aws_secretsmanager_secret.forced", "aws_secretsmanager_secret.instant"]);
+        assert_eq!(flagged_rule(&policy, tf, "cmek"), ["aws_secretsmanager_secret.aws-key", "aws_secretsmanager_secret.no-key"]);
+    }
+
+    #[test]
+    fn gcp_secrets_need_cmek_when_required() {
+        let cmek = json!([{ "kms_key_name": "projects/p/locations/global/keyRings/r/cryptoKeys/k" }]);
+        let tf = json!({ "resource": { "google_secret_manager_secret": {
+            "auto": { "replication": [{ "auto": [{ "customer_managed_encryption": cmek }] }] },
+            "replicas": { "replication": [{ "user_managed": [{ "replicas": [
+                { "location": "europe-west1", "customer_managed_encryption": cmek },
+                { "location": "europe-west4" },
+            ] }] }] },
+            "google-managed": { "replication": [{ "auto": [{}] }] },
+        } } });
+        let policy = with_rules(PolicyRules { require_cmek: true, ..Default::default() });
+        assert_eq!(flagged_rule(&policy, tf.clone(), "cmek"), ["google_secret_manager_secret.google-managed", "google_secret_manager_secret.replicas"]);
+        assert!(flagged_rule(&Policy::default(), tf, "cmek").is_empty());
+    }
+
+    fn policy_file(test: &str, yaml: &str) -> Result<Policy> {
+        let path = std::env::temp_dir().join(format!("r2iac-policy-{}-{}.yml", test, std::process::id()));
+        std::fs::write(&path, yaml).unwrap();
+        let policy = Policy::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        policy
+    }
+
+    #[test]
+    fn policy_file_validation() {
+        let policy = policy_file("ok", "required_tags: [owner]\nseverity: { acr-admin: deny }\nallowed_sizes: { aws_instance.instance_type: ['t3.*'] }\n").unwrap();
+        assert_eq!(policy.rules.required_tags, ["owner"]);
+        assert_eq!(policy.severity("acr-admin"), Severity::Deny);
+
+        for (yaml, msg) in [
+            ("required_encryption: [aws_sqs_queue]", "required_encryption: no built-in encryption check for 'aws_sqs_queue'"),
+            ("severity: { encyption: warn }", "severity: unknown rule 'encyption'"),
+            ("allowed_sizes: { instance_type: [t3.micro] }", "allowed_sizes: 'instance_type' must be <resource type>.<attribute>"),
+            ("naming: { aws_s3_bucket: '^(acme' }", "regex parse error"),
+            ("required_tag: [owner]", "unknown field `required_tag`"),
+            ("custom_rules: [{ id: encryption, resource_type: '*', path: x, op: present }]", "custom rule 'encryption': id clashes with a built-in rule"),
+            ("custom_rules: [{ id: tier, resource_type: '*', path: x, op: eq }]", "custom rule 'tier': op Eq needs a value"),
+            ("custom_rules: [{ id: tier, resource_type: '*', path: x, op: gte, value: high }]", "custom rule 'tier': Gte needs a numeric value"),
+            ("custom_rules: [{ id: tier, resource_type: '*', path: x, op: matches, value: '(' }]", "custom rule 'tier'"),
+            ("custom_rules: [{ id: tier, resource_type: '*', path: x, op: present }, { id: tier, resource_type: '*', path: y, op: absent }]", "custom rule 'tier' is defined twice"),
+        ] {
+            let err = format!("{:#}", policy_file("bad", yaml).unwrap_err());
+            assert!(err.contains("policy file") && err.contains(msg), "{}: {}", yaml, err);
+        }
+        let err = format!("{:#}", Policy::from_file(Path::new("/nonexistent/policy.yml")).unwrap_err());
+        assert!(err.starts_with("read policy file /nonexistent/policy.yml"), "{}", err);
+    }
+
+    #[test]
+    fn severity_overrides_and_strict() {
+        assert_eq!(Policy::default().severity("acr-admin"), Severity::Warn);
+        assert_eq!(Policy::default().severity("encryption"), Severity::Deny);
+        let policy = with_rules(PolicyRules { severity: [("encryption".to_string(), Severity::Warn)].into(), ..Default::default() });
+        let tf = json!({ "resource": {
+            "aws_ebs_volume": { "data": {} },
+            "azurerm_container_registry": { "main": { "admin_enabled": true } },
+        } });
+        let report = policy.check_tf_json(&tf, &Exemptions::new());
+        assert_eq!(report.denies().count(), 0);
+        assert_eq!(report.warnings().map(|v| v.rule.as_str()).collect::<Vec<_>>(), ["acr-admin", "encryption"]);
+
+        let strict = report.strict();
+        assert_eq!(strict.warnings().count(), 0);
+        assert_eq!(strict.summary(), "2 policy violations (2 deny, 0 warn)");
     }
 }
@@ -1,7 +1,16 @@
 use anyhow::{Context, Result};
-use serde_json::Value as Json;
-use std::path::Path;
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Runner { Terraform, Tofu }
@@ -13,31 +22,986 @@ pub fn pick_runner(prefer: Option<Runner>) -> Result<Runner> {
     else { anyhow::bail!("Neither 'tofu' nor 'terraform' found in PATH") }
 }
 
+/// The rendered config, as [`write_tf_json`] names it in the output directory.
+pub const TF_FILE: &str = "main.tf.json";
+
+/// The runner's own version, from `version -json`.
+pub fn runner_version(r: Runner) -> Result<semver::Version> {
+    let o = Command::new(bin(r)).args(["version", "-json"]).output()
+        .with_context(|| format!("spawn {} version", bin(r)))?;
+    if !o.status.success() { anyhow::bail!("{} version failed: {}", bin(r), String::from_utf8_lossy(&o.stderr).trim()) }
+    parse_version(r, &o.stdout)
+}
+
+fn parse_version(r: Runner, stdout: &[u8]) -> Result<semver::Version> {
+    let doc: Json = serde_json::from_slice(stdout).with_context(|| format!("parse {} version JSON", bin(r)))?;
+    // OpenTofu reports under the terraform key too, for compatibility.
+    let v = doc.get("terraform_version").or_else(|| doc.get("tofu_version")).and_then(|v| v.as_str())
+        .with_context(|| format!("{} version -json has no version", bin(r)))?;
+    semver::Version::parse(v).with_context(|| format!("{} version '{}'", bin(r), v))
+}
+
+/// Like [`pick_runner`], but the runner must satisfy `required` (e.g. `">= 1.5, < 2"`); in auto mode
+/// whichever installed binary does is used, tofu first.
+pub fn pick_runner_matching(prefer: Option<Runner>, required: Option<&str>) -> Result<Runner> {
+    let Some(required) = required else { return pick_runner(prefer) };
+    let candidates = match prefer { Some(p) => vec![p], None => vec![Runner::Tofu, Runner::Terraform] };
+    first_matching(required, &candidates, |r| {
+        if prefer.is_none() && which::which(bin(r)).is_err() { return Ok(None); }
+        runner_version(r).map(Some)
+    })
+}
+
+/// The first of `candidates` whose `version` (`None` when not installed) satisfies `required`;
+/// versions are only asked for until one matches.
+fn first_matching(required: &str, candidates: &[Runner], mut version: impl FnMut(Runner) -> Result<Option<semver::Version>>) -> Result<Runner> {
+    let req = semver::VersionReq::parse(required).with_context(|| format!("required_runner_version '{}'", required))?;
+    let mut found = Vec::new();
+    for &r in candidates {
+        match version(r)? {
+            None => found.push(format!("{} not found", bin(r))),
+            Some(v) if req.matches(&v) => return Ok(r),
+            Some(v) => found.push(format!("{} {}", bin(r), v)),
+        }
+    }
+    anyhow::bail!("no runner satisfies required_runner_version '{}': {}", required, found.join(", "))
+}
+
 pub fn write_tf_json(tf: &Json, out: &Path) -> Result<()> {
     std::fs::create_dir_all(out)?;
-    std::fs::write(out.join("main.tf.json"), serde_json::to_string_pretty(tf)?)?;
+    std::fs::write(out.join(TF_FILE), serde_json::to_string_pretty(tf)?)?;
     Ok(())
 }
 
+/// Where state lives, e.g. `{ type: s3, bucket, key, region }`; rendered to `terraform.backend.<type>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag="type", rename_all="snake_case")]
+pub enum Backend {
+    S3 {
+        #[serde(default)] bucket: Option<String>,
+        #[serde(default)] key: Option<String>,
+        #[serde(default)] region: Option<String>,
+        #[serde(default)] dynamodb_table: Option<String>,
+        #[serde(default="some_true")] encrypt: Option<bool>,
+    },
+    Gcs {
+        #[serde(default)] bucket: Option<String>,
+        #[serde(default)] prefix: Option<String>,
+    },
+    Azurerm {
+        #[serde(default)] storage_account_name: Option<String>,
+        #[serde(default)] container_name: Option<String>,
+        #[serde(default)] key: Option<String>,
+        #[serde(default)] resource_group_name: Option<String>,
+    },
+    Local {
+        #[serde(default)] path: Option<String>,
+    },
+}
+
+fn some_true() -> Option<bool> { Some(true) }
+
+impl Backend {
+    pub fn to_tf_json(&self) -> Result<Json> {
+        type Fields<'a> = Vec<(&'static str, &'a Option<String>)>;
+        let (kind, required, optional): (&str, Fields, Fields) = match self {
+            Backend::S3 { bucket, key, region, dynamodb_table, .. } =>
+                ("s3", vec![("bucket", bucket), ("key", key), ("region", region)], vec![("dynamodb_table", dynamodb_table)]),
+            Backend::Gcs { bucket, prefix } => ("gcs", vec![("bucket", bucket)], vec![("prefix", prefix)]),
+            Backend::Azurerm { storage_account_name, container_name, key, resource_group_name } =>
+                ("azurerm", vec![("storage_account_name", storage_account_name), ("container_name", container_name), ("key", key)],
+                 vec![("resource_group_name", resource_group_name)]),
+            Backend::Local { path } => ("local", vec![], vec![("path", path)]),
+        };
+        let mut body = json!({});
+        for (field, value) in required {
+            match value.as_deref().filter(|v| !v.is_empty()) {
+                Some(v) => body[field] = json!(v),
+                None => anyhow::bail!("backend.{}: {} is required", kind, field),
+            }
+        }
+        for (field, value) in optional {
+            if let Some(v) = value { body[field] = json!(v); }
+        }
+        if let Backend::S3 { encrypt: Some(e), .. } = self { body["encrypt"] = json!(e); }
+        Ok(json!({ "terraform": { "backend": { kind: body } } }))
+    }
+}
+
 fn bin(r: Runner) -> &'static str { match r { Runner::Terraform => "terraform", Runner::Tofu => "tofu" } }
 
-pub fn run_init(r: Runner, out: &Path) -> Result<()> {
-    let st = Command::new(bin(r)).args(["-chdir", out.to_str().unwrap(), "init"]).status()
-        .context("spawn init")?;
-    if !st.success() { anyhow::bail!("init failed") } ; Ok(())
+/// Every `<type>.<name>` declared under `resource` in a rendered tf.json, sorted.
+pub fn resource_addresses(tf: &Json) -> Vec<String> {
+    let mut out: Vec<String> = tf.get("resource").and_then(|r| r.as_object()).into_iter().flatten()
+        .flat_map(|(t, res)| res.as_object().into_iter().flatten().map(move |(n, _)| format!("{}.{}", t, n)))
+        .collect();
+    out.sort();
+    out
+}
+
+/// Process settings shared by every runner invocation.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// `-parallelism=`; the runner's own default (10) when unset.
+    pub parallelism: Option<u32>,
+    /// `-lock-timeout=`, how long to wait for a state lock held by someone else.
+    pub lock_timeout: Option<Duration>,
+    /// `false` passes `-refresh=false`, planning against state as recorded.
+    pub refresh: bool,
+    /// Set on the spawned process only, e.g. `AWS_PROFILE`.
+    pub env: BTreeMap<String, String>,
+    /// Like `env`, for values that must not touch disk or logs: sensitive `TF_VAR_`s, credentials.
+    pub secret_env: BTreeMap<String, SecretString>,
+    /// Appended to plan, apply, destroy and import after their own flags.
+    pub extra_args: Vec<String>,
+    /// Inherit stdio instead of forwarding output through `tracing`, for color and prompts.
+    pub raw_output: bool,
+    /// Limits plan, apply and destroy to these addresses and their dependencies, via `-target=`.
+    pub targets: Vec<String>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions { parallelism: None, lock_timeout: None, refresh: true, env: BTreeMap::new(), secret_env: BTreeMap::new(), raw_output: false, extra_args: Vec::new(), targets: Vec::new() }
+    }
+}
+
+/// Runner subcommands that take [`RunOptions`] flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Plan,
+    /// Plan that only updates state to match real infrastructure.
+    RefreshOnly,
+    /// Apply of a saved plan, whose path follows; targets and refresh were fixed when it was planned.
+    ApplyPlan,
+    Apply,
+    Destroy,
+    /// Plan of destroying everything in state, applied like any saved plan.
+    DestroyPlan,
+    /// Address and id follow the returned arguments.
+    Import,
+}
+
+/// Arguments after `-chdir=` for `op`, built without spawning anything. Plans take `-out=` and
+/// saved-plan applies the plan path after these.
+pub fn op_args(op: Op, opts: &RunOptions) -> Vec<String> {
+    let mut args: Vec<String> = match op {
+        Op::Plan => vec!["plan".into()],
+        Op::RefreshOnly => vec!["plan".into(), "-refresh-only".into()],
+        Op::ApplyPlan | Op::Apply => vec!["apply".into(), "-auto-approve".into()],
+        Op::Destroy => vec!["destroy".into(), "-auto-approve".into()],
+        Op::DestroyPlan => vec!["plan".into(), "-destroy".into()],
+        Op::Import => vec!["import".into()],
+    };
+    args.extend(capture_args(opts).iter().map(|a| a.to_string()));
+    if let Some(t) = opts.lock_timeout {
+        // Go duration syntax; whole seconds unless sub-second precision was asked for.
+        args.push(if t.subsec_nanos() == 0 { format!("-lock-timeout={}s", t.as_secs()) } else { format!("-lock-timeout={}ms", t.as_millis()) });
+    }
+    if let Some(p) = opts.parallelism { args.push(format!("-parallelism={}", p)); }
+    if matches!(op, Op::Plan | Op::Apply | Op::Destroy | Op::DestroyPlan) && !opts.refresh { args.push("-refresh=false".into()); }
+    if matches!(op, Op::Plan | Op::RefreshOnly | Op::Apply | Op::Destroy | Op::DestroyPlan) {
+        args.extend(opts.targets.iter().map(|t| format!("-target={}", t)));
+    }
+    args.extend(opts.extra_args.iter().cloned());
+    args
+}
+
+/// Captured output has no terminal behind it: nothing can answer a prompt or render color.
+fn capture_args(opts: &RunOptions) -> &'static [&'static str] {
+    if opts.raw_output { &[] } else { &["-input=false", "-no-color"] }
+}
+
+/// Lines of runner stderr kept for the error when a command fails.
+const STDERR_TAIL: usize = 20;
+
+/// Runs `cmd`, forwarding each output line through `tracing` unless `opts.raw_output`; returns the stderr tail.
+fn stream(cmd: &mut Command, opts: &RunOptions) -> std::io::Result<(ExitStatus, Vec<String>)> {
+    if opts.raw_output { return Ok((cmd.status()?, Vec::new())); }
+    let mut child = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let tail = std::thread::spawn(move || {
+        let mut tail = VecDeque::with_capacity(STDERR_TAIL);
+        for line in BufReader::new(stderr).lines().map_while(|l| l.ok()).filter(|l| !l.trim().is_empty()) {
+            if line.trim_start().starts_with("Error") { tracing::error!(target: "runner", "{}", line); }
+            else { tracing::warn!(target: "runner", "{}", line); }
+            if tail.len() == STDERR_TAIL { tail.pop_front(); }
+            tail.push_back(line);
+        }
+        Vec::from(tail)
+    });
+    for line in BufReader::new(child.stdout.take().expect("stdout is piped")).lines().map_while(|l| l.ok()) {
+        if !line.trim().is_empty() { tracing::info!(target: "runner", "{}", line); }
+    }
+    let status = child.wait()?;
+    Ok((status, tail.join().unwrap_or_default()))
+}
+
+fn failed(what: &str, tail: &[String]) -> anyhow::Error {
+    if tail.is_empty() { anyhow::anyhow!("{} failed", what) } else { anyhow::anyhow!("{} failed: {}", what, tail.join("\n")) }
+}
+
+fn run(cmd: &mut Command, what: &str, opts: &RunOptions) -> Result<()> {
+    let (st, tail) = stream(cmd, opts).with_context(|| format!("spawn {}", what))?;
+    if !st.success() { return Err(failed(what, &tail)); }
+    Ok(())
+}
+
+/// The runner with `-chdir=<out>`, which it only accepts as a single token.
+fn base_cmd(r: Runner, out: &Path) -> Command {
+    // Built as an OsString so a non-UTF-8 output directory is passed through untouched.
+    let mut chdir = OsString::from("-chdir=");
+    chdir.push(out);
+    let mut cmd = Command::new(bin(r));
+    cmd.arg(chdir);
+    cmd
+}
+
+fn command(r: Runner, out: &Path, opts: &RunOptions) -> Command {
+    let mut cmd = base_cmd(r, out);
+    cmd.envs(&opts.env);
+    for (k, v) in &opts.secret_env { cmd.env(k, v.expose_secret()); }
+    cmd
 }
-pub fn run_plan(r: Runner, out: &Path) -> Result<()> {
-    let st = Command::new(bin(r)).args(["-chdir", out.to_str().unwrap(), "plan"]).status()
+
+/// Non-sensitive variable values, picked up by the runner without any `-var` flags.
+pub const TFVARS_FILE: &str = "r2iac.auto.tfvars.json";
+
+/// Writes `values` to [`TFVARS_FILE`], or removes a stale one when there are none.
+pub fn write_tfvars_json(values: &BTreeMap<String, Json>, out: &Path) -> Result<()> {
+    let path = out.join(TFVARS_FILE);
+    if values.is_empty() {
+        if path.exists() { std::fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?; }
+        return Ok(());
+    }
+    std::fs::create_dir_all(out)?;
+    std::fs::write(&path, serde_json::to_string_pretty(values)?)?;
+    Ok(())
+}
+
+/// [`run_init_with`] with default [`RunOptions`]; kept for callers of the original API.
+pub fn run_init(r: Runner, out: &Path) -> Result<()> { run_init_with(r, out, &RunOptions::default()) }
+pub fn run_init_with(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).arg("init").args(capture_args(opts)), "init", opts)
+}
+/// Installs providers only, for commands like validate that must not need backend credentials.
+pub fn run_init_no_backend(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(["init", "-backend=false"]).args(capture_args(opts)), "init", opts)
+}
+/// Terraform only accepts names that need no escaping in a URL path segment.
+pub fn check_workspace_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        anyhow::bail!("workspace '{}' must be non-empty and use only letters, digits, '-', '_' and '.'", name);
+    }
+    Ok(())
+}
+pub fn run_workspace_new(r: Runner, out: &Path, name: &str, opts: &RunOptions) -> Result<()> {
+    check_workspace_name(name)?;
+    run(command(r, out, opts).args(["workspace", "new", name]), &format!("workspace new {}", name), opts)
+}
+/// Selects `name`, creating it first when it does not exist yet.
+pub fn run_workspace_select(r: Runner, out: &Path, name: &str, opts: &RunOptions) -> Result<()> {
+    check_workspace_name(name)?;
+    let o = command(r, out, opts).args(["workspace", "select", name]).output()
+        .context("spawn workspace select")?;
+    if o.status.success() { return Ok(()); }
+    let err = String::from_utf8_lossy(&o.stderr);
+    if !err.contains("doesn't exist") && !err.contains("does not exist") {
+        anyhow::bail!("workspace select {} failed: {}", name, err.trim());
+    }
+    run_workspace_new(r, out, name, opts)
+}
+
+/// Saved by [`run_plan_with`] in the output directory, for [`show_plan_json`] and [`run_apply_plan`].
+pub const PLAN_FILE: &str = "tfplan";
+
+pub fn run_plan(r: Runner, out: &Path) -> Result<()> { run_plan_with(r, out, &RunOptions::default()) }
+pub fn run_plan_with(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::Plan, opts)).arg(format!("-out={}", PLAN_FILE)), "plan", opts)
+}
+/// The runner resolves paths inside the output directory, so a caller's path must be made absolute.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    std::path::absolute(path).with_context(|| format!("resolve {}", path.display()))
+}
+/// Hex SHA-256 of the [`TF_FILE`] and any [`TFVARS_FILE`] in `out`, so a saved plan can be tied
+/// to the config and variable values it came from.
+pub fn config_hash(out: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for (name, required) in [(TF_FILE, true), (TFVARS_FILE, false)] {
+        let path = out.join(name);
+        let bytes = match std::fs::read(&path) {
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => continue,
+            r => r.with_context(|| format!("read {}", path.display()))?,
+        };
+        // Named and length-prefixed, so content cannot shift between the files unnoticed.
+        hasher.update(name);
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(&bytes);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+/// Written next to a saved plan: `<plan>.sha256`, holding [`config_hash`] at plan time.
+pub fn plan_hash_path(plan_path: &Path) -> PathBuf {
+    let mut p = plan_path.as_os_str().to_owned();
+    p.push(".sha256");
+    PathBuf::from(p)
+}
+/// [`run_plan_with`] into `plan_path`, kept for a later [`run_apply_saved`].
+pub fn run_plan_save(r: Runner, out: &Path, plan_path: &Path, opts: &RunOptions) -> Result<()> {
+    let plan = absolute(plan_path)?;
+    let mut out_flag = OsString::from("-out=");
+    out_flag.push(&plan);
+    run(command(r, out, opts).args(op_args(Op::Plan, opts)).arg(out_flag), "plan", opts)?;
+    let hash_path = plan_hash_path(&plan);
+    std::fs::write(&hash_path, config_hash(out)?).with_context(|| format!("write {}", hash_path.display()))
+}
+/// Fails unless `plan_path` was saved by [`run_plan_save`] from the [`TF_FILE`] and [`TFVARS_FILE`] now in `out`.
+pub fn check_saved_plan(out: &Path, plan_path: &Path) -> Result<()> {
+    let hash_path = plan_hash_path(plan_path);
+    let saved = std::fs::read_to_string(&hash_path)
+        .with_context(|| format!("read {}; was {} saved by plan --save?", hash_path.display(), plan_path.display()))?;
+    if saved.trim() != config_hash(out)? {
+        anyhow::bail!("{} was planned against a different {} or {}; plan again", plan_path.display(), TF_FILE, TFVARS_FILE);
+    }
+    Ok(())
+}
+/// Applies `plan_path` as saved, refusing when the rendered config has changed since it was planned.
+pub fn run_apply_saved(r: Runner, out: &Path, plan_path: &Path, opts: &RunOptions) -> Result<()> {
+    check_saved_plan(out, plan_path)?;
+    run(command(r, out, opts).args(op_args(Op::ApplyPlan, opts)).arg(absolute(plan_path)?), "apply", opts)
+}
+/// Plans `-refresh-only` into [`PLAN_FILE`]; applying it with [`run_apply_plan`] updates state
+/// and nothing else. `opts.refresh` does not apply.
+pub fn run_plan_refresh_only(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::RefreshOnly, opts)).arg(format!("-out={}", PLAN_FILE)), "refresh-only plan", opts)
+}
+/// [`run_plan_refresh_only`], then the saved plan parsed; its `drift` is what changed outside the tool.
+pub fn run_plan_refresh_only_json(r: Runner, out: &Path, opts: &RunOptions) -> Result<PlanSummary> {
+    run_plan_refresh_only(r, out, opts)?;
+    PlanSummary::from_plan_json(show_plan_json(r, out, opts)?)
+}
+/// Plans `-destroy` into [`PLAN_FILE`], so what would be deleted can be checked before
+/// [`run_apply_plan`] deletes exactly that.
+pub fn run_plan_destroy(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::DestroyPlan, opts)).arg(format!("-out={}", PLAN_FILE)), "destroy plan", opts)
+}
+/// [`run_plan_destroy`], then the saved plan parsed.
+pub fn run_plan_destroy_json(r: Runner, out: &Path, opts: &RunOptions) -> Result<PlanSummary> {
+    run_plan_destroy(r, out, opts)?;
+    PlanSummary::from_plan_json(show_plan_json(r, out, opts)?)
+}
+/// Whether a `-detailed-exitcode` plan found anything to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanOutcome { NoChanges, Changes }
+
+/// [`run_plan_with`] with `-detailed-exitcode`, so exit code 2 means changes rather than failure.
+pub fn run_plan_detailed(r: Runner, out: &Path, opts: &RunOptions) -> Result<PlanOutcome> {
+    let (st, tail) = stream(command(r, out, opts).args(op_args(Op::Plan, opts)).arg(format!("-out={}", PLAN_FILE)).arg("-detailed-exitcode"), opts)
         .context("spawn plan")?;
-    if !st.success() { anyhow::bail!("plan failed") } ; Ok(())
+    match st.code() {
+        Some(0) => Ok(PlanOutcome::NoChanges),
+        Some(2) => Ok(PlanOutcome::Changes),
+        code => Err(failed(&format!("plan ({})", code.map_or("signal".to_string(), |c| format!("exit {}", c))), &tail)),
+    }
+}
+/// The saved plan as `show -json` prints it.
+pub fn show_plan_json(r: Runner, out: &Path, opts: &RunOptions) -> Result<Json> {
+    show_saved_plan_json(r, out, &out.join(PLAN_FILE), opts)
+}
+/// [`show_plan_json`] for a plan saved anywhere, e.g. by [`run_plan_save`].
+pub fn show_saved_plan_json(r: Runner, out: &Path, plan_path: &Path, opts: &RunOptions) -> Result<Json> {
+    let o = command(r, out, opts).args(["show", "-json", "-no-color"]).arg(absolute(plan_path)?).output()
+        .context("spawn show")?;
+    if !o.status.success() { anyhow::bail!("show failed: {}", String::from_utf8_lossy(&o.stderr).trim()) }
+    serde_json::from_slice(&o.stdout).context("parse plan JSON")
+}
+/// What a plan does to one resource, folded from its `change.actions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action { NoOp, Read, Create, Update, Delete, Replace }
+
+impl Action {
+    fn from_actions(actions: &[String]) -> Self {
+        match actions.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+            ["create"] => Action::Create,
+            ["update"] => Action::Update,
+            ["delete"] => Action::Delete,
+            ["read"] => Action::Read,
+            // Either order: delete-then-create or create_before_destroy.
+            [_, _] => Action::Replace,
+            _ => Action::NoOp,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Action::NoOp => "no-op", Action::Read => "read", Action::Create => "create",
+            Action::Update => "update", Action::Delete => "delete", Action::Replace => "replace",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResourceChange {
+    pub address: String,
+    pub action: Action,
+    pub before: Json,
+    pub after: Json,
+}
+
+impl ResourceChange {
+    /// Attributes that differ between `before` and `after`, one level into nested objects
+    /// (e.g. `tags.env`); empty when either side is missing, as for deletes.
+    pub fn changed_attributes(&self) -> Vec<String> {
+        let (Some(before), Some(after)) = (self.before.as_object(), self.after.as_object()) else { return Vec::new() };
+        let mut keys: Vec<_> = before.keys().chain(after.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        let mut out = Vec::new();
+        for k in keys {
+            match (before.get(k), after.get(k)) {
+                (a, b) if a == b => {}
+                (Some(Json::Object(a)), Some(Json::Object(b))) => {
+                    let mut sub: Vec<_> = a.keys().chain(b.keys()).filter(|s| a.get(*s) != b.get(*s)).collect();
+                    sub.sort();
+                    sub.dedup();
+                    out.extend(sub.into_iter().map(|s| format!("{}.{}", k, s)));
+                }
+                _ => out.push(k.clone()),
+            }
+        }
+        out
+    }
+}
+
+/// What a refresh found changed outside the tool, as a table of address and attributes.
+pub struct DriftReport<'a>(pub &'a [ResourceChange]);
+
+impl fmt::Display for DriftReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() { return write!(f, "No drift: state matches the real infrastructure."); }
+        let width = self.0.iter().map(|c| c.address.len()).max().unwrap_or(0).max("ADDRESS".len());
+        writeln!(f, "{:<8}  {:<width$}  ATTRIBUTES", "DRIFT", "ADDRESS")?;
+        for c in self.0 {
+            let attrs = match (c.action, c.changed_attributes()) {
+                (Action::Delete, _) => "deleted outside r2iac".to_string(),
+                (_, a) if a.is_empty() => "-".to_string(),
+                (_, a) => a.join(", "),
+            };
+            writeln!(f, "{:<8}  {:<width$}  {}", c.action, c.address, attrs)?;
+        }
+        write!(f, "Drift: {} resource(s) changed outside r2iac.", self.0.len())
+    }
+}
+
+/// A saved plan, parsed from `show -json`. `plan` keeps the full document for checks that
+/// need more than the summary.
+#[derive(Debug, Clone)]
+pub struct PlanSummary {
+    pub to_add: usize,
+    pub to_change: usize,
+    pub to_destroy: usize,
+    pub resource_changes: Vec<ResourceChange>,
+    /// Objects whose real state differs from what state recorded, from `resource_drift`.
+    pub drift: Vec<ResourceChange>,
+    pub plan: Json,
+}
+
+// Only the fields read here; serde skips the rest, which grows between Terraform versions.
+#[derive(Deserialize)]
+struct RawPlan { #[serde(default)] resource_changes: Vec<RawResourceChange>, #[serde(default)] resource_drift: Vec<RawResourceChange> }
+#[derive(Deserialize)]
+struct RawResourceChange { address: String, change: RawChange }
+#[derive(Deserialize)]
+struct RawChange { #[serde(default)] actions: Vec<String>, #[serde(default)] before: Json, #[serde(default)] after: Json }
+
+impl PlanSummary {
+    pub fn from_plan_json(plan: Json) -> Result<Self> {
+        let raw: RawPlan = serde_json::from_value(plan.clone()).context("parse plan resource_changes")?;
+        let change = |rc: RawResourceChange| ResourceChange {
+            address: rc.address, action: Action::from_actions(&rc.change.actions), before: rc.change.before, after: rc.change.after,
+        };
+        let resource_changes: Vec<_> = raw.resource_changes.into_iter().map(change).collect();
+        let drift = raw.resource_drift.into_iter().map(change).filter(|c| c.action != Action::NoOp).collect();
+        let count = |f: fn(Action) -> bool| resource_changes.iter().filter(|c| f(c.action)).count();
+        Ok(Self {
+            to_add: count(|a| matches!(a, Action::Create | Action::Replace)),
+            to_change: count(|a| a == Action::Update),
+            to_destroy: count(|a| matches!(a, Action::Delete | Action::Replace)),
+            resource_changes, drift, plan,
+        })
+    }
+}
+
+/// Every changing resource, then Terraform's own closing line.
+impl fmt::Display for PlanSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows: Vec<_> = self.resource_changes.iter().filter(|c| !matches!(c.action, Action::NoOp | Action::Read)).collect();
+        rows.sort_by(|a, b| (a.action, &a.address).cmp(&(b.action, &b.address)));
+        if !rows.is_empty() {
+            writeln!(f, "{:<8}  ADDRESS", "ACTION")?;
+            for c in rows { writeln!(f, "{:<8}  {}", c.action, c.address)?; }
+        }
+        write!(f, "Plan: {} to add, {} to change, {} to destroy.", self.to_add, self.to_change, self.to_destroy)
+    }
+}
+
+/// [`run_plan_with`], then the saved plan parsed by [`show_plan_json`].
+pub fn run_plan_json(r: Runner, out: &Path, opts: &RunOptions) -> Result<PlanSummary> {
+    run_plan_with(r, out, opts)?;
+    PlanSummary::from_plan_json(show_plan_json(r, out, opts)?)
+}
+/// `output -json`: each output name to its `{ sensitive, type, value }` entry.
+pub fn run_output(r: Runner, out: &Path, opts: &RunOptions) -> Result<BTreeMap<String, Json>> {
+    let o = command(r, out, opts).args(["output", "-json"]).output()
+        .context("spawn output")?;
+    if !o.status.success() { anyhow::bail!("output failed: {}", String::from_utf8_lossy(&o.stderr).trim()) }
+    parse_outputs(&o.stdout)
+}
+
+fn parse_outputs(stdout: &[u8]) -> Result<BTreeMap<String, Json>> {
+    serde_json::from_slice(stdout).context("parse output JSON")
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum Severity { Error, Warning }
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self { Severity::Error => "error", Severity::Warning => "warning" })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pos { pub line: usize, pub column: usize }
+
+/// Where in the config a diagnostic points; `filename` is relative to the output directory.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceRange { pub filename: String, pub start: Pos, pub end: Pos }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub summary: String,
+    #[serde(default)] pub detail: String,
+    #[serde(default)] pub range: Option<SourceRange>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.summary)?;
+        if let Some(r) = &self.range { write!(f, "\n  at {}:{}:{}", r.filename, r.start.line, r.start.column)?; }
+        for l in self.detail.lines().filter(|l| !l.trim().is_empty()) { write!(f, "\n  {}", l)?; }
+        Ok(())
+    }
+}
+
+/// `validate -json`, which reports an invalid config in the document rather than by failing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidationResult {
+    pub valid: bool,
+    #[serde(default)] pub error_count: usize,
+    #[serde(default)] pub warning_count: usize,
+    #[serde(default)] pub diagnostics: Vec<Diagnostic>,
+}
+
+pub fn run_validate(r: Runner, out: &Path, opts: &RunOptions) -> Result<ValidationResult> {
+    let o = command(r, out, opts).args(["validate", "-json", "-no-color"]).output()
+        .context("spawn validate")?;
+    parse_validation(&o.stdout, &o.stderr)
+}
+
+// Exit 1 with a parseable document just means invalid; anything else is the runner failing.
+fn parse_validation(stdout: &[u8], stderr: &[u8]) -> Result<ValidationResult> {
+    serde_json::from_slice(stdout)
+        .with_context(|| format!("validate failed: {}", String::from_utf8_lossy(stderr).trim()))
+}
+
+/// The `<type>.<name>` whose block holds 1-based `line` of a [`TF_FILE`] written by [`write_tf_json`].
+pub fn address_at_line(tf_text: &str, line: usize) -> Option<String> {
+    let (mut in_resource, mut ty, mut addr) = (false, None::<String>, None);
+    for l in tf_text.lines().take(line) {
+        // Pretty-printed with two-space indents: resource at 2, type at 4, name at 6.
+        let key = |indent: usize| l.strip_prefix(" ".repeat(indent).as_str())
+            .and_then(|rest| rest.strip_prefix('"')).and_then(|rest| rest.split('"').next());
+        if let Some(k) = key(2) { in_resource = k == "resource"; ty = None; addr = None; }
+        else if !in_resource { continue; }
+        else if let Some(k) = key(4) { ty = Some(k.to_string()); addr = None; }
+        else if let Some(k) = key(6) { addr = ty.as_ref().map(|t| format!("{}.{}", t, k)); }
+    }
+    addr
+}
+pub fn run_import(r: Runner, out: &Path, address: &str, id: &str, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::Import, opts)).args([address, id]), &format!("import {}", address), opts)
+}
+/// Addresses tracked in state, one per line of `state list`.
+pub fn run_state_list(r: Runner, out: &Path, opts: &RunOptions) -> Result<Vec<String>> {
+    let o = command(r, out, opts).args(["state", "list"]).output()
+        .context("spawn state list")?;
+    if !o.status.success() { anyhow::bail!("state list failed: {}", String::from_utf8_lossy(&o.stderr).trim()) }
+    Ok(String::from_utf8_lossy(&o.stdout).lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect())
+}
+/// Forgets `addresses` without destroying them; the real objects are left unmanaged.
+pub fn run_state_rm(r: Runner, out: &Path, addresses: &[String], opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(["state", "rm"]).args(addresses), &format!("state rm {}", addresses.join(" ")), opts)
+}
+/// Re-keys state from `from` to `to`, e.g. after renaming a resource, so the plan does not replace it.
+pub fn run_state_mv(r: Runner, out: &Path, from: &str, to: &str, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(["state", "mv", from, to]), &format!("state mv {} {}", from, to), opts)
 }
-pub fn run_apply(r: Runner, out: &Path) -> Result<()> {
-    let st = Command::new(bin(r)).args(["-chdir", out.to_str().unwrap(), "apply", "-auto-approve"]).status()
-        .context("spawn apply")?;
-    if !st.success() { anyhow::bail!("apply failed") } ; Ok(())
+/// Applies exactly the saved plan, so what was checked is what gets applied; its targets are baked in.
+pub fn run_apply_plan(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::ApplyPlan, opts)).arg(PLAN_FILE), "apply", opts)
 }
-pub fn run_destroy(r: Runner, out: &Path) -> Result<()> {
-    let st = Command::new(bin(r)).args(["-chdir", out.to_str().unwrap(), "destroy", "-auto-approve"]).status()
-        .context("spawn destroy")?;
-    if !st.success() { anyhow::bail!("destroy failed") } ; Ok(())
+pub fn run_apply(r: Runner, out: &Path) -> Result<()> { run_apply_with(r, out, &RunOptions::default()) }
+pub fn run_apply_with(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::Apply, opts)), "apply", opts)
+}
+pub fn run_destroy(r: Runner, out: &Path) -> Result<()> { run_destroy_with(r, out, &RunOptions::default()) }
+pub fn run_destroy_with(r: Runner, out: &Path, opts: &RunOptions) -> Result<()> {
+    run(command(r, out, opts).args(op_args(Op::Destroy, opts)), "destroy", opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw() -> RunOptions { RunOptions { raw_output: true, ..Default::default() } }
+
+    #[test]
+    fn lock_timeout_in_seconds_or_milliseconds() {
+        let secs = RunOptions { lock_timeout: Some(Duration::from_secs(300)), ..raw() };
+        assert_eq!(op_args(Op::Plan, &secs), ["plan", "-lock-timeout=300s"]);
+        let millis = RunOptions { lock_timeout: Some(Duration::from_millis(1500)), ..raw() };
+        assert_eq!(op_args(Op::Plan, &millis), ["plan", "-lock-timeout=1500ms"]);
+    }
+
+    #[test]
+    fn parallelism_on_every_op() {
+        let opts = RunOptions { parallelism: Some(3), ..raw() };
+        for op in [Op::Plan, Op::RefreshOnly, Op::ApplyPlan, Op::Apply, Op::Destroy, Op::DestroyPlan, Op::Import] {
+            assert!(op_args(op, &opts).contains(&"-parallelism=3".to_string()), "{:?}", op);
+        }
+    }
+
+    #[test]
+    fn refresh_false_only_on_plan_apply_destroy() {
+        let opts = RunOptions { refresh: false, ..raw() };
+        for op in [Op::Plan, Op::Apply, Op::Destroy, Op::DestroyPlan] {
+            assert!(op_args(op, &opts).contains(&"-refresh=false".to_string()), "{:?}", op);
+        }
+        // A saved plan already fixed refresh, and -refresh-only rejects -refresh=false.
+        for op in [Op::RefreshOnly, Op::ApplyPlan, Op::Import] {
+            assert!(!op_args(op, &opts).contains(&"-refresh=false".to_string()), "{:?}", op);
+        }
+    }
+
+    #[test]
+    fn targets_skip_saved_plans_and_import() {
+        let opts = RunOptions { targets: vec!["aws_vpc.v".into(), "aws_subnet.a[0]".into()], ..raw() };
+        assert_eq!(op_args(Op::Destroy, &opts), ["destroy", "-auto-approve", "-target=aws_vpc.v", "-target=aws_subnet.a[0]"]);
+        assert_eq!(op_args(Op::DestroyPlan, &opts), ["plan", "-destroy", "-target=aws_vpc.v", "-target=aws_subnet.a[0]"]);
+        assert_eq!(op_args(Op::ApplyPlan, &opts), ["apply", "-auto-approve"]);
+        assert_eq!(op_args(Op::Import, &opts), ["import"]);
+    }
+
+    #[test]
+    fn extra_args_come_last() {
+        let opts = RunOptions {
+            parallelism: Some(2), lock_timeout: Some(Duration::from_secs(5)), refresh: false,
+            targets: vec!["aws_vpc.v".into()], extra_args: vec!["-compact-warnings".into()], ..Default::default()
+        };
+        assert_eq!(op_args(Op::Plan, &opts), [
+            "plan", "-input=false", "-no-color", "-lock-timeout=5s", "-parallelism=2",
+            "-refresh=false", "-target=aws_vpc.v", "-compact-warnings",
+        ]);
+    }
+
+    #[test]
+    fn base_cmd_passes_chdir_as_one_token() {
+        let cmd = base_cmd(Runner::Terraform, Path::new("/tmp/out dir"));
+        assert_eq!(cmd.get_program(), "terraform");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-chdir=/tmp/out dir"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn base_cmd_keeps_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+        let out = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/o\xff"));
+        let cmd = base_cmd(Runner::Tofu, out);
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args.len(), 1);
+        assert_eq!(args[0].as_bytes(), b"-chdir=/tmp/o\xff");
+    }
+
+    #[test]
+    fn op_args_per_op() {
+        let opts = RunOptions::default();
+        assert_eq!(op_args(Op::Plan, &opts), ["plan", "-input=false", "-no-color"]);
+        assert_eq!(op_args(Op::RefreshOnly, &opts), ["plan", "-refresh-only", "-input=false", "-no-color"]);
+        assert_eq!(op_args(Op::ApplyPlan, &opts), ["apply", "-auto-approve", "-input=false", "-no-color"]);
+        assert_eq!(op_args(Op::Apply, &opts), ["apply", "-auto-approve", "-input=false", "-no-color"]);
+        assert_eq!(op_args(Op::Destroy, &opts), ["destroy", "-auto-approve", "-input=false", "-no-color"]);
+        assert_eq!(op_args(Op::DestroyPlan, &opts), ["plan", "-destroy", "-input=false", "-no-color"]);
+        assert_eq!(op_args(Op::Import, &opts), ["import", "-input=false", "-no-color"]);
+    }
+
+    #[test]
+    fn raw_output_drops_capture_flags() {
+        assert_eq!(op_args(Op::Plan, &raw()), ["plan"]);
+        assert_eq!(op_args(Op::Import, &raw()), ["import"]);
+    }
+
+    #[test]
+    fn saved_plan_is_refused_after_config_or_tfvars_change() {
+        let out = std::env::temp_dir().join(format!("r2iac-saved-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&out).unwrap();
+        let plan = out.join("release.tfplan");
+        let save = || std::fs::write(plan_hash_path(&plan), config_hash(&out).unwrap()).unwrap();
+        std::fs::write(out.join(TF_FILE), r#"{"resource":{}}"#).unwrap();
+        std::fs::write(out.join(TFVARS_FILE), r#"{"size":1}"#).unwrap();
+        save();
+        check_saved_plan(&out, &plan).unwrap();
+
+        std::fs::write(out.join(TFVARS_FILE), r#"{"size":2}"#).unwrap();
+        let err = check_saved_plan(&out, &plan).unwrap_err();
+        assert!(err.to_string().contains("planned against a different"), "{}", err);
+
+        std::fs::remove_file(out.join(TFVARS_FILE)).unwrap();
+        assert!(check_saved_plan(&out, &plan).is_err());
+        save();
+        check_saved_plan(&out, &plan).unwrap();
+
+        std::fs::write(out.join(TF_FILE), r#"{"resource":{"aws_sqs_queue":{"q":{}}}}"#).unwrap();
+        assert!(check_saved_plan(&out, &plan).is_err());
+        std::fs::remove_dir_all(&out).unwrap();
+    }
+
+    #[test]
+    fn saved_plan_without_hash_is_refused() {
+        let err = check_saved_plan(Path::new("/nonexistent"), Path::new("/nonexistent/tfplan")).unwrap_err();
+        assert!(err.to_string().contains("saved by plan --save"), "{}", err);
+    }
+
+    /// Abridged `show -json` of a `-refresh-only` plan: one object deleted out of band, one
+    /// edited, one unchanged.
+    fn refresh_only_plan() -> Json {
+        json!({
+            "format_version": "1.2",
+            "resource_drift": [
+                { "address": "aws_sqs_queue.gone", "change": { "actions": ["delete"], "before": { "name": "gone" }, "after": null } },
+                { "address": "aws_s3_bucket.logs", "change": {
+                    "actions": ["update"],
+                    "before": { "bucket": "logs", "force_destroy": false, "tags": { "env": "prod", "owner": "ops" } },
+                    "after": { "bucket": "logs", "force_destroy": true, "tags": { "env": "staging", "owner": "ops" } },
+                } },
+                { "address": "aws_vpc.main", "change": { "actions": ["no-op"], "before": { "cidr_block": "10.0.0.0/16" }, "after": { "cidr_block": "10.0.0.0/16" } } },
+            ],
+            "resource_changes": [],
+        })
+    }
+
+    #[test]
+    fn drift_classifies_deletes_updates_and_skips_no_ops() {
+        let plan = PlanSummary::from_plan_json(refresh_only_plan()).unwrap();
+        let drift: Vec<_> = plan.drift.iter().map(|c| (c.address.as_str(), c.action)).collect();
+        assert_eq!(drift, [("aws_sqs_queue.gone", Action::Delete), ("aws_s3_bucket.logs", Action::Update)]);
+        assert_eq!(plan.drift[1].changed_attributes(), ["force_destroy", "tags.env"]);
+        assert_eq!((plan.to_add, plan.to_change, plan.to_destroy), (0, 0, 0));
+
+        let report = DriftReport(&plan.drift).to_string();
+        let lines: Vec<_> = report.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+        assert_eq!(lines, [
+            "DRIFT ADDRESS ATTRIBUTES",
+            "delete aws_sqs_queue.gone deleted outside r2iac",
+            "update aws_s3_bucket.logs force_destroy, tags.env",
+            "Drift: 2 resource(s) changed outside r2iac.",
+        ]);
+    }
+
+    #[test]
+    fn no_drift_report() {
+        let mut plan = refresh_only_plan();
+        plan["resource_drift"] = json!([]);
+        let plan = PlanSummary::from_plan_json(plan).unwrap();
+        assert_eq!(DriftReport(&plan.drift).to_string(), "No drift: state matches the real infrastructure.");
+    }
+
+    fn backend(b: Json) -> Result<Json> {
+        serde_json::from_value::<Backend>(b)?.to_tf_json()
+    }
+
+    #[test]
+    fn backend_blocks() {
+        assert_eq!(backend(json!({ "type": "s3", "bucket": "state", "key": "app.tfstate", "region": "us-east-1", "dynamodb_table": "locks" })).unwrap(),
+            json!({ "terraform": { "backend": { "s3": {
+                "bucket": "state", "key": "app.tfstate", "region": "us-east-1", "dynamodb_table": "locks", "encrypt": true,
+            } } } }));
+        assert_eq!(backend(json!({ "type": "gcs", "bucket": "state", "prefix": "app" })).unwrap(),
+            json!({ "terraform": { "backend": { "gcs": { "bucket": "state", "prefix": "app" } } } }));
+        assert_eq!(backend(json!({ "type": "azurerm", "storage_account_name": "st", "container_name": "tfstate", "key": "app.tfstate" })).unwrap(),
+            json!({ "terraform": { "backend": { "azurerm": { "storage_account_name": "st", "container_name": "tfstate", "key": "app.tfstate" } } } }));
+        assert_eq!(backend(json!({ "type": "local" })).unwrap(), json!({ "terraform": { "backend": { "local": {} } } }));
+    }
+
+    #[test]
+    fn backend_required_fields() {
+        for (b, msg) in [
+            (json!({ "type": "s3", "bucket": "state", "region": "us-east-1" }), "backend.s3: key is required"),
+            (json!({ "type": "s3", "bucket": "", "key": "k", "region": "us-east-1" }), "backend.s3: bucket is required"),
+            (json!({ "type": "gcs" }), "backend.gcs: bucket is required"),
+            (json!({ "type": "azurerm", "storage_account_name": "st", "key": "k" }), "backend.azurerm: container_name is required"),
+        ] {
+            assert_eq!(backend(b).unwrap_err().to_string(), msg);
+        }
+    }
+
+    #[test]
+    fn plan_summary_counts_and_table() {
+        let change = |address: &str, actions: &[&str]| json!({
+            "address": address, "mode": "managed", "provider_name": "registry.terraform.io/hashicorp/aws",
+            "change": { "actions": actions, "before": null, "after": {}, "after_unknown": {} },
+        });
+        let plan = PlanSummary::from_plan_json(json!({
+            "format_version": "1.2", "terraform_version": "1.9.8", "planned_values": {}, "configuration": {},
+            "resource_changes": [
+                change("aws_s3_bucket.logs", &["create"]),
+                change("aws_iam_role.app", &["update"]),
+                change("aws_sqs_queue.old", &["delete"]),
+                change("aws_instance.web", &["delete", "create"]),
+                change("aws_instance.api", &["create", "delete"]),
+                change("data.aws_caller_identity.current", &["read"]),
+                change("aws_vpc.main", &["no-op"]),
+            ],
+        })).unwrap();
+        // A replace counts as both an add and a destroy, as Terraform's own summary does.
+        assert_eq!((plan.to_add, plan.to_change, plan.to_destroy), (3, 1, 3));
+        assert_eq!(plan.resource_changes[3].action, Action::Replace);
+        assert_eq!(plan.resource_changes[4].action, Action::Replace);
+        assert!(plan.drift.is_empty());
+        let lines: Vec<_> = plan.to_string().lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+        assert_eq!(lines, [
+            "ACTION ADDRESS",
+            "create aws_s3_bucket.logs",
+            "update aws_iam_role.app",
+            "delete aws_sqs_queue.old",
+            "replace aws_instance.api",
+            "replace aws_instance.web",
+            "Plan: 3 to add, 1 to change, 3 to destroy.",
+        ]);
+
+        let empty = PlanSummary::from_plan_json(json!({ "format_version": "1.2" })).unwrap();
+        assert_eq!(empty.to_string(), "Plan: 0 to add, 0 to change, 0 to destroy.");
+        let err = PlanSummary::from_plan_json(json!({ "resource_changes": [{ "address": "aws_vpc.main" }] })).unwrap_err();
+        assert_eq!(err.to_string(), "parse plan resource_changes");
+    }
+
+    #[test]
+    fn workspace_names() {
+        for name in ["default", "staging", "pr-1234", "eu_west.1"] {
+            check_workspace_name(name).unwrap();
+        }
+        for name in ["", "feature/login", "my workspace", "prod!", "ünïcode"] {
+            let err = check_workspace_name(name).unwrap_err();
+            assert_eq!(err.to_string(), format!("workspace '{}' must be non-empty and use only letters, digits, '-', '_' and '.'", name));
+        }
+    }
+
+    #[test]
+    fn output_json_decodes_per_output() {
+        let outputs = parse_outputs(br#"{
+            "bucket_arn": { "sensitive": false, "type": "string", "value": "arn:aws:s3:::logs" },
+            "db_password": { "sensitive": true, "type": "string", "value": "hunter2" },
+            "subnet_ids": { "sensitive": false, "type": ["list", "string"], "value": ["subnet-1", "subnet-2"] }
+        }"#).unwrap();
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), ["bucket_arn", "db_password", "subnet_ids"]);
+        assert_eq!(outputs["db_password"]["sensitive"], true);
+        assert_eq!(outputs["subnet_ids"]["value"], json!(["subnet-1", "subnet-2"]));
+        // No outputs declared.
+        assert!(parse_outputs(b"{}").unwrap().is_empty());
+        assert_eq!(parse_outputs(b"Error: no state").unwrap_err().to_string(), "parse output JSON");
+    }
+
+    #[test]
+    fn validate_diagnostics_point_at_the_resource() {
+        let tf = json!({
+            "terraform": { "required_providers": { "aws": { "source": "hashicorp/aws" } } },
+            "resource": {
+                "aws_s3_bucket": { "logs": { "bucket": "logs" } },
+                "aws_instance": { "web": { "ami": "ami-123", "instance_type": "t3.micro" } },
+            },
+            "output": { "ip": { "value": "${aws_instance.web.public_ip}" } },
+        });
+        let text = serde_json::to_string_pretty(&tf).unwrap();
+        let line = |needle: &str| text.lines().position(|l| l.contains(needle)).unwrap() + 1;
+        assert_eq!(address_at_line(&text, line("\"instance_type\"")).as_deref(), Some("aws_instance.web"));
+        assert_eq!(address_at_line(&text, line("\"bucket\"")).as_deref(), Some("aws_s3_bucket.logs"));
+        assert_eq!(address_at_line(&text, line("\"hashicorp/aws\"")), None);
+        assert_eq!(address_at_line(&text, line("public_ip")), None);
+
+        let stdout = format!(r#"{{
+            "format_version": "1.0", "valid": false, "error_count": 1, "warning_count": 1,
+            "diagnostics": [
+                {{
+                    "severity": "error", "summary": "Missing required argument",
+                    "detail": "The argument \"ami\" is required.\n\nSee the docs.",
+                    "range": {{ "filename": "main.tf.json", "start": {{ "line": {}, "column": 9, "byte": 0 }}, "end": {{ "line": {}, "column": 20, "byte": 0 }} }},
+                    "snippet": {{ "context": null, "code": "", "start_line": 1 }}
+                }},
+                {{ "severity": "warning", "summary": "Deprecated attribute" }}
+            ]
+        }}"#, line("\"instance_type\""), line("\"instance_type\""));
+        let result = parse_validation(stdout.as_bytes(), b"").unwrap();
+        assert!(!result.valid);
+        assert_eq!((result.error_count, result.warning_count), (1, 1));
+        let d = &result.diagnostics[0];
+        assert_eq!(d.to_string(), format!(
+            "error: Missing required argument\n  at main.tf.json:{}:9\n  The argument \"ami\" is required.\n  See the docs.", line("\"instance_type\"")));
+        let r = d.range.as_ref().unwrap();
+        assert_eq!(address_at_line(&text, r.start.line).as_deref(), Some("aws_instance.web"));
+        assert_eq!(result.diagnostics[1].to_string(), "warning: Deprecated attribute");
+
+        let err = parse_validation(b"", b"Error: Terraform initialized in an empty directory!\n").unwrap_err();
+        assert_eq!(err.to_string(), "validate failed: Error: Terraform initialized in an empty directory!");
+    }
+
+    #[test]
+    fn runner_version_from_json() {
+        let tf = parse_version(Runner::Terraform, br#"{ "terraform_version": "1.9.8", "platform": "linux_amd64", "provider_selections": {}, "terraform_outdated": false }"#).unwrap();
+        assert_eq!(tf, semver::Version::new(1, 9, 8));
+        let tofu = parse_version(Runner::Tofu, br#"{ "terraform_version": "1.8.3", "platform": "darwin_arm64" }"#).unwrap();
+        assert_eq!(tofu, semver::Version::new(1, 8, 3));
+        assert_eq!(parse_version(Runner::Tofu, br#"{ "tofu_version": "1.10.0" }"#).unwrap(), semver::Version::new(1, 10, 0));
+        assert_eq!(parse_version(Runner::Terraform, b"Terraform v1.9.8").unwrap_err().to_string(), "parse terraform version JSON");
+        assert_eq!(parse_version(Runner::Tofu, b"{}").unwrap_err().to_string(), "tofu version -json has no version");
+        assert_eq!(parse_version(Runner::Terraform, br#"{ "terraform_version": "1.9" }"#).unwrap_err().to_string(), "terraform version '1.9'");
+    }
+
+    #[test]
+    fn runner_version_constraints() {
+        let installed = |tofu: Option<&'static str>, terraform: Option<&'static str>| move |r: Runner| Ok(match r {
+            Runner::Tofu => tofu, Runner::Terraform => terraform,
+        }.map(|v| semver::Version::parse(v).unwrap()));
+        let both = [Runner::Tofu, Runner::Terraform];
+        let pick = |required: &str, tofu, terraform| first_matching(required, &both, installed(tofu, terraform));
+
+        assert!(matches!(pick(">= 1.5, < 2", Some("1.8.3"), Some("1.9.8")).unwrap(), Runner::Tofu));
+        assert!(matches!(pick(">= 1.9", Some("1.8.3"), Some("1.9.8")).unwrap(), Runner::Terraform));
+        assert!(matches!(pick(">= 1.5", None, Some("1.9.8")).unwrap(), Runner::Terraform));
+        assert_eq!(pick(">= 2", Some("1.8.3"), None).unwrap_err().to_string(),
+            "no runner satisfies required_runner_version '>= 2': tofu 1.8.3, terraform not found");
+        assert!(matches!(pick("~1.6", Some("1.7.0"), Some("1.6.6")).unwrap(), Runner::Terraform));
+        assert_eq!(pick(">= one", Some("1.8.3"), None).unwrap_err().to_string(), "required_runner_version '>= one'");
+
+        // Once tofu matches, terraform's version is never asked for.
+        let mut asked = Vec::new();
+        let r = first_matching(">= 1.5", &both, |r| { asked.push(bin(r)); Ok(Some(semver::Version::new(1, 8, 3))) }).unwrap();
+        assert!(matches!(r, Runner::Tofu));
+        assert_eq!(asked, ["tofu"]);
+        // An explicit runner is the only candidate, matching or not.
+        assert_eq!(first_matching(">= 1.9", &[Runner::Tofu], installed(Some("1.8.3"), Some("1.9.8"))).unwrap_err().to_string(),
+            "no runner satisfies required_runner_version '>= 1.9': tofu 1.8.3");
+    }
 }